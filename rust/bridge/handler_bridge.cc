@@ -1,5 +1,9 @@
 
 #include "handler_bridge.h"
+#include "sql_priv.h"
+#include "sql_class.h"
+#include "sql_alter.h"
+#include "sql_table.h"
 
 extern "C" handler*
 ha_bridge_construct(handlerton *hton, TABLE_SHARE *table_args,
@@ -11,3 +15,112 @@ ha_bridge_construct(handlerton *hton, TABLE_SHARE *table_args,
 extern "C" void ha_bridge_destroy(handler*bridge) {
   delete (handler_bridge*)bridge;
 }
+
+extern "C" void ha_bridge_trans_register(THD *thd, handlerton *hton) {
+  trans_register_ha(thd, false, hton, 0);
+  if (ha_bridge_thd_in_transaction(thd))
+    trans_register_ha(thd, true, hton, 0);
+}
+
+extern "C" bool ha_bridge_thd_in_transaction(const THD *thd) {
+  return thd_test_options(thd, OPTION_NOT_AUTOCOMMIT | OPTION_BEGIN);
+}
+
+extern "C" enum thd_kill_levels ha_bridge_thd_kill_level(const THD *thd) {
+  return thd_kill_level(thd);
+}
+
+extern "C" void ha_bridge_admin_msg(THD *thd, enum ha_bridge_msg_level level,
+                                    const char *msg, size_t len) {
+  char buf[MYSQL_ERRMSG_SIZE];
+  snprintf(buf, sizeof(buf), "%.*s", (int) len, msg);
+  /* Conditions raised during the operation are returned as result rows */
+  if (level == HA_BRIDGE_MSG_ERROR)
+    my_message(ER_UNKNOWN_ERROR, buf, MYF(0));
+  else
+    push_warning(thd,
+                 level == HA_BRIDGE_MSG_NOTE ? Sql_condition::WARN_LEVEL_NOTE
+                                             : Sql_condition::WARN_LEVEL_WARN,
+                 ER_UNKNOWN_ERROR, buf);
+}
+
+extern "C" void ha_bridge_print_error(handler *h, int error) {
+  h->print_error(error, MYF(0));
+}
+
+extern "C" size_t ha_bridge_key_storage_length(const handler *h, uint index) {
+  return const_cast<handler*>(h)->get_table()->key_storage_length(index);
+}
+
+extern "C" ulonglong ha_bridge_alter_flags(const Alter_inplace_info *info) {
+  return info->handler_flags;
+}
+
+extern "C" HA_CREATE_INFO *ha_bridge_alter_create_info(const Alter_inplace_info *info) {
+  return info->create_info;
+}
+
+extern "C" int ha_bridge_alter_new_field_index(const Alter_inplace_info *info,
+                                               const Field *old_field) {
+  List_iterator_fast<Create_field> it(info->alter_info->create_list);
+  Create_field *new_field;
+  int idx= 0;
+  while ((new_field= it++)) {
+    if (new_field->field == old_field)
+      return idx;
+    idx++;
+  }
+  return -1;
+}
+
+extern "C" const KEY *ha_bridge_alter_key_info(const Alter_inplace_info *info, uint *count) {
+  *count= info->key_count;
+  return info->key_info_buffer;
+}
+
+extern "C" const uint *ha_bridge_alter_added_keys(const Alter_inplace_info *info,
+                                                  uint *count) {
+  *count= info->index_add_count;
+  return info->index_add_buffer;
+}
+
+extern "C" KEY *const *ha_bridge_alter_dropped_keys(const Alter_inplace_info *info,
+                                                    uint *count) {
+  *count= info->index_drop_count;
+  return info->index_drop_buffer;
+}
+
+extern "C" uint ha_bridge_alter_renamed_key_count(const Alter_inplace_info *info) {
+  return (uint) info->rename_keys.size();
+}
+
+extern "C" void ha_bridge_alter_renamed_key(const Alter_inplace_info *info, uint idx,
+                                            const KEY **old_key, const KEY **new_key) {
+  *old_key= info->rename_keys.at(idx).old_key;
+  *new_key= info->rename_keys.at(idx).new_key;
+}
+
+extern "C" void ha_bridge_alter_set_unsupported_reason(Alter_inplace_info *info,
+                                                       const char *reason) {
+  info->unsupported_reason= reason;
+}
+
+extern "C" bool ha_bridge_discovered_add_table(handlerton::discovered_list *list,
+                                               const char *name, size_t len) {
+  return list->add_table(name, len);
+}
+
+extern "C" size_t ha_bridge_filename_to_tablename(const char *from, size_t from_len,
+                                                  char *to, size_t to_len) {
+  char buf[FN_REFLEN + 1];
+  strmake(buf, from, MY_MIN(from_len, FN_REFLEN));
+  // Names that are not encoded (from before 5.1) get the `#mysql50#` prefix
+  return filename_to_tablename(buf, to, to_len, true);
+}
+
+extern "C" size_t ha_bridge_tablename_to_filename(const char *from, size_t from_len,
+                                                  char *to, size_t to_len) {
+  char buf[FN_REFLEN + 1];
+  strmake(buf, from, MY_MIN(from_len, FN_REFLEN));
+  return tablename_to_filename(buf, to, to_len);
+}
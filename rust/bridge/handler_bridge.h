@@ -21,27 +21,33 @@ class handler_bridge;
 /**
   A C representation of the handler class.
 
-  For now all function pointers must be nonnull. We could change this to check
-  for null then call the parent function if so at some point.
+  Function pointers must be nonnull unless noted as optional. Optional entries
+  that are null fall back to the `handler` implementation, which is used for
+  capabilities an engine does not provide (e.g. indexes).
 */
 typedef struct handler_bridge_vt {
   void (*constructor)(handler_bridge*, handlerton*, MEM_ROOT*, TABLE_SHARE*);
   void (*destructor)(handler_bridge*);
-  const char* (*index_type)(handler_bridge*, uint);
+  const char* (*index_type)(handler_bridge*, uint); /* optional */
   ulonglong  (*table_flags)(const handler_bridge*);
-  ulong (*index_flags)(const handler_bridge*, uint, uint, bool);
+  ulong (*index_flags)(const handler_bridge*, uint, uint, bool); /* optional */
   uint (*max_supported_record_length)(const handler_bridge*);
-  uint (*max_supported_keys)(const handler_bridge*);
-  uint (*max_supported_key_parts)(const handler_bridge*);
-  uint (*max_supported_key_length)(const handler_bridge*);
+  uint (*max_supported_keys)(const handler_bridge*); /* optional */
+  uint (*max_supported_key_parts)(const handler_bridge*); /* optional */
+  uint (*max_supported_key_length)(const handler_bridge*); /* optional */
   IO_AND_CPU_COST (*scan_time)(handler_bridge*);
-  IO_AND_CPU_COST (*keyread_time)(handler_bridge*, uint, ulong, ha_rows, ulonglong);
+  IO_AND_CPU_COST (*keyread_time)(handler_bridge*, uint, ulong, ha_rows, ulonglong); /* optional */
   IO_AND_CPU_COST (*rnd_pos_time)(handler_bridge*, ha_rows);
   int (*open)(handler_bridge*, const char*, int, uint);
   int (*close)(handler_bridge*);
   int (*write_row)(handler_bridge*, const uchar*);
+  void (*start_bulk_insert)(handler_bridge*, ha_rows, uint);
+  int (*end_bulk_insert)(handler_bridge*);
   int (*update_row)(handler_bridge*, const uchar*, const uchar*);
   int (*delete_row)(handler_bridge*, const uchar*);
+  /* Index reads are optional */
+  int (*index_init)(handler_bridge*, uint, bool);
+  int (*index_end)(handler_bridge*);
   int (*index_read_map)(handler_bridge*, uchar*, const uchar*, key_part_map,
                         enum ha_rkey_function);
   int (*index_next)(handler_bridge*, uchar*);
@@ -58,12 +64,44 @@ typedef struct handler_bridge_vt {
   int (*external_lock)(handler_bridge*, THD*, int);
   int (*delete_all_rows)(handler_bridge*);
   ha_rows (*records_in_range)(handler_bridge*, uint, const key_range*,
-                              const key_range*, page_range*);
+                              const key_range*, page_range*); /* optional */
   int (*delete_table)(handler_bridge*, const char*);
+  int (*rename_table)(handler_bridge*, const char*, const char*);
+  int (*truncate)(handler_bridge*);
   int (*create)(handler_bridge*, const char*, TABLE*,  HA_CREATE_INFO*);
+  /** Returns the message for `error` (with its length) or null if there is none */
+  const char* (*get_error_message)(handler_bridge*, int, size_t*);
+  /* In-place alter is optional */
   enum_alter_inplace_result
   (*check_if_supported_inplace_alter)(handler_bridge*, TABLE*, Alter_inplace_info*);
+  bool (*prepare_inplace_alter_table)(handler_bridge*, TABLE*, Alter_inplace_info*);
+  bool (*inplace_alter_table)(handler_bridge*, TABLE*, Alter_inplace_info*);
+  bool (*commit_inplace_alter_table)(handler_bridge*, TABLE*, Alter_inplace_info*, bool);
   THR_LOCK_DATA** (*store_lock)(handler_bridge*, THD*, THR_LOCK_DATA**, enum thr_lock_type);
+  /* Condition pushdown is optional */
+  const COND* (*cond_push)(handler_bridge*, const COND*);
+  void (*cond_pop)(handler_bridge*);
+  /* Table maintenance is optional */
+  int (*check)(handler_bridge*, THD*, HA_CHECK_OPT*);
+  int (*repair)(handler_bridge*, THD*, HA_CHECK_OPT*);
+  int (*analyze)(handler_bridge*, THD*, HA_CHECK_OPT*);
+  int (*optimize)(handler_bridge*, THD*, HA_CHECK_OPT*);
+  bool (*is_crashed)(const handler_bridge*);
+  bool (*auto_repair)(const handler_bridge*, int);
+  /* Engine managed auto increment is optional */
+  void (*get_auto_increment)(handler_bridge*, ulonglong, ulonglong, ulonglong,
+                             ulonglong*, ulonglong*);
+  int (*reset_auto_increment)(handler_bridge*, ulonglong);
+  /* Index condition pushdown and Multi-Range Read are optional */
+  Item* (*idx_cond_push)(handler_bridge*, uint, Item*);
+  void (*cancel_pushed_idx_cond)(handler_bridge*);
+  /** Returns the new mode, given the one chosen by the default implementation */
+  uint (*multi_range_read_info)(handler_bridge*, uint, uint, ha_rows, uint);
+  /** Called after `mrr_funcs` and `mrr_iter` are set up */
+  int (*multi_range_read_init)(handler_bridge*, uint);
+  int (*multi_range_read_next)(handler_bridge*, uchar*, range_id_t*);
+  /** Returns the index that caused the last duplicate key error, or `(uint) -1` */
+  uint (*dup_key)(const handler_bridge*); /* optional */
 } handler_bridge_vt;
 
 
@@ -76,11 +114,14 @@ class handler_bridge: public handler {
   void *data;
   /** Just a convenience point for a Rust type ID */
   uint8_t type_id[16];
+  /** Whether the current multi-range read uses the `handler` implementation */
+  bool mrr_use_default;
   
   handler_bridge(handlerton *hton, TABLE_SHARE *table_arg,
                  MEM_ROOT *mem_root, const handler_bridge_vt *const vt)
     :handler(hton, table_arg),
-    vt(vt)
+    vt(vt),
+    mrr_use_default(true)
   {
     vt->constructor(this, hton, mem_root, table_arg);
   }
@@ -89,18 +130,31 @@ class handler_bridge: public handler {
     vt->destructor(this);
   }
   
-  const char *index_type(uint inx) { return vt->index_type(this, inx); } 
+  const char *index_type(uint inx) {
+    return vt->index_type ? vt->index_type(this, inx) : "";
+  }
   ulonglong table_flags() const { return vt->table_flags(this); }
   ulong index_flags(uint inx, uint part, bool all_parts) const {
-    return vt->index_flags(this, inx, part, all_parts);
+    return vt->index_flags ? vt->index_flags(this, inx, part, all_parts) : 0;
   }
   uint max_supported_record_length() const { return vt->max_supported_record_length(this); }
-  uint max_supported_keys() const { return vt->max_supported_keys(this); }
-  uint max_supported_key_parts() const { return vt->max_supported_key_parts(this); }
-  uint max_supported_key_length() const { return vt->max_supported_key_length(this); }
+  uint max_supported_keys() const {
+    return vt->max_supported_keys ? vt->max_supported_keys(this)
+                                  : handler::max_supported_keys();
+  }
+  uint max_supported_key_parts() const {
+    return vt->max_supported_key_parts ? vt->max_supported_key_parts(this)
+                                       : handler::max_supported_key_parts();
+  }
+  uint max_supported_key_length() const {
+    return vt->max_supported_key_length ? vt->max_supported_key_length(this)
+                                        : handler::max_supported_key_length();
+  }
   virtual IO_AND_CPU_COST scan_time() { return vt->scan_time(this); }
   virtual IO_AND_CPU_COST keyread_time(uint index, ulong ranges, ha_rows rows,
                                        ulonglong blocks) {
+    if (!vt->keyread_time)
+      return handler::keyread_time(index, ranges, rows, blocks);
     return vt->keyread_time(this, index, ranges, rows, blocks);
   }
   virtual IO_AND_CPU_COST rnd_pos_time(ha_rows rows) { return vt->rnd_pos_time(this, rows); }
@@ -108,41 +162,198 @@ class handler_bridge: public handler {
     return vt->open(this, name, mode, test_if_locked);
   }
   int close(void) { return vt->close(this); }
-  int write_row(const uchar *buf) { return vt->write_row(this, buf); }
+  int write_row(const uchar *buf) {
+    /*
+      Fill in AUTO_INCREMENT here rather than from Rust: the handler must not be
+      borrowed while `get_auto_increment` calls back into it, and the row must
+      not be borrowed while the value is written into it.
+    */
+    if (table->next_number_field && buf == table->record[0])
+      if (int error= update_auto_increment())
+        return error;
+    return vt->write_row(this, buf);
+  }
+  void start_bulk_insert(ha_rows rows, uint flags) {
+    vt->start_bulk_insert(this, rows, flags);
+  }
+  int end_bulk_insert() { return vt->end_bulk_insert(this); }
   int update_row(const uchar *old_data, const uchar *new_data) { return vt->update_row(this, old_data, new_data); }
   int delete_row(const uchar *buf) { return vt->delete_row(this, buf); }
+  int index_init(uint idx, bool sorted) {
+    return vt->index_init ? vt->index_init(this, idx, sorted) : 0;
+  }
+  int index_end() { return vt->index_end ? vt->index_end(this) : 0; }
   int index_read_map(uchar *buf, const uchar *key,
                      key_part_map keypart_map, enum ha_rkey_function find_flag) {
+    if (!vt->index_read_map)
+      return handler::index_read_map(buf, key, keypart_map, find_flag);
     return vt->index_read_map(this, buf, key, keypart_map, find_flag);
   }
-  int index_next(uchar *buf) { return vt->index_next(this, buf); }
-  int index_prev(uchar *buf) { return vt->index_prev(this, buf); }
-  int index_first(uchar *buf) { return vt->index_first(this, buf); }
-  int index_last(uchar *buf) { return vt->index_last(this, buf); }
+  int index_next(uchar *buf) {
+    if (!vt->index_next)
+      return handler::index_next(buf);
+    return vt->index_next(this, buf);
+  }
+  int index_prev(uchar *buf) {
+    if (!vt->index_prev)
+      return handler::index_prev(buf);
+    return vt->index_prev(this, buf);
+  }
+  int index_first(uchar *buf) {
+    if (!vt->index_first)
+      return handler::index_first(buf);
+    return vt->index_first(this, buf);
+  }
+  int index_last(uchar *buf) {
+    if (!vt->index_last)
+      return handler::index_last(buf);
+    return vt->index_last(this, buf);
+  }
   int rnd_init(bool scan) { return vt->rnd_init(this, scan); }
   int rnd_end() { return vt->rnd_end(this); }
   int rnd_next(uchar *buf) { return vt->rnd_next(this, buf); }
   int rnd_pos(uchar *buf, uchar *pos) { return vt->rnd_pos(this, buf, pos); }
   void position(const uchar *record) { return vt->position(this, record); }
-  int info(uint flag) { return vt->info(this, flag); }
+  int info(uint flag) {
+    int res= vt->info(this, flag);
+    if ((flag & HA_STATUS_ERRKEY) && vt->dup_key)
+      errkey= vt->dup_key(this);
+    return res;
+  }
   int extra(enum ha_extra_function operation) { return vt->extra(this, operation); }
   int external_lock(THD *thd, int lock_type) { return vt->external_lock(this, thd, lock_type); }
   int delete_all_rows(void) { return vt->delete_all_rows(this); }
   ha_rows records_in_range(uint inx, const key_range *min_key, const key_range *max_key,
                            page_range *pages) {
+    if (!vt->records_in_range)
+      return handler::records_in_range(inx, min_key, max_key, pages);
     return vt->records_in_range(this, inx, min_key, max_key, pages);
   }
   int delete_table(const char *from) { return vt->delete_table(this, from); }
+  int rename_table(const char *from, const char *to) {
+    return vt->rename_table(this, from, to);
+  }
+  int truncate() {
+    int error= vt->truncate(this);
+    return error ? error : reset_auto_increment(0);
+  }
   int create(const char *name, TABLE *form,  HA_CREATE_INFO *create_info) {
     return vt->create(this, name, form, create_info);
   }
+  bool get_error_message(int error, String *buf) {
+    size_t length;
+    const char *msg= vt->get_error_message(this, error, &length);
+    if (msg)
+      buf->copy(msg, length, system_charset_info);
+    return false;
+  }
   enum_alter_inplace_result
   check_if_supported_inplace_alter(TABLE* altered_table, Alter_inplace_info* ha_alter_info) {
+    if (!vt->check_if_supported_inplace_alter)
+      return handler::check_if_supported_inplace_alter(altered_table, ha_alter_info);
     return vt->check_if_supported_inplace_alter(this, altered_table, ha_alter_info);
   }
+  bool prepare_inplace_alter_table(TABLE* altered_table, Alter_inplace_info* ha_alter_info) {
+    if (!vt->prepare_inplace_alter_table)
+      return handler::prepare_inplace_alter_table(altered_table, ha_alter_info);
+    return vt->prepare_inplace_alter_table(this, altered_table, ha_alter_info);
+  }
+  bool inplace_alter_table(TABLE* altered_table, Alter_inplace_info* ha_alter_info) {
+    if (!vt->inplace_alter_table)
+      return handler::inplace_alter_table(altered_table, ha_alter_info);
+    return vt->inplace_alter_table(this, altered_table, ha_alter_info);
+  }
+  bool commit_inplace_alter_table(TABLE* altered_table, Alter_inplace_info* ha_alter_info,
+                                  bool commit) {
+    if (!vt->commit_inplace_alter_table)
+      return handler::commit_inplace_alter_table(altered_table, ha_alter_info, commit);
+    return vt->commit_inplace_alter_table(this, altered_table, ha_alter_info, commit);
+  }
   THR_LOCK_DATA **store_lock(THD *thd, THR_LOCK_DATA **to, enum thr_lock_type lock_type) {
     return vt->store_lock(this, thd, to, lock_type);
   }
+  const COND *cond_push(const COND *cond) {
+    return vt->cond_push ? vt->cond_push(this, cond) : handler::cond_push(cond);
+  }
+  void cond_pop() {
+    if (vt->cond_pop)
+      vt->cond_pop(this);
+  }
+  int check(THD *thd, HA_CHECK_OPT *check_opt) {
+    return vt->check ? vt->check(this, thd, check_opt) : handler::check(thd, check_opt);
+  }
+  int repair(THD *thd, HA_CHECK_OPT *check_opt) {
+    return vt->repair ? vt->repair(this, thd, check_opt) : handler::repair(thd, check_opt);
+  }
+  int analyze(THD *thd, HA_CHECK_OPT *check_opt) {
+    return vt->analyze ? vt->analyze(this, thd, check_opt) : handler::analyze(thd, check_opt);
+  }
+  int optimize(THD *thd, HA_CHECK_OPT *check_opt) {
+    return vt->optimize ? vt->optimize(this, thd, check_opt)
+                        : handler::optimize(thd, check_opt);
+  }
+  bool is_crashed() const {
+    return vt->is_crashed ? vt->is_crashed(this) : handler::is_crashed();
+  }
+  bool auto_repair(int error) const {
+    return vt->auto_repair ? vt->auto_repair(this, error) : handler::auto_repair(error);
+  }
+  void get_auto_increment(ulonglong offset, ulonglong increment,
+                          ulonglong nb_desired_values, ulonglong *first_value,
+                          ulonglong *nb_reserved_values) {
+    if (!vt->get_auto_increment)
+      return handler::get_auto_increment(offset, increment, nb_desired_values,
+                                         first_value, nb_reserved_values);
+    vt->get_auto_increment(this, offset, increment, nb_desired_values, first_value,
+                           nb_reserved_values);
+  }
+  int reset_auto_increment(ulonglong value) {
+    if (!vt->reset_auto_increment)
+      return handler::reset_auto_increment(value);
+    return vt->reset_auto_increment(this, value);
+  }
+  Item *idx_cond_push(uint keyno, Item *idx_cond) {
+    if (!vt->idx_cond_push)
+      return handler::idx_cond_push(keyno, idx_cond);
+    return vt->idx_cond_push(this, keyno, idx_cond);
+  }
+  void cancel_pushed_idx_cond() {
+    handler::cancel_pushed_idx_cond();
+    if (vt->cancel_pushed_idx_cond)
+      vt->cancel_pushed_idx_cond(this);
+  }
+  ha_rows multi_range_read_info_const(uint keyno, RANGE_SEQ_IF *seq, void *seq_init_param,
+                                      uint n_ranges, uint *bufsz, uint *mrr_mode,
+                                      ha_rows limit, Cost_estimate *cost) {
+    ha_rows rows= handler::multi_range_read_info_const(keyno, seq, seq_init_param, n_ranges,
+                                                       bufsz, mrr_mode, limit, cost);
+    if (vt->multi_range_read_info && rows != HA_POS_ERROR)
+      *mrr_mode= vt->multi_range_read_info(this, keyno, n_ranges, rows, *mrr_mode);
+    return rows;
+  }
+  ha_rows multi_range_read_info(uint keyno, uint n_ranges, uint keys, uint key_parts,
+                                uint *bufsz, uint *mrr_mode, Cost_estimate *cost) {
+    ha_rows res= handler::multi_range_read_info(keyno, n_ranges, keys, key_parts, bufsz,
+                                                mrr_mode, cost);
+    if (vt->multi_range_read_info && !res)
+      *mrr_mode= vt->multi_range_read_info(this, keyno, n_ranges, keys, *mrr_mode);
+    return res;
+  }
+  int multi_range_read_init(RANGE_SEQ_IF *seq, void *seq_init_param, uint n_ranges,
+                            uint mrr_mode, HANDLER_BUFFER *buf) {
+    mrr_use_default= !vt->multi_range_read_init || (mrr_mode & HA_MRR_USE_DEFAULT_IMPL);
+    if (mrr_use_default)
+      return handler::multi_range_read_init(seq, seq_init_param, n_ranges, mrr_mode, buf);
+    mrr_iter= seq->init(seq_init_param, n_ranges, mrr_mode);
+    mrr_funcs= *seq;
+    ranges_in_seq= n_ranges;
+    return vt->multi_range_read_init(this, mrr_mode);
+  }
+  int multi_range_read_next(range_id_t *range_info) {
+    if (mrr_use_default)
+      return handler::multi_range_read_next(range_info);
+    return vt->multi_range_read_next(this, table->record[0], range_info);
+  }
 };
 
 /**
@@ -154,3 +365,92 @@ ha_bridge_construct(handlerton*, TABLE_SHARE*, MEM_ROOT*, const handler_bridge_v
 
 /** Destroy a `handler_bridge` */
 extern "C" void ha_bridge_destroy(handler*);
+
+/**
+  Register `hton` as taking part in the current statement, and also in the
+  current transaction if autocommit is off or `BEGIN` was used. Engines call this
+  from `external_lock` so that their commit and rollback hooks get called.
+*/
+extern "C" void ha_bridge_trans_register(THD *thd, handlerton *hton);
+
+/** Whether `thd` is within a multi-statement transaction */
+extern "C" bool ha_bridge_thd_in_transaction(const THD *thd);
+
+/** The kill level of `thd`, see `thd_kill_level` */
+extern "C" enum thd_kill_levels ha_bridge_thd_kill_level(const THD *thd);
+
+/** Severity of a message returned by a table maintenance statement */
+enum ha_bridge_msg_level {
+  HA_BRIDGE_MSG_NOTE,
+  HA_BRIDGE_MSG_WARNING,
+  HA_BRIDGE_MSG_ERROR,
+};
+
+/**
+  Add a message to the result set of a table maintenance statement (`CHECK TABLE`
+  etc.). `msg` does not need to be null terminated.
+*/
+extern "C" void ha_bridge_admin_msg(THD *thd, enum ha_bridge_msg_level level,
+                                    const char *msg, size_t len);
+
+/** Raise the error for `error` (`HA_ERR_*`), as the server does for handler calls */
+extern "C" void ha_bridge_print_error(handler*, int error);
+
+/** Bytes stored per row in an index, see `TABLE::key_storage_length` */
+extern "C" size_t ha_bridge_key_storage_length(const handler*, uint index);
+
+/** The `ALTER_*` flags describing an in-place alter */
+extern "C" ulonglong ha_bridge_alter_flags(const Alter_inplace_info*);
+
+/** Create options for the new version of the table */
+extern "C" HA_CREATE_INFO *ha_bridge_alter_create_info(const Alter_inplace_info*);
+
+/**
+  Index in the new table of a field of the old table, or -1 if the field is
+  dropped
+*/
+extern "C" int ha_bridge_alter_new_field_index(const Alter_inplace_info*,
+                                               const Field *old_field);
+
+/**
+  Keys of the new table, in the order of `TABLE::key_info` of the new table.
+  Note that `KEY_PART_INFO::fieldnr` is 0-based for these keys.
+*/
+extern "C" const KEY *ha_bridge_alter_key_info(const Alter_inplace_info*, uint *count);
+
+/** Indices into `ha_bridge_alter_key_info` of keys being added */
+extern "C" const uint *ha_bridge_alter_added_keys(const Alter_inplace_info*, uint *count);
+
+/** Keys of the old table being dropped */
+extern "C" KEY *const *ha_bridge_alter_dropped_keys(const Alter_inplace_info*, uint *count);
+
+/** Number of keys being renamed */
+extern "C" uint ha_bridge_alter_renamed_key_count(const Alter_inplace_info*);
+
+/** A renamed key: `old_key` is from the old table, `new_key` from the new one */
+extern "C" void ha_bridge_alter_renamed_key(const Alter_inplace_info*, uint idx,
+                                            const KEY **old_key, const KEY **new_key);
+
+/**
+  Set the reason shown to the user if the alter cannot be done in place.
+  `reason` must be a static string.
+*/
+extern "C" void ha_bridge_alter_set_unsupported_reason(Alter_inplace_info*,
+                                                       const char *reason);
+
+/**
+  Add a table name to the collector passed to `discover_table_names`. Returns
+  true on error (out of memory).
+*/
+extern "C" bool ha_bridge_discovered_add_table(handlerton::discovered_list *list,
+                                               const char *name, size_t len);
+
+/**
+  Convert a database or table name as encoded in the file system to its real
+  name, or back. `from` need not be NUL terminated. Return the length written
+  to `to`.
+*/
+extern "C" size_t ha_bridge_filename_to_tablename(const char *from, size_t from_len,
+                                                  char *to, size_t to_len);
+extern "C" size_t ha_bridge_tablename_to_filename(const char *from, size_t from_len,
+                                                  char *to, size_t to_len);
@@ -0,0 +1,118 @@
+#include "item_bridge.h"
+#include "sql_class.h"
+#include "item_cmpfunc.h"
+
+static item_bridge_kind const_kind(const Item *item) {
+  if (item->type() == Item::NULL_ITEM)
+    return ITEM_BRIDGE_NULL;
+  switch (item->cmp_type()) {
+  case INT_RESULT:
+    return item->unsigned_flag ? ITEM_BRIDGE_UINT : ITEM_BRIDGE_INT;
+  case REAL_RESULT:
+    return ITEM_BRIDGE_REAL;
+  case DECIMAL_RESULT:
+    return ITEM_BRIDGE_DECIMAL;
+  case STRING_RESULT:
+  case TIME_RESULT:
+    return ITEM_BRIDGE_STRING;
+  default:
+    return ITEM_BRIDGE_UNSUPPORTED;
+  }
+}
+
+extern "C" item_bridge_kind item_bridge_get_kind(const Item *item, const TABLE *table) {
+  Item *real= const_cast<Item*>(item)->real_item();
+  if (real->type() == Item::FIELD_ITEM) {
+    const Field *field= ((const Item_field*) real)->field;
+    return field && field->table == table ? ITEM_BRIDGE_FIELD : ITEM_BRIDGE_UNSUPPORTED;
+  }
+  if (item->const_item() && !const_cast<Item*>(item)->is_expensive())
+    return const_kind(item);
+  if (item->type() != Item::FUNC_ITEM && item->type() != Item::COND_ITEM)
+    return ITEM_BRIDGE_UNSUPPORTED;
+
+  const Item_func *func= (const Item_func*) item;
+  switch (func->functype()) {
+  case Item_func::EQ_FUNC: return ITEM_BRIDGE_EQ;
+  case Item_func::EQUAL_FUNC: return ITEM_BRIDGE_EQUAL;
+  case Item_func::NE_FUNC: return ITEM_BRIDGE_NE;
+  case Item_func::LT_FUNC: return ITEM_BRIDGE_LT;
+  case Item_func::LE_FUNC: return ITEM_BRIDGE_LE;
+  case Item_func::GE_FUNC: return ITEM_BRIDGE_GE;
+  case Item_func::GT_FUNC: return ITEM_BRIDGE_GT;
+  case Item_func::COND_AND_FUNC: return ITEM_BRIDGE_AND;
+  case Item_func::COND_OR_FUNC: return ITEM_BRIDGE_OR;
+  case Item_func::NOT_FUNC: return ITEM_BRIDGE_NOT;
+  case Item_func::ISNULL_FUNC: return ITEM_BRIDGE_IS_NULL;
+  case Item_func::ISNOTNULL_FUNC: return ITEM_BRIDGE_IS_NOT_NULL;
+  case Item_func::IN_FUNC:
+    return ((const Item_func_in*) item)->negated ? ITEM_BRIDGE_NOT_IN
+                                                 : ITEM_BRIDGE_IN;
+  case Item_func::LIKE_FUNC: {
+    const Item_func_like *like= (const Item_func_like*) item;
+    /* Only the default escape character is represented */
+    if (like->escape != '\\')
+      return ITEM_BRIDGE_UNSUPPORTED;
+    return like->negated ? ITEM_BRIDGE_NOT_LIKE : ITEM_BRIDGE_LIKE;
+  }
+  default:
+    return ITEM_BRIDGE_UNSUPPORTED;
+  }
+}
+
+extern "C" uint item_bridge_arg_count(Item *item) {
+  if (item->type() == Item::COND_ITEM)
+    return ((Item_cond*) item)->argument_list()->elements;
+  return ((Item_func*) item)->argument_count();
+}
+
+extern "C" void item_bridge_args(Item *item, Item **out) {
+  if (item->type() == Item::COND_ITEM) {
+    List_iterator_fast<Item> it(*((Item_cond*) item)->argument_list());
+    Item *arg;
+    while ((arg= it++))
+      *out++= arg;
+    return;
+  }
+  Item_func *func= (Item_func*) item;
+  for (uint i= 0; i < func->argument_count(); i++)
+    out[i]= func->arguments()[i];
+}
+
+extern "C" uint item_bridge_field_index(const Item *item) {
+  Item *real= const_cast<Item*>(item)->real_item();
+  return ((const Item_field*) real)->field->field_index;
+}
+
+extern "C" longlong item_bridge_val_int(Item *item) {
+  return item->val_int();
+}
+
+extern "C" double item_bridge_val_real(Item *item) {
+  return item->val_real();
+}
+
+extern "C" const char *item_bridge_val_str(Item *item, size_t *length) {
+  StringBuffer<MAX_FIELD_WIDTH> tmp;
+  String *res= item->val_str(&tmp);
+  if (!res)
+    return NULL;
+  *length= res->length();
+  return current_thd->strmake(res->ptr(), res->length());
+}
+
+extern "C" bool item_bridge_null_value(const Item *item) {
+  return item->null_value;
+}
+
+extern "C" Item *item_bridge_cond_and(THD *thd, Item **items, uint count) {
+  List<Item> list;
+  for (uint i= 0; i < count; i++)
+    if (list.push_back(items[i], thd->mem_root))
+      return NULL;
+  Item_cond_and *cond= new (thd->mem_root) Item_cond_and(thd, list);
+  if (!cond)
+    return NULL;
+  cond->quick_fix_field();
+  return cond;
+}
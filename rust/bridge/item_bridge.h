@@ -0,0 +1,82 @@
+/** @file item_bridge.h
+
+    @brief
+  C accessors for inspecting `Item` expression trees, used to convert pushed
+  conditions into a form that Rust can own.
+*/
+
+#pragma once
+
+#include "my_global.h"
+#include "table.h"
+#include "item.h"
+
+/** What an `Item` is, as far as condition pushdown is concerned */
+typedef enum item_bridge_kind {
+  /** Anything that cannot be converted */
+  ITEM_BRIDGE_UNSUPPORTED,
+  /** A column of the table passed to `item_bridge_kind` */
+  ITEM_BRIDGE_FIELD,
+  /* Constants, read with `item_bridge_val_*` */
+  ITEM_BRIDGE_NULL,
+  ITEM_BRIDGE_INT,
+  ITEM_BRIDGE_UINT,
+  ITEM_BRIDGE_REAL,
+  ITEM_BRIDGE_DECIMAL,
+  ITEM_BRIDGE_STRING,
+  /* Comparisons with two arguments */
+  ITEM_BRIDGE_EQ,
+  ITEM_BRIDGE_EQUAL,
+  ITEM_BRIDGE_NE,
+  ITEM_BRIDGE_LT,
+  ITEM_BRIDGE_LE,
+  ITEM_BRIDGE_GE,
+  ITEM_BRIDGE_GT,
+  /* Logical operators */
+  ITEM_BRIDGE_AND,
+  ITEM_BRIDGE_OR,
+  ITEM_BRIDGE_NOT,
+  /* Predicates; the first argument is the tested expression */
+  ITEM_BRIDGE_IN,
+  ITEM_BRIDGE_NOT_IN,
+  ITEM_BRIDGE_LIKE,
+  ITEM_BRIDGE_NOT_LIKE,
+  ITEM_BRIDGE_IS_NULL,
+  ITEM_BRIDGE_IS_NOT_NULL
+} item_bridge_kind;
+
+/**
+  Classify an item. Columns are only recognized if they belong to `table`;
+  constants are only recognized if they are cheap to evaluate.
+*/
+extern "C" item_bridge_kind item_bridge_get_kind(const Item*, const TABLE *table);
+
+/** Number of arguments of a function or `AND`/`OR` */
+extern "C" uint item_bridge_arg_count(Item*);
+
+/** Write all arguments to `out`, which must hold `item_bridge_arg_count` items */
+extern "C" void item_bridge_args(Item*, Item **out);
+
+/** Index of an `ITEM_BRIDGE_FIELD` column in `TABLE::field` */
+extern "C" uint item_bridge_field_index(const Item*);
+
+/** Evaluate an integer constant. Check `item_bridge_null_value` afterwards. */
+extern "C" longlong item_bridge_val_int(Item*);
+
+/** Evaluate a real constant. Check `item_bridge_null_value` afterwards. */
+extern "C" double item_bridge_val_real(Item*);
+
+/**
+  Evaluate a constant as a string, returning a copy on the statement's memory
+  root, or `NULL` if the value is `NULL`.
+*/
+extern "C" const char *item_bridge_val_str(Item*, size_t *length);
+
+/** Whether the last evaluation returned `NULL` */
+extern "C" bool item_bridge_null_value(const Item*);
+
+/**
+  Create the `AND` of `count` items, allocated on `thd`'s memory root. Returns
+  `NULL` on out of memory.
+*/
+extern "C" Item *item_bridge_cond_and(THD *thd, Item **items, uint count);
@@ -0,0 +1,159 @@
+#include "table_bridge.h"
+#include "key.h"
+
+extern "C" enum enum_field_types table_bridge_field_real_type(const Field *field) {
+  return field->real_type();
+}
+
+extern "C" enum enum_field_types table_bridge_field_type(const Field *field) {
+  return field->type();
+}
+
+extern "C" uint32 table_bridge_field_pack_length(const Field *field) {
+  return field->pack_length();
+}
+
+extern "C" uint table_bridge_field_decimals(const Field *field) {
+  return field->decimals();
+}
+
+extern "C" uint table_bridge_field_precision(const Field *field) {
+  if (field->real_type() != MYSQL_TYPE_NEWDECIMAL)
+    return 0;
+  return ((const Field_new_decimal*) field)->precision;
+}
+
+extern "C" longlong table_bridge_field_val_int(Field *field, const uchar *record) {
+  my_ptrdiff_t diff= (my_ptrdiff_t) (record - field->table->record[0]);
+  MY_BITMAP *org_bitmap= dbug_tmp_use_all_columns(field->table,
+                                                  &field->table->read_set);
+  field->move_field_offset(diff);
+  longlong ret= field->val_int();
+  field->move_field_offset(-diff);
+  dbug_tmp_restore_column_map(&field->table->read_set, org_bitmap);
+  return ret;
+}
+
+extern "C" int table_bridge_field_store_int(Field *field, uchar *record, longlong nr,
+                                            bool unsigned_val) {
+  my_ptrdiff_t diff= (my_ptrdiff_t) (record - field->table->record[0]);
+  MY_BITMAP *org_bitmap= dbug_tmp_use_all_columns(field->table,
+                                                  &field->table->write_set);
+  field->move_field_offset(diff);
+  int ret= field->store(nr, unsigned_val);
+  field->move_field_offset(-diff);
+  dbug_tmp_restore_column_map(&field->table->write_set, org_bitmap);
+  return ret;
+}
+
+extern "C" void table_bridge_field_val_str(Field *field, const uchar *record,
+                                           void (*sink)(void *ctx, const char *str,
+                                                        size_t length),
+                                           void *ctx) {
+  StringBuffer<MAX_FIELD_WIDTH> tmp(field->charset());
+  my_ptrdiff_t diff= (my_ptrdiff_t) (record - field->table->record[0]);
+  MY_BITMAP *org_bitmap= dbug_tmp_use_all_columns(field->table,
+                                                  &field->table->read_set);
+  field->move_field_offset(diff);
+  String *res= field->val_str(&tmp);
+  field->move_field_offset(-diff);
+  dbug_tmp_restore_column_map(&field->table->read_set, org_bitmap);
+  if (res)
+    sink(ctx, res->ptr(), res->length());
+}
+
+extern "C" int table_bridge_field_store_text(Field *field, uchar *record,
+                                             const char *str, size_t length) {
+  my_ptrdiff_t diff= (my_ptrdiff_t) (record - field->table->record[0]);
+  MY_BITMAP *org_bitmap= dbug_tmp_use_all_columns(field->table,
+                                                  &field->table->write_set);
+  field->move_field_offset(diff);
+  int ret= field->store_text(str, length, field->charset(), CHECK_FIELD_WARN);
+  field->move_field_offset(-diff);
+  dbug_tmp_restore_column_map(&field->table->write_set, org_bitmap);
+  return ret;
+}
+
+extern "C" const char *table_bridge_field_charset_name(const Field *field) {
+  if (!field->has_charset())
+    return NULL;
+  return field->charset()->cs_name.str;
+}
+
+extern "C" const char *table_bridge_field_collation_name(const Field *field) {
+  if (!field->has_charset())
+    return NULL;
+  return field->charset()->coll_name.str;
+}
+
+extern "C" bool table_bridge_field_is_read(const TABLE *table, uint field_index) {
+  return bitmap_is_set(table->read_set, field_index);
+}
+
+extern "C" int table_bridge_share_init_from_sql(TABLE_SHARE *share, THD *thd,
+                                                bool write, const char *sql,
+                                                size_t sql_length) {
+  return share->init_from_sql_statement_string(thd, write, sql, sql_length);
+}
+
+/** Length of a part of a sort key, without the null flag byte */
+static uint key_part_sort_length(const KEY_PART_INFO *key_part) {
+  Field *field= key_part->field;
+  uint length= field->sort_length();
+  CHARSET_INFO *cs= field->sort_charset();
+  if (use_strnxfrm(cs))
+    length= (uint) cs->strnxfrmlen(length);
+  return length;
+}
+
+extern "C" size_t table_bridge_key_sort_length(const KEY *key_info, uint parts) {
+  size_t length= 0;
+  for (uint i= 0; i < parts; i++)
+  {
+    const KEY_PART_INFO *key_part= &key_info->key_part[i];
+    length+= key_part_sort_length(key_part) + (key_part->field->maybe_null() ? 1 : 0);
+  }
+  return length;
+}
+
+extern "C" void table_bridge_key_sort_key(const KEY *key_info, uint parts,
+                                          const uchar *record, uchar *to) {
+  TABLE *table= key_info->table;
+  my_ptrdiff_t diff= (my_ptrdiff_t) (record - table->record[0]);
+  MY_BITMAP *org_bitmap= dbug_tmp_use_all_columns(table, &table->read_set);
+  for (uint i= 0; i < parts; i++)
+  {
+    const KEY_PART_INFO *key_part= &key_info->key_part[i];
+    Field *field= key_part->field;
+    uint sort_length= key_part_sort_length(key_part);
+    uint length= sort_length + (field->maybe_null() ? 1 : 0);
+    field->move_field_offset(diff);
+    field->make_sort_key_part(to, sort_length);
+    field->move_field_offset(-diff);
+    if (key_part->key_part_flag & HA_REVERSE_SORT)
+    {
+      for (uint j= 0; j < length; j++)
+        to[j]= (uchar) ~to[j];
+    }
+    to+= length;
+  }
+  dbug_tmp_restore_column_map(&table->read_set, org_bitmap);
+}
+
+extern "C" int table_bridge_key_sort_key_from_key(KEY *key_info, uint parts,
+                                                  const uchar *key, uchar *to) {
+  TABLE *table= key_info->table;
+  uchar *record= (uchar*) my_malloc(PSI_NOT_INSTRUMENTED, table->s->reclength,
+                                    MYF(MY_WME | MY_THREAD_SPECIFIC));
+  if (!record)
+    return HA_ERR_OUT_OF_MEM;
+  uint key_length= 0;
+  for (uint i= 0; i < parts; i++)
+    key_length+= key_info->key_part[i].store_length;
+  memcpy(record, table->s->default_values, table->s->reclength);
+  if (key_length)
+    key_restore(record, key, key_info, key_length);
+  table_bridge_key_sort_key(key_info, parts, record, to);
+  my_free(record);
+  return 0;
+}
@@ -0,0 +1,95 @@
+/** @file table_bridge.h
+
+    @brief
+  C accessors for `TABLE`, `TABLE_SHARE` and `Field` members that are only
+  reachable through C++ (virtual methods or subclass fields).
+*/
+
+#pragma once
+
+#include "my_global.h"
+#include "field.h"
+#include "table.h"
+
+/** The type of a field as seen by the storage engine (e.g. `MYSQL_TYPE_VARCHAR`) */
+extern "C" enum enum_field_types table_bridge_field_real_type(const Field*);
+
+/** The type of a field as seen by the SQL layer */
+extern "C" enum enum_field_types table_bridge_field_type(const Field*);
+
+/** Number of bytes this field takes up in a record */
+extern "C" uint32 table_bridge_field_pack_length(const Field*);
+
+/** Number of decimal digits (scale, or fractional seconds for temporal types) */
+extern "C" uint table_bridge_field_decimals(const Field*);
+
+/** Precision of a `DECIMAL` field, 0 for any other type */
+extern "C" uint table_bridge_field_precision(const Field*);
+
+/**
+  Read the integer value of a field stored in `record`, which must be a record
+  buffer of the field's table.
+*/
+extern "C" longlong table_bridge_field_val_int(Field*, const uchar *record);
+
+/** Store an integer value into `record` through the field's `store` method */
+extern "C" int table_bridge_field_store_int(Field*, uchar *record, longlong nr,
+                                            bool unsigned_val);
+
+/**
+  Convert the value of a non-NULL field stored in `record` to text, as `SELECT`
+  would show it, and pass the text to `sink` along with `ctx`.
+*/
+extern "C" void table_bridge_field_val_str(Field*, const uchar *record,
+                                           void (*sink)(void *ctx, const char *str,
+                                                        size_t length),
+                                           void *ctx);
+
+/**
+  Store a text value into `record` through the field's `store_text` method, in
+  the field's character set. Returns nonzero if the value did not fit the field.
+*/
+extern "C" int table_bridge_field_store_text(Field*, uchar *record, const char *str,
+                                             size_t length);
+
+/** Character set name (e.g. `utf8mb4`) of a text field, `NULL` for other fields */
+extern "C" const char *table_bridge_field_charset_name(const Field*);
+
+/** Collation name (e.g. `utf8mb4_general_ci`) of a text field, `NULL` for other fields */
+extern "C" const char *table_bridge_field_collation_name(const Field*);
+
+/**
+  Whether the current statement reads the field at `field_index`, i.e. it is set
+  in `table->read_set`
+*/
+extern "C" bool table_bridge_field_is_read(const TABLE*, uint field_index);
+
+/**
+  Fill in a share from a `CREATE TABLE` statement, for table discovery. If
+  `write` is set, an `.frm` file is also written. Returns nonzero on error.
+*/
+extern "C" int table_bridge_share_init_from_sql(TABLE_SHARE*, THD*, bool write,
+                                                const char *sql, size_t sql_length);
+
+/**
+  Number of bytes of a sort key for the first `parts` parts of an index, as
+  written by `table_bridge_key_sort_key`.
+*/
+extern "C" size_t table_bridge_key_sort_length(const KEY*, uint parts);
+
+/**
+  Write a sort key for the first `parts` parts of an index of the row in
+  `record` to `to`, which must hold `table_bridge_key_sort_length` bytes. Sort
+  keys compare with `memcmp` in index order, following each column's collation,
+  `NULL` first and descending parts reversed.
+*/
+extern "C" void table_bridge_key_sort_key(const KEY*, uint parts, const uchar *record,
+                                          uchar *to);
+
+/**
+  Like `table_bridge_key_sort_key`, but for a search key in key format (as passed
+  to `index_read_map`) made up of the first `parts` parts of the index. Returns
+  an `HA_ERR_*` code.
+*/
+extern "C" int table_bridge_key_sort_key_from_key(KEY*, uint parts, const uchar *key,
+                                                  uchar *to);
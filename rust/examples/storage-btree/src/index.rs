@@ -0,0 +1,139 @@
+//! Index entries and the key ranges used to search them
+//!
+//! An index is a set of entries, each a row's sort key for the index followed by its
+//! row id. Sort keys have a fixed length per index and compare with `memcmp`, so the
+//! set is ordered like the index and rows with equal keys are ordered by row id. A
+//! search key for the first few parts of the index is a prefix of the sort keys it
+//! matches.
+
+use std::collections::BTreeSet;
+use std::ops::Bound;
+
+/// Entries of one index
+pub type Entries = BTreeSet<Vec<u8>>;
+
+/// Length of the row id at the end of each entry
+const ROWID_LEN: usize = 8;
+
+/// The entry for row `rowid` with the sort key `key`. The row id is big endian so
+/// that it sorts numerically.
+pub fn entry(key: &[u8], rowid: u64) -> Vec<u8> {
+    let mut entry = Vec::with_capacity(key.len() + ROWID_LEN);
+    entry.extend_from_slice(key);
+    entry.extend_from_slice(&rowid.to_be_bytes());
+    entry
+}
+
+/// Split an entry into its sort key and row id
+pub fn split_entry(entry: &[u8]) -> (&[u8], u64) {
+    let (key, id) = entry.split_at(entry.len() - ROWID_LEN);
+    (key, u64::from_be_bytes(id.try_into().unwrap()))
+}
+
+/// The row id of an entry
+pub fn rowid(entry: &[u8]) -> u64 {
+    split_entry(entry).1
+}
+
+/// The smallest key greater than every key that starts with `prefix`, or `None` if
+/// there is none (the prefix is empty or all `0xff`)
+pub fn successor(prefix: &[u8]) -> Option<Vec<u8>> {
+    let last = prefix.iter().rposition(|&b| b != u8::MAX)?;
+    let mut key = prefix[..=last].to_vec();
+    key[last] += 1;
+    Some(key)
+}
+
+/// Lower bound of the entries that start with `prefix`, or follow them if `after`
+pub fn lower_bound(prefix: &[u8], after: bool) -> Option<Bound<Vec<u8>>> {
+    if !after {
+        return Some(Bound::Included(prefix.to_vec()));
+    }
+    // Nothing follows a prefix without a successor
+    successor(prefix).map(Bound::Included)
+}
+
+/// Upper bound of the entries that start with `prefix`, or precede them if `before`
+pub fn upper_bound(prefix: &[u8], before: bool) -> Bound<Vec<u8>> {
+    if before {
+        return Bound::Excluded(prefix.to_vec());
+    }
+    successor(prefix).map_or(Bound::Unbounded, Bound::Excluded)
+}
+
+/// The entries between two bounds. Crossed bounds give no entries, rather than the
+/// panic of [`BTreeSet::range`].
+pub fn range(
+    entries: &Entries,
+    lower: Bound<Vec<u8>>,
+    upper: Bound<Vec<u8>>,
+) -> impl DoubleEndedIterator<Item = &Vec<u8>> {
+    let empty = match (&lower, &upper) {
+        (Bound::Included(lo), Bound::Included(hi)) => lo > hi,
+        (Bound::Included(lo) | Bound::Excluded(lo), Bound::Excluded(hi))
+        | (Bound::Excluded(lo), Bound::Included(hi)) => lo >= hi,
+        _ => false,
+    };
+    let range = (!empty).then(|| entries.range((lower, upper)));
+    range.into_iter().flatten()
+}
+
+/// Whether a row other than `except` has the sort key `key`
+pub fn contains_other(entries: &Entries, key: &[u8], except: Option<u64>) -> bool {
+    range(
+        entries,
+        Bound::Included(key.to_vec()),
+        upper_bound(key, false),
+    )
+    .any(|e| Some(rowid(e)) != except)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_successor() {
+        assert_eq!(successor(b"ab"), Some(b"ac".to_vec()));
+        assert_eq!(successor(&[1, 0xff, 0xff]), Some(vec![2]));
+        assert_eq!(successor(&[0xff]), None);
+        assert_eq!(successor(&[]), None);
+    }
+
+    #[test]
+    fn test_ranges() {
+        let entries: Entries = [(b"aa", 1), (b"ab", 2), (b"ab", 3), (b"b\xff", 4)]
+            .iter()
+            .map(|(key, id)| entry(*key, *id))
+            .collect();
+        let ids = |lower, upper| -> Vec<u64> {
+            range(&entries, lower, upper).map(|e| rowid(e)).collect()
+        };
+
+        assert_eq!(rowid(&entry(b"xy", 0x0102)), 0x0102);
+        assert_eq!(split_entry(&entry(b"xy", 7)), (&b"xy"[..], 7));
+        // Entries starting with a prefix, and an empty prefix
+        let ab = || lower_bound(b"ab", false).unwrap();
+        assert_eq!(ids(ab(), upper_bound(b"ab", false)), [2, 3]);
+        assert_eq!(ids(ab(), upper_bound(b"a", false)), [2, 3]);
+        assert_eq!(
+            ids(lower_bound(b"", false).unwrap(), upper_bound(b"", false)),
+            [1, 2, 3, 4]
+        );
+        // After and before a prefix
+        assert_eq!(
+            ids(lower_bound(b"ab", true).unwrap(), Bound::Unbounded),
+            [4]
+        );
+        assert_eq!(ids(Bound::Unbounded, upper_bound(b"ab", true)), [1]);
+        assert_eq!(lower_bound(b"\xff", true), None);
+        // Crossed bounds
+        assert_eq!(ids(ab(), upper_bound(b"aa", false)), []);
+        assert_eq!(ids(ab(), upper_bound(b"ab", true)), []);
+
+        assert!(contains_other(&entries, b"ab", Some(2)));
+        assert!(!contains_other(&entries, b"aa", Some(1)));
+        assert!(contains_other(&entries, b"aa", None));
+        assert!(!contains_other(&entries, b"ac", None));
+    }
+}
@@ -0,0 +1,530 @@
+//! An in-memory storage engine with ordered indexes
+//!
+//! Like the server's `MEMORY` engine, rows only live as long as the server runs:
+//! tables are kept in memory by path, shared by all handlers that open them, and
+//! are empty again after a restart.
+//!
+//! Rows are stored by row id, and each index (`PRIMARY KEY`, `UNIQUE` or plain) is
+//! a [`BTreeSet`] of entries made of the row's sort key for that index and its row
+//! id (see [`index`]). Sort keys follow each column's collation, so the indexes
+//! support every kind of lookup in key order: exact and prefix matches, ranges,
+//! reading backwards and `ORDER BY` without sorting.
+
+mod index;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use mariadb::log::debug;
+use mariadb::plugin::{License, Maturity};
+use mariadb::storage::{
+    CreateInfo, Handler, Handlerton, IndexFlags, IndexableHandler, InfoFlags, KeyFind, KeyRange,
+    Mode, OpenOp, PageRange, Record, RecordMut, SearchKey, StatisticsMut, StorageError,
+    StorageResult, TableFlags, TablePath, ThrLock, ThrLockData, ThrLockType,
+};
+use mariadb::{register_plugin_storage, MemRoot, Table, TableShare, Thd};
+
+use crate::index::Entries;
+
+register_plugin_storage! {
+    name: "EXAMPLE_RUST_BTREE",
+    author: "Trevor Gross",
+    description: "In-memory storage engine with ordered indexes",
+    license: License::Gpl,
+    maturity: Maturity::Experimental,
+    version: "0.1",
+    handlerton: BtreeHton,
+}
+
+/// Most indexes a table may have (`MAX_KEY`)
+const MAX_KEYS: usize = 64;
+
+/// All tables, by path. Tables stay here until they are dropped.
+static SHARES: Mutex<BTreeMap<PathBuf, Arc<BtreeShare>>> = Mutex::new(BTreeMap::new());
+
+struct BtreeHton;
+
+impl Handlerton for BtreeHton {
+    type Handler = BtreeHandler;
+
+    type ConnectionData = ();
+    type SavePoint = ();
+    type TableOptions = ();
+    type FieldOptions = ();
+    type IndexOptions = ();
+}
+
+/// A table's rows and indexes
+struct BtreeShare {
+    lock: Arc<ThrLock>,
+    data: RwLock<TableData>,
+}
+
+struct TableData {
+    /// Rows in the server's record format, by row id
+    rows: BTreeMap<u64, Box<[u8]>>,
+    /// Row id of the next inserted row
+    next_id: u64,
+    /// Entries of each index, in the order of `Table::keys`
+    indexes: Vec<Entries>,
+}
+
+impl BtreeShare {
+    fn new(n_keys: usize) -> Arc<Self> {
+        Arc::new(Self {
+            lock: Arc::new(ThrLock::new()),
+            data: RwLock::new(TableData {
+                rows: BTreeMap::new(),
+                next_id: 0,
+                indexes: vec![BTreeSet::new(); n_keys],
+            }),
+        })
+    }
+
+    /// Find the table at `path`. A table that is not in memory, e.g. after a restart,
+    /// is empty.
+    fn get(path: &Path, n_keys: usize) -> Arc<Self> {
+        let mut shares = SHARES.lock().unwrap();
+        let share = shares
+            .entry(path.to_owned())
+            .or_insert_with(|| Self::new(n_keys));
+        Arc::clone(share)
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, TableData> {
+        self.data.read().unwrap()
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, TableData> {
+        self.data.write().unwrap()
+    }
+}
+
+impl TableData {
+    /// The entries of row `rowid` in each index. Every row is in every index, rows
+    /// with `NULL` key parts sorting first.
+    fn entries(row: &Record, rowid: u64) -> Vec<Vec<u8>> {
+        (0..row.table().keys().len())
+            .map(|idx| index::entry(&row.sort_key(idx), rowid))
+            .collect()
+    }
+
+    /// Find a unique index that already has the key of `row` for a row other than
+    /// `rowid`. Keys with a `NULL` part never conflict.
+    fn find_duplicate(&self, row: &Record, entries: &[Vec<u8>], rowid: u64) -> Option<usize> {
+        let keys = row.table().keys();
+        (0..keys.len()).find(|&idx| {
+            let key = &keys[idx];
+            if !key.is_unique()
+                || key
+                    .parts()
+                    .iter()
+                    .any(|p| p.field_index().is_some_and(|idx| row.is_null(idx)))
+            {
+                return false;
+            }
+            let (sort_key, _) = index::split_entry(&entries[idx]);
+            index::contains_other(&self.indexes[idx], sort_key, Some(rowid))
+        })
+    }
+
+    fn insert_entries(&mut self, entries: Vec<Vec<u8>>) {
+        for (index, entry) in self.indexes.iter_mut().zip(entries) {
+            index.insert(entry);
+        }
+    }
+
+    fn remove_entries(&mut self, entries: &[Vec<u8>]) {
+        for (index, entry) in self.indexes.iter_mut().zip(entries) {
+            index.remove(entry);
+        }
+    }
+
+    /// Bytes used by rows
+    fn data_length(&self) -> u64 {
+        self.rows.values().map(|row| row.len() as u64).sum()
+    }
+
+    /// Bytes used by index entries
+    fn index_length(&self) -> u64 {
+        self.indexes
+            .iter()
+            .flatten()
+            .map(|entry| entry.len() as u64)
+            .sum()
+    }
+}
+
+struct BtreeHandler {
+    share: Option<Arc<BtreeShare>>,
+    lock_data: Option<ThrLockData>,
+    /// Row id from which a table scan continues
+    scan_next: u64,
+    /// Row id of the row last read, for `position`, `update_row` and `delete_row`
+    current: Option<u64>,
+    /// Index being read, between `index_init` and `index_end`
+    active_index: Option<usize>,
+    /// Entry of the active index last read. The next read continues from it, so
+    /// the scan stays valid while rows are changed.
+    cursor: Option<Vec<u8>>,
+    /// Index of the last duplicate key error
+    dup_key: Option<usize>,
+}
+
+impl BtreeHandler {
+    /// The open table. This is a new reference, so that `self` can still be changed
+    /// while the table is locked.
+    fn share(&self) -> Arc<BtreeShare> {
+        Arc::clone(self.share.as_ref().expect("table is open"))
+    }
+
+    fn active_index(&self) -> usize {
+        self.active_index.expect("index is initialized")
+    }
+
+    /// Copy row `rowid` into `buf` and make it the current row
+    fn read_row(&mut self, data: &TableData, rowid: u64, buf: &mut RecordMut) -> StorageResult {
+        let row = data.rows.get(&rowid).ok_or(StorageError::KeyNotFound)?;
+        buf.as_bytes_mut().copy_from_slice(row);
+        self.current = Some(rowid);
+        Ok(())
+    }
+
+    /// Read the first entry of the active index within `lower` and `upper` (the last
+    /// one if `last` is set) and its row, or return `not_found` if there is none
+    fn read_index(
+        &mut self,
+        buf: &mut RecordMut,
+        lower: Bound<Vec<u8>>,
+        upper: Bound<Vec<u8>>,
+        last: bool,
+        not_found: StorageError,
+    ) -> StorageResult {
+        let share = self.share();
+        let data = share.read();
+        let entries = &data.indexes[self.active_index()];
+        let mut range = index::range(entries, lower, upper);
+        let found = if last {
+            range.next_back()
+        } else {
+            range.next()
+        };
+        let Some(entry) = found.cloned() else {
+            return Err(not_found);
+        };
+
+        self.read_row(&data, index::rowid(&entry), buf)?;
+        self.cursor = Some(entry);
+        Ok(())
+    }
+}
+
+impl Handler for BtreeHandler {
+    type Handlerton = BtreeHton;
+
+    const SUPPORTS_INDEX: bool = true;
+
+    const TABLE_FLAGS: TableFlags = TableFlags::NO_TRANSACTIONS
+        .union(TableFlags::REC_NOT_IN_SEQ)
+        .union(TableFlags::NO_BLOBS)
+        .union(TableFlags::NULL_IN_KEY)
+        .union(TableFlags::NO_PREFIX_CHAR_KEYS)
+        .union(TableFlags::STATS_RECORDS_IS_EXACT)
+        .union(TableFlags::BINLOG_ROW_CAPABLE)
+        .union(TableFlags::BINLOG_STMT_CAPABLE);
+
+    fn new(_table: Option<&TableShare>, _mem_root: MemRoot) -> Self {
+        Self {
+            share: None,
+            lock_data: None,
+            scan_next: 0,
+            current: None,
+            active_index: None,
+            cursor: None,
+            dup_key: None,
+        }
+    }
+
+    fn create(&mut self, name: &Path, form: &Table, _create_info: &CreateInfo) -> StorageResult {
+        // Replace anything left by a table that had the same name
+        let share = BtreeShare::new(form.keys().len());
+        SHARES.lock().unwrap().insert(name.to_owned(), share);
+        Ok(())
+    }
+
+    fn open(
+        &mut self,
+        name: &Path,
+        table: &Table,
+        _mode: Mode,
+        _open_options: OpenOp,
+    ) -> StorageResult {
+        let share = BtreeShare::get(name, table.keys().len());
+        debug!("btree: opened {}", name.display());
+        self.lock_data = Some(ThrLockData::new(Arc::clone(&share.lock)));
+        self.share = Some(share);
+        Ok(())
+    }
+
+    fn close(&mut self) -> StorageResult {
+        self.lock_data = None;
+        self.share = None;
+        Ok(())
+    }
+
+    fn write_row(&mut self, buf: &Record) -> StorageResult {
+        let share = self.share();
+        let mut data = share.write();
+        let rowid = data.next_id;
+        let entries = TableData::entries(buf, rowid);
+        if let Some(idx) = data.find_duplicate(buf, &entries, rowid) {
+            self.dup_key = Some(idx);
+            return Err(StorageError::FoundDuppKey);
+        }
+
+        data.next_id += 1;
+        data.rows.insert(rowid, buf.as_bytes().into());
+        data.insert_entries(entries);
+        Ok(())
+    }
+
+    fn update_row(&mut self, old_data: &Record, new_data: &Record) -> StorageResult {
+        let rowid = self.current.expect("a row was read");
+        let share = self.share();
+        let mut data = share.write();
+        let entries = TableData::entries(new_data, rowid);
+        if let Some(idx) = data.find_duplicate(new_data, &entries, rowid) {
+            self.dup_key = Some(idx);
+            return Err(StorageError::FoundDuppKey);
+        }
+
+        data.remove_entries(&TableData::entries(old_data, rowid));
+        data.insert_entries(entries);
+        data.rows.insert(rowid, new_data.as_bytes().into());
+        Ok(())
+    }
+
+    fn delete_row(&mut self, buf: &Record) -> StorageResult {
+        let rowid = self.current.expect("a row was read");
+        let share = self.share();
+        let mut data = share.write();
+        data.remove_entries(&TableData::entries(buf, rowid));
+        data.rows.remove(&rowid);
+        Ok(())
+    }
+
+    fn delete_all_rows(&mut self) -> StorageResult {
+        let share = self.share();
+        let mut data = share.write();
+        data.rows.clear();
+        data.indexes.iter_mut().for_each(BTreeSet::clear);
+        Ok(())
+    }
+
+    fn rnd_init(&mut self, _scan: bool) -> StorageResult {
+        self.scan_next = 0;
+        self.current = None;
+        Ok(())
+    }
+
+    fn rnd_next(&mut self, buf: &mut RecordMut) -> StorageResult {
+        let share = self.share();
+        let data = share.read();
+        let Some((&rowid, _)) = data.rows.range(self.scan_next..).next() else {
+            return Err(StorageError::EndOfFile);
+        };
+        self.scan_next = rowid + 1;
+        self.read_row(&data, rowid, buf)
+    }
+
+    fn rnd_pos(&mut self, buf: &mut RecordMut, pos: &[u8]) -> StorageResult {
+        let rowid = u64::from_le_bytes(pos.try_into().unwrap());
+        let share = self.share();
+        let data = share.read();
+        self.read_row(&data, rowid, buf)
+    }
+
+    fn position(&mut self, _row: &Record, pos: &mut [u8]) {
+        let rowid = self.current.expect("a row was read");
+        pos.copy_from_slice(&rowid.to_le_bytes());
+    }
+
+    fn info(&mut self, _flags: InfoFlags, stats: &mut StatisticsMut) -> StorageResult {
+        let share = self.share();
+        let data = share.read();
+        stats.set_records(data.rows.len() as u64);
+        stats.set_data_file_length(data.data_length());
+        stats.set_index_file_length(data.index_length());
+        Ok(())
+    }
+
+    fn delete_table(&mut self, path: &TablePath) -> StorageResult {
+        SHARES.lock().unwrap().remove(path.path());
+        Ok(())
+    }
+
+    fn rename_table(&mut self, from: &TablePath, to: &TablePath) -> StorageResult {
+        // A table that is not in memory is empty, and stays so under its new name
+        let mut shares = SHARES.lock().unwrap();
+        if let Some(share) = shares.remove(from.path()) {
+            shares.insert(to.path().to_owned(), share);
+        }
+        Ok(())
+    }
+
+    fn store_lock(&mut self, _thd: &Thd, lock_type: ThrLockType) -> Option<&mut ThrLockData> {
+        let lock_data = self.lock_data.as_mut()?;
+        lock_data.request(lock_type);
+        Some(lock_data)
+    }
+}
+
+impl IndexableHandler for BtreeHandler {
+    fn max_supported_keys(&self) -> usize {
+        MAX_KEYS
+    }
+
+    fn index_flags(&self, _index: usize, _part: usize, _all_parts: bool) -> IndexFlags {
+        IndexFlags::READ_NEXT
+            .union(IndexFlags::READ_PREV)
+            .union(IndexFlags::READ_ORDER)
+            .union(IndexFlags::READ_RANGE)
+    }
+
+    /// Count the entries in the range. This is exact, but takes time proportional to
+    /// the size of the range.
+    fn records_in_range(
+        &mut self,
+        index: usize,
+        min: Option<&KeyRange>,
+        max: Option<&KeyRange>,
+        _pages: &mut PageRange,
+    ) -> u64 {
+        let bound = |range: Option<&KeyRange>| match range {
+            Some(range) => range.key().sort_key().map(|key| Some((key, range.find()))),
+            None => Ok(None),
+        };
+        let (Ok(min), Ok(max)) = (bound(min), bound(max)) else {
+            // Any estimate will do
+            return 10;
+        };
+
+        let lower = match min {
+            None => Some(Bound::Unbounded),
+            Some((key, find)) => index::lower_bound(&key, find == KeyFind::After),
+        };
+        let upper = match max {
+            None => Bound::Unbounded,
+            Some((key, find)) => index::upper_bound(&key, find == KeyFind::Before),
+        };
+        let Some(lower) = lower else {
+            return 0;
+        };
+
+        let share = self.share();
+        let data = share.read();
+        index::range(&data.indexes[index], lower, upper).count() as u64
+    }
+
+    fn index_init(&mut self, index: usize, _sorted: bool) -> StorageResult {
+        self.active_index = Some(index);
+        self.cursor = None;
+        Ok(())
+    }
+
+    fn index_end(&mut self) -> StorageResult {
+        self.active_index = None;
+        self.cursor = None;
+        Ok(())
+    }
+
+    fn index_read_map(
+        &mut self,
+        buf: &mut RecordMut,
+        key: &SearchKey,
+        find: KeyFind,
+    ) -> StorageResult {
+        let prefix = key.sort_key()?;
+        let (lower, upper, last, not_found) = match find {
+            KeyFind::Exact | KeyFind::Prefix => (
+                index::lower_bound(&prefix, false),
+                index::upper_bound(&prefix, false),
+                false,
+                StorageError::KeyNotFound,
+            ),
+            KeyFind::PrefixLast => (
+                index::lower_bound(&prefix, false),
+                index::upper_bound(&prefix, false),
+                true,
+                StorageError::KeyNotFound,
+            ),
+            KeyFind::OrNext => (
+                index::lower_bound(&prefix, false),
+                Bound::Unbounded,
+                false,
+                StorageError::EndOfFile,
+            ),
+            KeyFind::After => (
+                index::lower_bound(&prefix, true),
+                Bound::Unbounded,
+                false,
+                StorageError::EndOfFile,
+            ),
+            KeyFind::OrPrev | KeyFind::PrefixLastOrPrev => (
+                Some(Bound::Unbounded),
+                index::upper_bound(&prefix, false),
+                true,
+                StorageError::EndOfFile,
+            ),
+            KeyFind::Before => (
+                Some(Bound::Unbounded),
+                index::upper_bound(&prefix, true),
+                true,
+                StorageError::EndOfFile,
+            ),
+            KeyFind::MbrContain
+            | KeyFind::MbrIntersect
+            | KeyFind::MbrWithin
+            | KeyFind::MbrDisjoint
+            | KeyFind::MbrEqual => return Err(StorageError::Unsupported),
+        };
+
+        // A lower bound past every key matches nothing
+        let Some(lower) = lower else {
+            return Err(not_found);
+        };
+        self.read_index(buf, lower, upper, last, not_found)
+    }
+
+    fn index_next(&mut self, buf: &mut RecordMut) -> StorageResult {
+        let lower = match self.cursor.clone() {
+            Some(cursor) => Bound::Excluded(cursor),
+            None => Bound::Unbounded,
+        };
+        self.read_index(buf, lower, Bound::Unbounded, false, StorageError::EndOfFile)
+    }
+
+    fn index_prev(&mut self, buf: &mut RecordMut) -> StorageResult {
+        let upper = match self.cursor.clone() {
+            Some(cursor) => Bound::Excluded(cursor),
+            None => Bound::Unbounded,
+        };
+        self.read_index(buf, Bound::Unbounded, upper, true, StorageError::EndOfFile)
+    }
+
+    fn index_first(&mut self, buf: &mut RecordMut) -> StorageResult {
+        let all = Bound::Unbounded;
+        self.read_index(buf, all.clone(), all, false, StorageError::EndOfFile)
+    }
+
+    fn index_last(&mut self, buf: &mut RecordMut) -> StorageResult {
+        let all = Bound::Unbounded;
+        self.read_index(buf, all.clone(), all, true, StorageError::EndOfFile)
+    }
+
+    fn dup_key(&self) -> Option<usize> {
+        self.dup_key
+    }
+}
@@ -0,0 +1,135 @@
+//! The row format of the data file, compatible with the server's `CSV` engine
+//!
+//! Each row is a line of comma separated fields. Numbers are written as is; other
+//! values are quoted, with `"`, `\`, carriage returns and newlines escaped by a
+//! backslash.
+
+/// Append `value` to `line` as a field, quoted if `quote` is set
+pub fn encode_field(line: &mut Vec<u8>, value: &[u8], quote: bool) {
+    if !quote {
+        line.extend_from_slice(value);
+        return;
+    }
+
+    line.reserve(value.len() + 2);
+    line.push(b'"');
+    for &b in value {
+        match b {
+            b'"' => line.extend_from_slice(b"\\\""),
+            b'\\' => line.extend_from_slice(b"\\\\"),
+            b'\r' => line.extend_from_slice(b"\\r"),
+            b'\n' => line.extend_from_slice(b"\\n"),
+            _ => line.push(b),
+        }
+    }
+    line.push(b'"');
+}
+
+/// Remove the line ending (`\n` or `\r\n`) from a line read from the data file.
+/// Returns `None` if there is none, i.e. the last row was cut off.
+pub fn strip_eol(line: &[u8]) -> Option<&[u8]> {
+    let line = line.strip_suffix(b"\n")?;
+    Some(line.strip_suffix(b"\r").unwrap_or(line))
+}
+
+/// Split a line (without its line ending) into `n_fields` unescaped values, stored
+/// in `fields`. Returns false if the line is malformed or has the wrong number of
+/// fields.
+pub fn parse_row(line: &[u8], n_fields: usize, fields: &mut Vec<Vec<u8>>) -> bool {
+    fields.resize_with(n_fields, Vec::new);
+    let mut rest = line;
+
+    for (idx, field) in fields.iter_mut().enumerate() {
+        field.clear();
+        let Some(len) = parse_field(rest, field) else {
+            return false;
+        };
+        rest = &rest[len..];
+
+        let last = idx + 1 == n_fields;
+        match (last, rest.split_first()) {
+            (true, None) => (),
+            (false, Some((b',', tail))) => rest = tail,
+            _ => return false,
+        }
+    }
+
+    true
+}
+
+/// Unescape the field at the start of `data` into `out`. Returns the number of
+/// bytes used, not including a following comma.
+fn parse_field(data: &[u8], out: &mut Vec<u8>) -> Option<usize> {
+    let quoted = data.first() == Some(&b'"');
+    let mut pos = usize::from(quoted);
+
+    loop {
+        let Some(&b) = data.get(pos) else {
+            // A quoted field must be closed before the end of the line
+            return (!quoted).then_some(pos);
+        };
+
+        // A quote only ends the field if a comma or the end of the line follows it;
+        // otherwise it is part of the value, as in files written by other programs
+        if quoted && b == b'"' && matches!(data.get(pos + 1), None | Some(b',')) {
+            return Some(pos + 1);
+        }
+        if !quoted && b == b',' {
+            return Some(pos);
+        }
+
+        if b == b'\\' && pos + 1 < data.len() {
+            pos += 1;
+            match data[pos] {
+                b'r' => out.push(b'\r'),
+                b'n' => out.push(b'\n'),
+                c @ (b'"' | b'\\') => out.push(c),
+                c => out.extend_from_slice(&[b'\\', c]),
+            }
+        } else {
+            out.push(b);
+        }
+        pos += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let values: [&[u8]; 5] = [b"12", b"plain", b"a \"quoted\", value", b"", b"x\\y\r\nz"];
+        let mut line = Vec::new();
+        for (idx, value) in values.iter().enumerate() {
+            if idx > 0 {
+                line.push(b',');
+            }
+            encode_field(&mut line, value, idx > 0);
+        }
+        assert_eq!(
+            line,
+            b"12,\"plain\",\"a \\\"quoted\\\", value\",\"\",\"x\\\\y\\r\\nz\""
+        );
+
+        let mut fields = Vec::new();
+        assert!(parse_row(&line, values.len(), &mut fields));
+        assert_eq!(fields, values);
+    }
+
+    #[test]
+    fn test_parse() {
+        let mut fields = Vec::new();
+        assert!(parse_row(b"1,,\"a\"b\"", 3, &mut fields));
+        assert_eq!(fields, [&b"1"[..], b"", b"a\"b"]);
+
+        // Wrong number of fields, unterminated quote
+        assert!(!parse_row(b"1,2", 3, &mut fields));
+        assert!(!parse_row(b"1,2,3,4", 3, &mut fields));
+        assert!(!parse_row(b"1,\"abc", 2, &mut fields));
+
+        assert_eq!(strip_eol(b"1,2\r\n"), Some(&b"1,2"[..]));
+        assert_eq!(strip_eol(b"1,2\n"), Some(&b"1,2"[..]));
+        assert_eq!(strip_eol(b"1,2"), None);
+    }
+}
@@ -1,46 +1,612 @@
-#![allow(unused)]
+//! A port of the server's `CSV` engine (`ha_tina`)
+//!
+//! Each table is stored as a text file `<name>.CSV` with one row per line, which
+//! other programs can read and write, plus a meta file `<name>.CSM` (see [`meta`]).
+//! There are no indexes; all reads are table scans.
+//!
+//! Rows are only ever appended. `UPDATE` and `DELETE` note which rows were
+//! removed while scanning, and rewrite the file without them when the scan ends.
+//!
+//! The meta file is marked dirty while the table is open for writing. If it is
+//! still dirty when the table is opened, the table is considered crashed until
+//! `REPAIR TABLE` keeps the rows up to the first damaged one.
 
-use std::ffi::{c_int, CStr};
-use std::path::Path;
+mod csv;
+mod meta;
 
-use mariadb::log::debug;
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use mariadb::log::{debug, warn};
 use mariadb::plugin::{License, Maturity};
-use mariadb::storage::{Handler, Handlerton, Mode, OpenOp, StorageResult};
-use mariadb::{register_plugin_storage, MemRoot, TableShare};
+use mariadb::storage::{
+    AdminMessages, AdminResult, CheckOptions, CreateInfo, Handler, Handlerton, InfoFlags, Mode,
+    OpenOp, Record, RecordMut, RepairingHandler, StatisticsMut, StorageError, StorageResult,
+    TableFlags, TablePath, ThrLock, ThrLockData, ThrLockType,
+};
+use mariadb::{register_plugin_storage, FieldType, MemRoot, Table, TableShare, Thd};
+
+use crate::meta::Meta;
 
 register_plugin_storage! {
     name: "EXAMPLE_RUST_CSV",
     author: "Trevor Gross",
-    description: "Reimplementation of ha_tina for debugging",
+    description: "CSV storage engine, a port of ha_tina",
     license: License::Gpl,
     maturity: Maturity::Experimental,
     version: "0.1",
-    handlerton: ExampleHton,
+    handlerton: CsvHton,
 }
 
-struct ExampleHton;
-struct ExampleHandler {}
+/// Extension of the data file
+const DATA_EXT: &str = ".CSV";
+/// Extension of the meta file
+const META_EXT: &str = ".CSM";
+/// Extension of the temporary file used while rewriting the data file
+const NEW_DATA_EXT: &str = ".CSN";
+
+/// Tables that are open in at least one handler, by path
+static SHARES: Mutex<BTreeMap<PathBuf, Arc<CsvShare>>> = Mutex::new(BTreeMap::new());
 
-impl Handlerton for ExampleHton {
-    type Handler = ExampleHandler;
+struct CsvHton;
 
+impl Handlerton for CsvHton {
+    type Handler = CsvHandler;
+
+    type ConnectionData = ();
     type SavePoint = ();
+    type TableOptions = ();
+    type FieldOptions = ();
+    type IndexOptions = ();
+
+    const TABLEFILE_EXTENSIONS: &'static [&'static str] = &[DATA_EXT, META_EXT];
+}
+
+/// State shared by all handlers that have the same table open
+struct CsvShare {
+    data_path: PathBuf,
+    meta_path: PathBuf,
+    new_data_path: PathBuf,
+    lock: Arc<ThrLock>,
+    state: Mutex<ShareState>,
+}
+
+struct ShareState {
+    /// Number of handlers that have the table open
+    use_count: usize,
+    /// Number of rows in the data file
+    rows: u64,
+    /// The meta file is marked dirty, because the data file is being written or the
+    /// table is crashed
+    dirty: bool,
+    /// The table must be repaired before it can be used
+    crashed: bool,
+    /// Appends rows to the data file, opened by the first write
+    writer: Option<File>,
+}
+
+impl CsvShare {
+    /// Find the share for the table at `path`, or read its meta file if no handler
+    /// has it open yet
+    fn get(path: &Path) -> StorageResult<Arc<Self>> {
+        let mut shares = SHARES.lock().unwrap();
+        if let Some(share) = shares.get(path) {
+            share.state().use_count += 1;
+            return Ok(Arc::clone(share));
+        }
+
+        let table_path = TablePath::new(path);
+        let meta_path = table_path.with_extension(META_EXT);
+        // A missing or damaged meta file is recreated by `REPAIR TABLE`
+        let (rows, crashed) = match Meta::read(&meta_path) {
+            Ok(meta) => (meta.rows, meta.dirty),
+            Err(e) => {
+                warn!("csv: bad meta file {}: {e}", meta_path.display());
+                (0, true)
+            }
+        };
+
+        let share = Arc::new(Self {
+            data_path: table_path.with_extension(DATA_EXT),
+            meta_path,
+            new_data_path: table_path.with_extension(NEW_DATA_EXT),
+            lock: Arc::new(ThrLock::new()),
+            state: Mutex::new(ShareState {
+                use_count: 1,
+                rows,
+                dirty: crashed,
+                crashed,
+                writer: None,
+            }),
+        });
+        shares.insert(path.to_owned(), Arc::clone(&share));
+        Ok(share)
+    }
+
+    /// Release a handler's use of the share. The last one writes the final row count
+    /// to the meta file.
+    fn release(self: Arc<Self>) -> StorageResult {
+        let mut shares = SHARES.lock().unwrap();
+        let mut state = self.state();
+        state.use_count -= 1;
+        if state.use_count > 0 {
+            return Ok(());
+        }
+
+        shares.retain(|_, share| !Arc::ptr_eq(share, &self));
+        state.writer = None;
+        if state.dirty {
+            state.dirty = state.crashed;
+            self.save_meta(&state)?;
+        }
+        Ok(())
+    }
+
+    fn state(&self) -> MutexGuard<'_, ShareState> {
+        self.state.lock().unwrap()
+    }
+
+    /// Write the row count and dirty flag to the meta file
+    fn save_meta(&self, state: &ShareState) -> StorageResult {
+        let meta = Meta {
+            rows: state.rows,
+            dirty: state.dirty,
+        };
+        meta.write(&self.meta_path).map_err(io_error)
+    }
+
+    /// Append `line` to the data file. The first write marks the meta file dirty.
+    fn append(&self, line: &[u8]) -> StorageResult {
+        let mut state = self.state();
+        if !state.dirty {
+            state.dirty = true;
+            self.save_meta(&state)?;
+        }
+        if state.writer.is_none() {
+            let file = OpenOptions::new()
+                .append(true)
+                .open(&self.data_path)
+                .map_err(io_error)?;
+            state.writer = Some(file);
+        }
+        state
+            .writer
+            .as_mut()
+            .unwrap()
+            .write_all(line)
+            .map_err(io_error)
+    }
+
+    /// Replace the data file with the bytes of the current one that `keep` selects,
+    /// by writing a new file and renaming it over the old one
+    fn rewrite(&self, keep: impl FnOnce(&mut File, &mut File) -> io::Result<()>) -> StorageResult {
+        let new_path = &self.new_data_path;
+        let mut state = self.state();
+        // Close the append handle before the rename; `append` reopens `data_path`
+        state.writer = None;
+
+        let res = (|| {
+            let mut old = File::open(&self.data_path)?;
+            let mut new = File::create(new_path)?;
+            keep(&mut old, &mut new)?;
+            new.sync_data()?;
+            fs::rename(new_path, &self.data_path)
+        })();
+        if res.is_err() {
+            // The `.CSV` file was not replaced, only the partial copy is left to clean up
+            let _ = fs::remove_file(new_path);
+        }
+        res.map_err(io_error)
+    }
+}
+
+/// Reads lines of the data file, up to its length when the reader was opened so
+/// that rows appended during a scan (e.g. by `UPDATE`) are not seen again
+struct RowReader {
+    file: BufReader<File>,
+    /// Offset of the next byte `file` will return
+    pos: u64,
+    end: u64,
+    line: Vec<u8>,
+}
+
+impl RowReader {
+    fn open(path: &Path) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let end = file.metadata()?.len();
+        Ok(Self {
+            file: BufReader::new(file),
+            pos: 0,
+            end,
+            line: Vec::new(),
+        })
+    }
+
+    /// Read the line starting at `pos`, including its line ending. Returns `None` at
+    /// the end of the file.
+    fn read_line(&mut self, pos: u64) -> io::Result<Option<&[u8]>> {
+        if pos >= self.end {
+            return Ok(None);
+        }
+        if pos != self.pos {
+            self.file.seek(SeekFrom::Start(pos))?;
+            self.pos = pos;
+        }
+
+        self.line.clear();
+        let len = (&mut self.file)
+            .take(self.end - pos)
+            .read_until(b'\n', &mut self.line)?;
+        self.pos += len as u64;
+        Ok((len > 0).then_some(&self.line[..]))
+    }
+}
+
+struct CsvHandler {
+    /// Number of columns in the table, set by `open`
+    n_fields: usize,
+    share: Option<Arc<CsvShare>>,
+    lock_data: Option<ThrLockData>,
+    reader: Option<RowReader>,
+    /// Offset of the row last read
+    current_pos: u64,
+    /// Offset of the row after it
+    next_pos: u64,
+    /// Rows removed by `update_row` and `delete_row` during the current scan
+    deleted: Vec<Range<u64>>,
+    /// Values of the row last read. Blob columns of the record point into these.
+    fields: Vec<Vec<u8>>,
+    /// Buffer for encoding a row
+    line: Vec<u8>,
+    /// A full scan counted the rows, so the row count is exact
+    records_is_known: bool,
 }
 
-impl Handler for ExampleHandler {
-    type Handlerton = ExampleHton;
+impl CsvHandler {
+    fn share(&self) -> &CsvShare {
+        self.share.as_deref().expect("table is open")
+    }
+
+    /// Read the row at `pos` into `buf`
+    fn read_row(&mut self, pos: u64, buf: &mut RecordMut) -> StorageResult {
+        let reader = self.reader.as_mut().expect("scan is initialized");
+        let Some(line) = reader.read_line(pos).map_err(io_error)? else {
+            return Err(StorageError::EndOfFile);
+        };
+        self.current_pos = pos;
+        self.next_pos = pos + line.len() as u64;
+
+        let row = csv::strip_eol(line).ok_or(StorageError::CrashedOnUsage)?;
+        if !csv::parse_row(row, self.n_fields, &mut self.fields) {
+            return Err(StorageError::CrashedOnUsage);
+        }
+
+        let table = buf.table();
+        let null_bytes = table.share().null_bytes();
+        buf.as_bytes_mut()[..null_bytes].fill(0);
+        for (idx, value) in self.fields.iter().enumerate() {
+            // As in `ha_tina`, `ENUM` values not in the list are read as the empty value
+            if buf.set_text(idx, value).is_err()
+                && table.fields()[idx].field_type() != FieldType::Enum
+            {
+                return Err(StorageError::CrashedOnUsage);
+            }
+        }
+        Ok(())
+    }
+
+    /// Encode `row` as a line into `self.line`
+    fn encode_row(&mut self, row: &Record) {
+        self.line.clear();
+        for (idx, field) in row.table().fields().iter().enumerate() {
+            if idx > 0 {
+                self.line.push(b',');
+            }
+            // CSV columns are `NOT NULL`, so there is always a value
+            csv::encode_field(
+                &mut self.line,
+                &row.get_text(idx).unwrap_or_default(),
+                needs_quotes(field.field_type()),
+            );
+        }
+        self.line.push(b'\n');
+    }
 
-    // #[mariadb::dbug::instrument]
-    fn new(table: &TableShare, mem_root: MemRoot) -> Self {
-        Self {}
+    /// Scan the data file up to the first damaged row. Returns the number of valid
+    /// rows, the offset after the last one, and whether the whole file was valid.
+    fn scan_valid_rows(&self) -> StorageResult<(u64, u64, bool)> {
+        let mut reader = RowReader::open(&self.share().data_path).map_err(io_error)?;
+        let mut fields = Vec::new();
+        let (mut rows, mut pos) = (0, 0);
+        while let Some(line) = reader.read_line(pos).map_err(io_error)? {
+            let valid = csv::strip_eol(line)
+                .is_some_and(|row| csv::parse_row(row, self.n_fields, &mut fields));
+            if !valid {
+                return Ok((rows, pos, false));
+            }
+            rows += 1;
+            pos += line.len() as u64;
+        }
+        Ok((rows, pos, true))
     }
+}
+
+impl Handler for CsvHandler {
+    type Handlerton = CsvHton;
 
-    fn open(name: &Path, mode: Mode, open_options: OpenOp) -> StorageResult {
-        // init share
-        todo!()
+    const SUPPORTS_REPAIR: bool = true;
+
+    const TABLE_FLAGS: TableFlags = TableFlags::NO_TRANSACTIONS
+        .union(TableFlags::REC_NOT_IN_SEQ)
+        .union(TableFlags::NO_AUTO_INCREMENT)
+        .union(TableFlags::BINLOG_ROW_CAPABLE)
+        .union(TableFlags::BINLOG_STMT_CAPABLE)
+        .union(TableFlags::CAN_REPAIR)
+        .union(TableFlags::SLOW_RND_POS);
+
+    fn new(_table: Option<&TableShare>, _mem_root: MemRoot) -> Self {
+        Self {
+            n_fields: 0,
+            share: None,
+            lock_data: None,
+            reader: None,
+            current_pos: 0,
+            next_pos: 0,
+            deleted: Vec::new(),
+            fields: Vec::new(),
+            line: Vec::new(),
+            records_is_known: false,
+        }
     }
 
-    fn write_row(buf: &CStr) {
-        // Nothing to do here
+    fn create(&mut self, name: &Path, form: &Table, _create_info: &CreateInfo) -> StorageResult {
+        // There is no way to write `NULL` that can be told apart from a string
+        if let Some(field) = form.fields().iter().find(|f| f.is_nullable()) {
+            return Err(StorageError::custom(format!(
+                "column '{}' is nullable; the CSV engine only supports NOT NULL columns",
+                field.name()
+            )));
+        }
+
+        let path = TablePath::new(name);
+        Meta::default()
+            .write(&path.with_extension(META_EXT))
+            .map_err(io_error)?;
+        File::create(path.with_extension(DATA_EXT)).map_err(io_error)?;
+        Ok(())
+    }
+
+    fn open(
+        &mut self,
+        name: &Path,
+        table: &Table,
+        _mode: Mode,
+        open_options: OpenOp,
+    ) -> StorageResult {
+        let share = CsvShare::get(name)?;
+        if share.state().crashed && !open_options.contains(OpenOp::FOR_REPAIR) {
+            share.release()?;
+            return Err(StorageError::CrashedOnUsage);
+        }
+
+        debug!("csv: opened {}", name.display());
+        self.n_fields = table.fields().len();
+        self.lock_data = Some(ThrLockData::new(Arc::clone(&share.lock)));
+        self.share = Some(share);
+        self.records_is_known = false;
+        Ok(())
+    }
+
+    fn close(&mut self) -> StorageResult {
+        self.reader = None;
+        self.lock_data = None;
+        match self.share.take() {
+            Some(share) => share.release(),
+            None => Ok(()),
+        }
+    }
+
+    fn write_row(&mut self, buf: &Record) -> StorageResult {
+        self.encode_row(buf);
+        let share = self.share();
+        share.append(&self.line)?;
+        share.state().rows += 1;
+        Ok(())
+    }
+
+    fn update_row(&mut self, _old_data: &Record, new_data: &Record) -> StorageResult {
+        // Remove the old row when the scan ends, and add the new one at the end
+        self.deleted.push(self.current_pos..self.next_pos);
+        self.encode_row(new_data);
+        let share = self.share();
+        share.append(&self.line)
+    }
+
+    fn delete_row(&mut self, _buf: &Record) -> StorageResult {
+        self.deleted.push(self.current_pos..self.next_pos);
+        let mut state = self.share().state();
+        state.rows = state.rows.saturating_sub(1);
+        Ok(())
+    }
+
+    fn delete_all_rows(&mut self) -> StorageResult {
+        let share = self.share();
+        let mut state = share.state();
+        File::options()
+            .write(true)
+            .open(&share.data_path)
+            .and_then(|f| f.set_len(0))
+            .map_err(io_error)?;
+        state.rows = 0;
+        share.save_meta(&state)?;
+        drop(state);
+        self.records_is_known = true;
+        Ok(())
+    }
+
+    fn rnd_init(&mut self, _scan: bool) -> StorageResult {
+        let share = self.share();
+        if share.state().crashed {
+            return Err(StorageError::CrashedOnUsage);
+        }
+
+        self.reader = Some(RowReader::open(&share.data_path).map_err(io_error)?);
+        self.current_pos = 0;
+        self.next_pos = 0;
+        self.deleted.clear();
+        Ok(())
+    }
+
+    fn rnd_next(&mut self, buf: &mut RecordMut) -> StorageResult {
+        let res = self.read_row(self.next_pos, buf);
+        if matches!(res, Err(StorageError::EndOfFile)) {
+            // Every row could be read, so the count is right
+            self.records_is_known = true;
+        }
+        res
+    }
+
+    fn rnd_pos(&mut self, buf: &mut RecordMut, pos: &[u8]) -> StorageResult {
+        let pos = u64::from_le_bytes(pos.try_into().unwrap());
+        self.read_row(pos, buf)
+    }
+
+    fn position(&mut self, _row: &Record, pos: &mut [u8]) {
+        pos.copy_from_slice(&self.current_pos.to_le_bytes());
+    }
+
+    fn rnd_end(&mut self) -> StorageResult {
+        self.reader = None;
+        if self.deleted.is_empty() {
+            return Ok(());
+        }
+
+        let mut deleted = std::mem::take(&mut self.deleted);
+        deleted.sort_by_key(|r| r.start);
+        let share = self.share();
+        share.rewrite(|old, new| {
+            let mut pos = 0;
+            for range in &deleted {
+                io::copy(&mut (&mut *old).take(range.start - pos), new)?;
+                old.seek(SeekFrom::Start(range.end))?;
+                pos = range.end;
+            }
+            io::copy(old, new)?;
+            Ok(())
+        })?;
+        share.save_meta(&share.state())
+    }
+
+    fn info(&mut self, _flags: InfoFlags, stats: &mut StatisticsMut) -> StorageResult {
+        let share = self.share();
+        let rows = share.state().rows;
+        // Unless the count is exact, report at least 2 rows so that the optimizer
+        // does not read the table as a constant
+        let records = if self.records_is_known {
+            rows
+        } else {
+            rows.max(2)
+        };
+        stats.set_records(records);
+        if let Ok(meta) = fs::metadata(&share.data_path) {
+            stats.set_data_file_length(meta.len());
+        }
+        Ok(())
+    }
+
+    fn store_lock(&mut self, _thd: &Thd, lock_type: ThrLockType) -> Option<&mut ThrLockData> {
+        let lock_data = self.lock_data.as_mut()?;
+        lock_data.request(lock_type);
+        Some(lock_data)
+    }
+}
+
+impl RepairingHandler for CsvHandler {
+    fn is_crashed(&self) -> bool {
+        self.share.as_deref().is_some_and(|s| s.state().crashed)
+    }
+
+    fn auto_repair(_error: i32) -> bool {
+        true
+    }
+
+    fn check(&mut self, msgs: &mut AdminMessages, _opts: &CheckOptions) -> AdminResult {
+        let Ok((rows, pos, complete)) = self.scan_valid_rows() else {
+            return AdminResult::InternalError;
+        };
+
+        let mut state = self.share().state();
+        if !complete {
+            msgs.error(&format!("damaged row at offset {pos}"));
+        } else if rows != state.rows {
+            msgs.error(&format!("found {rows} rows, expected {}", state.rows));
+        } else {
+            return AdminResult::Ok;
+        }
+
+        state.crashed = true;
+        state.dirty = true;
+        // Best effort, the table is also marked crashed in memory
+        let _ = self.share().save_meta(&state);
+        AdminResult::Corrupt
+    }
+
+    fn repair(&mut self, msgs: &mut AdminMessages, _opts: &CheckOptions) -> AdminResult {
+        let Ok((rows, end, complete)) = self.scan_valid_rows() else {
+            return AdminResult::InternalError;
+        };
+
+        if !complete {
+            // Keep the rows before the first damaged one
+            let res = self.share().rewrite(|old, new| {
+                io::copy(&mut old.take(end), new)?;
+                Ok(())
+            });
+            if res.is_err() {
+                return AdminResult::Failed;
+            }
+            msgs.note(&format!(
+                "kept {rows} rows, removed data after offset {end}"
+            ));
+        }
+
+        let share = self.share();
+        let mut state = share.state();
+        state.rows = rows;
+        state.crashed = false;
+        // Another handler may still be appending rows
+        state.dirty = state.writer.is_some();
+        match share.save_meta(&state) {
+            Ok(()) => AdminResult::Ok,
+            Err(_) => AdminResult::Failed,
+        }
+    }
+}
+
+/// Whether values of this type are written quoted. Numbers are not.
+fn needs_quotes(field_type: FieldType) -> bool {
+    !matches!(
+        field_type,
+        FieldType::Tiny
+            | FieldType::Short
+            | FieldType::Int24
+            | FieldType::Long
+            | FieldType::LongLong
+            | FieldType::Float
+            | FieldType::Double
+            | FieldType::Decimal
+            | FieldType::NewDecimal
+            | FieldType::Year
+    )
+}
+
+fn io_error(e: io::Error) -> StorageError {
+    match e.kind() {
+        io::ErrorKind::NotFound => StorageError::NoSuchTable,
+        _ => StorageError::custom(format!("CSV file error: {e}")),
     }
 }
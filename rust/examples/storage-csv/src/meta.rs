@@ -0,0 +1,76 @@
+//! The `.CSM` meta file, in the same format as the server's `CSV` engine
+//!
+//! It holds the number of rows, and a flag that is set while the data file is open
+//! for writing. If the flag is still set when the table is opened, the server
+//! stopped without closing the table and the data file may be damaged.
+
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::Path;
+
+/// First byte of the file
+const CHECK_HEADER: u8 = 254;
+const VERSION: u8 = 1;
+/// Header, version, row count, three unused counters and the dirty flag
+const META_LEN: usize = 2 + 4 * 8 + 1;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Meta {
+    /// Number of rows in the data file
+    pub rows: u64,
+    /// The data file is open for writing, or was found to be damaged
+    pub dirty: bool,
+}
+
+impl Meta {
+    /// Read the meta file at `path`. A file with the wrong size or header gives an
+    /// `InvalidData` error.
+    pub fn read(path: &Path) -> io::Result<Self> {
+        let buf = fs::read(path)?;
+        Self::decode(&buf).ok_or_else(|| io::Error::from(io::ErrorKind::InvalidData))
+    }
+
+    /// Replace the meta file at `path`, and sync it to disk
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        let mut file = File::create(path)?;
+        file.write_all(&self.encode())?;
+        file.sync_data()
+    }
+
+    fn encode(&self) -> [u8; META_LEN] {
+        let mut buf = [0u8; META_LEN];
+        buf[0] = CHECK_HEADER;
+        buf[1] = VERSION;
+        buf[2..10].copy_from_slice(&self.rows.to_le_bytes());
+        buf[META_LEN - 1] = self.dirty.into();
+        buf
+    }
+
+    fn decode(buf: &[u8]) -> Option<Self> {
+        if buf.len() != META_LEN || buf[0] != CHECK_HEADER || buf[1] != VERSION {
+            return None;
+        }
+        Some(Self {
+            rows: u64::from_le_bytes(buf[2..10].try_into().unwrap()),
+            dirty: buf[META_LEN - 1] != 0,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let meta = Meta {
+            rows: 0x0102_0304,
+            dirty: true,
+        };
+        let buf = meta.encode();
+        assert_eq!(buf.len(), 35);
+        assert_eq!(&buf[..4], [254, 1, 4, 3]);
+        assert_eq!(Meta::decode(&buf), Some(meta));
+        assert_eq!(Meta::decode(&buf[1..]), None);
+    }
+}
@@ -5,8 +5,10 @@ use std::path::Path;
 
 use mariadb::log::debug;
 use mariadb::plugin::{License, Maturity};
-use mariadb::storage::{Handler, Handlerton, Mode, OpenOp, StorageResult};
-use mariadb::{register_plugin_storage, MemRoot, TableShare};
+use mariadb::storage::{
+    Handler, Handlerton, Mode, OpenOp, Record, RecordMut, StorageError, StorageResult,
+};
+use mariadb::{register_plugin_storage, MemRoot, Table, TableShare};
 
 register_plugin_storage! {
     name: "EXAMPLE_RUST",
@@ -24,23 +26,49 @@ struct ExampleHandler {}
 impl Handlerton for ExampleHton {
     type Handler = ExampleHandler;
 
+    type ConnectionData = ();
     type SavePoint = ();
+    type TableOptions = ();
+    type FieldOptions = ();
+    type IndexOptions = ();
 }
 
 impl Handler for ExampleHandler {
     type Handlerton = ExampleHton;
 
     // #[mariadb::dbug::instrument]
-    fn new(table: &TableShare, mem_root: MemRoot) -> Self {
+    fn new(table: Option<&TableShare>, mem_root: MemRoot) -> Self {
         Self {}
     }
 
-    fn open(name: &Path, mode: Mode, open_options: OpenOp) -> StorageResult {
-        // init share
-        todo!()
+    fn open(
+        &mut self,
+        name: &Path,
+        table: &Table,
+        mode: Mode,
+        open_options: OpenOp,
+    ) -> StorageResult {
+        // Nothing to do here
+        Ok(())
     }
 
-    fn write_row(buf: &CStr) {
+    fn write_row(&mut self, buf: &Record) -> StorageResult {
         // Nothing to do here
+        Ok(())
+    }
+
+    fn rnd_init(&mut self, scan: bool) -> StorageResult {
+        Ok(())
+    }
+
+    fn rnd_next(&mut self, buf: &mut RecordMut) -> StorageResult {
+        // We never store anything
+        Err(StorageError::EndOfFile)
+    }
+
+    fn rnd_pos(&mut self, buf: &mut RecordMut, pos: &[u8]) -> StorageResult {
+        Err(StorageError::EndOfFile)
     }
+
+    fn position(&mut self, row: &Record, pos: &mut [u8]) {}
 }
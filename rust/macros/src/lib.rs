@@ -11,6 +11,7 @@ mod fields;
 mod helpers;
 mod parse_vars;
 mod register_plugin;
+mod table_options;
 use proc_macro::TokenStream;
 
 /// Macro to use to register a plugin
@@ -26,3 +27,38 @@ pub fn register_plugin(item: TokenStream) -> TokenStream {
 pub fn dbug_instrument(attr: TokenStream, item: TokenStream) -> TokenStream {
     dbug::instrument(attr, item)
 }
+
+/// Derive engine-defined table options, e.g. `CREATE TABLE t (...) ENGINE=X
+/// COMPRESSION='zstd'`.
+///
+/// Fields may be `Option<String>`, `u64`, `bool`, or an enum deriving `OptionEnum`.
+/// Each field can be configured with `#[option(...)]`:
+///
+/// - `name = "SQL_NAME"`: the option name, defaults to the uppercased field name
+/// - `default = ...`: default value for numbers, bools and enums
+/// - `min = ...`, `max = ...`, `block_size = ...`: limits for numbers
+///
+/// See the `storage` module in the main `mariadb` crate for examples.
+#[proc_macro_derive(TableOptions, attributes(option))]
+pub fn derive_table_options(item: TokenStream) -> TokenStream {
+    table_options::derive(item, table_options::OptionKind::Table)
+}
+
+/// Derive engine-defined per-column options. See [`TableOptions`](derive@TableOptions).
+#[proc_macro_derive(FieldOptions, attributes(option))]
+pub fn derive_field_options(item: TokenStream) -> TokenStream {
+    table_options::derive(item, table_options::OptionKind::Field)
+}
+
+/// Derive engine-defined per-index options. See [`TableOptions`](derive@TableOptions).
+#[proc_macro_derive(IndexOptions, attributes(option))]
+pub fn derive_index_options(item: TokenStream) -> TokenStream {
+    table_options::derive(item, table_options::OptionKind::Index)
+}
+
+/// Derive the list of values for an enum option. Values are the uppercased variant
+/// names unless overridden with `#[option(name = "...")]`.
+#[proc_macro_derive(OptionEnum, attributes(option))]
+pub fn derive_option_enum(item: TokenStream) -> TokenStream {
+    table_options::derive_enum(item)
+}
@@ -0,0 +1,337 @@
+//! Derive macros for engine-defined `CREATE TABLE` options
+//!
+//! ```ignore
+//! #[derive(TableOptions)]
+//! struct MyOptions {
+//!     #[option(name = "COMPRESSION", default = Compression::Zstd)]
+//!     compression: Compression,
+//!     #[option(default = 4096, min = 512, max = 65536, block_size = 512)]
+//!     block_size: u64,
+//!     #[option(default = true)]
+//!     checksum: bool,
+//!     comment_file: Option<String>,
+//! }
+//!
+//! #[derive(OptionEnum)]
+//! enum Compression {
+//!     None,
+//!     Zstd,
+//! }
+//! ```
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::spanned::Spanned;
+use syn::{
+    parse_macro_input, parse_quote, Data, DeriveInput, Error, Expr, Fields, GenericArgument, Ident,
+    LitStr, PathArguments, Type,
+};
+
+/// Which of the option lists a struct is for
+#[derive(Clone, Copy, Debug)]
+pub enum OptionKind {
+    Table,
+    Field,
+    Index,
+}
+
+/// The storage type of a single option, determined from the Rust type
+#[derive(Clone, Debug)]
+enum OptionType {
+    /// `Option<String>`, stored as `const char *`
+    String,
+    /// `u64`, stored as `ulonglong`
+    Number,
+    /// `bool`, stored as `bool`
+    Bool,
+    /// Any type implementing `OptionEnum`, stored as `uint`
+    Enum(Type),
+}
+
+/// A parsed struct field
+struct OptionField {
+    ident: Ident,
+    otype: OptionType,
+    name: LitStr,
+    default: Option<Expr>,
+    min: Option<Expr>,
+    max: Option<Expr>,
+    block_size: Option<Expr>,
+}
+
+pub fn derive(item: proc_macro::TokenStream, kind: OptionKind) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+    derive_struct(&input, kind)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+pub fn derive_enum(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+    derive_option_enum(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn derive_struct(input: &DeriveInput, kind: OptionKind) -> syn::Result<TokenStream> {
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new_spanned(input, "options must be a struct"));
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(Error::new_spanned(input, "options must have named fields"));
+    };
+    if !input.generics.params.is_empty() {
+        let msg = "options structs cannot be generic";
+        return Err(Error::new_spanned(&input.generics, msg));
+    }
+
+    let fields = fields
+        .named
+        .iter()
+        .map(parse_field)
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    let ident = &input.ident;
+    let internals = quote! { ::mariadb::plugin::internals };
+    let kind_trait = match kind {
+        OptionKind::Table => quote! { ::mariadb::storage::TableOptions },
+        OptionKind::Field => quote! { ::mariadb::storage::FieldOptions },
+        OptionKind::Index => quote! { ::mariadb::storage::IndexOptions },
+    };
+
+    let mut raw_fields = Vec::new();
+    let mut options = Vec::new();
+    let mut defaults = Vec::new();
+    let mut reads = Vec::new();
+
+    for field in &fields {
+        let fident = &field.ident;
+        let name = &field.name;
+        let offset = quote! { ::std::mem::offset_of!(__RawOptions, #fident) as isize };
+        let default = field.default.clone();
+
+        match &field.otype {
+            OptionType::String => {
+                if let Some(def) = &field.default {
+                    let msg = "string options cannot have a default";
+                    return Err(Error::new_spanned(def, msg));
+                }
+                raw_fields.push(quote! { #fident: *const ::std::ffi::c_char });
+                options.push(quote! {
+                    #internals::option_string(::mariadb::internals::cstr!(#name), #offset)
+                });
+                defaults.push(quote! { #fident: None });
+                reads.push(
+                    quote! { #fident: unsafe { #internals::read_option_string(raw.#fident) } },
+                );
+            }
+            OptionType::Number => {
+                let def = default.unwrap_or_else(|| parse_quote!(0));
+                let min = field.min.clone().unwrap_or_else(|| parse_quote!(0));
+                let max = field.max.clone().unwrap_or_else(|| parse_quote!(u64::MAX));
+                let blk = field.block_size.clone().unwrap_or_else(|| parse_quote!(1));
+                raw_fields.push(quote! { #fident: ::std::ffi::c_ulonglong });
+                options.push(quote! {
+                    #internals::option_number(
+                        ::mariadb::internals::cstr!(#name), #offset, #def, #min, #max, #blk
+                    )
+                });
+                defaults.push(quote! { #fident: #def });
+                reads.push(quote! { #fident: raw.#fident });
+            }
+            OptionType::Bool => {
+                let def = default.unwrap_or_else(|| parse_quote!(false));
+                raw_fields.push(quote! { #fident: bool });
+                options.push(quote! {
+                    #internals::option_bool(::mariadb::internals::cstr!(#name), #offset, #def)
+                });
+                defaults.push(quote! { #fident: #def });
+                reads.push(quote! { #fident: raw.#fident });
+            }
+            OptionType::Enum(ty) => {
+                let as_enum = quote! { <#ty as ::mariadb::storage::OptionEnum> };
+                let (def, value) = match default {
+                    Some(def) => (quote! { #def as ::std::ffi::c_uint }, quote! { #def }),
+                    None => (quote! { 0 }, quote! { #as_enum::DEFAULT }),
+                };
+                raw_fields.push(quote! { #fident: ::std::ffi::c_uint });
+                options.push(quote! {
+                    #internals::option_enum(
+                        ::mariadb::internals::cstr!(#name), #offset, #as_enum::VALUES, #def
+                    )
+                });
+                defaults.push(quote! { #fident: #value });
+                reads.push(quote! {
+                    #fident: #as_enum::from_index(raw.#fident).unwrap_or(#value)
+                });
+            }
+        }
+
+        if !matches!(field.otype, OptionType::Number) {
+            let extra = [&field.min, &field.max, &field.block_size];
+            if let Some(Some(expr)) = extra.iter().find(|e| e.is_some()) {
+                let msg = "`min`, `max` and `block_size` are only allowed for numbers";
+                return Err(Error::new_spanned(expr, msg));
+            }
+        }
+    }
+
+    let ret = quote! {
+        const _: () = {
+            /// Layout of the struct that the server fills in
+            #[repr(C)]
+            struct __RawOptions {
+                #( #raw_fields, )*
+            }
+
+            impl #internals::OptionStruct for #ident {
+                const OPTIONS: &'static [::mariadb::bindings::ha_create_table_option] = &[
+                    #( #options, )*
+                    #internals::OPTION_END,
+                ];
+
+                unsafe fn from_raw(ptr: *const ::std::ffi::c_void) -> Self {
+                    if ptr.is_null() {
+                        return Self { #( #defaults, )* };
+                    }
+                    let raw = unsafe { &*ptr.cast::<__RawOptions>() };
+                    Self { #( #reads, )* }
+                }
+            }
+
+            impl #kind_trait for #ident {}
+        };
+    };
+
+    Ok(ret)
+}
+
+fn parse_field(field: &syn::Field) -> syn::Result<OptionField> {
+    let ident = field.ident.clone().unwrap();
+    let mut ret = OptionField {
+        name: LitStr::new(&ident.to_string().to_uppercase(), ident.span()),
+        ident,
+        otype: option_type(&field.ty)?,
+        default: None,
+        min: None,
+        max: None,
+        block_size: None,
+    };
+
+    for attr in field.attrs.iter().filter(|a| a.path().is_ident("option")) {
+        attr.parse_nested_meta(|meta| {
+            let key = meta.path.get_ident().map(ToString::to_string);
+            match key.as_deref() {
+                Some("name") => ret.name = meta.value()?.parse()?,
+                Some("default") => ret.default = Some(meta.value()?.parse()?),
+                Some("min") => ret.min = Some(meta.value()?.parse()?),
+                Some("max") => ret.max = Some(meta.value()?.parse()?),
+                Some("block_size") => ret.block_size = Some(meta.value()?.parse()?),
+                _ => return Err(meta.error("unsupported option attribute")),
+            }
+            Ok(())
+        })?;
+    }
+
+    Ok(ret)
+}
+
+/// Figure out how to store a type
+fn option_type(ty: &Type) -> syn::Result<OptionType> {
+    let Type::Path(tpath) = ty else {
+        let msg = "unsupported option type; expected `Option<String>`, `u64`, `bool` or an enum";
+        return Err(Error::new_spanned(ty, msg));
+    };
+    let last = tpath.path.segments.last().unwrap();
+
+    if last.ident == "Option" {
+        let PathArguments::AngleBracketed(args) = &last.arguments else {
+            return Err(Error::new_spanned(ty, "expected `Option<String>`"));
+        };
+        return match args.args.first() {
+            Some(GenericArgument::Type(Type::Path(inner)))
+                if inner.path.is_ident("String") && args.args.len() == 1 =>
+            {
+                Ok(OptionType::String)
+            }
+            _ => Err(Error::new_spanned(ty, "only `Option<String>` is supported")),
+        };
+    }
+
+    if tpath.path.is_ident("u64") {
+        Ok(OptionType::Number)
+    } else if tpath.path.is_ident("bool") {
+        Ok(OptionType::Bool)
+    } else if tpath.path.is_ident("String") {
+        let msg = "string options may be unset, use `Option<String>`";
+        Err(Error::new_spanned(ty, msg))
+    } else {
+        Ok(OptionType::Enum(ty.clone()))
+    }
+}
+
+fn derive_option_enum(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let Data::Enum(data) = &input.data else {
+        return Err(Error::new_spanned(input, "`OptionEnum` must be an enum"));
+    };
+    if data.variants.is_empty() {
+        return Err(Error::new_spanned(
+            input,
+            "`OptionEnum` needs at least one variant",
+        ));
+    }
+
+    let mut names = Vec::new();
+    let mut arms = Vec::new();
+
+    for (idx, variant) in data.variants.iter().enumerate() {
+        if !matches!(variant.fields, Fields::Unit) {
+            let msg = "`OptionEnum` variants cannot have fields";
+            return Err(Error::new_spanned(variant, msg));
+        }
+        if let Some((_, disc)) = &variant.discriminant {
+            let msg = "`OptionEnum` variants cannot have explicit discriminants";
+            return Err(Error::new_spanned(disc, msg));
+        }
+
+        let mut name = variant.ident.to_string().to_uppercase();
+        for attr in variant.attrs.iter().filter(|a| a.path().is_ident("option")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("name") {
+                    name = meta.value()?.parse::<LitStr>()?.value();
+                    Ok(())
+                } else {
+                    Err(meta.error("unsupported option attribute"))
+                }
+            })?;
+        }
+        if name.contains(',') {
+            let msg = "enum option values cannot contain commas";
+            return Err(Error::new(variant.span(), msg));
+        }
+        names.push(name);
+
+        let vident = &variant.ident;
+        let idx = u32::try_from(idx).unwrap();
+        arms.push(quote! { #idx => Self::#vident });
+    }
+    let first = &data.variants[0].ident;
+
+    let ident = &input.ident;
+    let values = LitStr::new(&names.join(","), ident.span());
+
+    Ok(quote! {
+        impl ::mariadb::storage::OptionEnum for #ident {
+            const VALUES: &'static ::std::ffi::CStr = ::mariadb::internals::cstr!(#values);
+            const DEFAULT: Self = Self::#first;
+
+            fn from_index(index: u32) -> Option<Self> {
+                let value = match index {
+                    #( #arms, )*
+                    _ => return None,
+                };
+                Some(value)
+            }
+        }
+    })
+}
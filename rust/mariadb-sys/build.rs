@@ -201,13 +201,30 @@ fn make_bindings_with_includes(include_paths: &[PathBuf]) -> Result<Bindings, Er
         .allowlist_item("handlerton")
         .allowlist_item("handler")
         .allowlist_item(".*(ha|handler)_bridge.*")
+        .allowlist_item("table_bridge_.*")
+        .allowlist_item("item_bridge_.*")
+        // `Item` is a large class hierarchy that is only used through the item bridge
+        .opaque_type("Item")
         .allowlist_item("st_mysql_storage.*")
+        .allowlist_function("thd_(get|set)_ha_data")
         .allowlist_type("TABLE(_SHARE)?")
+        .allowlist_type("KEY(_PART_INFO)?")
         .allowlist_type("MYSQL_HANDLERTON.*")
+        .allowlist_type("THR_LOCK")
+        .allowlist_function("thr_lock(_data)?_(init|delete)")
         .allowlist_var("HA_.*")
         .allowlist_var("IO_SIZE")
         .allowlist_var("MAX_REF_PARTS")
         .allowlist_var("MAX_DATA_LENGTH_FOR_KEY")
+        .allowlist_var("MAX_KEY")
+        .allowlist_var("FN_REFLEN")
+        .allowlist_var("ALTER_.*")
+        .allowlist_var("(UNIQUE|EQ|NULL)_RANGE")
+        .allowlist_type("HA_CHECK_OPT")
+        .allowlist_var("T_(QUICK|FAST|MEDIUM|EXTEND|CHECK_ONLY_CHANGED|AUTO_REPAIR)")
+        .allowlist_var("F_(RD|WR|UN)LCK")
+        .allowlist_var("O_(RDONLY|WRONLY|RDWR)")
+        .allowlist_var("(NOT_NULL|UNSIGNED|BINARY|BLOB|AUTO_INCREMENT|NO_DEFAULT_VALUE)_FLAG")
         // Items for the SQL service. Note that `sql_service` (from `st_service_ref`) needs to
         // be handwritten because it doesn't seem to import with the expected values (a static vs.
         // dynamic thing).
@@ -5,6 +5,8 @@
 #include <my_dbug.h>
 #include <handler.h>
 #include <handler_bridge.h>
+#include <item_bridge.h>
+#include <table_bridge.h>
 #include <mysql.h>
 #include <mysql/plugin.h>
 #include <mysql/plugin_encryption.h>
@@ -29,7 +29,7 @@ pub mod storage;
 mod table;
 mod thd;
 mod util;
-#[cfg(feature = "service-sql")]
+#[cfg(any(feature = "service-sql", feature = "storage"))]
 mod value;
 
 #[cfg(test)]
@@ -39,11 +39,11 @@ pub use log;
 #[doc(hidden)]
 pub use mariadb_sys as bindings;
 pub use my_alloc::MemRoot;
-pub use table::TableShare;
+pub use table::{Field, FieldType, Key, KeyPart, Table, TableShare};
 #[cfg(test)]
 use tests::assert_layouts_eq;
-pub use thd::Thd;
-#[cfg(feature = "service-sql")]
+pub use thd::{Thd, ThdKillLevel};
+#[cfg(any(feature = "service-sql", feature = "storage"))]
 #[doc(inline)]
 pub use value::*;
 
@@ -99,13 +99,23 @@ pub mod internals {
     };
     #[cfg(feature = "storage")]
     pub use super::storage_wrapper::{
-        build_handler_vtable, wrap_storage_deinit_fn, wrap_storage_init_fn, HandlertonMeta,
+        build_handler_vtable, install_cond_pushdown_callbacks, install_discovery_hooks,
+        install_index_callbacks, wrap_storage_deinit_fn, wrap_storage_init_fn, HandlerProbe,
+        HandlertonMeta, HandlertonProbe, InstallAutoIncrement, InstallAutoIncrementFallback,
+        InstallCondPushdown, InstallCondPushdownFallback, InstallDiscovery,
+        InstallDiscoveryFallback, InstallIndex, InstallIndexFallback, InstallInplaceAlter,
+        InstallInplaceAlterFallback, InstallRepair, InstallRepairFallback,
     };
     pub use super::variables::SysVarInterface;
     pub use super::wrapper::{
         default_deinit_notype, default_init_notype, new_null_plugin_st, wrap_deinit_fn,
         wrap_init_fn, PluginMeta,
     };
+    #[cfg(feature = "storage")]
+    pub use crate::storage::options::{
+        option_bool, option_enum, option_number, option_string, read_option_string, OptionStruct,
+        OPTION_END,
+    };
 }
 
 /// Defines possible licenses for plugins
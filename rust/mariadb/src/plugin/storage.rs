@@ -61,9 +61,34 @@ macro_rules! register_plugin_storage {
 
         impl $crate::plugin::internals::HandlertonMeta for $hton {
             fn get_vtable() -> &'static $crate::bindings::handler_bridge_vt {
-                static VTABLE: $crate::bindings::handler_bridge_vt =
-                    $crate::plugin::internals::build_handler_vtable::<$hton>();
-                &VTABLE
+                use $crate::plugin::internals::{
+                    InstallAutoIncrement, InstallAutoIncrementFallback, InstallCondPushdown,
+                    InstallCondPushdownFallback, InstallIndex, InstallIndexFallback,
+                    InstallInplaceAlter, InstallInplaceAlterFallback, InstallRepair,
+                    InstallRepairFallback,
+                };
+
+                static VTABLE: ::std::sync::OnceLock<$crate::bindings::handler_bridge_vt> =
+                    ::std::sync::OnceLock::new();
+
+                VTABLE.get_or_init(|| {
+                    type Handler = <$hton as $crate::storage::Handlerton>::Handler;
+                    let mut vt = $crate::plugin::internals::build_handler_vtable::<$hton>();
+                    let probe = $crate::plugin::internals::HandlerProbe::<Handler>::new();
+                    (&probe).install_index(&mut vt);
+                    (&probe).install_cond_pushdown(&mut vt);
+                    (&probe).install_inplace_alter(&mut vt);
+                    (&probe).install_repair(&mut vt);
+                    (&probe).install_auto_increment(&mut vt);
+                    vt
+                })
+            }
+
+            fn install_hooks(hton: &mut $crate::bindings::handlerton) {
+                use $crate::plugin::internals::{InstallDiscovery, InstallDiscoveryFallback};
+
+                let probe = $crate::plugin::internals::HandlertonProbe::<$hton>::new();
+                (&probe).install_discovery(hton);
             }
         }
     };
@@ -3,27 +3,50 @@
 
 use std::any::TypeId;
 use std::ffi::{c_char, c_int, c_uchar, c_uint, c_ulong, c_ulonglong, c_void, CStr, CString};
+use std::marker::PhantomData;
+use std::path::Path;
 use std::sync::Mutex;
-use std::{mem, ptr};
+use std::{mem, ptr, slice};
 
 use log::info;
 
 use super::wrapper::{init_common, PluginMeta};
-use crate::storage::{Handler, Handlerton};
-use crate::{bindings, MemRoot, TableShare};
+use crate::internals::cstr;
+use crate::sql::{MAX_DATA_LENGTH_FOR_KEY, MAX_REFERENCE_PARTS};
+use crate::storage::{
+    condition_from_items, conjunct_items, last_error_message, option_list, remainder_indices,
+    to_errno, AdminMessages, AlterInplaceInfo, AutoIncrementHandler, AutoIncrementRequest,
+    CheckOptions, CondRemainder, Condition, ConditionPushdown, CreateInfo, DiscoveredTables,
+    DiscoveringHandlerton, ExternalLockType, Handler, HandlerCtx, Handlerton, HandlertonThd,
+    IndexableHandler, InfoFlags, InplaceAlterTable, IoAndCpuCost, KeyFind, KeyRange, Mode,
+    MrrFlags, OpenOp, PageRange, RangeSeq, Record, RecordMut, RepairingHandler, SearchKey,
+    StatisticsMut, StorageError, StorageResult, TablePath, ThrLockType,
+};
+use crate::{bindings, MemRoot, Table, TableShare, Thd, ThdKillLevel};
 
 /// Trait implemented by the macro for an easy
 pub trait HandlertonMeta: Handlerton + PluginMeta {
     /// This function should return the vtable, which should be located in statics.
     fn get_vtable() -> &'static bindings::handler_bridge_vt;
+
+    /// Install hooks for the optional handlerton traits that are implemented
+    fn install_hooks(hton: &mut bindings::handlerton);
 }
 
-// fn build_cstring(s: &str) -> &'static CStr {
-//     static ALL: Mutex<Vec<CString>> = Mutex::new(Vec::new());
-//     let cs = CString::try_from(s).unwrap();
-//     ALL.lock().unwrap().push(cs);
-//     todo!()
-// }
+/// Build the null-terminated list for `handlerton::tablefile_extensions`, or null if
+/// there are no extensions. The list is leaked since the server keeps it for as
+/// long as the engine is loaded.
+fn tablefile_extensions(exts: &[&str]) -> *mut *const c_char {
+    if exts.is_empty() {
+        return ptr::null_mut();
+    }
+    let mut list: Vec<*const c_char> = exts
+        .iter()
+        .map(|ext| CString::new(*ext).unwrap().into_raw().cast_const())
+        .collect();
+    list.push(ptr::null());
+    Box::leak(list.into_boxed_slice()).as_mut_ptr()
+}
 
 /// Initialize the handlerton
 pub extern "C" fn wrap_storage_init_fn<P: HandlertonMeta>(hton: *mut c_void) -> c_int {
@@ -44,7 +67,30 @@ pub extern "C" fn wrap_storage_init_fn<P: HandlertonMeta>(hton: *mut c_void) ->
     unsafe {
         (*hton).create = Some(create_handler::<P>);
         (*hton).flags = P::FLAGS;
-        // (*hton).tablefile_extensions =
+        (*hton).tablefile_extensions = tablefile_extensions(P::TABLEFILE_EXTENSIONS);
+        (*hton).table_options = option_list::<P::TableOptions>();
+        (*hton).field_options = option_list::<P::FieldOptions>();
+        (*hton).index_options = option_list::<P::IndexOptions>();
+
+        // The server replaces this with the actual offset after init
+        let Ok(savepoint_offset) = mem::size_of::<P::SavePoint>().try_into() else {
+            log::error!("savepoint type of storage engine {} is too large", P::NAME);
+            return 1;
+        };
+        (*hton).savepoint_offset = savepoint_offset;
+        (*hton).close_connection = Some(wrap_close_connection::<P>);
+        (*hton).kill_query = Some(wrap_kill_query::<P>);
+        (*hton).commit = Some(wrap_commit::<P>);
+        (*hton).rollback = Some(wrap_rollback::<P>);
+        if P::TWO_PHASE_COMMIT {
+            (*hton).prepare = Some(wrap_prepare::<P>);
+        }
+        (*hton).savepoint_set = Some(wrap_savepoint_set::<P>);
+        (*hton).savepoint_rollback = Some(wrap_savepoint_rollback::<P>);
+        (*hton).savepoint_rollback_can_release_mdl =
+            Some(wrap_savepoint_rollback_can_release_mdl::<P>);
+        (*hton).savepoint_release = Some(wrap_savepoint_release::<P>);
+        P::install_hooks(&mut *hton);
     }
 
     log::info!("loaded storage engine {}", P::NAME);
@@ -56,30 +102,136 @@ pub extern "C" fn wrap_storage_deinit_fn<P: Handlerton>(hton: *mut c_void) -> c_
     0
 }
 
+/// Only called if the engine has data set for the connection
+unsafe extern "C" fn wrap_close_connection<H: Handlerton>(
+    hton: *mut bindings::handlerton,
+    thd: *mut bindings::THD,
+) -> c_int {
+    let mut thd = unsafe { HandlertonThd::<H>::new(hton, thd) };
+    let res = H::close_connection(&mut thd);
+    drop(thd.take_data());
+    to_errno(res)
+}
+
+#[allow(improper_ctypes_definitions)] // level is not FFI-safe
+unsafe extern "C" fn wrap_kill_query<H: Handlerton>(
+    hton: *mut bindings::handlerton,
+    thd: *mut bindings::THD,
+    level: bindings::thd_kill_levels::Type,
+) {
+    let Some(level) = ThdKillLevel::from_raw(level) else {
+        return;
+    };
+    let thd = unsafe { HandlertonThd::<H>::new_foreign(hton, thd) };
+    H::kill_query(&thd, level);
+}
+
+unsafe extern "C" fn wrap_commit<H: Handlerton>(
+    hton: *mut bindings::handlerton,
+    thd: *mut bindings::THD,
+    all: bool,
+) -> c_int {
+    let mut thd = unsafe { HandlertonThd::<H>::new(hton, thd) };
+    to_errno(H::commit(&mut thd, all))
+}
+
+unsafe extern "C" fn wrap_rollback<H: Handlerton>(
+    hton: *mut bindings::handlerton,
+    thd: *mut bindings::THD,
+    all: bool,
+) -> c_int {
+    let mut thd = unsafe { HandlertonThd::<H>::new(hton, thd) };
+    to_errno(H::rollback(&mut thd, all))
+}
+
+unsafe extern "C" fn wrap_prepare<H: Handlerton>(
+    hton: *mut bindings::handlerton,
+    thd: *mut bindings::THD,
+    all: bool,
+) -> c_int {
+    let mut thd = unsafe { HandlertonThd::<H>::new(hton, thd) };
+    to_errno(H::prepare(&mut thd, all))
+}
+
+// The savepoint area has no alignment guarantees since each engine's area is placed
+// directly after the previous one, so use unaligned reads and writes.
+
+unsafe extern "C" fn wrap_savepoint_set<H: Handlerton>(
+    hton: *mut bindings::handlerton,
+    thd: *mut bindings::THD,
+    sv: *mut c_void,
+) -> c_int {
+    let mut thd = unsafe { HandlertonThd::<H>::new(hton, thd) };
+    match H::savepoint_set(&mut thd) {
+        Ok(savepoint) => {
+            unsafe { sv.cast::<H::SavePoint>().write_unaligned(savepoint) };
+            0
+        }
+        Err(e) => e.to_errno(),
+    }
+}
+
+unsafe extern "C" fn wrap_savepoint_rollback<H: Handlerton>(
+    hton: *mut bindings::handlerton,
+    thd: *mut bindings::THD,
+    sv: *mut c_void,
+) -> c_int {
+    let mut thd = unsafe { HandlertonThd::<H>::new(hton, thd) };
+    let sv = sv.cast::<H::SavePoint>();
+    let mut savepoint = unsafe { sv.read_unaligned() };
+    let res = H::savepoint_rollback(&mut thd, &mut savepoint);
+    unsafe { sv.write_unaligned(savepoint) };
+    to_errno(res)
+}
+
+unsafe extern "C" fn wrap_savepoint_rollback_can_release_mdl<H: Handlerton>(
+    hton: *mut bindings::handlerton,
+    thd: *mut bindings::THD,
+) -> bool {
+    let mut thd = unsafe { HandlertonThd::<H>::new(hton, thd) };
+    H::savepoint_rollback_can_release_mdl(&mut thd)
+}
+
+unsafe extern "C" fn wrap_savepoint_release<H: Handlerton>(
+    hton: *mut bindings::handlerton,
+    thd: *mut bindings::THD,
+    sv: *mut c_void,
+) -> c_int {
+    let mut thd = unsafe { HandlertonThd::<H>::new(hton, thd) };
+    let savepoint = unsafe { sv.cast::<H::SavePoint>().read_unaligned() };
+    to_errno(H::savepoint_release(&mut thd, savepoint))
+}
+
+/// Build the vtable for a handler. Optional capabilities (e.g. indexes) are left
+/// unset, and are added by the `install_*` functions.
 pub const fn build_handler_vtable<H: Handlerton>() -> bindings::handler_bridge_vt {
     bindings::handler_bridge_vt {
         constructor: Some(wrap_constructor::<H::Handler>),
         destructor: Some(wrap_destructor::<H::Handler>),
-        index_type: Some(wrap_index_type::<H::Handler>),
+        index_type: None,
         table_flags: Some(wrap_table_flags::<H::Handler>),
-        index_flags: Some(wrap_index_flags::<H::Handler>),
+        index_flags: None,
         max_supported_record_length: Some(wrap_max_supported_record_length::<H::Handler>),
-        max_supported_keys: Some(wrap_max_supported_keys::<H::Handler>),
-        max_supported_key_parts: Some(wrap_max_supported_key_parts::<H::Handler>),
-        max_supported_key_length: Some(wrap_max_supported_key_length::<H::Handler>),
+        max_supported_keys: None,
+        max_supported_key_parts: None,
+        max_supported_key_length: None,
         scan_time: Some(wrap_scan_time::<H::Handler>),
-        keyread_time: Some(wrap_keyread_time::<H::Handler>),
+        keyread_time: None,
         rnd_pos_time: Some(wrap_rnd_pos_time::<H::Handler>),
         open: Some(wrap_open::<H::Handler>),
         close: Some(wrap_close::<H::Handler>),
         write_row: Some(wrap_write_row::<H::Handler>),
+        start_bulk_insert: Some(wrap_start_bulk_insert::<H::Handler>),
+        end_bulk_insert: Some(wrap_end_bulk_insert::<H::Handler>),
         update_row: Some(wrap_update_row::<H::Handler>),
         delete_row: Some(wrap_delete_row::<H::Handler>),
-        index_read_map: Some(wrap_index_read_map::<H::Handler>),
-        index_next: Some(wrap_index_next::<H::Handler>),
-        index_prev: Some(wrap_index_prev::<H::Handler>),
-        index_first: Some(wrap_index_first::<H::Handler>),
-        index_last: Some(wrap_index_last::<H::Handler>),
+        index_init: None,
+        index_end: None,
+        index_read_map: None,
+        index_next: None,
+        index_prev: None,
+        index_first: None,
+        index_last: None,
         rnd_init: Some(wrap_rnd_init::<H::Handler>),
         rnd_end: Some(wrap_rnd_end::<H::Handler>),
         rnd_next: Some(wrap_rnd_next::<H::Handler>),
@@ -89,11 +241,554 @@ pub const fn build_handler_vtable<H: Handlerton>() -> bindings::handler_bridge_v
         extra: Some(wrap_extra::<H::Handler>),
         external_lock: Some(wrap_external_lock::<H::Handler>),
         delete_all_rows: Some(wrap_delete_all_rows::<H::Handler>),
-        records_in_range: Some(wrap_records_in_range::<H::Handler>),
+        records_in_range: None,
         delete_table: Some(wrap_delete_table::<H::Handler>),
+        rename_table: Some(wrap_rename_table::<H::Handler>),
+        truncate: Some(wrap_truncate::<H::Handler>),
         create: Some(wrap_create::<H::Handler>),
-        check_if_supported_inplace_alter: Some(wrap_check_if_supported_inplace_alter::<H::Handler>),
+        get_error_message: Some(wrap_get_error_message),
+        check_if_supported_inplace_alter: None,
+        prepare_inplace_alter_table: None,
+        inplace_alter_table: None,
+        commit_inplace_alter_table: None,
         store_lock: Some(wrap_store_lock::<H::Handler>),
+        cond_push: None,
+        cond_pop: None,
+        check: None,
+        repair: None,
+        analyze: None,
+        optimize: None,
+        is_crashed: None,
+        auto_repair: None,
+        get_auto_increment: None,
+        reset_auto_increment: None,
+        idx_cond_push: None,
+        cancel_pushed_idx_cond: None,
+        multi_range_read_info: None,
+        multi_range_read_init: None,
+        multi_range_read_next: None,
+        dup_key: None,
+    }
+}
+
+/// Add index callbacks to a vtable, for handlers with `SUPPORTS_INDEX`
+pub fn install_index_callbacks<H: IndexableHandler>(vt: &mut bindings::handler_bridge_vt) {
+    vt.index_type = Some(wrap_index_type::<H>);
+    vt.index_flags = Some(wrap_index_flags::<H>);
+    vt.max_supported_keys = Some(wrap_max_supported_keys::<H>);
+    vt.max_supported_key_parts = Some(wrap_max_supported_key_parts::<H>);
+    vt.max_supported_key_length = Some(wrap_max_supported_key_length::<H>);
+    vt.keyread_time = Some(wrap_keyread_time::<H>);
+    vt.records_in_range = Some(wrap_records_in_range::<H>);
+    vt.index_init = Some(wrap_index_init::<H>);
+    vt.index_end = Some(wrap_index_end::<H>);
+    vt.index_read_map = Some(wrap_index_read_map::<H>);
+    vt.index_next = Some(wrap_index_next::<H>);
+    vt.index_prev = Some(wrap_index_prev::<H>);
+    vt.index_first = Some(wrap_index_first::<H>);
+    vt.index_last = Some(wrap_index_last::<H>);
+    vt.idx_cond_push = Some(wrap_idx_cond_push::<H>);
+    vt.cancel_pushed_idx_cond = Some(wrap_cancel_pushed_idx_cond::<H>);
+    vt.dup_key = Some(wrap_dup_key::<H>);
+    if H::SUPPORTS_MRR {
+        vt.multi_range_read_info = Some(wrap_multi_range_read_info::<H>);
+        vt.multi_range_read_init = Some(wrap_multi_range_read_init::<H>);
+        vt.multi_range_read_next = Some(wrap_multi_range_read_next::<H>);
+    }
+}
+
+/// Install the callbacks for engine managed `AUTO_INCREMENT`
+pub fn install_auto_increment_callbacks<H: AutoIncrementHandler>(
+    vt: &mut bindings::handler_bridge_vt,
+) {
+    vt.get_auto_increment = Some(wrap_get_auto_increment::<H>);
+    vt.reset_auto_increment = Some(wrap_reset_auto_increment::<H>);
+}
+
+unsafe extern "C" fn wrap_get_auto_increment<H: AutoIncrementHandler>(
+    this: *mut bindings::handler_bridge,
+    offset: c_ulonglong,
+    increment: c_ulonglong,
+    nb_desired_values: c_ulonglong,
+    first_value: *mut c_ulonglong,
+    nb_reserved_values: *mut c_ulonglong,
+) {
+    let handler: &mut H = unsafe { get_handler(this) };
+    let req = AutoIncrementRequest::new(offset, increment, nb_desired_values);
+    match handler.get_auto_increment(req) {
+        Ok(res) => unsafe {
+            *first_value = res.first_value();
+            *nb_reserved_values = res.nb_reserved_values();
+        },
+        Err(e) => {
+            // The server only reports `HA_ERR_AUTOINC_READ_FAILED`, so log the cause
+            log::error!("failed to reserve AUTO_INCREMENT values: {e:?}");
+            // `ULONGLONG_MAX` as the first value signals an error
+            unsafe { *first_value = c_ulonglong::MAX };
+        }
+    }
+}
+
+unsafe extern "C" fn wrap_reset_auto_increment<H: AutoIncrementHandler>(
+    this: *mut bindings::handler_bridge,
+    value: c_ulonglong,
+) -> c_int {
+    let handler: &mut H = unsafe { get_handler(this) };
+    to_errno(handler.reset_auto_increment(value))
+}
+
+/// Install the table maintenance callbacks
+pub fn install_repair_callbacks<H: RepairingHandler>(vt: &mut bindings::handler_bridge_vt) {
+    vt.check = Some(wrap_check::<H>);
+    vt.repair = Some(wrap_repair::<H>);
+    vt.analyze = Some(wrap_analyze::<H>);
+    vt.optimize = Some(wrap_optimize::<H>);
+    vt.is_crashed = Some(wrap_is_crashed::<H>);
+    vt.auto_repair = Some(wrap_auto_repair::<H>);
+}
+
+unsafe extern "C" fn wrap_check<H: RepairingHandler>(
+    this: *mut bindings::handler_bridge,
+    thd: *mut bindings::THD,
+    check_opt: *mut bindings::HA_CHECK_OPT,
+) -> c_int {
+    let handler: &mut H = unsafe { get_handler(this) };
+    let mut msgs = unsafe { AdminMessages::new(thd) };
+    let opts = unsafe { CheckOptions::from_raw(check_opt) };
+    handler.check(&mut msgs, opts).to_raw()
+}
+
+unsafe extern "C" fn wrap_repair<H: RepairingHandler>(
+    this: *mut bindings::handler_bridge,
+    thd: *mut bindings::THD,
+    check_opt: *mut bindings::HA_CHECK_OPT,
+) -> c_int {
+    let handler: &mut H = unsafe { get_handler(this) };
+    let mut msgs = unsafe { AdminMessages::new(thd) };
+    let opts = unsafe { CheckOptions::from_raw(check_opt) };
+    handler.repair(&mut msgs, opts).to_raw()
+}
+
+unsafe extern "C" fn wrap_analyze<H: RepairingHandler>(
+    this: *mut bindings::handler_bridge,
+    thd: *mut bindings::THD,
+    check_opt: *mut bindings::HA_CHECK_OPT,
+) -> c_int {
+    let handler: &mut H = unsafe { get_handler(this) };
+    let mut msgs = unsafe { AdminMessages::new(thd) };
+    let opts = unsafe { CheckOptions::from_raw(check_opt) };
+    handler.analyze(&mut msgs, opts).to_raw()
+}
+
+unsafe extern "C" fn wrap_optimize<H: RepairingHandler>(
+    this: *mut bindings::handler_bridge,
+    thd: *mut bindings::THD,
+    check_opt: *mut bindings::HA_CHECK_OPT,
+) -> c_int {
+    let handler: &mut H = unsafe { get_handler(this) };
+    let mut msgs = unsafe { AdminMessages::new(thd) };
+    let opts = unsafe { CheckOptions::from_raw(check_opt) };
+    handler.optimize(&mut msgs, opts).to_raw()
+}
+
+unsafe extern "C" fn wrap_is_crashed<H: RepairingHandler>(
+    this: *const bindings::handler_bridge,
+) -> bool {
+    let handler: &H = unsafe { get_handler(this) };
+    handler.is_crashed()
+}
+
+unsafe extern "C" fn wrap_auto_repair<H: RepairingHandler>(
+    this: *const bindings::handler_bridge,
+    error: c_int,
+) -> bool {
+    H::auto_repair(error)
+}
+
+/// Install the in-place `ALTER TABLE` callbacks
+pub fn install_inplace_alter_callbacks<H: InplaceAlterTable>(vt: &mut bindings::handler_bridge_vt) {
+    vt.check_if_supported_inplace_alter = Some(wrap_check_if_supported_inplace_alter::<H>);
+    vt.prepare_inplace_alter_table = Some(wrap_prepare_inplace_alter_table::<H>);
+    vt.inplace_alter_table = Some(wrap_inplace_alter_table::<H>);
+    vt.commit_inplace_alter_table = Some(wrap_commit_inplace_alter_table::<H>);
+}
+
+unsafe extern "C" fn wrap_check_if_supported_inplace_alter<H: InplaceAlterTable>(
+    this: *mut bindings::handler_bridge,
+    altered_table: *mut bindings::TABLE,
+    ha_alter_info: *mut bindings::Alter_inplace_info,
+) -> bindings::enum_alter_inplace_result::Type {
+    let mut info = unsafe { alter_info(this, altered_table, ha_alter_info) };
+    let handler: &mut H = unsafe { get_handler(this) };
+    handler.check_if_supported_inplace_alter(&mut info).to_raw()
+}
+
+unsafe extern "C" fn wrap_prepare_inplace_alter_table<H: InplaceAlterTable>(
+    this: *mut bindings::handler_bridge,
+    altered_table: *mut bindings::TABLE,
+    ha_alter_info: *mut bindings::Alter_inplace_info,
+) -> bool {
+    let mut info = unsafe { alter_info(this, altered_table, ha_alter_info) };
+    let handler: &mut H = unsafe { get_handler(this) };
+    let res = handler.prepare_inplace_alter_table(&mut info);
+    unsafe { report_error(this, res) }
+}
+
+unsafe extern "C" fn wrap_inplace_alter_table<H: InplaceAlterTable>(
+    this: *mut bindings::handler_bridge,
+    altered_table: *mut bindings::TABLE,
+    ha_alter_info: *mut bindings::Alter_inplace_info,
+) -> bool {
+    let mut info = unsafe { alter_info(this, altered_table, ha_alter_info) };
+    let handler: &mut H = unsafe { get_handler(this) };
+    let res = handler.inplace_alter_table(&mut info);
+    unsafe { report_error(this, res) }
+}
+
+unsafe extern "C" fn wrap_commit_inplace_alter_table<H: InplaceAlterTable>(
+    this: *mut bindings::handler_bridge,
+    altered_table: *mut bindings::TABLE,
+    ha_alter_info: *mut bindings::Alter_inplace_info,
+    commit: bool,
+) -> bool {
+    let mut info = unsafe { alter_info(this, altered_table, ha_alter_info) };
+    let handler: &mut H = unsafe { get_handler(this) };
+    let res = handler.commit_inplace_alter_table(&mut info, commit);
+    unsafe { report_error(this, res) }
+}
+
+/// Raise the error of `res` in the server, for callbacks that only return whether
+/// they failed. Returns true on error.
+unsafe fn report_error(this: *mut bindings::handler_bridge, res: StorageResult) -> bool {
+    let Err(e) = res else {
+        return false;
+    };
+    // Records a custom message for `wrap_get_error_message`
+    let errno = e.to_errno();
+    unsafe { bindings::ha_bridge_print_error(ptr::addr_of_mut!((*this)._base), errno) };
+    true
+}
+
+/// Build the view passed to the in-place alter phases
+unsafe fn alter_info<'a>(
+    this: *mut bindings::handler_bridge,
+    altered_table: *mut bindings::TABLE,
+    ha_alter_info: *mut bindings::Alter_inplace_info,
+) -> AlterInplaceInfo<'a> {
+    unsafe {
+        AlterInplaceInfo::new(
+            ha_alter_info,
+            get_table(this),
+            Table::from_raw(altered_table),
+        )
+    }
+}
+
+/// Install the condition pushdown callbacks
+pub fn install_cond_pushdown_callbacks<H: ConditionPushdown>(vt: &mut bindings::handler_bridge_vt) {
+    vt.cond_push = Some(wrap_cond_push::<H>);
+    vt.cond_pop = Some(wrap_cond_pop::<H>);
+}
+
+unsafe extern "C" fn wrap_cond_push<H: ConditionPushdown>(
+    this: *mut bindings::handler_bridge,
+    cond: *const bindings::COND,
+) -> *const bindings::COND {
+    let handler: &mut H = unsafe { get_handler(this) };
+    unsafe {
+        push_condition(this, cond.cast_mut(), |condition| {
+            handler.cond_push(condition)
+        })
+    }
+}
+
+unsafe extern "C" fn wrap_cond_pop<H: ConditionPushdown>(this: *mut bindings::handler_bridge) {
+    let handler: &mut H = unsafe { get_handler(this) };
+    handler.cond_pop();
+}
+
+/// Convert `cond` to a [`Condition`], give it to `push`, and build the part of
+/// `cond` that the server must still check (null if none)
+///
+/// # Safety
+///
+/// `cond` must be a valid, fixed `Item` referring to the handler's table.
+unsafe fn push_condition(
+    this: *mut bindings::handler_bridge,
+    cond: *mut bindings::Item,
+    push: impl FnOnce(Condition) -> CondRemainder,
+) -> *mut bindings::Item {
+    let table = unsafe { get_base(this).table };
+    let items = unsafe { conjunct_items(cond, table) };
+    let condition = unsafe { condition_from_items(&items, table) };
+    let converted = condition.converted();
+    let keep = remainder_indices(&converted, push(condition));
+
+    match keep.as_slice() {
+        _ if keep.len() == items.len() => cond,
+        [] => ptr::null_mut(),
+        [idx] => items[*idx],
+        _ => {
+            let mut kept: Vec<_> = keep.iter().map(|&idx| items[idx]).collect();
+            let and = unsafe {
+                bindings::item_bridge_cond_and(
+                    (*table).in_use,
+                    kept.as_mut_ptr(),
+                    to_c_uint(kept.len()),
+                )
+            };
+            // If we cannot build the remainder, checking everything is still correct
+            if and.is_null() {
+                cond
+            } else {
+                and
+            }
+        }
+    }
+}
+
+/// Install the table discovery hooks
+pub fn install_discovery_hooks<H: DiscoveringHandlerton>(hton: &mut bindings::handlerton) {
+    hton.discover_table = Some(wrap_discover_table::<H>);
+    hton.discover_table_names = Some(wrap_discover_table_names::<H>);
+    hton.discover_table_existence = Some(wrap_discover_table_existence::<H>);
+}
+
+unsafe extern "C" fn wrap_discover_table<H: DiscoveringHandlerton>(
+    hton: *mut bindings::handlerton,
+    thd: *mut bindings::THD,
+    share: *mut bindings::TABLE_SHARE,
+) -> c_int {
+    let mut hthd = unsafe { HandlertonThd::<H>::new(hton, thd) };
+    let sql = match H::discover_table(&mut hthd, unsafe { TableShare::from_raw(share) }) {
+        Ok(sql) => sql,
+        Err(e) => return to_errno(Err(e)),
+    };
+    unsafe {
+        bindings::table_bridge_share_init_from_sql(
+            share,
+            thd,
+            H::WRITE_FRM,
+            sql.as_ptr().cast(),
+            sql.len(),
+        )
+    }
+}
+
+unsafe extern "C" fn wrap_discover_table_names<H: DiscoveringHandlerton>(
+    hton: *mut bindings::handlerton,
+    db: *const bindings::LEX_CSTRING,
+    dir: *mut bindings::MY_DIR,
+    result: *mut bindings::handlerton_discovered_list,
+) -> c_int {
+    let db = unsafe { lex_cstring_str(db) };
+    let mut tables = unsafe { DiscoveredTables::from_raw(result) };
+    // This callback returns 1 on error rather than an error code
+    c_int::from(H::discover_table_names(&db, &mut tables).is_err())
+}
+
+unsafe extern "C" fn wrap_discover_table_existence<H: DiscoveringHandlerton>(
+    hton: *mut bindings::handlerton,
+    db: *const c_char,
+    table_name: *const c_char,
+) -> c_int {
+    let db = unsafe { CStr::from_ptr(db) }.to_string_lossy();
+    let table_name = unsafe { CStr::from_ptr(table_name) }.to_string_lossy();
+    c_int::from(H::discover_table_existence(&db, &table_name))
+}
+
+/// Read a `LEX_CSTRING` passed by the server
+unsafe fn lex_cstring_str<'a>(s: *const bindings::LEX_CSTRING) -> std::borrow::Cow<'a, str> {
+    let s = unsafe { &*s };
+    if s.length == 0 {
+        return "".into();
+    }
+    let bytes = unsafe { slice::from_raw_parts(s.str_.cast::<u8>(), s.length) };
+    String::from_utf8_lossy(bytes)
+}
+
+/// Like [`HandlerProbe`], for optional traits on the handlerton
+pub struct HandlertonProbe<H>(PhantomData<H>);
+
+impl<H: Handlerton> HandlertonProbe<H> {
+    #[allow(clippy::new_without_default)]
+    pub const fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+pub trait InstallDiscovery {
+    fn install_discovery(&self, hton: &mut bindings::handlerton);
+}
+
+impl<H: DiscoveringHandlerton> InstallDiscovery for HandlertonProbe<H> {
+    fn install_discovery(&self, hton: &mut bindings::handlerton) {
+        if H::SUPPORTS_DISCOVERY {
+            install_discovery_hooks::<H>(hton);
+        }
+    }
+}
+
+pub trait InstallDiscoveryFallback {
+    fn install_discovery(&self, hton: &mut bindings::handlerton);
+}
+
+impl<H: Handlerton> InstallDiscoveryFallback for &HandlertonProbe<H> {
+    fn install_discovery(&self, hton: &mut bindings::handlerton) {
+        const {
+            assert!(
+                !H::SUPPORTS_DISCOVERY,
+                "handlertons with `SUPPORTS_DISCOVERY` must implement `DiscoveringHandlerton`"
+            );
+        }
+    }
+}
+
+/// Used by `register_plugin_storage!` to find out which optional traits a handler
+/// implements. This uses autoref specialization: method resolution picks the
+/// `Install*` impl on `HandlerProbe<H>` if the bound holds, and otherwise falls back
+/// to the `Install*Fallback` impl on `&HandlerProbe<H>`.
+pub struct HandlerProbe<H>(PhantomData<H>);
+
+impl<H: Handler> HandlerProbe<H> {
+    #[allow(clippy::new_without_default)]
+    pub const fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+pub trait InstallIndex {
+    fn install_index(&self, vt: &mut bindings::handler_bridge_vt);
+}
+
+impl<H: IndexableHandler> InstallIndex for HandlerProbe<H> {
+    fn install_index(&self, vt: &mut bindings::handler_bridge_vt) {
+        if H::SUPPORTS_INDEX {
+            install_index_callbacks::<H>(vt);
+        }
+    }
+}
+
+pub trait InstallIndexFallback {
+    fn install_index(&self, vt: &mut bindings::handler_bridge_vt);
+}
+
+impl<H: Handler> InstallIndexFallback for &HandlerProbe<H> {
+    fn install_index(&self, vt: &mut bindings::handler_bridge_vt) {
+        const {
+            assert!(
+                !H::SUPPORTS_INDEX,
+                "handlers with `SUPPORTS_INDEX` must implement `IndexableHandler`"
+            );
+        }
+    }
+}
+
+pub trait InstallInplaceAlter {
+    fn install_inplace_alter(&self, vt: &mut bindings::handler_bridge_vt);
+}
+
+impl<H: InplaceAlterTable> InstallInplaceAlter for HandlerProbe<H> {
+    fn install_inplace_alter(&self, vt: &mut bindings::handler_bridge_vt) {
+        if H::SUPPORTS_INPLACE_ALTER {
+            install_inplace_alter_callbacks::<H>(vt);
+        }
+    }
+}
+
+pub trait InstallInplaceAlterFallback {
+    fn install_inplace_alter(&self, vt: &mut bindings::handler_bridge_vt);
+}
+
+impl<H: Handler> InstallInplaceAlterFallback for &HandlerProbe<H> {
+    fn install_inplace_alter(&self, vt: &mut bindings::handler_bridge_vt) {
+        const {
+            assert!(
+                !H::SUPPORTS_INPLACE_ALTER,
+                "handlers with `SUPPORTS_INPLACE_ALTER` must implement `InplaceAlterTable`"
+            );
+        }
+    }
+}
+
+pub trait InstallAutoIncrement {
+    fn install_auto_increment(&self, vt: &mut bindings::handler_bridge_vt);
+}
+
+impl<H: AutoIncrementHandler> InstallAutoIncrement for HandlerProbe<H> {
+    fn install_auto_increment(&self, vt: &mut bindings::handler_bridge_vt) {
+        if H::SUPPORTS_AUTO_INCREMENT {
+            install_auto_increment_callbacks::<H>(vt);
+        }
+    }
+}
+
+pub trait InstallAutoIncrementFallback {
+    fn install_auto_increment(&self, vt: &mut bindings::handler_bridge_vt);
+}
+
+impl<H: Handler> InstallAutoIncrementFallback for &HandlerProbe<H> {
+    fn install_auto_increment(&self, vt: &mut bindings::handler_bridge_vt) {
+        const {
+            assert!(
+                !H::SUPPORTS_AUTO_INCREMENT,
+                "handlers with `SUPPORTS_AUTO_INCREMENT` must implement `AutoIncrementHandler`"
+            );
+        }
+    }
+}
+
+pub trait InstallRepair {
+    fn install_repair(&self, vt: &mut bindings::handler_bridge_vt);
+}
+
+impl<H: RepairingHandler> InstallRepair for HandlerProbe<H> {
+    fn install_repair(&self, vt: &mut bindings::handler_bridge_vt) {
+        if H::SUPPORTS_REPAIR {
+            install_repair_callbacks::<H>(vt);
+        }
+    }
+}
+
+pub trait InstallRepairFallback {
+    fn install_repair(&self, vt: &mut bindings::handler_bridge_vt);
+}
+
+impl<H: Handler> InstallRepairFallback for &HandlerProbe<H> {
+    fn install_repair(&self, vt: &mut bindings::handler_bridge_vt) {
+        const {
+            assert!(
+                !H::SUPPORTS_REPAIR,
+                "handlers with `SUPPORTS_REPAIR` must implement `RepairingHandler`"
+            );
+        }
+    }
+}
+
+pub trait InstallCondPushdown {
+    fn install_cond_pushdown(&self, vt: &mut bindings::handler_bridge_vt);
+}
+
+impl<H: ConditionPushdown> InstallCondPushdown for HandlerProbe<H> {
+    fn install_cond_pushdown(&self, vt: &mut bindings::handler_bridge_vt) {
+        if H::SUPPORTS_COND_PUSHDOWN {
+            install_cond_pushdown_callbacks::<H>(vt);
+        }
+    }
+}
+
+pub trait InstallCondPushdownFallback {
+    fn install_cond_pushdown(&self, vt: &mut bindings::handler_bridge_vt);
+}
+
+impl<H: Handler> InstallCondPushdownFallback for &HandlerProbe<H> {
+    fn install_cond_pushdown(&self, vt: &mut bindings::handler_bridge_vt) {
+        const {
+            assert!(
+                !H::SUPPORTS_COND_PUSHDOWN,
+                "handlers with `SUPPORTS_COND_PUSHDOWN` must implement `ConditionPushdown`"
+            );
+        }
     }
 }
 
@@ -103,7 +798,9 @@ unsafe extern "C" fn wrap_constructor<H: Handler>(
     mem_root: *mut bindings::MEM_ROOT,
     table: *mut bindings::TABLE_SHARE,
 ) {
-    let ha_rs = unsafe { H::new(TableShare::from_raw(table), MemRoot::from_raw(mem_root)) };
+    // The server passes no share for handlers used only for DDL
+    let table = unsafe { table.as_ref() }.map(|t| unsafe { TableShare::from_raw(t) });
+    let ha_rs = unsafe { H::new(table, MemRoot::from_raw(mem_root)) };
     unsafe { (*this).data = Box::into_raw(Box::new(ha_rs)).cast() };
 
     (*this).type_id = mem::transmute(TypeId::of::<H>());
@@ -120,247 +817,559 @@ unsafe extern "C" fn wrap_destructor<H: Handler>(this: *mut bindings::handler_br
     drop(ha_rs);
 }
 
-unsafe extern "C" fn wrap_index_type<H: Handler>(
-    this: *mut bindings::handler_bridge,
-    arg1: c_uint,
-) -> *const c_char {
-    todo!()
+/// Get the Rust handler stored in a bridge
+///
+/// # Safety
+///
+/// `this` must be a bridge that was set up by `wrap_constructor::<H>`, and no other
+/// reference to the handler may be live.
+unsafe fn get_handler<'a, H: Handler>(this: *const bindings::handler_bridge) -> &'a mut H {
+    debug_assert_eq!(
+        mem::transmute::<_, TypeId>((*this).type_id),
+        TypeId::of::<H>()
+    );
+    unsafe { &mut *(*this).data.cast::<H>() }
 }
 
-unsafe extern "C" fn wrap_table_flags<H: Handler>(
-    this: *const bindings::handler_bridge,
-) -> c_ulonglong {
-    todo!()
+/// Get the `handler` base class of a bridge
+unsafe fn get_base<'a>(this: *const bindings::handler_bridge) -> &'a bindings::handler {
+    unsafe { &(*this)._base }
 }
 
-unsafe extern "C" fn wrap_index_flags<H: Handler>(
-    this: *const bindings::handler_bridge,
-    arg1: c_uint,
-    arg2: c_uint,
-    arg3: bool,
-) -> c_ulong {
-    todo!()
+/// The currently open table
+unsafe fn get_table<'a>(this: *const bindings::handler_bridge) -> &'a Table<'a> {
+    unsafe { Table::from_raw(get_base(this).table) }
 }
 
-unsafe extern "C" fn wrap_max_supported_record_length<H: Handler>(
+/// Wrap a row buffer from the server
+unsafe fn make_record<'a>(
     this: *const bindings::handler_bridge,
-) -> c_uint {
-    todo!()
+    buf: *const c_uchar,
+) -> Record<'a> {
+    let table = unsafe { get_table(this) };
+    let buf = unsafe { slice::from_raw_parts(buf, table.share().reclength()) };
+    Record::new(table, buf)
 }
 
-unsafe extern "C" fn wrap_max_supported_keys<H: Handler>(
+/// Wrap a row buffer that the server expects us to fill
+unsafe fn make_record_mut<'a>(
     this: *const bindings::handler_bridge,
-) -> c_uint {
-    todo!()
+    buf: *mut c_uchar,
+) -> RecordMut<'a> {
+    let table = unsafe { get_table(this) };
+    let buf = unsafe { slice::from_raw_parts_mut(buf, table.share().reclength()) };
+    RecordMut::new(table, buf)
+}
+
+/// Turn a name provided by the server into a path
+unsafe fn path_from_ptr<'a>(name: *const c_char) -> Result<&'a Path, StorageError> {
+    let name = unsafe { CStr::from_ptr(name) };
+    // The server encodes table names, so paths are always ASCII
+    name.to_str()
+        .map(Path::new)
+        .map_err(|_| StorageError::InternalError)
+}
+
+/// Convert a count or index from the server, saturating if it does not fit
+fn to_usize<T: TryInto<usize>>(val: T) -> usize {
+    val.try_into().unwrap_or(usize::MAX)
+}
+
+/// Convert a count or index for the server, saturating if it does not fit
+fn to_c_uint(val: usize) -> c_uint {
+    val.try_into().unwrap_or(c_uint::MAX)
 }
 
-unsafe extern "C" fn wrap_max_supported_key_parts<H: Handler>(
+unsafe extern "C" fn wrap_table_flags<H: Handler>(
     this: *const bindings::handler_bridge,
-) -> c_uint {
-    todo!()
+) -> c_ulonglong {
+    let handler: &H = unsafe { get_handler(this) };
+    handler.table_flags().0
 }
 
-unsafe extern "C" fn wrap_max_supported_key_length<H: Handler>(
+unsafe extern "C" fn wrap_max_supported_record_length<H: Handler>(
     this: *const bindings::handler_bridge,
 ) -> c_uint {
-    todo!()
+    to_c_uint(H::MAX_SUPPORTED_RECORD_LENGTH)
 }
 
 unsafe extern "C" fn wrap_scan_time<H: Handler>(
-    arg1: *mut bindings::handler_bridge,
+    this: *mut bindings::handler_bridge,
 ) -> bindings::IO_AND_CPU_COST {
-    todo!()
+    let handler: &H = unsafe { get_handler(this) };
+    let ctx = unsafe { HandlerCtx::from_raw(get_base(this)) };
+    handler.scan_time(ctx).0
 }
 
-unsafe extern "C" fn wrap_keyread_time<H: Handler>(
+unsafe extern "C" fn wrap_keyread_time<H: IndexableHandler>(
     this: *mut bindings::handler_bridge,
-    arg2: c_uint,
-    arg3: c_ulong,
-    arg4: bindings::ha_rows,
-    arg5: c_ulonglong,
+    index: c_uint,
+    ranges: c_ulong,
+    rows: bindings::ha_rows,
+    blocks: c_ulonglong,
 ) -> bindings::IO_AND_CPU_COST {
-    todo!()
+    let handler: &H = unsafe { get_handler(this) };
+    let ctx = unsafe { HandlerCtx::from_raw(get_base(this)) };
+    handler
+        .keyread_time(
+            ctx,
+            to_usize(index),
+            to_usize(ranges),
+            to_usize(rows),
+            to_usize(blocks),
+        )
+        .0
 }
 
 unsafe extern "C" fn wrap_rnd_pos_time<H: Handler>(
     this: *mut bindings::handler_bridge,
-    arg2: bindings::ha_rows,
+    rows: bindings::ha_rows,
 ) -> bindings::IO_AND_CPU_COST {
-    todo!()
+    let handler: &H = unsafe { get_handler(this) };
+    let ctx = unsafe { HandlerCtx::from_raw(get_base(this)) };
+    handler.rnd_pos_time(ctx, to_usize(rows)).0
 }
 
 unsafe extern "C" fn wrap_open<H: Handler>(
     this: *mut bindings::handler_bridge,
-    arg2: *const c_char,
-    arg3: c_int,
-    arg4: c_uint,
+    name: *const c_char,
+    mode: c_int,
+    test_if_locked: c_uint,
 ) -> c_int {
-    todo!()
+    let handler: &mut H = unsafe { get_handler(this) };
+    let name = match unsafe { path_from_ptr(name) } {
+        Ok(name) => name,
+        Err(e) => return e.to_errno(),
+    };
+    let table = unsafe { get_table(this) };
+    to_errno(handler.open(name, table, Mode(mode), OpenOp(test_if_locked)))
 }
 
-unsafe extern "C" fn wrap_close<H: Handler>(arg1: *mut bindings::handler_bridge) -> c_int {
-    todo!()
+unsafe extern "C" fn wrap_close<H: Handler>(this: *mut bindings::handler_bridge) -> c_int {
+    let handler: &mut H = unsafe { get_handler(this) };
+    to_errno(handler.close())
 }
 
 unsafe extern "C" fn wrap_write_row<H: Handler>(
-    arg1: *mut bindings::handler_bridge,
-    arg2: *const c_uchar,
-) -> c_int {
-    todo!()
-}
-
-unsafe extern "C" fn wrap_update_row<H: Handler>(
-    arg1: *mut bindings::handler_bridge,
-    arg2: *const c_uchar,
-    arg3: *const c_uchar,
-) -> c_int {
-    todo!()
-}
-
-unsafe extern "C" fn wrap_delete_row<H: Handler>(
-    arg1: *mut bindings::handler_bridge,
-    arg2: *const c_uchar,
-) -> c_int {
-    todo!()
-}
-
-unsafe extern "C" fn wrap_index_read_map<H: Handler>(
-    arg1: *mut bindings::handler_bridge,
-    arg2: *mut c_uchar,
-    arg3: *const c_uchar,
-    arg4: bindings::key_part_map,
-    arg5: bindings::ha_rkey_function::Type,
+    this: *mut bindings::handler_bridge,
+    buf: *const c_uchar,
 ) -> c_int {
-    todo!()
+    let handler: &mut H = unsafe { get_handler(this) };
+    let buf = unsafe { make_record(this, buf) };
+    to_errno(handler.write_row(&buf))
 }
 
-unsafe extern "C" fn wrap_index_next<H: Handler>(
-    arg1: *mut bindings::handler_bridge,
-    arg2: *mut c_uchar,
-) -> c_int {
-    todo!()
+unsafe extern "C" fn wrap_start_bulk_insert<H: Handler>(
+    this: *mut bindings::handler_bridge,
+    rows: bindings::ha_rows,
+    flags: c_uint,
+) {
+    let handler: &mut H = unsafe { get_handler(this) };
+    handler.start_bulk_insert(rows);
 }
 
-unsafe extern "C" fn wrap_index_prev<H: Handler>(
-    arg1: *mut bindings::handler_bridge,
-    arg2: *mut c_uchar,
+unsafe extern "C" fn wrap_end_bulk_insert<H: Handler>(
+    this: *mut bindings::handler_bridge,
 ) -> c_int {
-    todo!()
+    let handler: &mut H = unsafe { get_handler(this) };
+    to_errno(handler.end_bulk_insert())
 }
 
-unsafe extern "C" fn wrap_index_first<H: Handler>(
-    arg1: *mut bindings::handler_bridge,
-    arg2: *mut c_uchar,
+unsafe extern "C" fn wrap_update_row<H: Handler>(
+    this: *mut bindings::handler_bridge,
+    old_data: *const c_uchar,
+    new_data: *const c_uchar,
 ) -> c_int {
-    todo!()
+    let handler: &mut H = unsafe { get_handler(this) };
+    let old_data = unsafe { make_record(this, old_data) };
+    let new_data = unsafe { make_record(this, new_data) };
+    to_errno(handler.update_row(&old_data, &new_data))
 }
 
-unsafe extern "C" fn wrap_index_last<H: Handler>(
-    arg1: *mut bindings::handler_bridge,
-    arg2: *mut c_uchar,
+unsafe extern "C" fn wrap_delete_row<H: Handler>(
+    this: *mut bindings::handler_bridge,
+    buf: *const c_uchar,
 ) -> c_int {
-    todo!()
+    let handler: &mut H = unsafe { get_handler(this) };
+    let buf = unsafe { make_record(this, buf) };
+    to_errno(handler.delete_row(&buf))
 }
 
 unsafe extern "C" fn wrap_rnd_init<H: Handler>(
-    arg1: *mut bindings::handler_bridge,
-    arg2: bool,
+    this: *mut bindings::handler_bridge,
+    scan: bool,
 ) -> c_int {
-    todo!()
+    let handler: &mut H = unsafe { get_handler(this) };
+    to_errno(handler.rnd_init(scan))
 }
 
-unsafe extern "C" fn wrap_rnd_end<H: Handler>(arg1: *mut bindings::handler_bridge) -> c_int {
-    todo!()
+unsafe extern "C" fn wrap_rnd_end<H: Handler>(this: *mut bindings::handler_bridge) -> c_int {
+    let handler: &mut H = unsafe { get_handler(this) };
+    to_errno(handler.rnd_end())
 }
 
 unsafe extern "C" fn wrap_rnd_next<H: Handler>(
-    arg1: *mut bindings::handler_bridge,
-    arg2: *mut c_uchar,
+    this: *mut bindings::handler_bridge,
+    buf: *mut c_uchar,
 ) -> c_int {
-    todo!()
+    let handler: &mut H = unsafe { get_handler(this) };
+    let mut buf = unsafe { make_record_mut(this, buf) };
+    to_errno(handler.rnd_next(&mut buf))
 }
 
 unsafe extern "C" fn wrap_rnd_pos<H: Handler>(
-    arg1: *mut bindings::handler_bridge,
-    arg2: *mut c_uchar,
-    arg3: *mut c_uchar,
+    this: *mut bindings::handler_bridge,
+    buf: *mut c_uchar,
+    pos: *mut c_uchar,
 ) -> c_int {
-    todo!()
+    let handler: &mut H = unsafe { get_handler(this) };
+    let mut buf = unsafe { make_record_mut(this, buf) };
+    let ref_length = unsafe { get_base(this).ref_length };
+    let pos = unsafe { slice::from_raw_parts(pos, to_usize(ref_length)) };
+    to_errno(handler.rnd_pos(&mut buf, pos))
 }
+
 unsafe extern "C" fn wrap_position<H: Handler>(
-    arg1: *mut bindings::handler_bridge,
-    arg2: *const c_uchar,
+    this: *mut bindings::handler_bridge,
+    record: *const c_uchar,
 ) {
-    todo!()
+    let handler: &mut H = unsafe { get_handler(this) };
+    let record = unsafe { make_record(this, record) };
+    let base = unsafe { get_base(this) };
+    let pos = unsafe { slice::from_raw_parts_mut(base.ref_, to_usize(base.ref_length)) };
+    handler.position(&record, pos);
 }
 
 unsafe extern "C" fn wrap_info<H: Handler>(
-    arg1: *mut bindings::handler_bridge,
-    arg2: c_uint,
+    this: *mut bindings::handler_bridge,
+    flag: c_uint,
 ) -> c_int {
-    todo!()
+    let stats = unsafe { StatisticsMut::from_raw(ptr::addr_of_mut!((*this)._base.stats)) };
+    let handler: &mut H = unsafe { get_handler(this) };
+    to_errno(handler.info(InfoFlags(flag), stats))
 }
 
 unsafe extern "C" fn wrap_extra<H: Handler>(
-    arg1: *mut bindings::handler_bridge,
-    arg2: bindings::ha_extra_function::Type,
+    this: *mut bindings::handler_bridge,
+    operation: bindings::ha_extra_function::Type,
 ) -> c_int {
-    todo!()
+    let handler: &mut H = unsafe { get_handler(this) };
+    to_errno(handler.extra(operation))
 }
 
 unsafe extern "C" fn wrap_external_lock<H: Handler>(
-    arg1: *mut bindings::handler_bridge,
-    arg2: *mut bindings::THD,
-    arg3: c_int,
+    this: *mut bindings::handler_bridge,
+    thd: *mut bindings::THD,
+    lock_type: c_int,
 ) -> c_int {
-    todo!()
+    let handler: &mut H = unsafe { get_handler(this) };
+    let mut thd = unsafe { HandlertonThd::<H::Handlerton>::new(get_base(this).ht, thd) };
+    let Some(lock_type) = ExternalLockType::from_raw(lock_type) else {
+        return StorageError::WrongCommand.to_errno();
+    };
+    to_errno(handler.external_lock(&mut thd, lock_type))
 }
 
 unsafe extern "C" fn wrap_delete_all_rows<H: Handler>(
-    arg1: *mut bindings::handler_bridge,
+    this: *mut bindings::handler_bridge,
 ) -> c_int {
-    todo!()
+    let handler: &mut H = unsafe { get_handler(this) };
+    to_errno(handler.delete_all_rows())
 }
 
-unsafe extern "C" fn wrap_records_in_range<H: Handler>(
-    arg1: *mut bindings::handler_bridge,
-    arg2: c_uint,
-    arg3: *const bindings::key_range,
-    arg4: *const bindings::key_range,
-    arg5: *mut bindings::page_range,
-) -> bindings::ha_rows {
-    todo!()
+unsafe extern "C" fn wrap_delete_table<H: Handler>(
+    this: *mut bindings::handler_bridge,
+    name: *const c_char,
+) -> c_int {
+    let handler: &mut H = unsafe { get_handler(this) };
+    let name = match unsafe { path_from_ptr(name) } {
+        Ok(name) => name,
+        Err(e) => return e.to_errno(),
+    };
+    to_errno(handler.delete_table(&TablePath::new(name)))
 }
 
-unsafe extern "C" fn wrap_delete_table<H: Handler>(
-    arg1: *mut bindings::handler_bridge,
-    arg2: *const c_char,
+unsafe extern "C" fn wrap_rename_table<H: Handler>(
+    this: *mut bindings::handler_bridge,
+    from: *const c_char,
+    to: *const c_char,
 ) -> c_int {
-    todo!()
+    let handler: &mut H = unsafe { get_handler(this) };
+    let (from, to) = match unsafe { (path_from_ptr(from), path_from_ptr(to)) } {
+        (Ok(from), Ok(to)) => (from, to),
+        (Err(e), _) | (_, Err(e)) => return e.to_errno(),
+    };
+    to_errno(handler.rename_table(&TablePath::new(from), &TablePath::new(to)))
+}
+
+unsafe extern "C" fn wrap_truncate<H: Handler>(this: *mut bindings::handler_bridge) -> c_int {
+    let handler: &mut H = unsafe { get_handler(this) };
+    to_errno(handler.truncate())
 }
 
 pub unsafe extern "C" fn wrap_create<H: Handler>(
-    arg1: *mut bindings::handler_bridge,
-    arg2: *const c_char,
-    arg3: *mut bindings::TABLE,
-    arg4: *mut bindings::HA_CREATE_INFO,
+    this: *mut bindings::handler_bridge,
+    name: *const c_char,
+    form: *mut bindings::TABLE,
+    create_info: *mut bindings::HA_CREATE_INFO,
 ) -> c_int {
-    todo!()
+    let handler: &mut H = unsafe { get_handler(this) };
+    let name = match unsafe { path_from_ptr(name) } {
+        Ok(name) => name,
+        Err(e) => return e.to_errno(),
+    };
+    let form = unsafe { Table::from_raw(form) };
+    let create_info = unsafe { CreateInfo::from_raw(create_info) };
+    to_errno(handler.create(name, form, create_info))
 }
 
-pub unsafe extern "C" fn wrap_check_if_supported_inplace_alter<H: Handler>(
-    arg1: *mut bindings::handler_bridge,
-    arg2: *mut bindings::TABLE,
-    arg3: *mut bindings::Alter_inplace_info,
-) -> bindings::enum_alter_inplace_result::Type {
-    todo!()
+/// Message for a [`StorageError::Custom`] that was just returned
+unsafe extern "C" fn wrap_get_error_message(
+    _this: *mut bindings::handler_bridge,
+    error: c_int,
+    length: *mut usize,
+) -> *const c_char {
+    match last_error_message(error) {
+        Some((msg, len)) => {
+            unsafe { *length = len };
+            msg
+        }
+        None => ptr::null(),
+    }
 }
 
 pub unsafe extern "C" fn wrap_store_lock<H: Handler>(
-    arg1: *mut bindings::handler_bridge,
-    arg2: *mut bindings::THD,
-    arg3: *mut *mut bindings::THR_LOCK_DATA,
-    arg4: bindings::thr_lock_type::Type,
+    this: *mut bindings::handler_bridge,
+    thd: *mut bindings::THD,
+    to: *mut *mut bindings::THR_LOCK_DATA,
+    lock_type: bindings::thr_lock_type::Type,
 ) -> *mut *mut bindings::THR_LOCK_DATA {
-    todo!()
+    let handler: &mut H = unsafe { get_handler(this) };
+    let thd = unsafe { Thd::from_raw(thd) };
+    let data = match ThrLockType::from_raw(lock_type) {
+        Some(lock_type) => handler.store_lock(thd, lock_type),
+        None => {
+            // A type added after this crate: get the lock data without changing it,
+            // then request the raw type as `ThrLockData::request` would
+            let mut data = handler.store_lock(thd, ThrLockType::Ignore);
+            if let Some(data) = &mut data {
+                data.request_raw(lock_type);
+            }
+            data
+        }
+    };
+    match data {
+        Some(data) => unsafe {
+            // The server sized the array using `lock_count`, which is 1
+            to.write(data.as_ptr());
+            to.add(1)
+        },
+        None => to,
+    }
+}
+
+unsafe extern "C" fn wrap_index_type<H: IndexableHandler>(
+    this: *mut bindings::handler_bridge,
+    index: c_uint,
+) -> *const c_char {
+    let handler: &H = unsafe { get_handler(this) };
+    handler
+        .index_type(to_usize(index))
+        .map_or(cstr!("").as_ptr(), CStr::as_ptr)
+}
+
+unsafe extern "C" fn wrap_index_flags<H: IndexableHandler>(
+    this: *const bindings::handler_bridge,
+    index: c_uint,
+    part: c_uint,
+    all_parts: bool,
+) -> c_ulong {
+    let handler: &H = unsafe { get_handler(this) };
+    handler
+        .index_flags(to_usize(index), to_usize(part), all_parts)
+        .0
+}
+
+unsafe extern "C" fn wrap_max_supported_keys<H: IndexableHandler>(
+    this: *const bindings::handler_bridge,
+) -> c_uint {
+    let handler: &H = unsafe { get_handler(this) };
+    to_c_uint(handler.max_supported_keys())
+}
+
+unsafe extern "C" fn wrap_max_supported_key_parts<H: IndexableHandler>(
+    this: *const bindings::handler_bridge,
+) -> c_uint {
+    let handler: &H = unsafe { get_handler(this) };
+    to_c_uint(handler.max_supported_key_parts())
+}
+
+unsafe extern "C" fn wrap_max_supported_key_length<H: IndexableHandler>(
+    this: *const bindings::handler_bridge,
+) -> c_uint {
+    let handler: &H = unsafe { get_handler(this) };
+    to_c_uint(handler.max_supported_key_length())
+}
+
+unsafe extern "C" fn wrap_index_init<H: IndexableHandler>(
+    this: *mut bindings::handler_bridge,
+    index: c_uint,
+    sorted: bool,
+) -> c_int {
+    let handler: &mut H = unsafe { get_handler(this) };
+    to_errno(handler.index_init(to_usize(index), sorted))
+}
+
+unsafe extern "C" fn wrap_index_end<H: IndexableHandler>(
+    this: *mut bindings::handler_bridge,
+) -> c_int {
+    let handler: &mut H = unsafe { get_handler(this) };
+    to_errno(handler.index_end())
+}
+
+unsafe extern "C" fn wrap_records_in_range<H: IndexableHandler>(
+    this: *mut bindings::handler_bridge,
+    index: c_uint,
+    min_key: *const bindings::key_range,
+    max_key: *const bindings::key_range,
+    pages: *mut bindings::page_range,
+) -> bindings::ha_rows {
+    let handler: &mut H = unsafe { get_handler(this) };
+    let table = unsafe { get_table(this) };
+    let index = to_usize(index);
+    let Some(key) = table.keys().get(index) else {
+        // `HA_POS_ERROR`
+        return bindings::ha_rows::MAX;
+    };
+    let min = unsafe { KeyRange::from_raw(index, key, min_key) };
+    let max = unsafe { KeyRange::from_raw(index, key, max_key) };
+    let (Ok(min), Ok(max)) = (min, max) else {
+        return bindings::ha_rows::MAX;
+    };
+    let pages = unsafe { PageRange::from_raw(pages) };
+    handler.records_in_range(index, min.as_ref(), max.as_ref(), pages)
+}
+
+unsafe extern "C" fn wrap_index_read_map<H: IndexableHandler>(
+    this: *mut bindings::handler_bridge,
+    buf: *mut c_uchar,
+    key: *const c_uchar,
+    keypart_map: bindings::key_part_map,
+    find_flag: bindings::ha_rkey_function::Type,
+) -> c_int {
+    let handler: &mut H = unsafe { get_handler(this) };
+    let table = unsafe { get_table(this) };
+    let index = to_usize(unsafe { get_base(this) }.active_index);
+    let Some(key_info) = table.keys().get(index) else {
+        return StorageError::WrongIndex.to_errno();
+    };
+    let Some(find) = KeyFind::from_raw(find_flag) else {
+        return StorageError::WrongCommand.to_errno();
+    };
+    let key = unsafe { SearchKey::new(index, key_info, key, keypart_map) };
+    let mut record = unsafe { make_record_mut(this, buf) };
+    to_errno(handler.index_read_map(&mut record, &key, find))
+}
+
+unsafe extern "C" fn wrap_index_next<H: IndexableHandler>(
+    this: *mut bindings::handler_bridge,
+    buf: *mut c_uchar,
+) -> c_int {
+    let handler: &mut H = unsafe { get_handler(this) };
+    let mut record = unsafe { make_record_mut(this, buf) };
+    to_errno(handler.index_next(&mut record))
+}
+
+unsafe extern "C" fn wrap_index_prev<H: IndexableHandler>(
+    this: *mut bindings::handler_bridge,
+    buf: *mut c_uchar,
+) -> c_int {
+    let handler: &mut H = unsafe { get_handler(this) };
+    let mut record = unsafe { make_record_mut(this, buf) };
+    to_errno(handler.index_prev(&mut record))
+}
+
+unsafe extern "C" fn wrap_index_first<H: IndexableHandler>(
+    this: *mut bindings::handler_bridge,
+    buf: *mut c_uchar,
+) -> c_int {
+    let handler: &mut H = unsafe { get_handler(this) };
+    let mut record = unsafe { make_record_mut(this, buf) };
+    to_errno(handler.index_first(&mut record))
+}
+
+unsafe extern "C" fn wrap_index_last<H: IndexableHandler>(
+    this: *mut bindings::handler_bridge,
+    buf: *mut c_uchar,
+) -> c_int {
+    let handler: &mut H = unsafe { get_handler(this) };
+    let mut record = unsafe { make_record_mut(this, buf) };
+    to_errno(handler.index_last(&mut record))
+}
+
+unsafe extern "C" fn wrap_dup_key<H: IndexableHandler>(
+    this: *const bindings::handler_bridge,
+) -> c_uint {
+    let handler: &H = unsafe { get_handler(this) };
+    handler.dup_key().map_or(c_uint::MAX, to_c_uint)
+}
+
+unsafe extern "C" fn wrap_idx_cond_push<H: IndexableHandler>(
+    this: *mut bindings::handler_bridge,
+    index: c_uint,
+    cond: *mut bindings::Item,
+) -> *mut bindings::Item {
+    let handler: &mut H = unsafe { get_handler(this) };
+    let index = to_usize(index);
+    unsafe {
+        push_condition(this, cond, |condition| {
+            handler.idx_cond_push(index, condition)
+        })
+    }
+}
+
+unsafe extern "C" fn wrap_cancel_pushed_idx_cond<H: IndexableHandler>(
+    this: *mut bindings::handler_bridge,
+) {
+    let handler: &mut H = unsafe { get_handler(this) };
+    handler.cancel_pushed_idx_cond();
+}
+
+unsafe extern "C" fn wrap_multi_range_read_info<H: IndexableHandler>(
+    this: *mut bindings::handler_bridge,
+    index: c_uint,
+    n_ranges: c_uint,
+    rows: bindings::ha_rows,
+    mode: c_uint,
+) -> c_uint {
+    let handler: &mut H = unsafe { get_handler(this) };
+    handler
+        .multi_range_read_info(to_usize(index), to_usize(n_ranges), rows, MrrFlags(mode))
+        .0
+}
+
+unsafe extern "C" fn wrap_multi_range_read_init<H: IndexableHandler>(
+    this: *mut bindings::handler_bridge,
+    mode: c_uint,
+) -> c_int {
+    let handler: &mut H = unsafe { get_handler(this) };
+    let mut ranges = unsafe { RangeSeq::from_handler(get_base(this), get_table(this)) };
+    to_errno(handler.multi_range_read_init(&mut ranges, MrrFlags(mode)))
+}
+
+unsafe extern "C" fn wrap_multi_range_read_next<H: IndexableHandler>(
+    this: *mut bindings::handler_bridge,
+    buf: *mut c_uchar,
+    range_info: *mut bindings::range_id_t,
+) -> c_int {
+    let handler: &mut H = unsafe { get_handler(this) };
+    let mut ranges = unsafe { RangeSeq::from_handler(get_base(this), get_table(this)) };
+    let mut record = unsafe { make_record_mut(this, buf) };
+    match handler.multi_range_read_next(&mut record, &mut ranges) {
+        Ok(id) => {
+            unsafe { *range_info = id.0 };
+            0
+        }
+        Err(e) => e.to_errno(),
+    }
 }
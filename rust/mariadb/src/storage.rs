@@ -4,13 +4,93 @@
 
 #![allow(unused)]
 
+/// Implement the set operations shared by the `HA_*` flag newtypes
+macro_rules! impl_flags {
+    ($name:ident, $bits:ty) => {
+        impl $name {
+            /// No flags set
+            pub const fn empty() -> Self {
+                Self(0)
+            }
+
+            pub const fn bits(self) -> $bits {
+                self.0
+            }
+
+            pub const fn is_empty(self) -> bool {
+                self.0 == 0
+            }
+
+            /// Flags set in either `self` or `other`
+            pub const fn union(self, other: Self) -> Self {
+                Self(self.0 | other.0)
+            }
+
+            /// Flags set in `self` but not in `other`
+            pub const fn difference(self, other: Self) -> Self {
+                Self(self.0 & !other.0)
+            }
+
+            /// True if all flags in `other` are set
+            pub const fn contains(self, other: Self) -> bool {
+                self.0 & other.0 == other.0
+            }
+
+            /// True if any flag in `other` is set
+            pub const fn intersects(self, other: Self) -> bool {
+                self.0 & other.0 != 0
+            }
+        }
+
+        impl std::ops::BitOr for $name {
+            type Output = Self;
+
+            fn bitor(self, rhs: Self) -> Self {
+                self.union(rhs)
+            }
+        }
+    };
+}
+
+mod admin;
+mod alter;
+mod auto_increment;
+mod cond;
 mod error;
 mod handler;
 mod handlerton;
+mod key;
+mod lock;
+mod mrr;
+pub(crate) mod options;
+mod record;
+mod table_path;
 
+pub use admin::{AdminMessages, AdminResult, CheckFlags, CheckOptions};
+pub use alter::{AlterFlags, AlterInplaceInfo, AlterInplaceResult};
+pub use auto_increment::{AutoIncrementRequest, AutoIncrementReservation};
+pub(crate) use cond::{condition_from_items, conjunct_items, expr_from_item, remainder_indices};
+pub use cond::{CmpOp, CondRemainder, Condition, Constant, Expr};
+pub(crate) use error::{last_error_message, to_errno};
 pub use error::{StorageError, StorageResult};
-pub use handler::{Handler, Mode, OpenOp};
-pub use handlerton::{Handlerton, HandlertonCtx};
+pub use handler::{
+    AutoIncrementHandler, ConditionPushdown, CreateInfo, ExternalLockType, Handler, HandlerCtx,
+    IndexFlags, IndexableHandler, InfoFlags, InplaceAlterTable, IoAndCpuCost, Mode, OpenOp,
+    OptimizerCosts, RepairingHandler, Statistics, StatisticsMut, TableFlags,
+};
+pub use handlerton::{
+    DiscoveredTables, DiscoveringHandlerton, Handlerton, HandlertonCtx, HandlertonThd,
+};
+pub use key::{KeyFind, KeyRange, PageRange, SearchKey};
+pub use lock::{ThrLock, ThrLockData, ThrLockType};
+pub use mariadb_macros::{FieldOptions, IndexOptions, OptionEnum, TableOptions};
+pub use mrr::{MrrFlags, MultiRange, RangeId, RangeSeq};
+pub(crate) use options::option_list;
+pub use options::{FieldOptions, IndexOptions, OptionEnum, TableOptions};
+pub use record::{Record, RecordMut};
+pub use table_path::{
+    delete_table_files, filename_to_tablename, rename_table_files, tablename_to_filename, TablePath,
+};
 
 use crate::bindings;
 
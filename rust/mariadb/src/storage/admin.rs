@@ -0,0 +1,152 @@
+//! Table maintenance statements: `CHECK`, `REPAIR`, `ANALYZE` and `OPTIMIZE TABLE`
+//!
+//! These are implemented by [`RepairingHandler`](super::RepairingHandler). Each
+//! statement returns one result row per message, plus a final row with the status.
+
+use std::ffi::c_int;
+use std::marker::PhantomData;
+
+use crate::bindings;
+
+/// The result of a maintenance statement (`HA_ADMIN_*`), shown in the final row of
+/// its result set
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AdminResult {
+    /// The operation succeeded
+    Ok,
+    /// Nothing needed to be done, e.g. the table is already up to date
+    AlreadyDone,
+    /// The operation is not supported by this engine
+    NotImplemented,
+    /// The operation failed
+    Failed,
+    /// The table is corrupt and should be repaired
+    Corrupt,
+    /// An internal error occurred
+    InternalError,
+    /// The arguments were invalid for this table
+    Invalid,
+    /// The operation was rejected
+    Reject,
+    /// The engine does not support the operation, but `ALTER TABLE` (recreating the
+    /// table) will have the same effect
+    TryAlter,
+    /// The table checksum does not match
+    WrongChecksum,
+    /// The table was created by an older version and needs to be upgraded
+    NeedsUpgrade,
+    /// The table needs to be recreated with `ALTER TABLE`
+    NeedsAlter,
+    /// The table should be checked
+    NeedsCheck,
+}
+
+impl AdminResult {
+    pub(crate) fn to_raw(self) -> c_int {
+        match self {
+            Self::Ok => bindings::HA_ADMIN_OK,
+            Self::AlreadyDone => bindings::HA_ADMIN_ALREADY_DONE,
+            Self::NotImplemented => bindings::HA_ADMIN_NOT_IMPLEMENTED,
+            Self::Failed => bindings::HA_ADMIN_FAILED,
+            Self::Corrupt => bindings::HA_ADMIN_CORRUPT,
+            Self::InternalError => bindings::HA_ADMIN_INTERNAL_ERROR,
+            Self::Invalid => bindings::HA_ADMIN_INVALID,
+            Self::Reject => bindings::HA_ADMIN_REJECT,
+            Self::TryAlter => bindings::HA_ADMIN_TRY_ALTER,
+            Self::WrongChecksum => bindings::HA_ADMIN_WRONG_CHECKSUM,
+            Self::NeedsUpgrade => bindings::HA_ADMIN_NEEDS_UPGRADE,
+            Self::NeedsAlter => bindings::HA_ADMIN_NEEDS_ALTER,
+            Self::NeedsCheck => bindings::HA_ADMIN_NEEDS_CHECK,
+        }
+    }
+}
+
+/// Options given to a maintenance statement (`HA_CHECK_OPT`)
+#[repr(transparent)]
+pub struct CheckOptions(bindings::HA_CHECK_OPT);
+
+impl CheckOptions {
+    pub(crate) unsafe fn from_raw<'a>(opts: *const bindings::HA_CHECK_OPT) -> &'a Self {
+        unsafe { &*opts.cast() }
+    }
+
+    pub fn flags(&self) -> CheckFlags {
+        CheckFlags(self.0.flags)
+    }
+
+    /// When the operation started, in seconds since the Unix epoch
+    pub fn start_time(&self) -> i64 {
+        self.0.start_time.try_into().unwrap()
+    }
+}
+
+/// Flags set by the options of a maintenance statement (`T_*`)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CheckFlags(pub(crate) u32);
+
+impl CheckFlags {
+    /// `QUICK`: `CHECK` should not scan rows, `REPAIR` should only repair indexes
+    pub const QUICK: Self = Self(bindings::T_QUICK);
+    /// `FAST`: only check tables that were not closed properly
+    pub const FAST: Self = Self(bindings::T_FAST);
+    /// `MEDIUM`: scan rows and verify links between rows and indexes (the default)
+    pub const MEDIUM: Self = Self(bindings::T_MEDIUM);
+    /// `EXTENDED`: do a full lookup of every key for every row
+    pub const EXTEND: Self = Self(bindings::T_EXTEND);
+    /// `CHANGED`: only check tables changed since the last check
+    pub const CHANGED: Self = Self(bindings::T_CHECK_ONLY_CHANGED);
+    /// This is an automatic repair of a crashed table, not a user statement
+    pub const AUTO_REPAIR: Self = Self(bindings::T_AUTO_REPAIR);
+}
+
+impl_flags!(CheckFlags, u32);
+
+/// Messages to return in the result set of a maintenance statement
+///
+/// Each message becomes a row with the `Msg_type` `note`, `warning` or `error`,
+/// before the final status row.
+pub struct AdminMessages<'a> {
+    thd: *mut bindings::THD,
+    phantom: PhantomData<&'a mut bindings::THD>,
+}
+
+impl<'a> AdminMessages<'a> {
+    pub(crate) unsafe fn new(thd: *mut bindings::THD) -> Self {
+        Self {
+            thd,
+            phantom: PhantomData,
+        }
+    }
+
+    pub fn note(&mut self, msg: &str) {
+        self.push(bindings::ha_bridge_msg_level::HA_BRIDGE_MSG_NOTE, msg);
+    }
+
+    pub fn warning(&mut self, msg: &str) {
+        self.push(bindings::ha_bridge_msg_level::HA_BRIDGE_MSG_WARNING, msg);
+    }
+
+    pub fn error(&mut self, msg: &str) {
+        self.push(bindings::ha_bridge_msg_level::HA_BRIDGE_MSG_ERROR, msg);
+    }
+
+    fn push(&mut self, level: bindings::ha_bridge_msg_level::Type, msg: &str) {
+        unsafe { bindings::ha_bridge_admin_msg(self.thd, level, msg.as_ptr().cast(), msg.len()) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_flags() {
+        let flags = CheckFlags::QUICK | CheckFlags::CHANGED;
+        assert!(flags.contains(CheckFlags::QUICK));
+        assert!(!flags.contains(CheckFlags::QUICK | CheckFlags::EXTEND));
+        assert!(flags.intersects(CheckFlags::EXTEND | CheckFlags::CHANGED));
+        assert_eq!(AdminResult::Ok.to_raw(), 0);
+        assert!(AdminResult::Corrupt.to_raw() < 0);
+    }
+}
@@ -0,0 +1,288 @@
+//! In-place `ALTER TABLE`
+//!
+//! Without in-place support, the server implements `ALTER TABLE` by creating a new
+//! table and copying every row. An engine implementing
+//! [`InplaceAlterTable`](super::InplaceAlterTable) can instead apply some changes
+//! (e.g. adding an index) to the existing table.
+
+use std::ffi::CStr;
+use std::marker::PhantomData;
+use std::{ptr, slice};
+
+use super::CreateInfo;
+use crate::{bindings, Field, Key, Table};
+
+/// Whether and how an `ALTER TABLE` can be done in place, mirroring
+/// `enum_alter_inplace_result`
+///
+/// The lock variants say which lock must be held while
+/// [`InplaceAlterTable::inplace_alter_table`](super::InplaceAlterTable::inplace_alter_table)
+/// runs. The server may pick a stronger lock if the user requested one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AlterInplaceResult {
+    /// An error occurred
+    Error,
+    /// The table is rebuilt, concurrent reads and writes are allowed
+    CopyNoLock,
+    /// The table is rebuilt, concurrent reads are allowed
+    CopyLock,
+    /// The table is not rebuilt, concurrent reads are allowed
+    NoCopyLock,
+    /// The table is not rebuilt, concurrent reads and writes are allowed
+    NoCopyNoLock,
+    /// Only metadata changes, no data is touched
+    Instant,
+    /// The change must be done by copying the table
+    NotSupported,
+    /// No concurrent access is allowed
+    ExclusiveLock,
+    /// Concurrent reads are allowed
+    SharedLock,
+    /// Concurrent reads and writes are allowed
+    NoLock,
+}
+
+impl AlterInplaceResult {
+    pub(crate) fn to_raw(self) -> bindings::enum_alter_inplace_result::Type {
+        use bindings::enum_alter_inplace_result as r;
+        match self {
+            Self::Error => r::HA_ALTER_ERROR,
+            Self::CopyNoLock => r::HA_ALTER_INPLACE_COPY_NO_LOCK,
+            Self::CopyLock => r::HA_ALTER_INPLACE_COPY_LOCK,
+            Self::NoCopyLock => r::HA_ALTER_INPLACE_NOCOPY_LOCK,
+            Self::NoCopyNoLock => r::HA_ALTER_INPLACE_NOCOPY_NO_LOCK,
+            Self::Instant => r::HA_ALTER_INPLACE_INSTANT,
+            Self::NotSupported => r::HA_ALTER_INPLACE_NOT_SUPPORTED,
+            Self::ExclusiveLock => r::HA_ALTER_INPLACE_EXCLUSIVE_LOCK,
+            Self::SharedLock => r::HA_ALTER_INPLACE_SHARED_LOCK,
+            Self::NoLock => r::HA_ALTER_INPLACE_NO_LOCK,
+        }
+    }
+}
+
+/// The kinds of change made by an `ALTER TABLE` (`ALTER_*` flags)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AlterFlags(pub(crate) u64);
+
+impl AlterFlags {
+    pub const ADD_VIRTUAL_COLUMN: Self = Self(bindings::ALTER_ADD_VIRTUAL_COLUMN);
+    pub const ADD_STORED_BASE_COLUMN: Self = Self(bindings::ALTER_ADD_STORED_BASE_COLUMN);
+    pub const ADD_STORED_GENERATED_COLUMN: Self = Self(bindings::ALTER_ADD_STORED_GENERATED_COLUMN);
+    /// Any column is added
+    pub const ADD_COLUMN: Self = Self(
+        Self::ADD_VIRTUAL_COLUMN.0
+            | Self::ADD_STORED_BASE_COLUMN.0
+            | Self::ADD_STORED_GENERATED_COLUMN.0,
+    );
+    pub const DROP_VIRTUAL_COLUMN: Self = Self(bindings::ALTER_DROP_VIRTUAL_COLUMN);
+    pub const DROP_STORED_COLUMN: Self = Self(bindings::ALTER_DROP_STORED_COLUMN);
+    /// Any column is dropped
+    pub const DROP_COLUMN: Self = Self(Self::DROP_VIRTUAL_COLUMN.0 | Self::DROP_STORED_COLUMN.0);
+    /// A column is renamed
+    pub const COLUMN_NAME: Self = Self(bindings::ALTER_COLUMN_NAME);
+    /// The type of a stored column changes
+    pub const STORED_COLUMN_TYPE: Self = Self(bindings::ALTER_STORED_COLUMN_TYPE);
+    /// Stored columns are reordered
+    pub const STORED_COLUMN_ORDER: Self = Self(bindings::ALTER_STORED_COLUMN_ORDER);
+    /// A column changes from `NOT NULL` to `NULL`
+    pub const COLUMN_NULLABLE: Self = Self(bindings::ALTER_COLUMN_NULLABLE);
+    /// A column changes from `NULL` to `NOT NULL`
+    pub const COLUMN_NOT_NULLABLE: Self = Self(bindings::ALTER_COLUMN_NOT_NULLABLE);
+    /// A column default is set or dropped
+    pub const COLUMN_DEFAULT: Self = Self(bindings::ALTER_CHANGE_COLUMN_DEFAULT);
+    /// Engine-defined column options changed
+    pub const COLUMN_OPTION: Self = Self(bindings::ALTER_COLUMN_OPTION);
+    pub const ADD_INDEX: Self = Self(bindings::ALTER_ADD_NON_UNIQUE_NON_PRIM_INDEX);
+    pub const DROP_INDEX: Self = Self(bindings::ALTER_DROP_NON_UNIQUE_NON_PRIM_INDEX);
+    pub const ADD_UNIQUE_INDEX: Self = Self(bindings::ALTER_ADD_UNIQUE_INDEX);
+    pub const DROP_UNIQUE_INDEX: Self = Self(bindings::ALTER_DROP_UNIQUE_INDEX);
+    pub const ADD_PK_INDEX: Self = Self(bindings::ALTER_ADD_PK_INDEX);
+    pub const DROP_PK_INDEX: Self = Self(bindings::ALTER_DROP_PK_INDEX);
+    /// An index is renamed
+    pub const RENAME_INDEX: Self = Self(bindings::ALTER_RENAME_INDEX);
+    /// Table options (including engine-defined ones) changed
+    pub const CHANGE_CREATE_OPTION: Self = Self(bindings::ALTER_OPTIONS);
+    /// `FORCE`, or `ENGINE=` with the same engine
+    pub const RECREATE: Self = Self(bindings::ALTER_RECREATE);
+}
+
+impl_flags!(AlterFlags, u64);
+
+/// A description of an in-place `ALTER TABLE` (`Alter_inplace_info`)
+pub struct AlterInplaceInfo<'a> {
+    inner: *mut bindings::Alter_inplace_info,
+    table: &'a Table<'a>,
+    altered_table: &'a Table<'a>,
+    phantom: PhantomData<&'a mut bindings::Alter_inplace_info>,
+}
+
+impl<'a> AlterInplaceInfo<'a> {
+    pub(crate) unsafe fn new(
+        inner: *mut bindings::Alter_inplace_info,
+        table: &'a Table<'a>,
+        altered_table: &'a Table<'a>,
+    ) -> Self {
+        debug_assert!(!inner.is_null());
+        Self {
+            inner,
+            table,
+            altered_table,
+            phantom: PhantomData,
+        }
+    }
+
+    /// What kinds of changes are made
+    pub fn flags(&self) -> AlterFlags {
+        AlterFlags(unsafe { bindings::ha_bridge_alter_flags(self.inner) })
+    }
+
+    /// The table as it is before the change
+    pub fn table(&self) -> &'a Table<'a> {
+        self.table
+    }
+
+    /// The table as it will be after the change. This is not opened by the engine;
+    /// only use it to look at the new definition.
+    pub fn altered_table(&self) -> &'a Table<'a> {
+        self.altered_table
+    }
+
+    /// Create options for the new version of the table
+    pub fn create_info(&self) -> &'a CreateInfo<'a> {
+        unsafe { CreateInfo::from_raw(bindings::ha_bridge_alter_create_info(self.inner)) }
+    }
+
+    /// Whether table options changed, see [`AlterInplaceInfo::create_info`]
+    pub fn options_changed(&self) -> bool {
+        self.flags().contains(AlterFlags::CHANGE_CREATE_OPTION)
+    }
+
+    /// Index in [`AlterInplaceInfo::altered_table`] of a column of the old table,
+    /// `None` if it is dropped
+    pub fn new_field_index(&self, old: &Field) -> Option<usize> {
+        let idx = unsafe { bindings::ha_bridge_alter_new_field_index(self.inner, old.as_raw()) };
+        idx.try_into().ok()
+    }
+
+    /// Columns of the new table that do not exist in the old one
+    pub fn added_columns(&self) -> Vec<&'a Field<'a>> {
+        let kept: Vec<usize> = self
+            .table
+            .fields()
+            .iter()
+            .filter_map(|f| self.new_field_index(f))
+            .collect();
+        let fields: &'a [&'a Field<'a>] = self.altered_table.fields();
+        fields
+            .iter()
+            .enumerate()
+            .filter(|(idx, _)| !kept.contains(idx))
+            .map(|(_, f)| *f)
+            .collect()
+    }
+
+    /// Columns of the old table that are dropped
+    pub fn dropped_columns(&self) -> Vec<&'a Field<'a>> {
+        let fields: &'a [&'a Field<'a>] = self.table.fields();
+        fields
+            .iter()
+            .filter(|f| self.new_field_index(f).is_none())
+            .copied()
+            .collect()
+    }
+
+    /// Renamed columns as `(old, new)`
+    pub fn renamed_columns(&self) -> Vec<(&'a Field<'a>, &'a Field<'a>)> {
+        let old_fields: &'a [&'a Field<'a>] = self.table.fields();
+        let new_fields: &'a [&'a Field<'a>] = self.altered_table.fields();
+        old_fields
+            .iter()
+            .filter_map(|old| {
+                let new = new_fields[self.new_field_index(old)?];
+                (old.name() != new.name()).then_some((*old, new))
+            })
+            .collect()
+    }
+
+    /// Indexes of the new table that are added
+    pub fn added_indexes(&self) -> Vec<&'a Key<'a>> {
+        let mut count = 0;
+        let ptr = unsafe { bindings::ha_bridge_alter_added_keys(self.inner, &mut count) };
+        let keys: &'a [Key<'a>] = self.altered_table.keys();
+        // SAFETY: this is `index_add_buffer`, with `count` key numbers that live as long
+        // as the alter info
+        unsafe { raw_slice(ptr, count) }
+            .iter()
+            .map(|&idx| &keys[usize::try_from(idx).unwrap()])
+            .collect()
+    }
+
+    /// Indexes of the old table that are dropped
+    pub fn dropped_indexes(&self) -> Vec<&'a Key<'a>> {
+        let mut count = 0;
+        let ptr = unsafe { bindings::ha_bridge_alter_dropped_keys(self.inner, &mut count) };
+        // SAFETY: this is `index_drop_buffer`, with `count` keys that live as long as
+        // the alter info
+        unsafe { raw_slice(ptr, count) }
+            .iter()
+            .map(|&key| unsafe { Key::from_raw(key) })
+            .collect()
+    }
+
+    /// Renamed indexes as `(old, new)`, where the definition stays the same
+    pub fn renamed_indexes(&self) -> Vec<(&'a Key<'a>, &'a Key<'a>)> {
+        let count = unsafe { bindings::ha_bridge_alter_renamed_key_count(self.inner) };
+        let mut buf_len = 0;
+        let buf = unsafe { bindings::ha_bridge_alter_key_info(self.inner, &mut buf_len) };
+        let keys: &'a [Key<'a>] = self.altered_table.keys();
+
+        (0..count)
+            .map(|i| {
+                let mut old = ptr::null();
+                let mut new = ptr::null();
+                unsafe { bindings::ha_bridge_alter_renamed_key(self.inner, i, &mut old, &mut new) };
+                // New keys point into the alter buffer, which has the same order as the
+                // altered table's keys
+                let new_idx = unsafe { new.offset_from(buf) };
+                let new_key = &keys[usize::try_from(new_idx).unwrap()];
+                (unsafe { Key::from_raw(old) }, new_key)
+            })
+            .collect()
+    }
+
+    /// Set the reason shown to the user when returning
+    /// [`AlterInplaceResult::NotSupported`] for an `ALTER TABLE` with an explicit
+    /// `ALGORITHM`
+    pub fn set_unsupported_reason(&mut self, reason: &'static CStr) {
+        unsafe { bindings::ha_bridge_alter_set_unsupported_reason(self.inner, reason.as_ptr()) }
+    }
+}
+
+/// A slice of `count` elements at `ptr`, empty if `ptr` is null
+///
+/// # Safety
+///
+/// Unless `ptr` is null or `count` is 0, `ptr` must point to `count` initialized
+/// values of `T` that stay valid and unchanged for `'b`.
+unsafe fn raw_slice<'b, T>(ptr: *const T, count: u32) -> &'b [T] {
+    if count == 0 || ptr.is_null() {
+        return &[];
+    }
+    unsafe { slice::from_raw_parts(ptr, count.try_into().unwrap()) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_alter_flags() {
+        let flags = AlterFlags::ADD_STORED_BASE_COLUMN | AlterFlags::ADD_INDEX;
+        assert!(flags.intersects(AlterFlags::ADD_COLUMN));
+        assert!(!flags.contains(AlterFlags::ADD_COLUMN));
+        assert!(flags.contains(AlterFlags::ADD_INDEX));
+        assert!(flags
+            .difference(AlterFlags::ADD_COLUMN | AlterFlags::ADD_INDEX)
+            .is_empty());
+    }
+}
@@ -0,0 +1,112 @@
+//! `AUTO_INCREMENT` value reservation
+//!
+//! When a row is inserted without a value for its `AUTO_INCREMENT` column, the
+//! server asks the engine to reserve one or more values via
+//! [`AutoIncrementHandler::get_auto_increment`](super::AutoIncrementHandler::get_auto_increment),
+//! then hands them out itself for this and the following rows of the statement.
+
+/// A request for `AUTO_INCREMENT` values
+///
+/// The server wants values of the form `offset + N * increment`, taken from the
+/// `auto_increment_offset` and `auto_increment_increment` variables. Engines do not
+/// need to return an aligned value; the server rounds up to the next one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AutoIncrementRequest {
+    offset: u64,
+    increment: u64,
+    nb_desired_values: u64,
+}
+
+impl AutoIncrementRequest {
+    pub(crate) fn new(offset: u64, increment: u64, nb_desired_values: u64) -> Self {
+        Self {
+            offset,
+            increment,
+            nb_desired_values,
+        }
+    }
+
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    pub fn increment(&self) -> u64 {
+        self.increment
+    }
+
+    /// How many values the statement expects to use. This is a hint; 0 means
+    /// unknown, e.g. for `INSERT ... SELECT`.
+    pub fn nb_desired_values(&self) -> u64 {
+        self.nb_desired_values
+    }
+
+    /// The first value of the requested form that is greater than `after`, or
+    /// `None` on overflow. This is the server's `compute_next_insert_id`.
+    pub fn next_value(&self, after: u64) -> Option<u64> {
+        let increment = self.increment.max(1);
+        let next = match after.checked_add(increment)?.checked_sub(self.offset) {
+            Some(n) => (n / increment)
+                .checked_mul(increment)?
+                .checked_add(self.offset)?,
+            None => self.offset,
+        };
+        (next > after).then_some(next)
+    }
+}
+
+/// The values reserved by
+/// [`AutoIncrementHandler::get_auto_increment`](super::AutoIncrementHandler::get_auto_increment)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AutoIncrementReservation {
+    first_value: u64,
+    nb_reserved_values: u64,
+}
+
+impl AutoIncrementReservation {
+    /// Reserve `nb_reserved_values` values starting at `first_value`. No other
+    /// statement may be given values in that interval.
+    pub fn new(first_value: u64, nb_reserved_values: u64) -> Self {
+        Self {
+            first_value,
+            nb_reserved_values,
+        }
+    }
+
+    /// Reserve every value from `first_value` on, for engines that lock the table
+    /// for the whole statement
+    pub fn unlimited(first_value: u64) -> Self {
+        Self::new(first_value, u64::MAX)
+    }
+
+    pub fn first_value(&self) -> u64 {
+        self.first_value
+    }
+
+    /// Number of values reserved, `u64::MAX` if unlimited
+    pub fn nb_reserved_values(&self) -> u64 {
+        self.nb_reserved_values
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_value() {
+        let req = AutoIncrementRequest::new(1, 1, 1);
+        assert_eq!(req.next_value(0), Some(1));
+        assert_eq!(req.next_value(41), Some(42));
+        assert_eq!(req.next_value(u64::MAX), None);
+
+        let req = AutoIncrementRequest::new(3, 10, 1);
+        assert_eq!(req.next_value(0), Some(3));
+        assert_eq!(req.next_value(3), Some(13));
+        assert_eq!(req.next_value(12), Some(13));
+        assert_eq!(req.next_value(13), Some(23));
+
+        let req = AutoIncrementRequest::new(20, 5, 1);
+        assert_eq!(req.next_value(0), Some(20));
+        assert_eq!(req.next_value(20), Some(25));
+    }
+}
@@ -0,0 +1,379 @@
+//! Conditions pushed down from the server's `WHERE` clause
+//!
+//! The server represents expressions as a tree of `Item`s. For pushdown these are
+//! converted into an owned [`Expr`] tree, which only covers what an engine can
+//! reasonably evaluate itself: columns of its table, constants, comparisons, logical
+//! operators, `IN`, `LIKE` and `IS NULL`.
+//!
+//! A condition is split into its top-level `AND` terms ("conjuncts"). Terms that
+//! cannot be converted are still reported, so the engine can tell which terms it
+//! has seen, but the server always keeps checking them.
+
+use std::slice;
+
+use crate::{bindings, Value};
+
+/// An expression from a pushed condition
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expr {
+    /// A column of this handler's table, as an index into
+    /// [`Table::fields`](crate::Table::fields)
+    Column(usize),
+    /// A constant
+    Const(Constant),
+    /// A comparison between two expressions
+    Cmp {
+        op: CmpOp,
+        left: Box<Expr>,
+        right: Box<Expr>,
+    },
+    /// All expressions are true
+    And(Vec<Expr>),
+    /// Any expression is true
+    Or(Vec<Expr>),
+    /// The expression is false
+    Not(Box<Expr>),
+    /// `expr IN (list...)`, or `NOT IN` if `negated` is set
+    In {
+        expr: Box<Expr>,
+        list: Vec<Expr>,
+        negated: bool,
+    },
+    /// `expr LIKE pattern`, or `NOT LIKE` if `negated` is set. The pattern uses `\`
+    /// as its escape character.
+    Like {
+        expr: Box<Expr>,
+        pattern: Box<Expr>,
+        negated: bool,
+    },
+    /// `expr IS NULL`, or `IS NOT NULL` if `negated` is set
+    IsNull { expr: Box<Expr>, negated: bool },
+}
+
+/// A comparison operator
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CmpOp {
+    /// `=`
+    Eq,
+    /// `<=>`, which is true if both sides are `NULL`
+    NullSafeEq,
+    /// `<>` or `!=`
+    Ne,
+    /// `<`
+    Lt,
+    /// `<=`
+    Le,
+    /// `>`
+    Gt,
+    /// `>=`
+    Ge,
+}
+
+/// A constant value in an expression
+#[derive(Clone, Debug, PartialEq)]
+pub struct Constant(ConstInner);
+
+#[derive(Clone, Debug, PartialEq)]
+enum ConstInner {
+    Null,
+    Int(i64),
+    UInt(u64),
+    Real(f64),
+    Decimal(Box<[u8]>),
+    String(Box<[u8]>),
+}
+
+impl Constant {
+    /// The value of this constant. Integers are [`Value::LongLong`] or
+    /// [`Value::ULongLong`], reals are [`Value::F64`], decimals are [`Value::Decimal`]
+    /// in text form, and everything else (including temporal values) is a
+    /// [`Value::String`] as the server would print it.
+    pub fn value(&self) -> Value<'_> {
+        match &self.0 {
+            ConstInner::Null => Value::Null,
+            ConstInner::Int(v) => Value::LongLong(*v),
+            ConstInner::UInt(v) => Value::ULongLong(*v),
+            ConstInner::Real(v) => Value::F64(*v),
+            ConstInner::Decimal(v) => Value::Decimal(v),
+            ConstInner::String(v) => Value::String(v),
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self.0, ConstInner::Null)
+    }
+}
+
+/// A condition pushed to the engine, split into its top-level `AND` terms
+#[derive(Clone, Debug)]
+pub struct Condition {
+    conjuncts: Vec<Option<Expr>>,
+}
+
+impl Condition {
+    /// All terms of the condition; `None` for terms that could not be converted.
+    /// Indices into this slice are used by [`CondRemainder::Conjuncts`].
+    pub fn conjuncts(&self) -> &[Option<Expr>] {
+        &self.conjuncts
+    }
+
+    /// Terms that could be converted, with their index
+    pub fn supported(&self) -> impl Iterator<Item = (usize, &Expr)> {
+        self.conjuncts
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, expr)| expr.as_ref().map(|e| (idx, e)))
+    }
+
+    pub fn into_conjuncts(self) -> Vec<Option<Expr>> {
+        self.conjuncts
+    }
+
+    /// Which conjuncts could be converted
+    pub(crate) fn converted(&self) -> Vec<bool> {
+        self.conjuncts.iter().map(Option::is_some).collect()
+    }
+}
+
+/// What the server still needs to check after a condition was pushed
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CondRemainder {
+    /// The engine only returns rows matching the whole condition
+    Nothing,
+    /// The engine did not use the condition
+    All,
+    /// The server must check these terms (by index into
+    /// [`Condition::conjuncts`]). Terms that could not be converted are always
+    /// checked, so they do not need to be listed.
+    Conjuncts(Vec<usize>),
+}
+
+/// Split `cond` into its top-level `AND` terms
+///
+/// # Safety
+///
+/// `cond` must be a valid, fixed `Item`.
+pub(crate) unsafe fn conjunct_items(
+    cond: *mut bindings::Item,
+    table: *const bindings::TABLE,
+) -> Vec<*mut bindings::Item> {
+    let kind = unsafe { bindings::item_bridge_get_kind(cond, table) };
+    if kind == bindings::item_bridge_kind::ITEM_BRIDGE_AND {
+        unsafe { item_args(cond) }
+    } else {
+        vec![cond]
+    }
+}
+
+/// Convert conjuncts into a [`Condition`]
+///
+/// # Safety
+///
+/// All items must be valid and fixed, and `table` must be the table they refer to.
+pub(crate) unsafe fn condition_from_items(
+    items: &[*mut bindings::Item],
+    table: *const bindings::TABLE,
+) -> Condition {
+    let conjuncts = items
+        .iter()
+        .map(|&item| unsafe { expr_from_item(item, table) })
+        .collect();
+    Condition { conjuncts }
+}
+
+/// Convert an `Item` into an expression, `None` if any part of it is not supported
+///
+/// # Safety
+///
+/// `item` must be valid and fixed, and `table` must be the table it refers to.
+pub(crate) unsafe fn expr_from_item(
+    item: *mut bindings::Item,
+    table: *const bindings::TABLE,
+) -> Option<Expr> {
+    use bindings::item_bridge_kind as ik;
+
+    let kind = unsafe { bindings::item_bridge_get_kind(item, table) };
+    let args = || unsafe { item_args(item) };
+    let arg_exprs = || {
+        args()
+            .into_iter()
+            .map(|arg| unsafe { expr_from_item(arg, table) })
+            .collect::<Option<Vec<_>>>()
+    };
+    let cmp = |op| {
+        let [left, right]: [Expr; 2] = arg_exprs()?.try_into().ok()?;
+        Some(Expr::Cmp {
+            op,
+            left: Box::new(left),
+            right: Box::new(right),
+        })
+    };
+    let unary = || {
+        let [expr]: [Expr; 1] = arg_exprs()?.try_into().ok()?;
+        Some(Box::new(expr))
+    };
+
+    let ret = match kind {
+        ik::ITEM_BRIDGE_FIELD => {
+            let idx = unsafe { bindings::item_bridge_field_index(item) };
+            Expr::Column(idx.try_into().unwrap())
+        }
+        ik::ITEM_BRIDGE_NULL
+        | ik::ITEM_BRIDGE_INT
+        | ik::ITEM_BRIDGE_UINT
+        | ik::ITEM_BRIDGE_REAL
+        | ik::ITEM_BRIDGE_DECIMAL
+        | ik::ITEM_BRIDGE_STRING => Expr::Const(unsafe { constant_from_item(item, kind) }),
+        ik::ITEM_BRIDGE_EQ => cmp(CmpOp::Eq)?,
+        ik::ITEM_BRIDGE_EQUAL => cmp(CmpOp::NullSafeEq)?,
+        ik::ITEM_BRIDGE_NE => cmp(CmpOp::Ne)?,
+        ik::ITEM_BRIDGE_LT => cmp(CmpOp::Lt)?,
+        ik::ITEM_BRIDGE_LE => cmp(CmpOp::Le)?,
+        ik::ITEM_BRIDGE_GT => cmp(CmpOp::Gt)?,
+        ik::ITEM_BRIDGE_GE => cmp(CmpOp::Ge)?,
+        ik::ITEM_BRIDGE_AND => Expr::And(arg_exprs()?),
+        ik::ITEM_BRIDGE_OR => Expr::Or(arg_exprs()?),
+        ik::ITEM_BRIDGE_NOT => Expr::Not(unary()?),
+        ik::ITEM_BRIDGE_IN | ik::ITEM_BRIDGE_NOT_IN => {
+            let mut list = arg_exprs()?;
+            if list.is_empty() {
+                return None;
+            }
+            let expr = list.remove(0);
+            Expr::In {
+                expr: Box::new(expr),
+                list,
+                negated: kind == ik::ITEM_BRIDGE_NOT_IN,
+            }
+        }
+        ik::ITEM_BRIDGE_LIKE | ik::ITEM_BRIDGE_NOT_LIKE => {
+            let [expr, pattern]: [Expr; 2] = arg_exprs()?.try_into().ok()?;
+            Expr::Like {
+                expr: Box::new(expr),
+                pattern: Box::new(pattern),
+                negated: kind == ik::ITEM_BRIDGE_NOT_LIKE,
+            }
+        }
+        ik::ITEM_BRIDGE_IS_NULL => Expr::IsNull {
+            expr: unary()?,
+            negated: false,
+        },
+        ik::ITEM_BRIDGE_IS_NOT_NULL => Expr::IsNull {
+            expr: unary()?,
+            negated: true,
+        },
+        _ => return None,
+    };
+
+    Some(ret)
+}
+
+/// Evaluate a constant item
+unsafe fn constant_from_item(
+    item: *mut bindings::Item,
+    kind: bindings::item_bridge_kind::Type,
+) -> Constant {
+    use bindings::item_bridge_kind as ik;
+
+    let inner = match kind {
+        ik::ITEM_BRIDGE_INT => ConstInner::Int(unsafe { bindings::item_bridge_val_int(item) }),
+        ik::ITEM_BRIDGE_UINT => {
+            // The server returns unsigned values as the same bits in a `longlong`
+            let val = unsafe { bindings::item_bridge_val_int(item) };
+            ConstInner::UInt(u64::from_ne_bytes(val.to_ne_bytes()))
+        }
+        ik::ITEM_BRIDGE_REAL => ConstInner::Real(unsafe { bindings::item_bridge_val_real(item) }),
+        ik::ITEM_BRIDGE_DECIMAL | ik::ITEM_BRIDGE_STRING => {
+            let mut len = 0;
+            let ptr = unsafe { bindings::item_bridge_val_str(item, &mut len) };
+            if ptr.is_null() {
+                return Constant(ConstInner::Null);
+            }
+            let bytes: Box<[u8]> = unsafe { slice::from_raw_parts(ptr.cast(), len) }.into();
+            if kind == ik::ITEM_BRIDGE_DECIMAL {
+                ConstInner::Decimal(bytes)
+            } else {
+                ConstInner::String(bytes)
+            }
+        }
+        _ => ConstInner::Null,
+    };
+
+    if unsafe { bindings::item_bridge_null_value(item) } {
+        Constant(ConstInner::Null)
+    } else {
+        Constant(inner)
+    }
+}
+
+/// Arguments of a function or `AND`/`OR` item
+unsafe fn item_args(item: *mut bindings::Item) -> Vec<*mut bindings::Item> {
+    let count = unsafe { bindings::item_bridge_arg_count(item) };
+    let mut args = vec![std::ptr::null_mut(); count.try_into().unwrap()];
+    unsafe { bindings::item_bridge_args(item, args.as_mut_ptr()) };
+    args
+}
+
+/// Indices of the conjuncts that the server still has to check. `converted` says
+/// which conjuncts were passed to the engine as expressions.
+pub(crate) fn remainder_indices(converted: &[bool], remainder: CondRemainder) -> Vec<usize> {
+    let unsupported = converted
+        .iter()
+        .enumerate()
+        .filter(|(_, &ok)| !ok)
+        .map(|(idx, _)| idx);
+    let mut keep: Vec<usize> = match remainder {
+        CondRemainder::Nothing => unsupported.collect(),
+        CondRemainder::All => (0..converted.len()).collect(),
+        CondRemainder::Conjuncts(list) => unsupported
+            .chain(list)
+            .filter(|&idx| idx < converted.len())
+            .collect(),
+    };
+    keep.sort_unstable();
+    keep.dedup();
+    keep
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn col_eq(col: usize, val: i64) -> Expr {
+        Expr::Cmp {
+            op: CmpOp::Eq,
+            left: Box::new(Expr::Column(col)),
+            right: Box::new(Expr::Const(Constant(ConstInner::Int(val)))),
+        }
+    }
+
+    #[test]
+    fn test_remainder_indices() {
+        let cond = Condition {
+            conjuncts: vec![Some(col_eq(0, 1)), None, Some(col_eq(1, 2))],
+        };
+        let converted = cond.converted();
+        assert_eq!(converted, [true, false, true]);
+        assert_eq!(remainder_indices(&converted, CondRemainder::Nothing), [1]);
+        assert_eq!(remainder_indices(&converted, CondRemainder::All), [0, 1, 2]);
+        assert_eq!(
+            remainder_indices(&converted, CondRemainder::Conjuncts(vec![2, 1, 7])),
+            [1, 2]
+        );
+        assert_eq!(
+            cond.supported().map(|(idx, _)| idx).collect::<Vec<_>>(),
+            [0, 2]
+        );
+    }
+
+    #[test]
+    fn test_constant_value() {
+        assert!(matches!(
+            Constant(ConstInner::Int(-3)).value(),
+            Value::LongLong(-3)
+        ));
+        let s = Constant(ConstInner::String(b"abc"[..].into()));
+        assert_eq!(s.value().as_str(), Some("abc"));
+        assert!(Constant(ConstInner::Null).is_null());
+    }
+}
@@ -1,158 +1,265 @@
+use std::cell::RefCell;
+use std::ffi::{c_char, c_int};
+
 use strum::{EnumIter, IntoEnumIterator};
 
 use crate::bindings;
 
 pub type StorageResult<T = ()> = Result<T, StorageError>;
 
+thread_local! {
+    /// The error number and message of the last error returned to the server on this
+    /// thread if it was a [`StorageError::Custom`], for `handler::get_error_message`
+    static LAST_CUSTOM: RefCell<Option<(c_int, Box<str>)>> = const { RefCell::new(None) };
+
+    /// The message most recently handed to the server, kept alive until it has been
+    /// copied
+    static REPORTED: RefCell<Option<Box<str>>> = const { RefCell::new(None) };
+}
+
 /// Storage handler error types, as defined in `my_base.h`
-#[derive(Debug, EnumIter)]
+///
+/// Engine specific errors can be returned with [`StorageError::custom`], which
+/// carries a message to show the user.
+#[derive(Clone, Debug, EnumIter)]
+#[repr(i32)]
 pub enum StorageError {
+    /// An engine specific error with a message, created with
+    /// [`StorageError::custom`] or [`StorageError::custom_errno`]
+    #[strum(disabled)]
+    Custom {
+        errno: c_int,
+        message: Box<str>,
+    } = -1,
     /// Didn't find key on read or update
-    KeyNotFound = bindings::HA_ERR_KEY_NOT_FOUND as isize,
+    KeyNotFound = bindings::HA_ERR_KEY_NOT_FOUND as c_int,
     /// Duplicate key on write
-    FoundDuppKey = bindings::HA_ERR_FOUND_DUPP_KEY as isize,
+    FoundDuppKey = bindings::HA_ERR_FOUND_DUPP_KEY as c_int,
     /// Internal error
-    InternalError = bindings::HA_ERR_INTERNAL_ERROR as isize,
+    InternalError = bindings::HA_ERR_INTERNAL_ERROR as c_int,
     /// Update with is recoverable
-    RecordChanged = bindings::HA_ERR_RECORD_CHANGED as isize,
+    RecordChanged = bindings::HA_ERR_RECORD_CHANGED as c_int,
     /// Wrong index given to function
-    WrongIndex = bindings::HA_ERR_WRONG_INDEX as isize,
+    WrongIndex = bindings::HA_ERR_WRONG_INDEX as c_int,
     /// Indexfile is crashed
-    Crashed = bindings::HA_ERR_CRASHED as isize,
+    Crashed = bindings::HA_ERR_CRASHED as c_int,
     /// Record-file is crashed or table is corrupt
-    WrongInRecord = bindings::HA_ERR_WRONG_IN_RECORD as isize,
+    WrongInRecord = bindings::HA_ERR_WRONG_IN_RECORD as c_int,
     /// Out of memory
-    OutOfMem = bindings::HA_ERR_OUT_OF_MEM as isize,
+    OutOfMem = bindings::HA_ERR_OUT_OF_MEM as c_int,
     /// Initialization failed and should be retried
-    RetryInit = bindings::HA_ERR_RETRY_INIT as isize,
+    RetryInit = bindings::HA_ERR_RETRY_INIT as c_int,
     /// not a MYI file - no signature
-    NotAtAble = bindings::HA_ERR_NOT_A_TABLE as isize,
+    NotAtAble = bindings::HA_ERR_NOT_A_TABLE as c_int,
     /// Command not supported
-    WrongCommand = bindings::HA_ERR_WRONG_COMMAND as isize,
+    WrongCommand = bindings::HA_ERR_WRONG_COMMAND as c_int,
     /// old databasfile
-    OldFile = bindings::HA_ERR_OLD_FILE as isize,
+    OldFile = bindings::HA_ERR_OLD_FILE as c_int,
     /// No record read in `update()`
-    NoActiveRecord = bindings::HA_ERR_NO_ACTIVE_RECORD as isize,
+    NoActiveRecord = bindings::HA_ERR_NO_ACTIVE_RECORD as c_int,
     /// A record is not there
-    RecordDeleted = bindings::HA_ERR_RECORD_DELETED as isize,
+    RecordDeleted = bindings::HA_ERR_RECORD_DELETED as c_int,
     /// No more room in file
-    RecordFileFull = bindings::HA_ERR_RECORD_FILE_FULL as isize,
+    RecordFileFull = bindings::HA_ERR_RECORD_FILE_FULL as c_int,
     /// No more room in file
-    IndexFileFull = bindings::HA_ERR_INDEX_FILE_FULL as isize,
+    IndexFileFull = bindings::HA_ERR_INDEX_FILE_FULL as c_int,
     /// end in next/prev/first/last
-    EndOfFile = bindings::HA_ERR_END_OF_FILE as isize,
+    EndOfFile = bindings::HA_ERR_END_OF_FILE as c_int,
     /// unsupported extension used
-    Unsupported = bindings::HA_ERR_UNSUPPORTED as isize,
+    Unsupported = bindings::HA_ERR_UNSUPPORTED as c_int,
     /// Too big row
-    ToBigRow = bindings::HA_ERR_TO_BIG_ROW as isize,
+    ToBigRow = bindings::HA_ERR_TO_BIG_ROW as c_int,
     /// Wrong create option
-    GCreateOption = bindings::HA_WRONG_CREATE_OPTION as isize,
+    GCreateOption = bindings::HA_WRONG_CREATE_OPTION as c_int,
     /// Duplicate unique on write
-    FoundDuppUnique = bindings::HA_ERR_FOUND_DUPP_UNIQUE as isize,
+    FoundDuppUnique = bindings::HA_ERR_FOUND_DUPP_UNIQUE as c_int,
     /// Can't open charset
-    UnknownCharset = bindings::HA_ERR_UNKNOWN_CHARSET as isize,
+    UnknownCharset = bindings::HA_ERR_UNKNOWN_CHARSET as c_int,
     /// conflicting tables in MERGE
-    WrongMrgTableDef = bindings::HA_ERR_WRONG_MRG_TABLE_DEF as isize,
+    WrongMrgTableDef = bindings::HA_ERR_WRONG_MRG_TABLE_DEF as c_int,
     /// Last (automatic?) repair failed
-    CrashedOnRepair = bindings::HA_ERR_CRASHED_ON_REPAIR as isize,
+    CrashedOnRepair = bindings::HA_ERR_CRASHED_ON_REPAIR as c_int,
     /// Table must be repaired
-    CrashedOnUsage = bindings::HA_ERR_CRASHED_ON_USAGE as isize,
-    LockWaitTimeout = bindings::HA_ERR_LOCK_WAIT_TIMEOUT as isize,
-    LockTableFull = bindings::HA_ERR_LOCK_TABLE_FULL as isize,
+    CrashedOnUsage = bindings::HA_ERR_CRASHED_ON_USAGE as c_int,
+    LockWaitTimeout = bindings::HA_ERR_LOCK_WAIT_TIMEOUT as c_int,
+    LockTableFull = bindings::HA_ERR_LOCK_TABLE_FULL as c_int,
     /// Updates not allowed
-    ReadOnlyTransaction = bindings::HA_ERR_READ_ONLY_TRANSACTION as isize,
-    LockDeadlock = bindings::HA_ERR_LOCK_DEADLOCK as isize,
+    ReadOnlyTransaction = bindings::HA_ERR_READ_ONLY_TRANSACTION as c_int,
+    LockDeadlock = bindings::HA_ERR_LOCK_DEADLOCK as c_int,
     /// Cannot add a foreign key constr.
-    CannotAddForeign = bindings::HA_ERR_CANNOT_ADD_FOREIGN as isize,
+    CannotAddForeign = bindings::HA_ERR_CANNOT_ADD_FOREIGN as c_int,
     /// Cannot add a child row
-    NoReferencedRow = bindings::HA_ERR_NO_REFERENCED_ROW as isize,
+    NoReferencedRow = bindings::HA_ERR_NO_REFERENCED_ROW as c_int,
     /// Cannot delete a parent row
-    RowIsReferenced = bindings::HA_ERR_ROW_IS_REFERENCED as isize,
+    RowIsReferenced = bindings::HA_ERR_ROW_IS_REFERENCED as c_int,
     /// No savepoint with that name
-    NoSavepoint = bindings::HA_ERR_NO_SAVEPOINT as isize,
+    NoSavepoint = bindings::HA_ERR_NO_SAVEPOINT as c_int,
     /// Non unique key block size
-    NonUniqueBlockSize = bindings::HA_ERR_NON_UNIQUE_BLOCK_SIZE as isize,
+    NonUniqueBlockSize = bindings::HA_ERR_NON_UNIQUE_BLOCK_SIZE as c_int,
     /// The table does not exist in engine
-    NoSuchTable = bindings::HA_ERR_NO_SUCH_TABLE as isize,
+    NoSuchTable = bindings::HA_ERR_NO_SUCH_TABLE as c_int,
     /// The table existed in storage engine
-    TableExist = bindings::HA_ERR_TABLE_EXIST as isize,
+    TableExist = bindings::HA_ERR_TABLE_EXIST as c_int,
     /// Could not connect to storage engine
-    NoConnection = bindings::HA_ERR_NO_CONNECTION as isize,
+    NoConnection = bindings::HA_ERR_NO_CONNECTION as c_int,
     /// NULLs are not supported in spatial index
-    NullInSpatial = bindings::HA_ERR_NULL_IN_SPATIAL as isize,
+    NullInSpatial = bindings::HA_ERR_NULL_IN_SPATIAL as c_int,
     /// The table changed in storage engine
-    TableDefChanged = bindings::HA_ERR_TABLE_DEF_CHANGED as isize,
+    TableDefChanged = bindings::HA_ERR_TABLE_DEF_CHANGED as c_int,
     /// There's no partition in table for given value
-    NoPartitionFound = bindings::HA_ERR_NO_PARTITION_FOUND as isize,
+    NoPartitionFound = bindings::HA_ERR_NO_PARTITION_FOUND as c_int,
     /// Row-based binlogging of row failed
-    RbrLoggingFailed = bindings::HA_ERR_RBR_LOGGING_FAILED as isize,
+    RbrLoggingFailed = bindings::HA_ERR_RBR_LOGGING_FAILED as c_int,
     /// Index needed in foreign key constr
-    DropIndexFk = bindings::HA_ERR_DROP_INDEX_FK as isize,
+    DropIndexFk = bindings::HA_ERR_DROP_INDEX_FK as c_int,
     /// Upholding foreign key constraints would lead to a duplicate key error in e other table.
-    ForeignDuplicateKey = bindings::HA_ERR_FOREIGN_DUPLICATE_KEY as isize,
+    ForeignDuplicateKey = bindings::HA_ERR_FOREIGN_DUPLICATE_KEY as c_int,
     /// The table changed in storage engine
-    TableNeedsUpgrade = bindings::HA_ERR_TABLE_NEEDS_UPGRADE as isize,
+    TableNeedsUpgrade = bindings::HA_ERR_TABLE_NEEDS_UPGRADE as c_int,
     /// The table is not writable
-    TableReadonly = bindings::HA_ERR_TABLE_READONLY as isize,
+    TableReadonly = bindings::HA_ERR_TABLE_READONLY as c_int,
     /// Failed to get next autoinc value
-    AutoincReadFailed = bindings::HA_ERR_AUTOINC_READ_FAILED as isize,
+    AutoincReadFailed = bindings::HA_ERR_AUTOINC_READ_FAILED as c_int,
     /// Failed to set row autoinc value
-    AutoincErange = bindings::HA_ERR_AUTOINC_ERANGE as isize,
+    AutoincErange = bindings::HA_ERR_AUTOINC_ERANGE as c_int,
     /// Generic error
-    Generic = bindings::HA_ERR_GENERIC as isize,
+    Generic = bindings::HA_ERR_GENERIC as c_int,
     /// row not actually updated: new values same as the old values
-    RecordIsTheSame = bindings::HA_ERR_RECORD_IS_THE_SAME as isize,
+    RecordIsTheSame = bindings::HA_ERR_RECORD_IS_THE_SAME as c_int,
     /// It is not possible to log this statement
-    LoggingImpossible = bindings::HA_ERR_LOGGING_IMPOSSIBLE as isize,
+    LoggingImpossible = bindings::HA_ERR_LOGGING_IMPOSSIBLE as c_int,
     /// The event was corrupt, leading to illegal data being read
-    CorruptEvent = bindings::HA_ERR_CORRUPT_EVENT as isize,
+    CorruptEvent = bindings::HA_ERR_CORRUPT_EVENT as c_int,
     /// New file format
-    NewFile = bindings::HA_ERR_NEW_FILE as isize,
+    NewFile = bindings::HA_ERR_NEW_FILE as c_int,
     /// The event could not be processed. No other handler error happene.
-    RowsEventApply = bindings::HA_ERR_ROWS_EVENT_APPLY as isize,
+    RowsEventApply = bindings::HA_ERR_ROWS_EVENT_APPLY as c_int,
     /// Error during initialization
-    Initialization = bindings::HA_ERR_INITIALIZATION as isize,
+    Initialization = bindings::HA_ERR_INITIALIZATION as c_int,
     /// File too short
-    FileTooShort = bindings::HA_ERR_FILE_TOO_SHORT as isize,
+    FileTooShort = bindings::HA_ERR_FILE_TOO_SHORT as c_int,
     /// Wrong CRC on page
-    WrongCrc = bindings::HA_ERR_WRONG_CRC as isize,
+    WrongCrc = bindings::HA_ERR_WRONG_CRC as c_int,
     /// oo many active concurrent transactions
-    TooManyConcurrentTrxs = bindings::HA_ERR_TOO_MANY_CONCURRENT_TRXS as isize,
+    TooManyConcurrentTrxs = bindings::HA_ERR_TOO_MANY_CONCURRENT_TRXS as c_int,
     /// There's no explicitly listed partition in table for the given value
-    NotInLockPartitions = bindings::HA_ERR_NOT_IN_LOCK_PARTITIONS as isize,
+    NotInLockPartitions = bindings::HA_ERR_NOT_IN_LOCK_PARTITIONS as c_int,
     /// Index column length exceeds limit
-    IndexColTooLong = bindings::HA_ERR_INDEX_COL_TOO_LONG as isize,
+    IndexColTooLong = bindings::HA_ERR_INDEX_COL_TOO_LONG as c_int,
     /// Index corrupted
-    IndexCorrupt = bindings::HA_ERR_INDEX_CORRUPT as isize,
+    IndexCorrupt = bindings::HA_ERR_INDEX_CORRUPT as c_int,
     /// Undo log record too big
-    UndoRecTooBig = bindings::HA_ERR_UNDO_REC_TOO_BIG as isize,
+    UndoRecTooBig = bindings::HA_ERR_UNDO_REC_TOO_BIG as c_int,
     /// Invalid InnoDB Doc ID
-    InvalidDocid = bindings::HA_FTS_INVALID_DOCID as isize,
+    InvalidDocid = bindings::HA_FTS_INVALID_DOCID as c_int,
     // /// Table being used in foreign key check (disabled in `my_base.h`)
-    // TableInFkCheck = bindings::HA_ERR_TABLE_IN_FK_CHECK as isize,
+    // TableInFkCheck = bindings::HA_ERR_TABLE_IN_FK_CHECK as c_int,
     /// The tablespace existed in storage engine
-    TablespaceExists = bindings::HA_ERR_TABLESPACE_EXISTS as isize,
+    TablespaceExists = bindings::HA_ERR_TABLESPACE_EXISTS as c_int,
     /// Table has too many columns
-    TooManyFields = bindings::HA_ERR_TOO_MANY_FIELDS as isize,
+    TooManyFields = bindings::HA_ERR_TOO_MANY_FIELDS as c_int,
     /// Row in wrong partition
-    RowInWrongPartition = bindings::HA_ERR_ROW_IN_WRONG_PARTITION as isize,
-    RowNotVisible = bindings::HA_ERR_ROW_NOT_VISIBLE as isize,
-    AbortedByUser = bindings::HA_ERR_ABORTED_BY_USER as isize,
-    DiskFull = bindings::HA_ERR_DISK_FULL as isize,
-    IncompatibleDefinition = bindings::HA_ERR_INCOMPATIBLE_DEFINITION as isize,
+    RowInWrongPartition = bindings::HA_ERR_ROW_IN_WRONG_PARTITION as c_int,
+    RowNotVisible = bindings::HA_ERR_ROW_NOT_VISIBLE as c_int,
+    AbortedByUser = bindings::HA_ERR_ABORTED_BY_USER as c_int,
+    DiskFull = bindings::HA_ERR_DISK_FULL as c_int,
+    IncompatibleDefinition = bindings::HA_ERR_INCOMPATIBLE_DEFINITION as c_int,
     /// Too many words in a phrase
-    FtsTooManyWordsInPhrase = bindings::HA_ERR_FTS_TOO_MANY_WORDS_IN_PHRASE as isize,
+    FtsTooManyWordsInPhrase = bindings::HA_ERR_FTS_TOO_MANY_WORDS_IN_PHRASE as c_int,
     /// Table encrypted but decrypt failed
-    DecryptionFailed = bindings::HA_ERR_DECRYPTION_FAILED as isize,
+    DecryptionFailed = bindings::HA_ERR_DECRYPTION_FAILED as c_int,
     /// FK cascade depth exceeded
-    FkDepthExceeded = bindings::HA_ERR_FK_DEPTH_EXCEEDED as isize,
+    FkDepthExceeded = bindings::HA_ERR_FK_DEPTH_EXCEEDED as c_int,
     /// Missing Tablespace
-    TablespaceMissing = bindings::HA_ERR_TABLESPACE_MISSING as isize,
-    SequenceInvalidData = bindings::HA_ERR_SEQUENCE_INVALID_DATA as isize,
-    SequenceRunOut = bindings::HA_ERR_SEQUENCE_RUN_OUT as isize,
-    CommitError = bindings::HA_ERR_COMMIT_ERROR as isize,
-    PartitionList = bindings::HA_ERR_PARTITION_LIST as isize,
-    NoEncryption = bindings::HA_ERR_NO_ENCRYPTION as isize,
+    TablespaceMissing = bindings::HA_ERR_TABLESPACE_MISSING as c_int,
+    SequenceInvalidData = bindings::HA_ERR_SEQUENCE_INVALID_DATA as c_int,
+    SequenceRunOut = bindings::HA_ERR_SEQUENCE_RUN_OUT as c_int,
+    CommitError = bindings::HA_ERR_COMMIT_ERROR as c_int,
+    PartitionList = bindings::HA_ERR_PARTITION_LIST as c_int,
+    NoEncryption = bindings::HA_ERR_NO_ENCRYPTION as c_int,
+}
+
+impl StorageError {
+    /// An engine specific error with a message, reported as `HA_ERR_GENERIC`
+    ///
+    /// The server shows this as "Got error 168 'message' from ENGINE".
+    pub fn custom(message: impl Into<String>) -> Self {
+        Self::Custom {
+            errno: bindings::HA_ERR_GENERIC as c_int,
+            message: message.into().into_boxed_str(),
+        }
+    }
+
+    /// An engine specific error with a message and an engine error number. The
+    /// number must be greater than `HA_ERR_LAST` (199) so it is not mistaken for
+    /// one of the other variants; smaller numbers are reported as `HA_ERR_GENERIC`.
+    pub fn custom_errno(errno: c_int, message: impl Into<String>) -> Self {
+        if errno <= bindings::HA_ERR_LAST as c_int {
+            return Self::custom(message);
+        }
+        Self::Custom {
+            errno,
+            message: message.into().into_boxed_str(),
+        }
+    }
+
+    /// The `HA_ERR_*` value for this error
+    ///
+    /// The message of a `Custom` error is saved so that the server can get it with
+    /// [`last_error_message`] when it reports the error. Any other error discards
+    /// a message that was not reported.
+    pub(crate) fn to_errno(self) -> c_int {
+        let (errno, last) = match self {
+            Self::Custom { errno, message } => (errno, Some((errno, message))),
+            _ => (self.discriminant(), None),
+        };
+        LAST_CUSTOM.with(|cell| *cell.borrow_mut() = last);
+        errno
+    }
+
+    fn discriminant(&self) -> c_int {
+        // SAFETY: the enum is `repr(i32)`, so its discriminant is stored first
+        unsafe { *(self as *const Self).cast::<c_int>() }
+    }
+
+    /// The error for an `HA_ERR_*` value returned by the server, or `InternalError`
+    /// if it is not recognized
+    pub(crate) fn from_errno(errno: c_int) -> Self {
+        Self::iter()
+            .find(|e| e.discriminant() == errno)
+            .unwrap_or(Self::InternalError)
+    }
+}
+
+/// Take the message of the last error on this thread if it was a `Custom` error
+/// with number `errno`, as a pointer and length that are valid until the next call.
+/// The message is only returned once.
+pub(crate) fn last_error_message(errno: c_int) -> Option<(*const c_char, usize)> {
+    let message = LAST_CUSTOM.with(|last| {
+        let mut last = last.borrow_mut();
+        match last.take() {
+            Some((last_errno, message)) if last_errno == errno => Some(message),
+            other => {
+                *last = other;
+                None
+            }
+        }
+    });
+    REPORTED.with(|reported| {
+        let mut reported = reported.borrow_mut();
+        *reported = message;
+        reported
+            .as_ref()
+            .map(|message| (message.as_ptr().cast(), message.len()))
+    })
+}
+
+/// Turn a result into `0` on success or the `HA_ERR_*` error code
+pub(crate) fn to_errno(res: StorageResult) -> c_int {
+    match res {
+        Ok(()) => 0,
+        Err(e) => e.to_errno(),
+    }
 }
 
 #[cfg(test)]
@@ -169,4 +276,53 @@ mod tests {
             bindings::HA_ERR_ERRORS.try_into().unwrap()
         );
     }
+
+    #[test]
+    fn test_from_errno() {
+        let errno = StorageError::AutoincErange.to_errno();
+        assert!(matches!(
+            StorageError::from_errno(errno),
+            StorageError::AutoincErange
+        ));
+        assert!(matches!(
+            StorageError::from_errno(-1),
+            StorageError::InternalError
+        ));
+    }
+
+    #[test]
+    fn test_custom() {
+        assert_eq!(
+            StorageError::EndOfFile.to_errno(),
+            bindings::HA_ERR_END_OF_FILE as c_int
+        );
+
+        let errno = StorageError::custom("segment 12 is corrupt").to_errno();
+        assert_eq!(errno, bindings::HA_ERR_GENERIC as c_int);
+        let (ptr, len) = last_error_message(errno).unwrap();
+        let msg = unsafe { std::slice::from_raw_parts(ptr.cast::<u8>(), len) };
+        assert_eq!(msg, b"segment 12 is corrupt");
+
+        let errno = StorageError::custom_errno(1000, "checksum mismatch").to_errno();
+        assert_eq!(errno, 1000);
+        assert!(last_error_message(bindings::HA_ERR_GENERIC as c_int).is_none());
+        assert_eq!(
+            last_error_message(1000).unwrap().1,
+            "checksum mismatch".len()
+        );
+        // Messages are only reported once
+        assert!(last_error_message(1000).is_none());
+
+        // A later error replaces a message that was never reported
+        StorageError::custom_errno(1000, "stale").to_errno();
+        assert_eq!(
+            StorageError::EndOfFile.to_errno(),
+            bindings::HA_ERR_END_OF_FILE as c_int
+        );
+        assert!(last_error_message(1000).is_none());
+
+        // Numbers used by the server are reported as `HA_ERR_GENERIC`
+        let errno = StorageError::custom_errno(120, "too small").to_errno();
+        assert_eq!(errno, bindings::HA_ERR_GENERIC as c_int);
+    }
 }
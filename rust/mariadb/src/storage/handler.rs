@@ -6,18 +6,168 @@ use std::marker::PhantomData;
 use std::path::Path;
 use std::{mem, ptr};
 
-use super::{Handlerton, StorageError, StorageResult, MAX_RECORD_LENGTH};
+use super::table_path::{delete_table_files, rename_table_files};
+use super::{
+    AdminMessages, AdminResult, AlterInplaceInfo, AlterInplaceResult, AutoIncrementRequest,
+    AutoIncrementReservation, CheckOptions, CondRemainder, Condition, Handlerton, HandlertonThd,
+    KeyFind, KeyRange, MrrFlags, PageRange, RangeId, RangeSeq, Record, RecordMut, SearchKey,
+    StorageError, StorageResult, TablePath, ThrLockData, ThrLockType, MAX_RECORD_LENGTH,
+};
 use crate::sql::{MAX_DATA_LENGTH_FOR_KEY, MAX_REFERENCE_PARTS};
-use crate::{bindings, MemRoot, TableShare};
+use crate::{bindings, MemRoot, Table, TableShare, Thd};
 
 pub const IO_SIZE: usize = bindings::IO_SIZE as usize;
 
-#[derive(Clone, Default)]
-pub struct TableFlags(bindings::handler_Table_flags);
-#[derive(Clone, Default)]
-pub struct IndexFlags(c_ulong);
+/// Index blocks are assumed to be 3/4 full, from `optimizer_costs.h`
+const INDEX_BLOCK_FILL_FACTOR_DIV: usize = 3;
+const INDEX_BLOCK_FILL_FACTOR_MUL: usize = 4;
+
+/// Capabilities of a handler's tables (`HA_*` table flags), returned by
+/// [`Handler::table_flags`]
+///
+/// Combine flags with `|`, or with [`TableFlags::union`] in `const` contexts such
+/// as [`Handler::TABLE_FLAGS`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TableFlags(pub(crate) bindings::handler_Table_flags);
+
+impl TableFlags {
+    /// Transactions are not supported
+    pub const NO_TRANSACTIONS: Self =
+        Self(bindings::HA_NO_TRANSACTIONS as bindings::handler_Table_flags);
+    /// Reads may not return all columns, only those in the read set
+    pub const PARTIAL_COLUMN_READ: Self =
+        Self(bindings::HA_PARTIAL_COLUMN_READ as bindings::handler_Table_flags);
+    /// There is no separate data file; a table scan reads an index
+    pub const TABLE_SCAN_ON_INDEX: Self =
+        Self(bindings::HA_TABLE_SCAN_ON_INDEX as bindings::handler_Table_flags);
+    /// Row positions are not in insertion order, so `rnd_pos` must be used to read
+    /// rows again rather than scanning
+    pub const REC_NOT_IN_SEQ: Self =
+        Self(bindings::HA_REC_NOT_IN_SEQ as bindings::handler_Table_flags);
+    /// Geometry columns are supported
+    pub const CAN_GEOMETRY: Self = Self(bindings::HA_CAN_GEOMETRY as bindings::handler_Table_flags);
+    /// Nullable columns can be indexed
+    pub const NULL_IN_KEY: Self = Self(bindings::HA_NULL_IN_KEY as bindings::handler_Table_flags);
+    /// `position` gives the position of the duplicate row after a duplicate key error
+    pub const DUPLICATE_POS: Self =
+        Self(bindings::HA_DUPLICATE_POS as bindings::handler_Table_flags);
+    /// `BLOB` and `TEXT` columns are not supported
+    pub const NO_BLOBS: Self = Self(bindings::HA_NO_BLOBS as bindings::handler_Table_flags);
+    /// `BLOB` and `TEXT` columns can be indexed
+    pub const CAN_INDEX_BLOBS: Self =
+        Self(bindings::HA_CAN_INDEX_BLOBS as bindings::handler_Table_flags);
+    /// The `AUTO_INCREMENT` column may be a later part of a multi-part key
+    pub const AUTO_PART_KEY: Self =
+        Self(bindings::HA_AUTO_PART_KEY as bindings::handler_Table_flags);
+    /// Tables must have a primary key
+    pub const REQUIRE_PRIMARY_KEY: Self =
+        Self(bindings::HA_REQUIRE_PRIMARY_KEY as bindings::handler_Table_flags);
+    /// [`Statistics::records`] is exact, not an estimate
+    pub const STATS_RECORDS_IS_EXACT: Self =
+        Self(bindings::HA_STATS_RECORDS_IS_EXACT as bindings::handler_Table_flags);
+    /// `INSERT DELAYED` is supported
+    pub const CAN_INSERT_DELAYED: Self =
+        Self(bindings::HA_CAN_INSERT_DELAYED as bindings::handler_Table_flags);
+    /// Reading a secondary index also returns the primary key columns
+    pub const PRIMARY_KEY_IN_READ_INDEX: Self =
+        Self(bindings::HA_PRIMARY_KEY_IN_READ_INDEX as bindings::handler_Table_flags);
+    /// `position` needs the primary key columns, so they are always read
+    pub const PRIMARY_KEY_REQUIRED_FOR_POSITION: Self =
+        Self(bindings::HA_PRIMARY_KEY_REQUIRED_FOR_POSITION as bindings::handler_Table_flags);
+    /// `SPATIAL` (R-tree) indexes are supported
+    pub const CAN_RTREEKEYS: Self =
+        Self(bindings::HA_CAN_RTREEKEYS as bindings::handler_Table_flags);
+    /// Prefixes of character columns cannot be indexed
+    pub const NO_PREFIX_CHAR_KEYS: Self =
+        Self(bindings::HA_NO_PREFIX_CHAR_KEYS as bindings::handler_Table_flags);
+    /// `FULLTEXT` indexes are supported
+    pub const CAN_FULLTEXT: Self = Self(bindings::HA_CAN_FULLTEXT as bindings::handler_Table_flags);
+    /// The `HANDLER` statement is supported
+    pub const CAN_SQL_HANDLER: Self =
+        Self(bindings::HA_CAN_SQL_HANDLER as bindings::handler_Table_flags);
+    /// `AUTO_INCREMENT` columns are not supported
+    pub const NO_AUTO_INCREMENT: Self =
+        Self(bindings::HA_NO_AUTO_INCREMENT as bindings::handler_Table_flags);
+    /// Each table is stored in its own files
+    pub const FILE_BASED: Self = Self(bindings::HA_FILE_BASED as bindings::handler_Table_flags);
+    /// `BIT` columns are supported
+    pub const CAN_BIT_FIELD: Self =
+        Self(bindings::HA_CAN_BIT_FIELD as bindings::handler_Table_flags);
+    /// `records()` returns an exact count cheaply
+    pub const HAS_RECORDS: Self = Self(bindings::HA_HAS_RECORDS as bindings::handler_Table_flags);
+    /// Row-based binary logging is supported
+    pub const BINLOG_ROW_CAPABLE: Self =
+        Self(bindings::HA_BINLOG_ROW_CAPABLE as bindings::handler_Table_flags);
+    /// Statement-based binary logging is supported
+    pub const BINLOG_STMT_CAPABLE: Self =
+        Self(bindings::HA_BINLOG_STMT_CAPABLE as bindings::handler_Table_flags);
+    /// `REPAIR TABLE` is supported, see [`RepairingHandler`]
+    pub const CAN_REPAIR: Self = Self(bindings::HA_CAN_REPAIR as bindings::handler_Table_flags);
+    /// Virtual generated columns are supported
+    pub const CAN_VIRTUAL_COLUMNS: Self =
+        Self(bindings::HA_CAN_VIRTUAL_COLUMNS as bindings::handler_Table_flags);
+    /// Conditions can be pushed down, see [`ConditionPushdown`]
+    pub const CAN_TABLE_CONDITION_PUSHDOWN: Self =
+        Self(bindings::HA_CAN_TABLE_CONDITION_PUSHDOWN as bindings::handler_Table_flags);
+    /// `OPTIMIZE TABLE` may run concurrently with reads and writes
+    pub const CONCURRENT_OPTIMIZE: Self =
+        Self(bindings::HA_CONCURRENT_OPTIMIZE as bindings::handler_Table_flags);
+    /// Changes are not rolled back, even with transactions
+    pub const CAN_TABLES_WITHOUT_ROLLBACK: Self =
+        Self(bindings::HA_CAN_TABLES_WITHOUT_ROLLBACK as bindings::handler_Table_flags);
+    /// `rnd_pos` is slow, so the optimizer should avoid it
+    pub const SLOW_RND_POS: Self = Self(bindings::HA_SLOW_RND_POS as bindings::handler_Table_flags);
+    /// Tables can be copied by `mariadb-backup` while in use
+    pub const CAN_ONLINE_BACKUPS: Self =
+        Self(bindings::HA_CAN_ONLINE_BACKUPS as bindings::handler_Table_flags);
+    /// `HASH` indexes are supported
+    pub const CAN_HASH_KEYS: Self =
+        Self(bindings::HA_CAN_HASH_KEYS as bindings::handler_Table_flags);
+    /// Tables are not corrupted by a crash
+    pub const CRASH_SAFE: Self = Self(bindings::HA_CRASH_SAFE as bindings::handler_Table_flags);
+    /// Row positions from `position` cannot be compared with `memcmp`
+    pub const NON_COMPARABLE_ROWID: Self =
+        Self(bindings::HA_NON_COMPARABLE_ROWID as bindings::handler_Table_flags);
+    /// `SKIP LOCKED` is supported
+    pub const CAN_SKIP_LOCKED: Self =
+        Self(bindings::HA_CAN_SKIP_LOCKED as bindings::handler_Table_flags);
+}
+
+impl_flags!(TableFlags, u64);
+
+/// Capabilities of an index (`HA_*` index flags), returned by
+/// [`IndexableHandler::index_flags`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct IndexFlags(pub(crate) c_ulong);
+
+impl IndexFlags {
+    /// `index_next` is supported
+    pub const READ_NEXT: Self = Self(bindings::HA_READ_NEXT as c_ulong);
+    /// `index_prev` is supported
+    pub const READ_PREV: Self = Self(bindings::HA_READ_PREV as c_ulong);
+    /// `index_next` and `index_prev` return rows in index order
+    pub const READ_ORDER: Self = Self(bindings::HA_READ_ORDER as c_ulong);
+    /// All rows in a key range can be found
+    pub const READ_RANGE: Self = Self(bindings::HA_READ_RANGE as c_ulong);
+    /// Only searches on all parts of the index are supported
+    pub const ONLY_WHOLE_INDEX: Self = Self(bindings::HA_ONLY_WHOLE_INDEX as c_ulong);
+    /// The indexed columns can be read from the index alone (`HA_EXTRA_KEYREAD`)
+    pub const KEYREAD_ONLY: Self = Self(bindings::HA_KEYREAD_ONLY as c_ulong);
+    /// Index scans do not return rows ordered by row position
+    pub const KEY_SCAN_NOT_ROR: Self = Self(bindings::HA_KEY_SCAN_NOT_ROR as c_ulong);
+    /// Index conditions can be pushed down
+    pub const DO_INDEX_COND_PUSHDOWN: Self = Self(bindings::HA_DO_INDEX_COND_PUSHDOWN as c_ulong);
+    /// The index contains the whole row
+    pub const CLUSTERED_INDEX: Self = Self(bindings::HA_CLUSTERED_INDEX as c_ulong);
+    /// Range filters can be pushed down
+    pub const DO_RANGE_FILTER_PUSHDOWN: Self =
+        Self(bindings::HA_DO_RANGE_FILTER_PUSHDOWN as c_ulong);
+}
+
+impl_flags!(IndexFlags, c_ulong);
+
 #[derive(Debug)]
-pub struct IoAndCpuCost(bindings::IO_AND_CPU_COST);
+pub struct IoAndCpuCost(pub(crate) bindings::IO_AND_CPU_COST);
 
 impl IoAndCpuCost {
     pub fn new(io: f64, cpu: f64) -> Self {
@@ -45,6 +195,8 @@ impl<'a> CreateInfo<'a> {
     }
 }
 
+/// Read-only view of the server's state for a handler, passed to the cost
+/// estimate functions
 #[repr(transparent)]
 pub struct HandlerCtx<'a> {
     inner: bindings::handler,
@@ -52,14 +204,59 @@ pub struct HandlerCtx<'a> {
 }
 
 impl<'a> HandlerCtx<'a> {
-    fn stats(&self) -> &'a Statistics {
-        unsafe { *ptr::addr_of!(self.inner.stats).cast() }
+    pub(crate) unsafe fn from_raw(handler: *const bindings::handler) -> &'a Self {
+        unsafe { &*handler.cast() }
+    }
+
+    /// The statistics last set by [`Handler::info`]
+    pub fn stats(&self) -> &Statistics {
+        unsafe { &*ptr::addr_of!(self.inner.stats).cast() }
     }
-    fn costs(&self) -> &'a OptimizerCosts {
+
+    /// The optimizer cost constants for this engine
+    pub fn costs(&self) -> &'a OptimizerCosts {
         unsafe { *ptr::addr_of!(self.inner.costs).cast() }
     }
+
+    /// Bytes stored per row in the index at `index`: the key plus the row
+    /// reference, or the whole row for a clustered index
+    pub fn key_storage_length(&self, index: usize) -> usize {
+        let index = index.try_into().unwrap();
+        unsafe { bindings::ha_bridge_key_storage_length(&self.inner, index) }
+    }
+}
+
+/// Flags passed to [`Handler::info`] saying which statistics to update
+/// (`HA_STATUS_*`)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct InfoFlags(pub(crate) u32);
+
+impl InfoFlags {
+    /// Store the position of the last read row (for `rnd_pos`)
+    pub const POS: Self = Self(bindings::HA_STATUS_POS);
+    /// Do not take any locks, the statistics may be stale
+    pub const NO_LOCK: Self = Self(bindings::HA_STATUS_NO_LOCK);
+    /// Update the creation, check and update times
+    pub const TIME: Self = Self(bindings::HA_STATUS_TIME);
+    /// Update values that only change with `ALTER TABLE`, like the maximum file sizes
+    /// and block size
+    pub const CONST: Self = Self(bindings::HA_STATUS_CONST);
+    /// Update values that change with the data: row counts and file lengths
+    pub const VARIABLE: Self = Self(bindings::HA_STATUS_VARIABLE);
+    /// Update the index of the key that caused the last duplicate key error
+    pub const ERRKEY: Self = Self(bindings::HA_STATUS_ERRKEY);
+    /// Update the next `AUTO_INCREMENT` value
+    pub const AUTO: Self = Self(bindings::HA_STATUS_AUTO);
+    /// Update `VARIABLE` values that are expensive to compute, like the delete length
+    pub const VARIABLE_EXTRA: Self = Self(bindings::HA_STATUS_VARIABLE_EXTRA);
+    /// Called when the table is opened
+    pub const OPEN: Self = Self(bindings::HA_STATUS_OPEN);
 }
 
+impl_flags!(InfoFlags, u32);
+
+/// Table statistics (`ha_statistics`), used by the optimizer and shown by
+/// `SHOW TABLE STATUS`
 #[repr(transparent)]
 pub struct Statistics(bindings::ha_statistics);
 
@@ -79,8 +276,8 @@ impl Statistics {
     pub fn delete_length(&self) -> usize {
         self.0.delete_length.try_into().unwrap()
     }
-    pub fn auto_increment_value(&self) -> usize {
-        self.0.auto_increment_value.try_into().unwrap()
+    pub fn auto_increment_value(&self) -> u64 {
+        self.0.auto_increment_value
     }
     pub fn records(&self) -> usize {
         self.0.records.try_into().unwrap()
@@ -99,46 +296,280 @@ impl Statistics {
     }
 }
 
+/// Writable table statistics, passed to [`Handler::info`]
+///
+/// Values that are not set keep what they were before.
+#[repr(transparent)]
+pub struct StatisticsMut(Statistics);
+
+impl StatisticsMut {
+    pub(crate) unsafe fn from_raw<'a>(stats: *mut bindings::ha_statistics) -> &'a mut Self {
+        unsafe { &mut *stats.cast() }
+    }
+
+    /// Number of rows. This is an estimate unless the engine sets
+    /// `HA_STATS_RECORDS_IS_EXACT`, but must only be 0 if the table is empty.
+    pub fn set_records(&mut self, records: u64) {
+        self.0 .0.records = records;
+    }
+
+    /// Number of deleted rows that still take up space
+    pub fn set_deleted(&mut self, deleted: u64) {
+        self.0 .0.deleted = deleted;
+    }
+
+    /// Size of the data in bytes
+    pub fn set_data_file_length(&mut self, len: u64) {
+        self.0 .0.data_file_length = len;
+    }
+
+    pub fn set_max_data_file_length(&mut self, len: u64) {
+        self.0 .0.max_data_file_length = len;
+    }
+
+    /// Size of the indexes in bytes
+    pub fn set_index_file_length(&mut self, len: u64) {
+        self.0 .0.index_file_length = len;
+    }
+
+    pub fn set_max_index_file_length(&mut self, len: u64) {
+        self.0 .0.max_index_file_length = len;
+    }
+
+    /// Bytes taken up by deleted rows that could be reclaimed
+    pub fn set_delete_length(&mut self, len: u64) {
+        self.0 .0.delete_length = len;
+    }
+
+    /// The next value that `AUTO_INCREMENT` will assign
+    pub fn set_auto_increment_value(&mut self, value: u64) {
+        self.0 .0.auto_increment_value = value;
+    }
+
+    /// Average length of a row in bytes
+    pub fn set_mean_rec_length(&mut self, len: u64) {
+        self.0 .0.mean_rec_length = len.try_into().unwrap_or(c_ulong::MAX);
+    }
+
+    /// Size of an index block
+    pub fn set_block_size(&mut self, size: u32) {
+        self.0 .0.block_size = size;
+    }
+
+    /// Table creation time, in seconds since the Unix epoch
+    pub fn set_create_time(&mut self, time: i64) {
+        self.0 .0.create_time = saturating_time(time);
+    }
+
+    /// Time of the last `CHECK TABLE`, in seconds since the Unix epoch
+    pub fn set_check_time(&mut self, time: i64) {
+        self.0 .0.check_time = saturating_time(time);
+    }
+
+    /// Time of the last modification, in seconds since the Unix epoch
+    pub fn set_update_time(&mut self, time: i64) {
+        self.0 .0.update_time = saturating_time(time);
+    }
+
+    /// The live checksum of the table, or `None` if it is `NULL`
+    pub fn set_checksum(&mut self, checksum: Option<u32>) {
+        self.0 .0.checksum = checksum.unwrap_or(0);
+        self.0 .0.checksum_null = checksum.is_none();
+    }
+}
+
+/// Convert to `time_t`, saturating where it is narrower than `i64`
+fn saturating_time(time: i64) -> bindings::time_t {
+    time.try_into().unwrap_or(if time < 0 {
+        bindings::time_t::MIN
+    } else {
+        bindings::time_t::MAX
+    })
+}
+
+impl std::ops::Deref for StatisticsMut {
+    type Target = Statistics;
+
+    fn deref(&self) -> &Statistics {
+        &self.0
+    }
+}
+
+/// Cost constants used by the optimizer (`OPTIMIZER_COSTS`), in milliseconds.
+/// These can be changed by the user per engine.
 #[repr(transparent)]
 pub struct OptimizerCosts(bindings::OPTIMIZER_COSTS);
 
 impl OptimizerCosts {
-    fn index_block_copy_cost(&self) -> f64 {
+    /// Cost of reading a block from disk
+    pub fn disk_read_cost(&self) -> f64 {
+        self.0.disk_read_cost
+    }
+    /// Cost of copying an index block from the cache
+    pub fn index_block_copy_cost(&self) -> f64 {
         self.0.index_block_copy_cost
     }
+    /// Cost of comparing two keys
+    pub fn key_cmp_cost(&self) -> f64 {
+        self.0.key_cmp_cost
+    }
+    /// Cost of copying a key to the record buffer
+    pub fn key_copy_cost(&self) -> f64 {
+        self.0.key_copy_cost
+    }
+    /// Cost of looking up a key
+    pub fn key_lookup_cost(&self) -> f64 {
+        self.0.key_lookup_cost
+    }
+    /// Cost of finding the next key in an index
+    pub fn key_next_find_cost(&self) -> f64 {
+        self.0.key_next_find_cost
+    }
+    /// Ratio of reads expected to go to disk rather than the cache
+    pub fn disk_read_ratio(&self) -> f64 {
+        self.0.disk_read_ratio
+    }
+    /// Cost of copying a row to the record buffer
+    pub fn row_copy_cost(&self) -> f64 {
+        self.0.row_copy_cost
+    }
+    /// Cost of finding a row by its position
+    pub fn row_lookup_cost(&self) -> f64 {
+        self.0.row_lookup_cost
+    }
+    /// Cost of finding the next row in a table scan
+    pub fn row_next_find_cost(&self) -> f64 {
+        self.0.row_next_find_cost
+    }
 }
 
+/// How [`Handler::open`] should open the table (`O_RDONLY` or `O_RDWR`)
 #[repr(transparent)]
-pub struct Mode(c_int);
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Mode(pub(crate) c_int);
+
+impl Mode {
+    /// The table will only be read
+    pub const READ_ONLY: Self = Self(bindings::O_RDONLY as c_int);
+    /// The table may be read and written
+    pub const READ_WRITE: Self = Self(bindings::O_RDWR as c_int);
+
+    /// Mask of the access mode bits
+    const ACCESS_MASK: c_int =
+        (bindings::O_RDONLY | bindings::O_WRONLY | bindings::O_RDWR) as c_int;
+
+    pub const fn bits(self) -> i32 {
+        self.0
+    }
+
+    /// True if the table is opened for reading only
+    pub const fn is_read_only(self) -> bool {
+        self.0 & Self::ACCESS_MASK == Self::READ_ONLY.0
+    }
+}
+
+/// Options for [`Handler::open`] (`HA_OPEN_*`)
 #[repr(transparent)]
-pub struct OpenOp(c_uint);
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct OpenOp(pub(crate) c_uint);
+
+impl OpenOp {
+    /// Wait for locks held by other threads
+    pub const WAIT_IF_LOCKED: Self = Self(bindings::HA_OPEN_WAIT_IF_LOCKED);
+    /// Open the table even if it is marked as crashed, e.g. for `REPAIR TABLE`
+    pub const FOR_REPAIR: Self = Self(bindings::HA_OPEN_FOR_REPAIR);
+    /// The table is an internal temporary table
+    pub const INTERNAL_TABLE: Self = Self(bindings::HA_OPEN_INTERNAL_TABLE);
+}
+
+impl_flags!(OpenOp, u32);
+
+/// The lock passed to `external_lock`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExternalLockType {
+    /// A statement will read from the table
+    Read,
+    /// A statement will write to the table
+    Write,
+    /// The statement is done with the table
+    Unlock,
+}
+
+impl ExternalLockType {
+    pub(crate) fn from_raw(lock_type: c_int) -> Option<Self> {
+        const READ: c_int = bindings::F_RDLCK as c_int;
+        const WRITE: c_int = bindings::F_WRLCK as c_int;
+        const UNLOCK: c_int = bindings::F_UNLCK as c_int;
+
+        match lock_type {
+            READ => Some(Self::Read),
+            WRITE => Some(Self::Write),
+            UNLOCK => Some(Self::Unlock),
+            _ => None,
+        }
+    }
+}
 
 pub trait Handler: 'static {
     type Handlerton: Handlerton;
 
     /// Set this to true if index support is available. If so, this type must
-    /// also implement `IndexHandler`.
+    /// also implement [`IndexableHandler`], and its index callbacks will be used.
     const SUPPORTS_INDEX: bool = false;
 
-    // const TABLE_FLAGS: TableFlags = TableFlags(0);
+    /// Set this to true if conditions can be pushed to this handler. If so, this type
+    /// must also implement [`ConditionPushdown`].
+    const SUPPORTS_COND_PUSHDOWN: bool = false;
+
+    /// Set this to true if some `ALTER TABLE` changes can be done in place. If so,
+    /// this type must also implement [`InplaceAlterTable`].
+    const SUPPORTS_INPLACE_ALTER: bool = false;
+
+    /// Set this to true if the engine reserves `AUTO_INCREMENT` values itself. If so,
+    /// this type must also implement [`AutoIncrementHandler`].
+    const SUPPORTS_AUTO_INCREMENT: bool = false;
+
+    /// Set this to true if the table maintenance statements are supported. If so,
+    /// this type must also implement [`RepairingHandler`].
+    const SUPPORTS_REPAIR: bool = false;
+
+    /// The capabilities of this handler's tables, returned by the default
+    /// [`Handler::table_flags`]
+    const TABLE_FLAGS: TableFlags = TableFlags::empty();
+
     const MAX_SUPPORTED_RECORD_LENGTH: usize = bindings::HA_MAX_REC_LENGTH as usize;
 
-    /// Create a new handler
+    /// Create a new handler for the table whose share is `table`.
+    ///
+    /// `table` is `None` for handlers that are only used for DDL, so anything about
+    /// the table's columns and keys should be read in [`Handler::open`] or
+    /// [`Handler::create`] instead.
     ///
     /// # When is this called?
     ///
     /// - Every time there is a new connection
-    fn new(table: &TableShare, mem_root: MemRoot) -> Self;
+    /// - Without a share for `CREATE TABLE`, `DROP TABLE` and `RENAME TABLE`
+    fn new(table: Option<&TableShare>, mem_root: MemRoot) -> Self;
 
-    /// Open a table, the name will be the name of the file.
-    ///
-    /// Closing happens by dropping this item.
+    /// Open a table, the name will be the name of the file. `table` is the table
+    /// being opened, which the handler uses from now until [`Handler::close`].
     ///
     /// # When is this called?
     ///
     /// Not for every request, more to come...
     // TODO: figure out the interaction with ::extra in the C docs, maybe refactor
-    fn open(name: &Path, mode: Mode, open_options: OpenOp) -> StorageResult;
+    fn open(
+        &mut self,
+        name: &Path,
+        table: &Table,
+        mode: Mode,
+        open_options: OpenOp,
+    ) -> StorageResult;
+
+    /// Close a table opened with `open`.
+    fn close(&mut self) -> StorageResult {
+        Ok(())
+    }
 
     /// Create a new table and exit
     ///
@@ -147,49 +578,201 @@ pub trait Handler: 'static {
     /// # When is this called?
     ///
     /// - SQL `CREATE TABLE` statements
-    fn create(&self, name: &CStr, form: TableShare, create_info: &CreateInfo) {}
+    fn create(&mut self, name: &Path, form: &Table, create_info: &CreateInfo) -> StorageResult {
+        Ok(())
+    }
 
+    /// The capabilities of this handler's tables. Override this only if they depend
+    /// on the table; the default is [`Handler::TABLE_FLAGS`].
     fn table_flags(&self) -> TableFlags {
-        TableFlags::default()
+        Self::TABLE_FLAGS
     }
 
     /// This is an INSERT statement
-    fn write_row(buf: &CStr) {}
+    ///
+    /// If the table has an `AUTO_INCREMENT` column, the server has already filled
+    /// in its value in `buf`.
+    fn write_row(&mut self, buf: &Record) -> StorageResult {
+        Err(StorageError::WrongCommand)
+    }
 
-    fn update_row() {}
+    /// Prepare for many calls to [`Handler::write_row`]. Engines may buffer rows until
+    /// [`Handler::end_bulk_insert`]; errors can then be reported from there.
+    ///
+    /// `rows_estimate` is the expected number of rows, or 0 if unknown.
+    ///
+    /// ## When is this called?
+    ///
+    /// - `LOAD DATA`, multi-row `INSERT` and `INSERT ... SELECT`
+    /// - When copying rows for `ALTER TABLE`
+    fn start_bulk_insert(&mut self, rows_estimate: u64) {}
+
+    /// Write out any rows buffered since [`Handler::start_bulk_insert`]
+    ///
+    /// ## When is this called?
+    ///
+    /// - After the last row of a bulk insert, including after a failed
+    ///   [`Handler::write_row`]
+    fn end_bulk_insert(&mut self) -> StorageResult {
+        Ok(())
+    }
 
-    fn delete_row() {}
+    /// Replace `old_data` with `new_data`
+    fn update_row(&mut self, old_data: &Record, new_data: &Record) -> StorageResult {
+        Err(StorageError::WrongCommand)
+    }
+
+    /// Delete the row that was last read. `buf` contains that row.
+    fn delete_row(&mut self, buf: &Record) -> StorageResult {
+        Err(StorageError::WrongCommand)
+    }
 
     /// Delete all rows
     ///
     /// ## When is this called?
     ///
     /// - SQL `DELETE` with no `WHERE` clause
-    fn delete_all_rows() {}
-
-    fn store_lock(&self) {}
-
-    /// Time for a full table data scan
-    fn scan_time(&self, ctx: HandlerCtx) -> IoAndCpuCost {
-        // Duplicated from handler.h
-        let length = ctx.stats().data_file_length() as f64;
-        let io = length / (IO_SIZE as f64);
-        let bsize = ctx.stats().block_size() as f64;
-        let cpu = ((length + bsize - 1.0) / bsize) + ctx.costs().index_block_copy_cost();
-        let cpu = cpu.clamp(0.0, 1e200);
-        IoAndCpuCost::new(io, cpu)
+    fn delete_all_rows(&mut self) -> StorageResult {
+        Err(StorageError::WrongCommand)
     }
 
-    /// Cost of fetching `rows` records through `rnd_pos`.
-    fn rnd_pos_time(&self, ctx: HandlerCtx, rows: usize) -> IoAndCpuCost {
-        let r = rows as f64;
-        let io = ((ctx.stats().block_size() + IO_SIZE).saturating_sub(1) as f64) / (IO_SIZE as f64);
-        let cpu = r + ctx.costs().index_block_copy_cost();
+    /// Prepare for a table scan. `scan` is true if the whole table will be read.
+    fn rnd_init(&mut self, scan: bool) -> StorageResult;
+
+    /// End a table scan started with `rnd_init`
+    fn rnd_end(&mut self) -> StorageResult {
+        Ok(())
+    }
+
+    /// Read the next row into `buf`. Return `StorageError::EndOfFile` once the scan
+    /// is complete.
+    fn rnd_next(&mut self, buf: &mut RecordMut) -> StorageResult;
 
+    /// Read the row at the position `pos` (as written by `position`) into `buf`.
+    fn rnd_pos(&mut self, buf: &mut RecordMut, pos: &[u8]) -> StorageResult;
+
+    /// Store the position of the current row `row` in `pos`, which can later be given
+    /// to `rnd_pos`. `pos` has length `ref_length`.
+    fn position(&mut self, row: &Record, pos: &mut [u8]);
+
+    /// Update the statistics selected by `flags` in `stats`.
+    ///
+    /// ## When is this called?
+    ///
+    /// - When the table is opened, with [`InfoFlags::OPEN`]
+    /// - By the optimizer before planning a query, usually with
+    ///   [`InfoFlags::VARIABLE`]
+    /// - `SHOW TABLE STATUS` and `information_schema.TABLES`
+    fn info(&mut self, flags: InfoFlags, stats: &mut StatisticsMut) -> StorageResult {
+        Ok(())
+    }
+
+    /// Hint from the server, `operation` is a `ha_extra_function`.
+    fn extra(&mut self, operation: u32) -> StorageResult {
+        Ok(())
+    }
+
+    /// Called at the start and end of every statement that uses this table.
+    ///
+    /// Transactional engines should call [`HandlertonThd::register_transaction`]
+    /// here when a lock is taken.
+    fn external_lock(
+        &mut self,
+        thd: &mut HandlertonThd<Self::Handlerton>,
+        lock_type: ExternalLockType,
+    ) -> StorageResult {
+        Ok(())
+    }
+
+    /// Delete the table at `path`. The table is not open.
+    ///
+    /// The default deletes the table's file for each of
+    /// [`Handlerton::TABLEFILE_EXTENSIONS`], see [`delete_table_files`].
+    ///
+    /// ## When is this called?
+    ///
+    /// - SQL `DROP TABLE`, once for each partition of a partitioned table
+    /// - To remove the old table after `ALTER TABLE` copied it
+    fn delete_table(&mut self, path: &TablePath) -> StorageResult {
+        delete_table_files(path, Self::Handlerton::TABLEFILE_EXTENSIONS)
+    }
+
+    /// Rename the table at `from` to `to`, possibly in another database. The table is
+    /// not open.
+    ///
+    /// The default renames the table's file for each of
+    /// [`Handlerton::TABLEFILE_EXTENSIONS`], see [`rename_table_files`].
+    ///
+    /// ## When is this called?
+    ///
+    /// - SQL `RENAME TABLE` and `ALTER TABLE ... RENAME`
+    /// - To swap in the new table after `ALTER TABLE` copied it
+    fn rename_table(&mut self, from: &TablePath, to: &TablePath) -> StorageResult {
+        rename_table_files(from, to, Self::Handlerton::TABLEFILE_EXTENSIONS)
+    }
+
+    /// Quickly remove all rows from an open table, and reset the `AUTO_INCREMENT`
+    /// counter. The table is locked exclusively.
+    ///
+    /// The default calls [`Handler::delete_all_rows`]. After this returns, the server
+    /// also calls [`AutoIncrementHandler::reset_auto_increment`] with 0.
+    ///
+    /// ## When is this called?
+    ///
+    /// - SQL `TRUNCATE TABLE`, unless foreign keys reference the table
+    fn truncate(&mut self) -> StorageResult {
+        self.delete_all_rows()
+    }
+
+    /// Return the lock data to add to the server's lock array for this statement,
+    /// or `None` if the engine does not use table-level locks.
+    ///
+    /// `lock_type` is the lock the server wants. Engines usually call
+    /// [`ThrLockData::request`], possibly with a different lock type; e.g. an engine
+    /// with row-level locking might turn [`ThrLockType::Write`] into
+    /// [`ThrLockType::WriteAllowWrite`], or one supporting concurrent inserts might
+    /// turn [`ThrLockType::ReadNoInsert`] into [`ThrLockType::Read`].
+    ///
+    /// ## When is this called?
+    ///
+    /// - Before each statement, before [`Handler::external_lock`]
+    /// - With [`ThrLockType::Unlock`] when releasing locks, e.g. for `LOCK TABLES`
+    fn store_lock(&mut self, thd: &Thd, lock_type: ThrLockType) -> Option<&mut ThrLockData> {
+        None
+    }
+
+    /// Cost of a full table scan, not counting the cost of copying or comparing
+    /// rows. `io` is in blocks and `cpu` in the units of [`OptimizerCosts`].
+    ///
+    /// The default is `handler::scan_time`, based on
+    /// [`Statistics::data_file_length`] and [`Statistics::block_size`].
+    fn scan_time(&self, ctx: &HandlerCtx) -> IoAndCpuCost {
+        let length = ctx.stats().data_file_length();
+        let io = (length / IO_SIZE) as f64;
+        let cpu = match ctx.stats().block_size() {
+            0 => 0.0,
+            bsize => length.div_ceil(bsize) as f64 * ctx.costs().index_block_copy_cost(),
+        };
         IoAndCpuCost::new(io, cpu)
     }
+
+    /// Cost of fetching `rows` rows through [`Handler::rnd_pos`], not counting the
+    /// cost of copying them.
+    ///
+    /// The default is `handler::rnd_pos_time`, which assumes one block read per row.
+    fn rnd_pos_time(&self, ctx: &HandlerCtx, rows: usize) -> IoAndCpuCost {
+        let r = rows as f64;
+        let blocks = ctx.stats().block_size().div_ceil(IO_SIZE) as f64;
+        IoAndCpuCost::new(r * blocks, r * ctx.costs().index_block_copy_cost())
+    }
 }
 
+/// A handler that supports the table maintenance statements `CHECK TABLE`,
+/// `REPAIR TABLE`, `ANALYZE TABLE` and `OPTIMIZE TABLE`
+///
+/// Requires [`Handler::SUPPORTS_REPAIR`]. Each statement defaults to
+/// [`AdminResult::NotImplemented`]. Messages written to `msgs` are returned as
+/// rows of the statement's result set, followed by a row for the returned status.
 pub trait RepairingHandler: Handler {
     fn pre_calculate_checksum(&self) -> u32 {
         0
@@ -199,16 +782,116 @@ pub trait RepairingHandler: Handler {
         0
     }
 
+    /// Return true if the table is marked as crashed. The server will then refuse
+    /// to open it until it is repaired, or repair it automatically if
+    /// [`RepairingHandler::auto_repair`] allows it.
     fn is_crashed(&self) -> bool {
         false
     }
 
+    /// Return true if the server should try `REPAIR TABLE` when opening fails with
+    /// the errno `error`, usually `HA_ERR_CRASHED_ON_USAGE`
     fn auto_repair(error: i32) -> bool {
         false
     }
+
+    /// Verify the table's data and indexes. Return [`AdminResult::Corrupt`] if
+    /// problems are found, after marking the table as crashed.
+    ///
+    /// ## When is this called?
+    ///
+    /// - SQL `CHECK TABLE`
+    fn check(&mut self, msgs: &mut AdminMessages, opts: &CheckOptions) -> AdminResult {
+        AdminResult::NotImplemented
+    }
+
+    /// Repair a crashed table
+    ///
+    /// ## When is this called?
+    ///
+    /// - SQL `REPAIR TABLE`
+    /// - When opening a crashed table, if [`RepairingHandler::auto_repair`] is true
+    fn repair(&mut self, msgs: &mut AdminMessages, opts: &CheckOptions) -> AdminResult {
+        AdminResult::NotImplemented
+    }
+
+    /// Update the statistics used by the optimizer, e.g. key distributions
+    ///
+    /// ## When is this called?
+    ///
+    /// - SQL `ANALYZE TABLE`
+    fn analyze(&mut self, msgs: &mut AdminMessages, opts: &CheckOptions) -> AdminResult {
+        AdminResult::NotImplemented
+    }
+
+    /// Reclaim unused space and defragment the table. Return
+    /// [`AdminResult::TryAlter`] to have the server recreate the table instead.
+    ///
+    /// ## When is this called?
+    ///
+    /// - SQL `OPTIMIZE TABLE`
+    fn optimize(&mut self, msgs: &mut AdminMessages, opts: &CheckOptions) -> AdminResult {
+        AdminResult::NotImplemented
+    }
+}
+
+/// A handler that keeps its own `AUTO_INCREMENT` counter
+///
+/// Requires [`Handler::SUPPORTS_AUTO_INCREMENT`]. Without it, the server finds the
+/// next value by reading the last entry of the `AUTO_INCREMENT` index.
+///
+/// The engine should also report the next value as
+/// [`Statistics::auto_increment_value`] from [`Handler::info`] with
+/// [`InfoFlags::AUTO`].
+pub trait AutoIncrementHandler: Handler {
+    /// Reserve one or more values for the `AUTO_INCREMENT` column
+    ///
+    /// ## When is this called?
+    ///
+    /// - Before [`Handler::write_row`] when a row is inserted without a value and
+    ///   no reserved values are left
+    fn get_auto_increment(
+        &mut self,
+        req: AutoIncrementRequest,
+    ) -> StorageResult<AutoIncrementReservation>;
+
+    /// Set the counter so that the next value will be `value`
+    ///
+    /// ## When is this called?
+    ///
+    /// - `TRUNCATE TABLE`, with `value = 0`
+    /// - `ALTER TABLE ... AUTO_INCREMENT = value`
+    fn reset_auto_increment(&mut self, value: u64) -> StorageResult {
+        Ok(())
+    }
+}
+
+/// A handler that can filter rows itself, using conditions from the `WHERE` clause
+///
+/// Requires [`Handler::SUPPORTS_COND_PUSHDOWN`].
+pub trait ConditionPushdown: Handler {
+    /// Only return rows matching `cond` from the following scans, and report which
+    /// parts of it the server still needs to check. Returning
+    /// [`CondRemainder::All`] is always correct.
+    ///
+    /// Pushed conditions form a stack: a condition stays active until the matching
+    /// [`ConditionPushdown::cond_pop`].
+    ///
+    /// ## When is this called?
+    ///
+    /// - After a statement's query plan is chosen, before the scans start
+    fn cond_push(&mut self, cond: Condition) -> CondRemainder;
+
+    /// Remove the most recently pushed condition
+    fn cond_pop(&mut self) {}
 }
 
 pub trait IndexableHandler: Handler {
+    /// Set this to true if this handler implements multi-range reads itself, via
+    /// [`IndexableHandler::multi_range_read_info`] and the other `multi_range_read_*`
+    /// methods. Otherwise the server reads each range with `index_read_map`.
+    const SUPPORTS_MRR: bool = false;
+
     fn max_supported_record_length(&self) -> usize {
         MAX_RECORD_LENGTH
     }
@@ -229,16 +912,22 @@ pub trait IndexableHandler: Handler {
         None
     }
 
+    /// The capabilities of the index at `index`, for its parts up to `part` if
+    /// `all_parts` is set, else for only that part
     fn index_flags(&self, index: usize, part: usize, all_parts: bool) -> IndexFlags {
-        IndexFlags::default()
+        IndexFlags::empty()
     }
 
     /// Calculate the cost of `index_only` scan for a given index, a number of ranges,
     /// and a number of records.
     ///
     /// - `index`: the index to read
+    /// - `ranges`: number of ranges to scan, at least 1
     /// - `rows`: number of records to read
     /// - `blocks`: number of IO blocks that need to be accessed, or 0 if not known.
+    ///
+    /// The default is `handler::keyread_time`, which estimates the number of index
+    /// blocks from [`HandlerCtx::key_storage_length`] if `blocks` is 0.
     fn keyread_time(
         &self,
         ctx: &HandlerCtx,
@@ -247,7 +936,24 @@ pub trait IndexableHandler: Handler {
         rows: usize,
         blocks: usize,
     ) -> IoAndCpuCost {
-        todo!()
+        let bsize = ctx.stats().block_size();
+        let (blocks, io_blocks) = match (bsize, blocks) {
+            // Engines without blocks, e.g. in-memory ones
+            (0, _) => (blocks, 0),
+            // A single row lookup reads one block
+            (_, 0) if rows <= 1 => (1, bsize.div_ceil(IO_SIZE)),
+            (_, 0) => {
+                let len = ctx.key_storage_length(index);
+                let bytes = rows * len / INDEX_BLOCK_FILL_FACTOR_DIV * INDEX_BLOCK_FILL_FACTOR_MUL;
+                let blocks = (bytes + bsize - 1) / bsize + ranges.saturating_sub(1);
+                (blocks, blocks * bsize / IO_SIZE)
+            }
+            (_, _) => (blocks, blocks * bsize / IO_SIZE),
+        };
+        IoAndCpuCost::new(
+            io_blocks as f64,
+            blocks as f64 * ctx.costs().index_block_copy_cost(),
+        )
     }
 
     /// Time for a full table index scan without copy or compare cost.
@@ -255,14 +961,185 @@ pub trait IndexableHandler: Handler {
         Self::keyread_time(self, ctx, index, 1, max(rows, 1), 0)
     }
 
-    fn index_read_map() {}
-    fn index_next() {}
-    fn index_prev() {}
-    fn index_first() {}
-    fn index_last() {}
+    /// Estimate the number of rows in the index at `index` between `min` and `max`.
+    /// A missing bound means the range is open on that side.
+    ///
+    /// The result must not be 0 unless the range is certainly empty, since the
+    /// optimizer will then skip the table. Engines that know which pages the range
+    /// spans may set them in `pages`.
+    ///
+    /// The default returns 10, like `handler::records_in_range`.
+    ///
+    /// ## When is this called?
+    ///
+    /// - While choosing a query plan, for each range the optimizer considers
+    fn records_in_range(
+        &mut self,
+        index: usize,
+        min: Option<&KeyRange>,
+        max: Option<&KeyRange>,
+        pages: &mut PageRange,
+    ) -> u64 {
+        10
+    }
+
+    /// Prepare to scan the index at `index`. `sorted` is set if rows must be returned
+    /// in index order.
+    fn index_init(&mut self, index: usize, sorted: bool) -> StorageResult {
+        Ok(())
+    }
+
+    /// End an index scan started with `index_init`
+    fn index_end(&mut self) -> StorageResult {
+        Ok(())
+    }
+
+    /// Position the active index according to `key` and `find`, and return the row
+    /// found there in `buf`.
+    ///
+    /// Return `KeyNotFound` if no row matches, or `EndOfFile` if the index is
+    /// exhausted.
+    fn index_read_map(
+        &mut self,
+        buf: &mut RecordMut,
+        key: &SearchKey,
+        find: KeyFind,
+    ) -> StorageResult;
+
+    /// Read the next row in the active index
+    fn index_next(&mut self, buf: &mut RecordMut) -> StorageResult;
+
+    /// Read the previous row in the active index
+    fn index_prev(&mut self, buf: &mut RecordMut) -> StorageResult {
+        Err(StorageError::WrongCommand)
+    }
+
+    /// Read the first row in the active index
+    fn index_first(&mut self, buf: &mut RecordMut) -> StorageResult;
+
+    /// Read the last row in the active index
+    fn index_last(&mut self, buf: &mut RecordMut) -> StorageResult {
+        Err(StorageError::WrongCommand)
+    }
+
+    /// The index that caused the last [`StorageError::FoundDuppKey`], if known. The
+    /// server uses it to name the key in the error message, and to find the
+    /// conflicting row for `REPLACE` and `INSERT ... ON DUPLICATE KEY UPDATE`.
+    fn dup_key(&self) -> Option<usize> {
+        None
+    }
+
+    /// Only return rows matching `cond` from the following reads of the index at
+    /// `index`, and report which parts of it the server still needs to check. The
+    /// condition only uses columns of that index, so it can be checked before the
+    /// rest of the row is read. Returning [`CondRemainder::All`] is always correct.
+    ///
+    /// This is only called for indexes with [`IndexFlags::DO_INDEX_COND_PUSHDOWN`].
+    ///
+    /// ## When is this called?
+    ///
+    /// - After a statement's query plan is chosen, before the index is read
+    fn idx_cond_push(&mut self, index: usize, cond: Condition) -> CondRemainder {
+        CondRemainder::All
+    }
+
+    /// Forget the condition from [`IndexableHandler::idx_cond_push`]
+    fn cancel_pushed_idx_cond(&mut self) {}
+
+    /// Choose how to read `n_ranges` ranges of the index at `index`, which are
+    /// expected to contain `rows` rows in total (`n_ranges` is 0 if unknown).
+    ///
+    /// `mode` has [`MrrFlags::USE_DEFAULT_IMPL`] set, which makes the server read
+    /// each range with `index_read_map`. Return it without that flag to use
+    /// [`IndexableHandler::multi_range_read_init`] and
+    /// [`IndexableHandler::multi_range_read_next`] instead. The cost estimate is
+    /// that of the server's implementation.
+    ///
+    /// Only used with [`IndexableHandler::SUPPORTS_MRR`].
+    ///
+    /// ## When is this called?
+    ///
+    /// - While choosing a query plan, for range scans and batched key access joins
+    fn multi_range_read_info(
+        &mut self,
+        index: usize,
+        n_ranges: usize,
+        rows: u64,
+        mode: MrrFlags,
+    ) -> MrrFlags {
+        mode
+    }
+
+    /// Start a multi-range read of the active index, with the mode returned by
+    /// [`IndexableHandler::multi_range_read_info`]
+    ///
+    /// Ranges may be read from `ranges` here or in `multi_range_read_next`. With
+    /// [`MrrFlags::SORTED`], rows must be returned in index order; otherwise any
+    /// order will do, e.g. sorted by their position in storage.
+    ///
+    /// ## When is this called?
+    ///
+    /// - After `index_init`, possibly several times before `index_end`
+    fn multi_range_read_init(&mut self, ranges: &mut RangeSeq, mode: MrrFlags) -> StorageResult {
+        Ok(())
+    }
+
+    /// Read the next row of a multi-range read into `buf`, and return the
+    /// [`RangeId`] of the range it belongs to (any value with
+    /// [`MrrFlags::NO_ASSOCIATION`]). Return `EndOfFile` after the last row.
+    ///
+    /// Rows for which [`RangeSeq::skip_record`] or [`RangeSeq::skip_index_tuple`]
+    /// are true should not be returned.
+    fn multi_range_read_next(
+        &mut self,
+        buf: &mut RecordMut,
+        ranges: &mut RangeSeq,
+    ) -> StorageResult<RangeId> {
+        Err(StorageError::WrongCommand)
+    }
 }
 
-pub trait InplaceAlterTable {}
+/// A handler that can apply some `ALTER TABLE` changes without copying the table
+///
+/// Requires [`Handler::SUPPORTS_INPLACE_ALTER`]. The phases are called in order:
+/// `check_if_supported_inplace_alter`, then (if supported) `prepare_inplace_alter_table`,
+/// `inplace_alter_table` and `commit_inplace_alter_table`. If any phase fails,
+/// `commit_inplace_alter_table` is called with `commit = false` to roll back.
+pub trait InplaceAlterTable: Handler {
+    /// Check whether the change described by `info` can be done in place, and which
+    /// locks are needed. Return [`AlterInplaceResult::NotSupported`] to fall back to
+    /// copying the table.
+    ///
+    /// This is called without holding any table lock.
+    fn check_if_supported_inplace_alter(
+        &mut self,
+        info: &mut AlterInplaceInfo,
+    ) -> AlterInplaceResult;
+
+    /// Update internal structures while concurrent writes are blocked
+    fn prepare_inplace_alter_table(&mut self, info: &mut AlterInplaceInfo) -> StorageResult {
+        Ok(())
+    }
+
+    /// Do the actual change. Concurrent access is allowed as returned from
+    /// [`InplaceAlterTable::check_if_supported_inplace_alter`].
+    fn inplace_alter_table(&mut self, info: &mut AlterInplaceInfo) -> StorageResult {
+        Ok(())
+    }
+
+    /// Make the change permanent if `commit` is true, otherwise roll it back. Rolling
+    /// back must not fail.
+    ///
+    /// This may be called with `commit = false` without the other phases having been
+    /// called first.
+    fn commit_inplace_alter_table(
+        &mut self,
+        info: &mut AlterInplaceInfo,
+        commit: bool,
+    ) -> StorageResult {
+        Ok(())
+    }
+}
 
 // fn foo<T>(a: Box<dyn Handler<Handlerton = T>>) {
 //     todo!()
@@ -270,3 +1147,21 @@ pub trait InplaceAlterTable {}
 // fn bar<T>(a: Box<dyn IndexHandler<Handlerton = T>>) {
 // todo!()
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flags() {
+        const FLAGS: TableFlags = TableFlags::NO_TRANSACTIONS.union(TableFlags::CAN_SQL_HANDLER);
+        assert!(FLAGS.contains(TableFlags::NO_TRANSACTIONS));
+        assert!(!FLAGS.intersects(TableFlags::REC_NOT_IN_SEQ | TableFlags::CAN_REPAIR));
+        assert_eq!(TableFlags::CAN_REPAIR.bits(), 1 << 37);
+
+        let flags = IndexFlags::READ_NEXT | IndexFlags::READ_ORDER;
+        assert!(flags.contains(IndexFlags::READ_ORDER));
+        assert!(!flags.contains(IndexFlags::READ_ORDER | IndexFlags::KEYREAD_ONLY));
+        assert_eq!(IndexFlags::default(), IndexFlags::empty());
+    }
+}
@@ -1,10 +1,10 @@
-use super::Handler;
+use std::marker::PhantomData;
+use std::ptr;
+
+use super::{FieldOptions, Handler, IndexOptions, StorageError, StorageResult, TableOptions};
 use crate::thd::ThdKillLevel;
 use crate::{bindings, MemRoot, TableShare, Thd};
 
-pub enum Error {}
-pub type Result<T = (), E = Error> = std::result::Result<T, E>;
-
 pub struct HandlertonCtx<'a> {
     hton: &'a mut bindings::handlerton,
     thd: &'a mut bindings::THD,
@@ -21,24 +21,104 @@ impl<'a> HandlertonCtx<'a> {
     }
 }
 
-pub struct HandlertonThd<'a> {
-    thd: &'a mut Thd<'a>,
-    slot: usize,
+/// A connection (`THD`) as seen by a specific storage engine
+pub struct HandlertonThd<'a, H: Handlerton> {
+    thd: &'a Thd<'a>,
+    hton: *mut bindings::handlerton,
+    /// The connection is running on another thread, so its data must not be touched
+    foreign: bool,
+    phantom: PhantomData<H>,
 }
 
-impl<'a> HandlertonThd<'a> {
-    unsafe fn new(hton: *mut bindings::handlerton, thd: *mut bindings::THD) -> Self {
+impl<'a, H: Handlerton> HandlertonThd<'a, H> {
+    pub(crate) unsafe fn new(hton: *mut bindings::handlerton, thd: *mut bindings::THD) -> Self {
         debug_assert!(!hton.is_null());
         debug_assert!(!thd.is_null());
         Self {
-            thd: unsafe { Thd::new_mut(thd) },
-            slot: unsafe { (*hton).slot }.try_into().unwrap(),
+            thd: unsafe { Thd::from_raw(thd) },
+            hton,
+            foreign: false,
+            phantom: PhantomData,
+        }
+    }
+
+    /// A connection that is owned by a different thread than the caller's, as in
+    /// [`Handlerton::kill_query`]. Its data is not accessible.
+    pub(crate) unsafe fn new_foreign(
+        hton: *mut bindings::handlerton,
+        thd: *mut bindings::THD,
+    ) -> Self {
+        Self {
+            foreign: true,
+            ..unsafe { Self::new(hton, thd) }
         }
     }
 
-    fn data(&self) {
-        todo!()
-        // let x = self.thd.0.ha_data[self.slot];
+    /// The underlying connection
+    pub fn thd(&self) -> &Thd<'a> {
+        self.thd
+    }
+
+    fn thd_ptr(&self) -> *mut bindings::THD {
+        ptr::addr_of!(self.thd.0).cast_mut()
+    }
+
+    /// Register this engine in the current statement, and in the current transaction
+    /// if autocommit is off or one was started with `BEGIN`.
+    ///
+    /// This should be called from [`Handler::external_lock`] by transactional engines;
+    /// [`Handlerton::commit`] and [`Handlerton::rollback`] are only called for
+    /// registered engines.
+    pub fn register_transaction(&mut self) {
+        unsafe { bindings::ha_bridge_trans_register(self.thd_ptr(), self.hton) }
+    }
+
+    /// Whether this connection is in a multi-statement transaction
+    pub fn in_transaction(&self) -> bool {
+        unsafe { bindings::ha_bridge_thd_in_transaction(self.thd_ptr()) }
+    }
+
+    /// This engine's data for the connection, if it has been set
+    ///
+    /// Always `None` in [`Handlerton::kill_query`], since the connection's own
+    /// thread may be using the data.
+    pub fn data(&self) -> Option<&H::ConnectionData> {
+        if self.foreign {
+            return None;
+        }
+        unsafe { self.data_ptr().as_ref() }
+    }
+
+    /// Mutable access to this engine's data for the connection, if it has been set
+    pub fn data_mut(&mut self) -> Option<&mut H::ConnectionData> {
+        unsafe { self.data_ptr().as_mut() }
+    }
+
+    /// Get this engine's data for the connection, creating it with `f` if it has not
+    /// yet been set. It is dropped when the connection closes.
+    pub fn data_or_init<F>(&mut self, f: F) -> &mut H::ConnectionData
+    where
+        F: FnOnce() -> H::ConnectionData,
+    {
+        if self.data_ptr().is_null() {
+            let data = Box::into_raw(Box::new(f()));
+            unsafe { bindings::thd_set_ha_data(self.thd_ptr(), self.hton, data.cast()) };
+        }
+        unsafe { &mut *self.data_ptr() }
+    }
+
+    /// Remove this engine's data from the connection and return it
+    pub fn take_data(&mut self) -> Option<H::ConnectionData> {
+        let ptr = self.data_ptr();
+        if ptr.is_null() {
+            return None;
+        }
+        unsafe { bindings::thd_set_ha_data(self.thd_ptr(), self.hton, ptr::null()) };
+        Some(*unsafe { Box::from_raw(ptr) })
+    }
+
+    fn data_ptr(&self) -> *mut H::ConnectionData {
+        unsafe { bindings::thd_get_ha_data(self.thd_ptr(), self.hton) }.cast()
     }
 }
 
@@ -47,55 +127,158 @@ impl<'a> HandlertonThd<'a> {
 /// A "handlerton" ("handler singleton") is the entrypoint for a storage engine handler.
 ///
 /// This defines registration and creation information.
-pub trait Handlerton {
+pub trait Handlerton: 'static {
     type Handler: Handler;
+    /// Data kept for each connection that uses this engine, e.g. an open transaction.
+    /// See [`HandlertonThd::data_or_init`].
+    type ConnectionData: Send;
     /// A type of data that is stored during a savepoint.
-    type SavePoint;
+    ///
+    /// This is stored in memory provided by the server, which is freed without
+    /// notification when a transaction ends. Hence it must be `Copy`.
+    type SavePoint: Copy;
+    /// Engine-defined table options, see [`TableOptions`](derive@super::TableOptions).
+    /// Read them with [`TableShare::options`].
+    type TableOptions: TableOptions;
+    /// Engine-defined column options, see [`FieldOptions`](derive@super::FieldOptions)
+    type FieldOptions: FieldOptions;
+    /// Engine-defined index options, see [`IndexOptions`](derive@super::IndexOptions)
+    type IndexOptions: IndexOptions;
     const FLAGS: u32 = 0;
 
     /// Extensions of files created for a single table in the database directory
     /// (`datadir/db_name/`).
     const TABLEFILE_EXTENSIONS: &'static [&'static str] = &[];
 
-    // fn close_connection(thd: &HandlertonThd) -> Result;
-    // fn kill_query(thd: &HandlertonThd, level: ThdKillLevel);
-
-    // TODO: should Savepoint be its own trait?
-
-    // /// Create a new savepoint
-    // fn savepoint_set(thd: &HandlertonThd) -> Result<Self::SavePoint>;
-    // /// Restore to a previous savepoint
-    // fn savepoint_rollback(thd: &HandlertonThd, sv: &mut Self::SavePoint) -> Result;
-    // fn savepoint_rollback_can_release_mdl(thd: &HandlertonThd) -> bool {
-    //     false
-    // }
-    // fn savepoint_release(thd: &HandlertonThd, sv: &mut Self::SavePoint) -> Result;
-
-    // /// Perform the commit.
-    // ///
-    // /// If `is_true_commit` is false, we are in an end of statement within a transaction
-    // fn commit(thd: &HandlertonThd, is_true_commit: bool) -> Result;
-    // fn commit_ordered(thd: &HandlertonThd, is_true_commit: bool) -> Result;
-    // fn rollback(thd: &HandlertonThd, is_true_commit: bool) -> Result;
-    // fn prepare(thd: &HandlertonThd, is_true_commit: bool) -> Result;
-    // fn prepare_ordered(thd: &HandlertonThd, is_true_commit: bool) -> Result;
-
-    //... more to do
+    /// Set this to true if the engine can report its own tables. If so, this type
+    /// must also implement [`DiscoveringHandlerton`].
+    const SUPPORTS_DISCOVERY: bool = false;
+
+    /// Set this to true if [`Handlerton::prepare`] is implemented, which allows the
+    /// engine to take part in two-phase commit (e.g. XA or with the binary log).
+    const TWO_PHASE_COMMIT: bool = false;
+
+    /// Called when a connection that has [`HandlertonThd::data`] set closes. The data
+    /// is dropped after this returns.
+    fn close_connection(thd: &mut HandlertonThd<Self>) -> StorageResult {
+        Ok(())
+    }
+
+    /// Called when a query using this engine is killed, e.g. with `KILL QUERY`. Use
+    /// this to interrupt anything that does not poll [`Thd::killed`] (such as waiting
+    /// on a lock).
+    ///
+    /// This is called from a different thread than the one running the query, so
+    /// the connection's [`HandlertonThd::data`] is not available here.
+    fn kill_query(thd: &HandlertonThd<Self>, level: ThdKillLevel) {}
+
+    /// Commit a transaction.
+    ///
+    /// If `all` is false, this is the end of a statement within a transaction and
+    /// only the statement's changes should be committed.
+    fn commit(thd: &mut HandlertonThd<Self>, all: bool) -> StorageResult {
+        Ok(())
+    }
+
+    /// Roll back a transaction, or only the current statement if `all` is false.
+    fn rollback(thd: &mut HandlertonThd<Self>, all: bool) -> StorageResult {
+        Ok(())
+    }
+
+    /// Prepare to commit a transaction (the first phase of two-phase commit). After
+    /// this succeeds, a following `commit` must not fail.
+    ///
+    /// Only used if [`Handlerton::TWO_PHASE_COMMIT`] is set.
+    fn prepare(thd: &mut HandlertonThd<Self>, all: bool) -> StorageResult {
+        Ok(())
+    }
+
+    /// Create a new savepoint, returning the data needed to roll back to it
+    fn savepoint_set(thd: &mut HandlertonThd<Self>) -> StorageResult<Self::SavePoint> {
+        Err(StorageError::WrongCommand)
+    }
+
+    /// Restore to a previous savepoint. The savepoint stays valid and may be rolled
+    /// back to again.
+    fn savepoint_rollback(
+        thd: &mut HandlertonThd<Self>,
+        sv: &mut Self::SavePoint,
+    ) -> StorageResult {
+        Err(StorageError::WrongCommand)
+    }
+
+    /// Whether metadata locks acquired after a savepoint may be released when rolling
+    /// back to it
+    fn savepoint_rollback_can_release_mdl(thd: &mut HandlertonThd<Self>) -> bool {
+        false
+    }
+
+    /// Release a savepoint, which will not be used again
+    fn savepoint_release(thd: &mut HandlertonThd<Self>, sv: Self::SavePoint) -> StorageResult {
+        Ok(())
+    }
 }
 
-// TODO: also take table_options, field_options, and index_options. Maybe we can put these
-// into traits?
-pub fn initialize_handlerton<T: Handlerton>(hton: &mut bindings::handlerton) {
-    hton.kill_query = Some(wrap_kill_query::<T>);
+/// A storage engine that knows its tables without the server's `.frm` files, e.g.
+/// one backed by external data files. The server asks it for tables that it cannot
+/// find otherwise.
+///
+/// Requires [`Handlerton::SUPPORTS_DISCOVERY`].
+pub trait DiscoveringHandlerton: Handlerton {
+    /// Set this to true to write an `.frm` file for discovered tables, so they do
+    /// not need to be discovered again after a restart.
+    const WRITE_FRM: bool = false;
+
+    /// Describe the table `share.db()`.`share.table_name()` as a `CREATE TABLE`
+    /// statement. The table name used in the statement does not matter.
+    ///
+    /// Return [`StorageError::NoSuchTable`] if the engine does not have this table.
+    ///
+    /// ## When is this called?
+    ///
+    /// - When a table is opened that has no `.frm` file
+    fn discover_table(thd: &mut HandlertonThd<Self>, share: &TableShare) -> StorageResult<String>;
+
+    /// Add the names of all tables in `db` to `tables`
+    ///
+    /// ## When is this called?
+    ///
+    /// - `SHOW TABLES`, `information_schema.TABLES` and `DROP DATABASE`
+    fn discover_table_names(db: &str, tables: &mut DiscoveredTables) -> StorageResult;
+
+    /// Whether the engine has the table `db`.`table_name`. This should be cheaper
+    /// than [`DiscoveringHandlerton::discover_table`].
+    fn discover_table_existence(db: &str, table_name: &str) -> bool;
 }
 
-#[allow(clippy::extra_unused_type_parameters)] // expected until H is used
-#[allow(improper_ctypes_definitions)] // level is not FFI-safe
-unsafe extern "C" fn wrap_kill_query<H: Handlerton>(
-    hton: *mut bindings::handlerton,
-    thd: *mut bindings::THD,
-    level: bindings::thd_kill_levels::Type,
-) {
-    let ctx = unsafe { HandlertonCtx::new(hton, thd) };
-    todo!()
+/// Collects table names for [`DiscoveringHandlerton::discover_table_names`]
+pub struct DiscoveredTables<'a> {
+    inner: *mut bindings::handlerton_discovered_list,
+    phantom: PhantomData<&'a mut ()>,
+}
+
+impl<'a> DiscoveredTables<'a> {
+    pub(crate) unsafe fn from_raw(inner: *mut bindings::handlerton_discovered_list) -> Self {
+        debug_assert!(!inner.is_null());
+        Self {
+            inner,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Report a table by name. Names may be added more than once.
+    pub fn add(&mut self, table_name: &str) -> StorageResult {
+        let err = unsafe {
+            bindings::ha_bridge_discovered_add_table(
+                self.inner,
+                table_name.as_ptr().cast(),
+                table_name.len(),
+            )
+        };
+        if err {
+            Err(StorageError::OutOfMem)
+        } else {
+            Ok(())
+        }
+    }
 }
@@ -0,0 +1,283 @@
+//! Search keys passed to index lookups
+//!
+//! The server passes keys in "key format": each key part is optionally prefixed by a
+//! null flag byte, and variable length types (`VARCHAR`, blobs) are stored as a two
+//! byte length followed by data padded to the key part length. Only a prefix of the
+//! index's parts may be present, as indicated by a `key_part_map`.
+
+use super::record::decode_key_part;
+use super::{StorageError, StorageResult};
+use crate::{bindings, Key, Value};
+
+/// How a search key should be matched when positioning an index
+///
+/// This mirrors `ha_rkey_function`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyFind {
+    /// Find the first record equal to the key, else return `KeyNotFound`
+    Exact,
+    /// Find the first record equal to or after the key
+    OrNext,
+    /// Find the last record equal to or before the key
+    OrPrev,
+    /// Find the first record strictly after the key
+    After,
+    /// Find the last record strictly before the key
+    Before,
+    /// Find the first record whose key starts with the given prefix
+    Prefix,
+    /// Find the last record whose key starts with the given prefix
+    PrefixLast,
+    /// Find the last record with the given prefix, or the record before it
+    PrefixLastOrPrev,
+    /// Spatial: the indexed MBR contains the key
+    MbrContain,
+    /// Spatial: the indexed MBR intersects the key
+    MbrIntersect,
+    /// Spatial: the indexed MBR is within the key
+    MbrWithin,
+    /// Spatial: the indexed MBR is disjoint from the key
+    MbrDisjoint,
+    /// Spatial: the indexed MBR equals the key
+    MbrEqual,
+}
+
+impl KeyFind {
+    /// The search mode for an `ha_rkey_function`, or `None` if it is not recognized
+    pub(crate) fn from_raw(find: bindings::ha_rkey_function::Type) -> Option<Self> {
+        use bindings::ha_rkey_function as rk;
+        let find = match find {
+            rk::HA_READ_KEY_EXACT => Self::Exact,
+            rk::HA_READ_KEY_OR_NEXT => Self::OrNext,
+            rk::HA_READ_KEY_OR_PREV => Self::OrPrev,
+            rk::HA_READ_AFTER_KEY => Self::After,
+            rk::HA_READ_BEFORE_KEY => Self::Before,
+            rk::HA_READ_PREFIX => Self::Prefix,
+            rk::HA_READ_PREFIX_LAST => Self::PrefixLast,
+            rk::HA_READ_PREFIX_LAST_OR_PREV => Self::PrefixLastOrPrev,
+            rk::HA_READ_MBR_CONTAIN => Self::MbrContain,
+            rk::HA_READ_MBR_INTERSECT => Self::MbrIntersect,
+            rk::HA_READ_MBR_WITHIN => Self::MbrWithin,
+            rk::HA_READ_MBR_DISJOINT => Self::MbrDisjoint,
+            rk::HA_READ_MBR_EQUAL => Self::MbrEqual,
+            _ => return None,
+        };
+        Some(find)
+    }
+}
+
+/// A decoded search key for an index lookup
+///
+/// Contains a value for each key part that is part of the search, which is always
+/// a prefix of the index's parts.
+#[derive(Clone, Debug)]
+pub struct SearchKey<'a> {
+    index: usize,
+    key: &'a Key<'a>,
+    parts: Vec<Value<'a>>,
+    raw: &'a [u8],
+}
+
+impl<'a> SearchKey<'a> {
+    /// Decode `raw` against the parts of `key` selected by `keypart_map`
+    ///
+    /// # Safety
+    ///
+    /// Unless `keypart_map` selects no parts, `raw` must point to a key in the
+    /// server's key format for `key` that is valid for `'a` and holds at least the
+    /// selected parts, i.e. the sum of their
+    /// [`KeyPart::store_length`](crate::KeyPart::store_length).
+    pub(crate) unsafe fn new(
+        index: usize,
+        key: &'a Key<'a>,
+        raw: *const u8,
+        keypart_map: bindings::key_part_map,
+    ) -> Self {
+        let used = used_parts(keypart_map).min(key.parts().len());
+        let key_parts = &key.parts()[..used];
+        let len = key_parts.iter().map(|p| p.store_length()).sum();
+        let raw = if len == 0 {
+            &[]
+        } else {
+            unsafe { std::slice::from_raw_parts(raw, len) }
+        };
+
+        let mut parts = Vec::with_capacity(used);
+        let mut pos = 0;
+        for part in key_parts {
+            let data = &raw[pos..pos + part.store_length()];
+            pos += part.store_length();
+            if part.is_nullable() {
+                if data[0] != 0 {
+                    parts.push(Value::Null);
+                    continue;
+                }
+                parts.push(decode_key_part(part, &data[1..]));
+            } else {
+                parts.push(decode_key_part(part, data));
+            }
+        }
+
+        Self {
+            index,
+            key,
+            parts,
+            raw,
+        }
+    }
+
+    /// Index of the key in [`Table::keys`](crate::Table::keys)
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Values of the searched key parts, in index order. `NULL` parts are
+    /// [`Value::Null`].
+    pub fn parts(&self) -> &[Value<'a>] {
+        &self.parts
+    }
+
+    /// Number of key parts in the search
+    pub fn len(&self) -> usize {
+        self.parts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parts.is_empty()
+    }
+
+    /// The key in the server's key format
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.raw
+    }
+
+    /// A sort key for the searched parts, which compares with `memcmp` against
+    /// [`Record::sort_key`](super::Record::sort_key) following the index's order
+    /// and collations. A row matches the search key if its sort key starts with
+    /// this one.
+    pub fn sort_key(&self) -> StorageResult<Vec<u8>> {
+        let parts = self.len().try_into().unwrap();
+        let key = self.key.as_raw();
+        let len = unsafe { bindings::table_bridge_key_sort_length(key, parts) };
+        let mut buf = vec![0u8; len];
+        let res = unsafe {
+            bindings::table_bridge_key_sort_key_from_key(
+                key,
+                parts,
+                self.raw.as_ptr(),
+                buf.as_mut_ptr(),
+            )
+        };
+        match res {
+            0 => Ok(buf),
+            _ => Err(StorageError::from_errno(res)),
+        }
+    }
+}
+
+/// One end of a key range, as passed to
+/// [`IndexableHandler::records_in_range`](super::IndexableHandler::records_in_range)
+#[derive(Clone, Debug)]
+pub struct KeyRange<'a> {
+    key: SearchKey<'a>,
+    find: KeyFind,
+}
+
+impl<'a> KeyRange<'a> {
+    /// Decode a `key_range`, or `None` if `range` is null (an open end)
+    ///
+    /// # Safety
+    ///
+    /// `range` must be null or point to a valid `key_range` for `key`, whose key
+    /// data is valid for `'a`.
+    pub(crate) unsafe fn from_raw(
+        index: usize,
+        key: &'a Key<'a>,
+        range: *const bindings::key_range,
+    ) -> StorageResult<Option<Self>> {
+        let Some(range) = (unsafe { range.as_ref() }) else {
+            return Ok(None);
+        };
+        let find = KeyFind::from_raw(range.flag).ok_or(StorageError::WrongCommand)?;
+        Ok(Some(Self {
+            key: unsafe { SearchKey::new(index, key, range.key, range.keypart_map) },
+            find,
+        }))
+    }
+
+    /// The bound's key value
+    pub fn key(&self) -> &SearchKey<'a> {
+        &self.key
+    }
+
+    /// How the bound compares. For a lower bound, [`KeyFind::OrNext`] or
+    /// [`KeyFind::Exact`] include the key and [`KeyFind::After`] excludes it; for an
+    /// upper bound, [`KeyFind::After`] includes it and [`KeyFind::Before`] excludes it.
+    pub fn find(&self) -> KeyFind {
+        self.find
+    }
+}
+
+/// The index pages spanned by a range (`page_range`), which engines may fill in
+/// from [`IndexableHandler::records_in_range`](super::IndexableHandler::records_in_range)
+#[repr(transparent)]
+pub struct PageRange(bindings::page_range);
+
+impl PageRange {
+    pub(crate) unsafe fn from_raw<'a>(pages: *mut bindings::page_range) -> &'a mut Self {
+        unsafe { &mut *pages.cast() }
+    }
+
+    /// The first page of the range, if known
+    pub fn first_page(&self) -> Option<u64> {
+        (self.0.first_page != UNUSED_PAGE_NO).then_some(self.0.first_page)
+    }
+
+    /// The last page of the range, if known
+    pub fn last_page(&self) -> Option<u64> {
+        (self.0.last_page != UNUSED_PAGE_NO).then_some(self.0.last_page)
+    }
+
+    /// Record the pages spanned by the range
+    pub fn set(&mut self, first_page: u64, last_page: u64) {
+        self.0.first_page = first_page;
+        self.0.last_page = last_page;
+    }
+}
+
+/// `UNUSED_PAGE_NO`
+const UNUSED_PAGE_NO: u64 = u64::MAX;
+
+/// Number of key parts selected by a `key_part_map`. The server only ever selects a
+/// prefix of the parts, so this is the number of trailing ones.
+fn used_parts(keypart_map: bindings::key_part_map) -> usize {
+    keypart_map.trailing_ones().try_into().unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_used_parts() {
+        assert_eq!(used_parts(0), 0);
+        assert_eq!(used_parts(0b1), 1);
+        assert_eq!(used_parts(0b111), 3);
+        assert_eq!(used_parts(0b1011), 2);
+        assert_eq!(used_parts(bindings::key_part_map::MAX), 64);
+    }
+
+    #[test]
+    fn test_page_range() {
+        let mut raw = bindings::page_range {
+            first_page: UNUSED_PAGE_NO,
+            last_page: UNUSED_PAGE_NO,
+        };
+        let pages = unsafe { PageRange::from_raw(&mut raw) };
+        assert_eq!(pages.first_page(), None);
+        assert_eq!(pages.last_page(), None);
+        pages.set(3, 7);
+        assert_eq!(pages.first_page(), Some(3));
+        assert_eq!(pages.last_page(), Some(7));
+    }
+}
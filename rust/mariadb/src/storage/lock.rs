@@ -0,0 +1,264 @@
+//! Table-level locks (`THR_LOCK`)
+//!
+//! Before a statement runs, the server collects a `THR_LOCK_DATA` from each handler
+//! via [`Handler::store_lock`](super::Handler::store_lock) and locks them all at once.
+//! An engine that relies on these locks keeps one [`ThrLock`] per table, shared by
+//! all handlers open on it, and one [`ThrLockData`] per handler.
+
+use std::cell::UnsafeCell;
+use std::mem::MaybeUninit;
+use std::ptr;
+use std::sync::Arc;
+
+use crate::bindings;
+
+/// The type of a table-level lock, mirroring `thr_lock_type`
+///
+/// Variants are ordered as in `thr_lock.h`, so locks compare by strength the same
+/// way the server compares them.
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ThrLockType {
+    /// Do not change the current lock
+    Ignore,
+    /// No lock
+    Unlock,
+    /// Resolved by the server to `Read` or `ReadNoInsert`; never seen by engines
+    ReadDefault,
+    /// Read lock
+    Read,
+    /// Read lock from `SELECT ... LOCK IN SHARE MODE`
+    ReadWithSharedLocks,
+    /// Read lock with higher priority than `Write`, allowing concurrent inserts
+    ReadHighPriority,
+    /// Read lock that does not allow concurrent inserts
+    ReadNoInsert,
+    /// Read lock that skips locked rows
+    ReadSkipLocked,
+    /// Write lock that allows other writers, for engines with row-level locking
+    WriteAllowWrite,
+    /// Write lock allowing concurrent `ReadHighPriority` readers; used for inserts
+    WriteConcurrentInsert,
+    /// Write lock for `INSERT DELAYED`
+    WriteDelayed,
+    /// Resolved by the server to a concrete write lock; never seen by engines
+    WriteDefault,
+    /// Write lock with lower priority than `Read`
+    WriteLowPriority,
+    /// Write lock that skips locked rows
+    WriteSkipLocked,
+    /// Normal write lock
+    Write,
+    /// Write lock that makes all new lock requests on the table fail with an error
+    WriteOnly,
+}
+
+impl ThrLockType {
+    /// The lock type for a raw value, or `None` if it is not a known type
+    pub(crate) fn from_raw(lock_type: bindings::thr_lock_type::Type) -> Option<Self> {
+        use bindings::thr_lock_type as tl;
+        let ret = match lock_type {
+            tl::TL_IGNORE => Self::Ignore,
+            tl::TL_UNLOCK => Self::Unlock,
+            tl::TL_READ_DEFAULT => Self::ReadDefault,
+            tl::TL_READ => Self::Read,
+            tl::TL_READ_WITH_SHARED_LOCKS => Self::ReadWithSharedLocks,
+            tl::TL_READ_HIGH_PRIORITY => Self::ReadHighPriority,
+            tl::TL_READ_NO_INSERT => Self::ReadNoInsert,
+            tl::TL_READ_SKIP_LOCKED => Self::ReadSkipLocked,
+            tl::TL_WRITE_ALLOW_WRITE => Self::WriteAllowWrite,
+            tl::TL_WRITE_CONCURRENT_INSERT => Self::WriteConcurrentInsert,
+            tl::TL_WRITE_DELAYED => Self::WriteDelayed,
+            tl::TL_WRITE_DEFAULT => Self::WriteDefault,
+            tl::TL_WRITE_LOW_PRIORITY => Self::WriteLowPriority,
+            tl::TL_WRITE_SKIP_LOCKED => Self::WriteSkipLocked,
+            tl::TL_WRITE => Self::Write,
+            tl::TL_WRITE_ONLY => Self::WriteOnly,
+            _ => return None,
+        };
+        Some(ret)
+    }
+
+    pub(crate) fn to_raw(self) -> bindings::thr_lock_type::Type {
+        use bindings::thr_lock_type as tl;
+        match self {
+            Self::Ignore => tl::TL_IGNORE,
+            Self::Unlock => tl::TL_UNLOCK,
+            Self::ReadDefault => tl::TL_READ_DEFAULT,
+            Self::Read => tl::TL_READ,
+            Self::ReadWithSharedLocks => tl::TL_READ_WITH_SHARED_LOCKS,
+            Self::ReadHighPriority => tl::TL_READ_HIGH_PRIORITY,
+            Self::ReadNoInsert => tl::TL_READ_NO_INSERT,
+            Self::ReadSkipLocked => tl::TL_READ_SKIP_LOCKED,
+            Self::WriteAllowWrite => tl::TL_WRITE_ALLOW_WRITE,
+            Self::WriteConcurrentInsert => tl::TL_WRITE_CONCURRENT_INSERT,
+            Self::WriteDelayed => tl::TL_WRITE_DELAYED,
+            Self::WriteDefault => tl::TL_WRITE_DEFAULT,
+            Self::WriteLowPriority => tl::TL_WRITE_LOW_PRIORITY,
+            Self::WriteSkipLocked => tl::TL_WRITE_SKIP_LOCKED,
+            Self::Write => tl::TL_WRITE,
+            Self::WriteOnly => tl::TL_WRITE_ONLY,
+        }
+    }
+
+    /// True for any read lock
+    pub fn is_read(self) -> bool {
+        self >= Self::ReadDefault && self < Self::WriteAllowWrite
+    }
+
+    /// True for any write lock
+    pub fn is_write(self) -> bool {
+        self >= Self::WriteAllowWrite
+    }
+}
+
+/// A table lock shared by all handlers open on the same table (`THR_LOCK`)
+///
+/// Usually stored in an `Arc` in the engine's per-table state.
+pub struct ThrLock(Box<UnsafeCell<bindings::THR_LOCK>>);
+
+impl ThrLock {
+    pub fn new() -> Self {
+        let lock: Box<UnsafeCell<MaybeUninit<bindings::THR_LOCK>>> =
+            Box::new(UnsafeCell::new(MaybeUninit::zeroed()));
+        unsafe { bindings::thr_lock_init(lock.get().cast()) };
+        // SAFETY: `thr_lock_init` initialized the lock, and the layouts match
+        Self(unsafe { Box::from_raw(Box::into_raw(lock).cast()) })
+    }
+
+    fn as_ptr(&self) -> *mut bindings::THR_LOCK {
+        self.0.get()
+    }
+}
+
+impl Default for ThrLock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for ThrLock {
+    fn drop(&mut self) {
+        unsafe { bindings::thr_lock_delete(self.as_ptr()) }
+    }
+}
+
+// SAFETY: `THR_LOCK` is protected by its own mutex, and the server only accesses it
+// through the lock functions.
+unsafe impl Send for ThrLock {}
+unsafe impl Sync for ThrLock {}
+
+/// A single handler's request on a [`ThrLock`] (`THR_LOCK_DATA`)
+///
+/// This keeps the lock alive as long as it exists.
+pub struct ThrLockData {
+    data: Box<UnsafeCell<bindings::THR_LOCK_DATA>>,
+    lock: Arc<ThrLock>,
+}
+
+impl ThrLockData {
+    pub fn new(lock: Arc<ThrLock>) -> Self {
+        let data: Box<UnsafeCell<MaybeUninit<bindings::THR_LOCK_DATA>>> =
+            Box::new(UnsafeCell::new(MaybeUninit::zeroed()));
+        unsafe { bindings::thr_lock_data_init(lock.as_ptr(), data.get().cast(), ptr::null_mut()) };
+        Self {
+            // SAFETY: initialized by `thr_lock_data_init`
+            data: unsafe { Box::from_raw(Box::into_raw(data).cast()) },
+            lock,
+        }
+    }
+
+    /// The lock this data belongs to
+    pub fn lock(&self) -> &Arc<ThrLock> {
+        &self.lock
+    }
+
+    /// The currently requested or held lock type, `None` if it is not a known type
+    pub fn lock_type(&self) -> Option<ThrLockType> {
+        ThrLockType::from_raw(unsafe { (*self.data.get()).type_ })
+    }
+
+    /// Set the lock type to request. Should only be changed from `store_lock`.
+    pub fn set_lock_type(&mut self, lock_type: ThrLockType) {
+        self.data.get_mut().type_ = lock_type.to_raw();
+    }
+
+    /// The usual `store_lock` behavior: if `lock_type` is not [`ThrLockType::Ignore`]
+    /// and no lock is currently held, request `lock_type`.
+    ///
+    /// `LOCK TABLES` calls `store_lock` for tables that are already locked; the
+    /// existing lock is kept in that case.
+    pub fn request(&mut self, lock_type: ThrLockType) {
+        self.request_raw(lock_type.to_raw());
+    }
+
+    /// [`ThrLockData::request`] for a raw lock type, which need not be known
+    pub(crate) fn request_raw(&mut self, lock_type: bindings::thr_lock_type::Type) {
+        use bindings::thr_lock_type as tl;
+        let data = self.data.get_mut();
+        if lock_type != tl::TL_IGNORE && data.type_ == tl::TL_UNLOCK {
+            data.type_ = lock_type;
+        }
+    }
+
+    pub(crate) fn as_ptr(&self) -> *mut bindings::THR_LOCK_DATA {
+        self.data.get()
+    }
+}
+
+// SAFETY: the data is only modified by its owning handler, or by the server while
+// holding the `THR_LOCK` mutex.
+unsafe impl Send for ThrLockData {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lock_type_roundtrip() {
+        for lt in [
+            ThrLockType::Ignore,
+            ThrLockType::Unlock,
+            ThrLockType::Read,
+            ThrLockType::ReadNoInsert,
+            ThrLockType::WriteAllowWrite,
+            ThrLockType::WriteConcurrentInsert,
+            ThrLockType::Write,
+            ThrLockType::WriteOnly,
+        ] {
+            assert_eq!(ThrLockType::from_raw(lt.to_raw()), Some(lt));
+        }
+        assert_eq!(ThrLockType::from_raw(1000), None);
+        assert!(ThrLockType::ReadNoInsert.is_read());
+        assert!(!ThrLockType::ReadNoInsert.is_write());
+        assert!(ThrLockType::WriteConcurrentInsert.is_write());
+        assert!(!ThrLockType::Unlock.is_read());
+    }
+
+    #[test]
+    fn test_lock_type_order() {
+        let all = [
+            ThrLockType::Ignore,
+            ThrLockType::Unlock,
+            ThrLockType::ReadDefault,
+            ThrLockType::Read,
+            ThrLockType::ReadWithSharedLocks,
+            ThrLockType::ReadHighPriority,
+            ThrLockType::ReadNoInsert,
+            ThrLockType::ReadSkipLocked,
+            ThrLockType::WriteAllowWrite,
+            ThrLockType::WriteConcurrentInsert,
+            ThrLockType::WriteDelayed,
+            ThrLockType::WriteDefault,
+            ThrLockType::WriteLowPriority,
+            ThrLockType::WriteSkipLocked,
+            ThrLockType::Write,
+            ThrLockType::WriteOnly,
+        ];
+        for a in all {
+            for b in all {
+                assert_eq!(a.cmp(&b), a.to_raw().cmp(&b.to_raw()), "{a:?} vs {b:?}");
+            }
+        }
+    }
+}
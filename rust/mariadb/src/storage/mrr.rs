@@ -0,0 +1,196 @@
+//! Multi-Range Read (MRR)
+//!
+//! For batched key lookups (e.g. range scans over several intervals, or joins using
+//! the join buffer) the server can hand an engine all of the ranges at once instead
+//! of calling `index_read_map` for each. The engine may then read them in whatever
+//! order is cheapest, such as sorted by physical location, as long as it reports
+//! which range each row belongs to.
+//!
+//! See [`IndexableHandler::multi_range_read_init`](super::IndexableHandler::multi_range_read_init).
+
+use std::ffi::c_uint;
+use std::{mem, ptr};
+
+use super::{KeyRange, StorageResult};
+use crate::{bindings, Key, Table};
+
+/// Flags describing a multi-range read (`HA_MRR_*`)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MrrFlags(pub(crate) c_uint);
+
+impl MrrFlags {
+    /// All ranges are single-point (equality) ranges
+    pub const SINGLE_POINT: Self = Self(bindings::HA_MRR_SINGLE_POINT);
+    /// All ranges use the same key parts
+    pub const FIXED_KEY: Self = Self(bindings::HA_MRR_FIXED_KEY);
+    /// The caller does not need to know which range a row belongs to
+    pub const NO_ASSOCIATION: Self = Self(bindings::HA_MRR_NO_ASSOCIATION);
+    /// Ranges are given in key order, and rows must be returned in key order
+    pub const SORTED: Self = Self(bindings::HA_MRR_SORTED);
+    /// Only the index columns need to be read
+    pub const INDEX_ONLY: Self = Self(bindings::HA_MRR_INDEX_ONLY);
+    /// The server's implementation is used, which reads each range in turn
+    pub const USE_DEFAULT_IMPL: Self = Self(bindings::HA_MRR_USE_DEFAULT_IMPL);
+    /// No range bound contains `NULL`
+    pub const NO_NULL_ENDPOINTS: Self = Self(bindings::HA_MRR_NO_NULL_ENDPOINTS);
+    /// Range keys stay valid for the whole scan, not just until the next range
+    pub const MATERIALIZED_KEYS: Self = Self(bindings::HA_MRR_MATERIALIZED_KEYS);
+}
+
+impl_flags!(MrrFlags, u32);
+
+/// The server's identifier for a range (`range_id_t`). Rows returned from
+/// [`IndexableHandler::multi_range_read_next`](super::IndexableHandler::multi_range_read_next)
+/// report the range they belong to with this.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RangeId(pub(crate) bindings::range_id_t);
+
+impl RangeId {
+    /// An identifier for rows when the scan has [`MrrFlags::NO_ASSOCIATION`]
+    pub const NONE: Self = Self(ptr::null_mut());
+}
+
+/// One range of a multi-range read
+#[derive(Clone, Debug)]
+pub struct MultiRange<'a> {
+    start: Option<KeyRange<'a>>,
+    end: Option<KeyRange<'a>>,
+    id: RangeId,
+    flags: c_uint,
+}
+
+impl<'a> MultiRange<'a> {
+    /// The lower bound, `None` if the range is open below
+    pub fn start(&self) -> Option<&KeyRange<'a>> {
+        self.start.as_ref()
+    }
+
+    /// The upper bound, `None` if the range is open above
+    pub fn end(&self) -> Option<&KeyRange<'a>> {
+        self.end.as_ref()
+    }
+
+    pub fn id(&self) -> RangeId {
+        self.id
+    }
+
+    /// The range is a single key value, given by [`MultiRange::start`]
+    pub fn is_eq(&self) -> bool {
+        self.flags & bindings::EQ_RANGE != 0
+    }
+
+    /// The range is a single value of a unique index, so it has at most one row
+    pub fn is_unique(&self) -> bool {
+        self.flags & bindings::UNIQUE_RANGE != 0
+    }
+
+    /// The range is `key IS NULL`
+    pub fn is_null(&self) -> bool {
+        self.flags & bindings::NULL_RANGE != 0
+    }
+}
+
+/// The sequence of ranges to read (`RANGE_SEQ_IF`)
+pub struct RangeSeq<'a> {
+    funcs: bindings::RANGE_SEQ_IF,
+    iter: bindings::range_seq_t,
+    n_ranges: usize,
+    index: usize,
+    key: &'a Key<'a>,
+    current: bindings::KEY_MULTI_RANGE,
+}
+
+impl<'a> RangeSeq<'a> {
+    /// The sequence stored in `handler::mrr_funcs` and `handler::mrr_iter`, for the
+    /// active index
+    ///
+    /// # Safety
+    ///
+    /// The sequence must have been initialized by the bridge's
+    /// `multi_range_read_init`, and `table` must be the handler's table.
+    pub(crate) unsafe fn from_handler(base: &bindings::handler, table: &'a Table<'a>) -> Self {
+        let index: usize = base.active_index.try_into().unwrap();
+        Self {
+            funcs: base.mrr_funcs,
+            iter: base.mrr_iter,
+            n_ranges: base.ranges_in_seq.try_into().unwrap(),
+            index,
+            key: &table.keys()[index],
+            current: unsafe { mem::zeroed() },
+        }
+    }
+
+    /// The number of ranges the server expects, or 0 if unknown
+    pub fn n_ranges(&self) -> usize {
+        self.n_ranges
+    }
+
+    /// The index being read
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// The next range, or `None` after the last one
+    ///
+    /// Key values are only valid until the next call unless the scan has
+    /// [`MrrFlags::MATERIALIZED_KEYS`]; copy them to keep them longer.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> StorageResult<Option<MultiRange<'_>>> {
+        let Some(next) = self.funcs.next else {
+            return Ok(None);
+        };
+        if unsafe { next(self.iter, &mut self.current) } {
+            return Ok(None);
+        }
+
+        let range = &self.current;
+        let bound = |key: &bindings::key_range| {
+            if key.keypart_map == 0 {
+                Ok(None)
+            } else {
+                unsafe { KeyRange::from_raw(self.index, self.key, key) }
+            }
+        };
+        Ok(Some(MultiRange {
+            start: bound(&range.start_key)?,
+            end: bound(&range.end_key)?,
+            id: RangeId(range.ptr),
+            flags: range.range_flag,
+        }))
+    }
+
+    /// True if the server does not want a row of range `id` with row position
+    /// `rowid` (from [`Handler::position`](super::Handler::position)), e.g. because
+    /// it already found a match for that range. Such rows should be skipped.
+    pub fn skip_record(&self, id: RangeId, rowid: Option<&[u8]>) -> bool {
+        let Some(skip) = self.funcs.skip_record else {
+            return false;
+        };
+        let rowid = rowid.map_or(ptr::null_mut(), |r| r.as_ptr().cast_mut());
+        unsafe { skip(self.iter, id.0, rowid) }
+    }
+
+    /// True if the index columns that were just read (into the record buffer) do not
+    /// match the condition the server checks for range `id`. Such rows should be
+    /// skipped before reading the rest of the row.
+    pub fn skip_index_tuple(&self, id: RangeId) -> bool {
+        let Some(skip) = self.funcs.skip_index_tuple else {
+            return false;
+        };
+        unsafe { skip(self.iter, id.0) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mrr_flags() {
+        let flags = MrrFlags::SORTED | MrrFlags::USE_DEFAULT_IMPL;
+        assert!(flags.contains(MrrFlags::SORTED));
+        let flags = flags.difference(MrrFlags::USE_DEFAULT_IMPL);
+        assert!(!flags.intersects(MrrFlags::USE_DEFAULT_IMPL));
+        assert_eq!(flags, MrrFlags::SORTED);
+    }
+}
@@ -0,0 +1,259 @@
+//! Engine-defined options for `CREATE TABLE`, its columns and its indexes
+//!
+//! The server parses these options (e.g. `ENGINE=X COMPRESSION='zstd'`) into a
+//! struct whose layout is described by a list of `ha_create_table_option`. That list
+//! and the conversion back to Rust are generated by `#[derive(TableOptions)]`,
+//! `#[derive(FieldOptions)]` and `#[derive(IndexOptions)]`.
+
+use std::ffi::{c_char, c_uint, c_ulonglong, c_void, CStr};
+use std::ptr;
+
+use super::Handlerton;
+use crate::{bindings, Field, Key, TableShare};
+
+/// Options that can be given for a table, e.g. `CREATE TABLE t (...) ENGINE=X
+/// BLOCK_SIZE=4096`. Use `()` if the engine has none.
+pub trait TableOptions: OptionStruct {}
+
+/// Options that can be given for a column, e.g. `CREATE TABLE t (a INT COMPRESSED=1)`.
+/// Use `()` if the engine has none.
+pub trait FieldOptions: OptionStruct {}
+
+/// Options that can be given for an index, e.g. `CREATE TABLE t (..., KEY (a)
+/// CLUSTERED=1)`. Use `()` if the engine has none.
+pub trait IndexOptions: OptionStruct {}
+
+/// An enum usable as an option value, implemented by `#[derive(OptionEnum)]`
+pub trait OptionEnum: Sized {
+    /// Comma-separated names of all variants, in order
+    const VALUES: &'static CStr;
+
+    /// The value of an option without a `default`, which is the first variant
+    const DEFAULT: Self;
+
+    /// Create a variant from its position in `VALUES`, or `None` if it is out of range
+    fn from_index(index: u32) -> Option<Self>;
+}
+
+/// The layout of an options struct. Implemented by the derive macros.
+#[doc(hidden)]
+pub trait OptionStruct: Sized {
+    /// Option definitions, terminated with [`OPTION_END`]
+    const OPTIONS: &'static [bindings::ha_create_table_option];
+
+    /// Read the struct that the server filled in. `ptr` may be null, in which case
+    /// defaults are used.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be null or point to a struct described by `OPTIONS`.
+    unsafe fn from_raw(ptr: *const c_void) -> Self;
+}
+
+impl OptionStruct for () {
+    const OPTIONS: &'static [bindings::ha_create_table_option] = &[];
+
+    unsafe fn from_raw(_ptr: *const c_void) -> Self {}
+}
+
+impl TableOptions for () {}
+impl FieldOptions for () {}
+impl IndexOptions for () {}
+
+impl<'a> TableShare<'a> {
+    /// The engine-defined table options given in `CREATE TABLE`
+    ///
+    /// # Safety
+    ///
+    /// `H` must be the engine of this table, e.g. `Self::Handlerton` for the table
+    /// passed to a [`Handler`](super::Handler). The server stores the options of
+    /// whichever engine the table uses, so any other `H` reads them as the wrong type.
+    pub unsafe fn options<H: Handlerton>(&self) -> H::TableOptions {
+        unsafe { H::TableOptions::from_raw(self.inner().option_struct.cast()) }
+    }
+}
+
+impl<'a> Field<'a> {
+    /// The engine-defined options given for this column
+    ///
+    /// # Safety
+    ///
+    /// `H` must be the engine of the column's table, as for [`TableShare::options`].
+    pub unsafe fn options<H: Handlerton>(&self) -> H::FieldOptions {
+        unsafe { H::FieldOptions::from_raw(self.inner().option_struct.cast()) }
+    }
+}
+
+impl<'a> Key<'a> {
+    /// The engine-defined options given for this index
+    ///
+    /// # Safety
+    ///
+    /// `H` must be the engine of the index's table, as for [`TableShare::options`].
+    pub unsafe fn options<H: Handlerton>(&self) -> H::IndexOptions {
+        unsafe { H::IndexOptions::from_raw(self.inner().option_struct.cast()) }
+    }
+}
+
+/// The pointer to store in the handlerton for an option list, null if there are no
+/// options
+pub(crate) fn option_list<T: OptionStruct>() -> *mut bindings::ha_create_table_option {
+    if T::OPTIONS.is_empty() {
+        ptr::null_mut()
+    } else {
+        // The server never writes to the list
+        T::OPTIONS.as_ptr().cast_mut()
+    }
+}
+
+/// Marks the end of an option list (`HA_xOPTION_END`)
+pub const OPTION_END: bindings::ha_create_table_option = option(
+    bindings::ha_option_type::HA_OPTION_TYPE_ULL,
+    None,
+    0,
+    ptr::null(),
+    0,
+    0,
+    0,
+    0,
+);
+
+/// A string option (`HA_TOPTION_STRING`), stored as a `const char *`
+pub const fn option_string(name: &'static CStr, offset: isize) -> bindings::ha_create_table_option {
+    option(
+        bindings::ha_option_type::HA_OPTION_TYPE_STRING,
+        Some(name),
+        offset,
+        ptr::null(),
+        0,
+        0,
+        0,
+        0,
+    )
+}
+
+/// A number option (`HA_TOPTION_NUMBER`), stored as a `ulonglong`
+pub const fn option_number(
+    name: &'static CStr,
+    offset: isize,
+    default: c_ulonglong,
+    min: c_ulonglong,
+    max: c_ulonglong,
+    block_size: c_ulonglong,
+) -> bindings::ha_create_table_option {
+    option(
+        bindings::ha_option_type::HA_OPTION_TYPE_ULL,
+        Some(name),
+        offset,
+        ptr::null(),
+        default,
+        min,
+        max,
+        block_size,
+    )
+}
+
+/// A boolean option (`HA_TOPTION_BOOL`), stored as a `bool`
+pub const fn option_bool(
+    name: &'static CStr,
+    offset: isize,
+    default: bool,
+) -> bindings::ha_create_table_option {
+    option(
+        bindings::ha_option_type::HA_OPTION_TYPE_BOOL,
+        Some(name),
+        offset,
+        ptr::null(),
+        default as c_ulonglong,
+        0,
+        1,
+        0,
+    )
+}
+
+/// An enum option (`HA_TOPTION_ENUM`), stored as a `uint` index into `values`
+pub const fn option_enum(
+    name: &'static CStr,
+    offset: isize,
+    values: &'static CStr,
+    default: c_uint,
+) -> bindings::ha_create_table_option {
+    option(
+        bindings::ha_option_type::HA_OPTION_TYPE_ENUM,
+        Some(name),
+        offset,
+        values.as_ptr(),
+        default as c_ulonglong,
+        0,
+        values.to_bytes().len() as c_ulonglong,
+        0,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+const fn option(
+    type_: bindings::ha_option_type::Type,
+    name: Option<&'static CStr>,
+    offset: isize,
+    values: *const c_char,
+    def_value: c_ulonglong,
+    min_value: c_ulonglong,
+    max_value: c_ulonglong,
+    block_size: c_ulonglong,
+) -> bindings::ha_create_table_option {
+    let (name, name_length) = match name {
+        Some(name) => (name.as_ptr(), name.to_bytes().len()),
+        None => (ptr::null(), 0),
+    };
+    bindings::ha_create_table_option {
+        type_,
+        name,
+        name_length,
+        values,
+        def_value,
+        min_value,
+        max_value,
+        block_size,
+        offset,
+        var: ptr::null_mut(),
+    }
+}
+
+/// Read a string option, which is null if it was not given
+///
+/// # Safety
+///
+/// `ptr` must be null or point to a nul-terminated string.
+pub unsafe fn read_option_string(ptr: *const c_char) -> Option<String> {
+    if ptr.is_null() {
+        return None;
+    }
+    Some(
+        unsafe { CStr::from_ptr(ptr) }
+            .to_string_lossy()
+            .into_owned(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::internals::cstr;
+
+    #[test]
+    fn test_option_enum() {
+        let opt = option_enum(cstr!("FORMAT"), 8, cstr!("ROW,COLUMN"), 1);
+        assert_eq!(opt.type_, bindings::ha_option_type::HA_OPTION_TYPE_ENUM);
+        assert_eq!(opt.name_length, 6);
+        assert_eq!(opt.offset, 8);
+        assert_eq!(opt.def_value, 1);
+        assert_eq!(opt.max_value, 10);
+    }
+
+    #[test]
+    fn test_option_end() {
+        assert!(OPTION_END.name.is_null());
+        assert_eq!(OPTION_END.name_length, 0);
+        assert!(option_list::<()>().is_null());
+    }
+}
@@ -0,0 +1,866 @@
+//! Conversion between the server's row format (`record[0]`) and [`Value`]s
+//!
+//! A record is a packed buffer of `reclength` bytes. It starts with the null
+//! bitmap, followed by each field at its offset. Most fields are stored inline,
+//! but blobs store a length and a pointer to data that lives elsewhere.
+
+use std::ffi::{c_char, c_void};
+use std::{mem, ptr, slice};
+
+use super::{StorageError, StorageResult};
+use crate::bindings::enum_field_types as ft;
+use crate::value::{Date, DateTime, Time, TimeStamp, Value};
+use crate::{bindings, Field, KeyPart, Table};
+
+/// Size of the length prefix of variable length key parts
+const KEY_BLOB_LENGTH: usize = bindings::HA_KEY_BLOB_LENGTH as usize;
+/// Offset added to the integer part of `TIME2` values
+const TIMEF_INT_OFS: i64 = 0x80_0000;
+/// Offset added to the full packed value of `TIME2` values
+const TIMEF_OFS: i64 = 0x8000_0000_0000;
+/// Offset added to the integer part of `DATETIME2` values
+const DATETIMEF_INT_OFS: i64 = 0x80_0000_0000;
+
+/// A read-only view of a row in the server's record format
+#[derive(Clone, Copy)]
+pub struct Record<'a> {
+    table: &'a Table<'a>,
+    buf: &'a [u8],
+}
+
+impl<'a> Record<'a> {
+    pub(crate) fn new(table: &'a Table<'a>, buf: &'a [u8]) -> Self {
+        debug_assert_eq!(buf.len(), table.share().reclength());
+        Self { table, buf }
+    }
+
+    /// The table this row belongs to
+    pub fn table(&self) -> &'a Table<'a> {
+        self.table
+    }
+
+    /// The raw record buffer
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.buf
+    }
+
+    /// Number of columns in this row
+    pub fn len(&self) -> usize {
+        self.table.fields().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Whether the column at `idx` is `NULL`
+    pub fn is_null(&self, idx: usize) -> bool {
+        let field = self.table.fields()[idx];
+        is_null(self.table, field, self.buf)
+    }
+
+    /// Decode the column at `idx`
+    pub fn get(&self, idx: usize) -> Value<'a> {
+        let field = self.table.fields()[idx];
+        if is_null(self.table, field, self.buf) {
+            return Value::Null;
+        }
+        decode(self.table, field, self.buf)
+    }
+
+    /// Decode the column with the given name
+    pub fn get_by_name(&self, name: &str) -> Option<Value<'a>> {
+        let idx = self.table.fields().iter().position(|f| f.name() == name)?;
+        Some(self.get(idx))
+    }
+
+    /// Iterate over all column values in definition order
+    pub fn values(&self) -> impl Iterator<Item = Value<'a>> + '_ {
+        (0..self.len()).map(|idx| self.get(idx))
+    }
+
+    /// The column at `idx` as text, formatted by the server the same way as for
+    /// `SELECT`, or `None` if it is `NULL`
+    pub fn get_text(&self, idx: usize) -> Option<Vec<u8>> {
+        /// Copy the text from the server
+        unsafe extern "C" fn sink(ctx: *mut c_void, text: *const c_char, len: usize) {
+            let buf = unsafe { &mut *ctx.cast::<Vec<u8>>() };
+            if len > 0 {
+                buf.extend_from_slice(unsafe { slice::from_raw_parts(text.cast(), len) });
+            }
+        }
+
+        if self.is_null(idx) {
+            return None;
+        }
+        let field = self.table.fields()[idx];
+        let mut buf = Vec::new();
+        unsafe {
+            bindings::table_bridge_field_val_str(
+                field.as_raw(),
+                self.buf.as_ptr(),
+                Some(sink),
+                ptr::addr_of_mut!(buf).cast(),
+            );
+        }
+        Some(buf)
+    }
+
+    /// A sort key for this row's columns in the index at `index` (from
+    /// [`Table::keys`]). Sort keys compare with `memcmp` in index order, following
+    /// each column's collation, with `NULL` first and descending parts reversed.
+    /// Rows with equal sort keys are duplicates for a unique index, unless a part
+    /// is `NULL`.
+    pub fn sort_key(&self, index: usize) -> Vec<u8> {
+        let key = &self.table.keys()[index];
+        let parts = key.parts().len().try_into().unwrap();
+        let len = unsafe { bindings::table_bridge_key_sort_length(key.as_raw(), parts) };
+        let mut buf = vec![0u8; len];
+        unsafe {
+            bindings::table_bridge_key_sort_key(
+                key.as_raw(),
+                parts,
+                self.buf.as_ptr(),
+                buf.as_mut_ptr(),
+            );
+        }
+        buf
+    }
+}
+
+impl<'a> Table<'a> {
+    /// A row holding each column's default value, as given in `CREATE TABLE`
+    pub fn default_record(&'a self) -> Record<'a> {
+        let len = self.share().reclength();
+        let buf = unsafe { slice::from_raw_parts(self.share().default_values(), len) };
+        Record::new(self, buf)
+    }
+}
+
+/// A writable row in the server's record format, used to return rows to the server
+pub struct RecordMut<'a> {
+    table: &'a Table<'a>,
+    buf: &'a mut [u8],
+}
+
+impl<'a> RecordMut<'a> {
+    pub(crate) fn new(table: &'a Table<'a>, buf: &'a mut [u8]) -> Self {
+        debug_assert_eq!(buf.len(), table.share().reclength());
+        Self { table, buf }
+    }
+
+    /// The table this row belongs to
+    pub fn table(&self) -> &'a Table<'a> {
+        self.table
+    }
+
+    /// View the current contents as a [`Record`]
+    pub fn as_record(&self) -> Record<'_> {
+        Record {
+            table: self.table,
+            buf: self.buf,
+        }
+    }
+
+    /// The raw record buffer, useful for engines that store rows in the server's
+    /// format
+    pub fn as_bytes_mut(&mut self) -> &mut [u8] {
+        self.buf
+    }
+
+    /// Set the column at `idx` to `NULL`. Returns an error if the column is `NOT NULL`.
+    pub fn set_null(&mut self, idx: usize) -> StorageResult {
+        let field = self.table.fields()[idx];
+        let Some((offset, bit)) = field.null_offset(self.table) else {
+            return Err(StorageError::WrongInRecord);
+        };
+        self.buf[offset] |= bit;
+        Ok(())
+    }
+
+    /// Encode `value` into the column at `idx`.
+    ///
+    /// `Value::Null` is the same as calling [`RecordMut::set_null`]. Blob columns only
+    /// store a pointer to their data, so the data must stay valid until the server
+    /// is done with the row (i.e. until the next call into the handler).
+    ///
+    /// Returns `StorageError::WrongInRecord` if the value does not fit the column type.
+    pub fn set(&mut self, idx: usize, value: Value<'a>) -> StorageResult {
+        if matches!(value, Value::Null) {
+            return self.set_null(idx);
+        }
+
+        let field = self.table.fields()[idx];
+        if let Some((offset, bit)) = field.null_offset(self.table) {
+            self.buf[offset] &= !bit;
+        }
+        encode(self.table, field, self.buf, value)
+    }
+
+    /// Point the blob column at `idx` to `data` without copying it, like
+    /// [`RecordMut::set`] with `Value::Blob`. `data` must stay valid until the server
+    /// is done with the row, so it should live in a buffer owned by the handler.
+    ///
+    /// Returns `StorageError::WrongInRecord` if the column is not a blob or `data` is
+    /// too long for it.
+    pub fn set_blob(&mut self, idx: usize, data: &[u8]) -> StorageResult {
+        let field = self.table.fields()[idx];
+        if !matches!(
+            field.real_type(),
+            ft::MYSQL_TYPE_TINY_BLOB
+                | ft::MYSQL_TYPE_MEDIUM_BLOB
+                | ft::MYSQL_TYPE_LONG_BLOB
+                | ft::MYSQL_TYPE_BLOB
+                | ft::MYSQL_TYPE_GEOMETRY
+        ) {
+            return Err(StorageError::WrongInRecord);
+        }
+        if let Some((offset, bit)) = field.null_offset(self.table) {
+            self.buf[offset] &= !bit;
+        }
+        encode(self.table, field, self.buf, Value::Blob(data))
+    }
+
+    /// Parse `text` into the column at `idx` the same way the server converts a
+    /// string literal in `INSERT`, e.g. `"12"` into an integer column.
+    ///
+    /// As with [`RecordMut::set`], blob columns may keep a pointer to `text`, so it
+    /// must stay valid until the server is done with the row.
+    ///
+    /// Returns `StorageError::WrongInRecord` if the text could not be stored without
+    /// loss, such as a non-numeric string in an integer column.
+    pub fn set_text(&mut self, idx: usize, text: &[u8]) -> StorageResult {
+        let field = self.table.fields()[idx];
+        if let Some((offset, bit)) = field.null_offset(self.table) {
+            self.buf[offset] &= !bit;
+        }
+        let res = unsafe {
+            bindings::table_bridge_field_store_text(
+                field.as_raw(),
+                self.buf.as_mut_ptr(),
+                text.as_ptr().cast(),
+                text.len(),
+            )
+        };
+        if res == 0 {
+            Ok(())
+        } else {
+            Err(StorageError::WrongInRecord)
+        }
+    }
+}
+
+fn is_null(table: &Table, field: &Field, buf: &[u8]) -> bool {
+    field
+        .null_offset(table)
+        .is_some_and(|(offset, bit)| buf[offset] & bit != 0)
+}
+
+/// Read a little endian unsigned integer of up to 8 bytes
+fn read_le(bytes: &[u8]) -> u64 {
+    let mut tmp = [0u8; 8];
+    tmp[..bytes.len()].copy_from_slice(bytes);
+    u64::from_le_bytes(tmp)
+}
+
+/// Read a big endian unsigned integer of up to 8 bytes
+fn read_be(bytes: &[u8]) -> u64 {
+    let mut tmp = [0u8; 8];
+    tmp[8 - bytes.len()..].copy_from_slice(bytes);
+    u64::from_be_bytes(tmp)
+}
+
+/// Write the low `dst.len()` bytes of `val` as little endian
+fn write_le(dst: &mut [u8], val: u64) {
+    let len = dst.len();
+    dst.copy_from_slice(&val.to_le_bytes()[..len]);
+}
+
+/// Write the low `dst.len()` bytes of `val` as big endian
+fn write_be(dst: &mut [u8], val: u64) {
+    let len = dst.len();
+    dst.copy_from_slice(&val.to_be_bytes()[8 - len..]);
+}
+
+/// Sign extend the low `bytes` bytes of `val`
+fn sign_extend(val: u64, bytes: usize) -> i64 {
+    let shift = 64 - bytes * 8;
+    ((val << shift) as i64) >> shift
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn decode<'a>(table: &Table, field: &Field, buf: &'a [u8]) -> Value<'a> {
+    let offset = field.offset(table);
+    let data = &buf[offset..offset + field.pack_length()];
+
+    if field.real_type() == ft::MYSQL_TYPE_BIT {
+        // Bit fields may keep some bits in the null bitmap, so let the server decode
+        let val = unsafe { bindings::table_bridge_field_val_int(field.as_raw(), buf.as_ptr()) };
+        return Value::Bit(val as u64);
+    }
+
+    decode_value(field, data)
+}
+
+/// Decode a key part value (without its null flag byte) from a search key
+pub(super) fn decode_key_part<'a>(part: &KeyPart, data: &'a [u8]) -> Value<'a> {
+    let field = part.field();
+    match field.real_type() {
+        ft::MYSQL_TYPE_VARCHAR
+        | ft::MYSQL_TYPE_TINY_BLOB
+        | ft::MYSQL_TYPE_MEDIUM_BLOB
+        | ft::MYSQL_TYPE_LONG_BLOB
+        | ft::MYSQL_TYPE_BLOB
+        | ft::MYSQL_TYPE_GEOMETRY => {
+            // Keys always use a two byte length, followed by the (possibly prefixed) data
+            let len = read_le(&data[..KEY_BLOB_LENGTH]) as usize;
+            let bytes = &data[KEY_BLOB_LENGTH..KEY_BLOB_LENGTH + len];
+            if field.real_type() == ft::MYSQL_TYPE_VARCHAR {
+                Value::String(bytes)
+            } else {
+                Value::Blob(bytes)
+            }
+        }
+        ft::MYSQL_TYPE_STRING if part.length() < field.pack_length() => {
+            // Prefix of a `CHAR` column
+            let end = data.iter().rposition(|b| *b != b' ').map_or(0, |p| p + 1);
+            Value::String(&data[..end])
+        }
+        _ => decode_value(field, &data[..field.pack_length().min(data.len())]),
+    }
+}
+
+/// Decode a field's value from its packed representation
+#[allow(clippy::cast_possible_truncation)]
+fn decode_value<'a>(field: &Field, data: &'a [u8]) -> Value<'a> {
+    let len = data.len();
+    let unsigned = field.is_unsigned();
+    let dec = field.decimals();
+
+    match field.real_type() {
+        ft::MYSQL_TYPE_TINY if unsigned => Value::U8(data[0]),
+        ft::MYSQL_TYPE_TINY => Value::I8(data[0] as i8),
+        ft::MYSQL_TYPE_SHORT if unsigned => Value::U16(read_le(data) as u16),
+        ft::MYSQL_TYPE_SHORT => Value::I16(read_le(data) as i16),
+        ft::MYSQL_TYPE_INT24 | ft::MYSQL_TYPE_LONG | ft::MYSQL_TYPE_LONGLONG => {
+            let val = read_le(data);
+            match (field.real_type(), unsigned) {
+                (ft::MYSQL_TYPE_LONGLONG, true) => Value::ULongLong(val),
+                (ft::MYSQL_TYPE_LONGLONG, false) => Value::LongLong(val as i64),
+                (_, true) => Value::ULong(val),
+                (_, false) => Value::Long(sign_extend(val, len)),
+            }
+        }
+        ft::MYSQL_TYPE_FLOAT => Value::F32(f32::from_le_bytes(data.try_into().unwrap())),
+        ft::MYSQL_TYPE_DOUBLE => Value::F64(f64::from_le_bytes(data.try_into().unwrap())),
+        ft::MYSQL_TYPE_YEAR => Value::Year(if data[0] == 0 {
+            0
+        } else {
+            u16::from(data[0]) + 1900
+        }),
+        ft::MYSQL_TYPE_NEWDATE => {
+            let tmp = read_le(data);
+            Value::Date(Date {
+                year: (tmp >> 9) as u16,
+                month: ((tmp >> 5) & 15) as u8,
+                day: (tmp & 31) as u8,
+            })
+        }
+        ft::MYSQL_TYPE_DATE => {
+            // Pre-5.0 format, `YYYYMMDD` as an integer
+            let tmp = read_le(data);
+            Value::Date(Date {
+                year: (tmp / 10000) as u16,
+                month: (tmp / 100 % 100) as u8,
+                day: (tmp % 100) as u8,
+            })
+        }
+        ft::MYSQL_TYPE_TIME => {
+            // Old format, `HHMMSS` as a signed integer
+            let tmp = sign_extend(read_le(data), 3);
+            let abs = tmp.unsigned_abs();
+            Value::Time(Time {
+                negative: tmp < 0,
+                hour: (abs / 10000) as u32,
+                minute: (abs / 100 % 100) as u8,
+                second: (abs % 100) as u8,
+                microsecond: 0,
+            })
+        }
+        ft::MYSQL_TYPE_TIME2 => Value::Time(time_from_packed(time_packed_from_binary(data, dec))),
+        ft::MYSQL_TYPE_DATETIME => {
+            // Old format, `YYYYMMDDHHMMSS` as an integer
+            let tmp = read_le(data);
+            let (ymd, hms) = (tmp / 1_000_000, tmp % 1_000_000);
+            Value::DateTime(DateTime {
+                date: Date {
+                    year: (ymd / 10000) as u16,
+                    month: (ymd / 100 % 100) as u8,
+                    day: (ymd % 100) as u8,
+                },
+                hour: (hms / 10000) as u8,
+                minute: (hms / 100 % 100) as u8,
+                second: (hms % 100) as u8,
+                microsecond: 0,
+            })
+        }
+        ft::MYSQL_TYPE_DATETIME2 => {
+            Value::DateTime(datetime_from_packed(datetime_packed_from_binary(data, dec)))
+        }
+        ft::MYSQL_TYPE_TIMESTAMP => Value::TimeStamp(TimeStamp {
+            seconds: read_le(data) as u32,
+            microsecond: 0,
+        }),
+        ft::MYSQL_TYPE_TIMESTAMP2 => {
+            let microsecond = match dec {
+                0 => 0,
+                1 | 2 => u32::from(data[4]) * 10000,
+                3 | 4 => read_be(&data[4..6]) as u32 * 100,
+                _ => read_be(&data[4..7]) as u32,
+            };
+            Value::TimeStamp(TimeStamp {
+                seconds: read_be(&data[..4]) as u32,
+                microsecond,
+            })
+        }
+        ft::MYSQL_TYPE_VARCHAR => {
+            let length_bytes = len - field.field_length();
+            let str_len = read_le(&data[..length_bytes]) as usize;
+            Value::String(&data[length_bytes..length_bytes + str_len])
+        }
+        ft::MYSQL_TYPE_STRING if field.is_binary() => Value::String(data),
+        ft::MYSQL_TYPE_STRING => {
+            // `CHAR` is padded with spaces
+            let end = data.iter().rposition(|b| *b != b' ').map_or(0, |p| p + 1);
+            Value::String(&data[..end])
+        }
+        ft::MYSQL_TYPE_TINY_BLOB
+        | ft::MYSQL_TYPE_MEDIUM_BLOB
+        | ft::MYSQL_TYPE_LONG_BLOB
+        | ft::MYSQL_TYPE_BLOB
+        | ft::MYSQL_TYPE_GEOMETRY => Value::Blob(unsafe { read_blob(data) }),
+        ft::MYSQL_TYPE_NEWDECIMAL => Value::PackedDecimal {
+            data,
+            precision: field.precision(),
+            scale: dec,
+        },
+        ft::MYSQL_TYPE_ENUM => Value::Enum(read_le(data) as u16),
+        ft::MYSQL_TYPE_SET => Value::Set(read_le(data)),
+        // Only reached for keys, where bits are stored big endian without using the
+        // null bitmap
+        ft::MYSQL_TYPE_BIT => Value::Bit(read_be(data)),
+        ft::MYSQL_TYPE_NULL => Value::Null,
+        _ => Value::Blob(data),
+    }
+}
+
+#[allow(clippy::cast_possible_truncation)]
+#[allow(clippy::cast_sign_loss)]
+fn encode<'a>(table: &Table, field: &Field, buf: &mut [u8], value: Value<'a>) -> StorageResult {
+    let offset = field.offset(table);
+    let len = field.pack_length();
+    let dec = field.decimals();
+    let unsigned = field.is_unsigned();
+    let data = &mut buf[offset..offset + len];
+
+    match (field.real_type(), value) {
+        (
+            ft::MYSQL_TYPE_TINY
+            | ft::MYSQL_TYPE_SHORT
+            | ft::MYSQL_TYPE_INT24
+            | ft::MYSQL_TYPE_LONG
+            | ft::MYSQL_TYPE_LONGLONG,
+            _,
+        ) => {
+            let val = int_value(value).ok_or(StorageError::WrongInRecord)?;
+            let bits = len as u32 * 8;
+            let fits = if unsigned {
+                val >= 0 && val < (1i128 << bits)
+            } else {
+                val >= -(1i128 << (bits - 1)) && val < (1i128 << (bits - 1))
+            };
+            if !fits {
+                return Err(StorageError::WrongInRecord);
+            }
+            write_le(data, val as u64);
+        }
+        (ft::MYSQL_TYPE_FLOAT, Value::F32(v)) => data.copy_from_slice(&v.to_le_bytes()),
+        (ft::MYSQL_TYPE_FLOAT, Value::F64(v)) => data.copy_from_slice(&(v as f32).to_le_bytes()),
+        (ft::MYSQL_TYPE_DOUBLE, Value::F32(v)) => data.copy_from_slice(&f64::from(v).to_le_bytes()),
+        (ft::MYSQL_TYPE_DOUBLE, Value::F64(v)) => data.copy_from_slice(&v.to_le_bytes()),
+        (ft::MYSQL_TYPE_YEAR, Value::Year(v)) => {
+            data[0] = match v {
+                0 => 0,
+                1901..=2155 => (v - 1900) as u8,
+                _ => return Err(StorageError::WrongInRecord),
+            };
+        }
+        (ft::MYSQL_TYPE_NEWDATE, Value::Date(d)) => {
+            let tmp = u64::from(d.day) | (u64::from(d.month) << 5) | (u64::from(d.year) << 9);
+            write_le(data, tmp);
+        }
+        (ft::MYSQL_TYPE_DATE, Value::Date(d)) => {
+            let tmp = u64::from(d.year) * 10000 + u64::from(d.month) * 100 + u64::from(d.day);
+            write_le(data, tmp);
+        }
+        (ft::MYSQL_TYPE_TIME, Value::Time(t)) => {
+            let tmp = i64::from(t.hour) * 10000 + i64::from(t.minute) * 100 + i64::from(t.second);
+            write_le(data, (if t.negative { -tmp } else { tmp }) as u64);
+        }
+        (ft::MYSQL_TYPE_TIME2, Value::Time(t)) => {
+            time_packed_to_binary(time_to_packed(&t, dec), data, dec);
+        }
+        (ft::MYSQL_TYPE_DATETIME, Value::DateTime(dt)) => {
+            let ymd = u64::from(dt.date.year) * 10000
+                + u64::from(dt.date.month) * 100
+                + u64::from(dt.date.day);
+            let hms =
+                u64::from(dt.hour) * 10000 + u64::from(dt.minute) * 100 + u64::from(dt.second);
+            write_le(data, ymd * 1_000_000 + hms);
+        }
+        (ft::MYSQL_TYPE_DATETIME2, Value::DateTime(dt)) => {
+            datetime_packed_to_binary(datetime_to_packed(&dt, dec), data, dec);
+        }
+        (ft::MYSQL_TYPE_TIMESTAMP, Value::TimeStamp(ts)) => write_le(data, ts.seconds.into()),
+        (ft::MYSQL_TYPE_TIMESTAMP2, Value::TimeStamp(ts)) => {
+            write_be(&mut data[..4], ts.seconds.into());
+            let usec = u64::from(truncate_usec(ts.microsecond, dec));
+            match dec {
+                0 => (),
+                1 | 2 => data[4] = (usec / 10000) as u8,
+                3 | 4 => write_be(&mut data[4..6], usec / 100),
+                _ => write_be(&mut data[4..7], usec),
+            }
+        }
+        (ft::MYSQL_TYPE_VARCHAR, Value::String(s) | Value::Blob(s) | Value::Json(s)) => {
+            let length_bytes = len - field.field_length();
+            if s.len() > field.field_length() {
+                return Err(StorageError::WrongInRecord);
+            }
+            write_le(&mut data[..length_bytes], s.len() as u64);
+            data[length_bytes..length_bytes + s.len()].copy_from_slice(s);
+        }
+        (ft::MYSQL_TYPE_STRING, Value::String(s) | Value::Blob(s)) => {
+            if s.len() > len {
+                return Err(StorageError::WrongInRecord);
+            }
+            let pad = if field.is_binary() { 0 } else { b' ' };
+            data[..s.len()].copy_from_slice(s);
+            data[s.len()..].fill(pad);
+        }
+        (
+            ft::MYSQL_TYPE_TINY_BLOB
+            | ft::MYSQL_TYPE_MEDIUM_BLOB
+            | ft::MYSQL_TYPE_LONG_BLOB
+            | ft::MYSQL_TYPE_BLOB
+            | ft::MYSQL_TYPE_GEOMETRY,
+            Value::String(s) | Value::Blob(s) | Value::Json(s),
+        ) => {
+            let packlength = len - mem::size_of::<*const u8>();
+            if packlength < 8 && s.len() as u64 >= 1u64 << (packlength * 8) {
+                return Err(StorageError::WrongInRecord);
+            }
+            write_le(&mut data[..packlength], s.len() as u64);
+            data[packlength..].copy_from_slice(&(s.as_ptr() as usize).to_ne_bytes());
+        }
+        (
+            ft::MYSQL_TYPE_NEWDECIMAL,
+            Value::PackedDecimal {
+                data: src,
+                precision,
+                scale,
+            },
+        ) => {
+            if src.len() != len || precision != field.precision() || scale != dec {
+                return Err(StorageError::WrongInRecord);
+            }
+            data.copy_from_slice(src);
+        }
+        (ft::MYSQL_TYPE_ENUM, Value::Enum(v)) => {
+            if len == 1 && v > 0xff {
+                return Err(StorageError::WrongInRecord);
+            }
+            write_le(data, v.into());
+        }
+        (ft::MYSQL_TYPE_SET, Value::Set(v)) => write_le(data, v),
+        (ft::MYSQL_TYPE_BIT, Value::Bit(v)) => {
+            let res = unsafe {
+                bindings::table_bridge_field_store_int(
+                    field.as_raw(),
+                    buf.as_mut_ptr(),
+                    v as i64,
+                    true,
+                )
+            };
+            if res != 0 {
+                return Err(StorageError::WrongInRecord);
+            }
+        }
+        _ => return Err(StorageError::WrongInRecord),
+    }
+
+    Ok(())
+}
+
+/// Get any integer value as an `i128`
+fn int_value(value: Value) -> Option<i128> {
+    match value {
+        Value::I8(v) => Some(v.into()),
+        Value::I16(v) => Some(v.into()),
+        Value::Long(v) | Value::LongLong(v) => Some(v.into()),
+        Value::U8(v) => Some(v.into()),
+        Value::U16(v) => Some(v.into()),
+        Value::ULong(v) | Value::ULongLong(v) => Some(v.into()),
+        _ => None,
+    }
+}
+
+/// Read a blob's length and data pointer
+///
+/// # Safety
+///
+/// `data` must be a blob field from a valid record
+unsafe fn read_blob<'a>(data: &[u8]) -> &'a [u8] {
+    let packlength = data.len() - mem::size_of::<*const u8>();
+    let len = read_le(&data[..packlength]).try_into().unwrap();
+    if len == 0 {
+        return &[];
+    }
+    let ptr = usize::from_ne_bytes(data[packlength..].try_into().unwrap()) as *const u8;
+    unsafe { slice::from_raw_parts(ptr, len) }
+}
+
+/// Drop fractional digits that are not stored with `dec` precision
+fn truncate_usec(usec: u32, dec: u8) -> u32 {
+    let div = 10u32.pow(6 - u32::from(dec.min(6)));
+    usec / div * div
+}
+
+/// Port of `my_time_packed_from_binary`
+fn time_packed_from_binary(data: &[u8], dec: u8) -> i64 {
+    let intpart = read_be(&data[..3]) as i64 - TIMEF_INT_OFS;
+    match dec {
+        0 => intpart << 24,
+        1 | 2 => {
+            let mut intpart = intpart;
+            let mut frac = i64::from(data[3]);
+            if intpart < 0 && frac != 0 {
+                // Negative values store the fraction in reverse order
+                intpart += 1;
+                frac -= 0x100;
+            }
+            (intpart << 24) + frac * 10000
+        }
+        3 | 4 => {
+            let mut intpart = intpart;
+            let mut frac = read_be(&data[3..5]) as i64;
+            if intpart < 0 && frac != 0 {
+                intpart += 1;
+                frac -= 0x10000;
+            }
+            (intpart << 24) + frac * 100
+        }
+        _ => read_be(&data[..6]) as i64 - TIMEF_OFS,
+    }
+}
+
+/// Port of `my_time_packed_to_binary`
+#[allow(clippy::cast_possible_truncation)]
+fn time_packed_to_binary(nr: i64, data: &mut [u8], dec: u8) {
+    let intpart = nr >> 24;
+    let frac = nr % (1 << 24);
+    match dec {
+        0 => write_be(&mut data[..3], (TIMEF_INT_OFS + intpart) as u64),
+        1 | 2 => {
+            write_be(&mut data[..3], (TIMEF_INT_OFS + intpart) as u64);
+            data[3] = (frac / 10000) as i8 as u8;
+        }
+        3 | 4 => {
+            write_be(&mut data[..3], (TIMEF_INT_OFS + intpart) as u64);
+            write_be(&mut data[3..5], (frac / 100) as u64);
+        }
+        _ => write_be(&mut data[..6], (nr + TIMEF_OFS) as u64),
+    }
+}
+
+/// Port of `TIME_from_longlong_time_packed`
+#[allow(clippy::cast_possible_truncation)]
+fn time_from_packed(packed: i64) -> Time {
+    let negative = packed < 0;
+    let tmp = packed.abs();
+    let hms = tmp >> 24;
+    Time {
+        negative,
+        hour: ((hms >> 12) % (1 << 10)) as u32,
+        minute: ((hms >> 6) % (1 << 6)) as u8,
+        second: (hms % (1 << 6)) as u8,
+        microsecond: (tmp % (1 << 24)) as u32,
+    }
+}
+
+/// Port of `TIME_to_longlong_time_packed`
+fn time_to_packed(t: &Time, dec: u8) -> i64 {
+    let hms = (i64::from(t.hour) << 12) | (i64::from(t.minute) << 6) | i64::from(t.second);
+    let tmp = (hms << 24) + i64::from(truncate_usec(t.microsecond, dec));
+    if t.negative {
+        -tmp
+    } else {
+        tmp
+    }
+}
+
+/// Port of `my_datetime_packed_from_binary`
+fn datetime_packed_from_binary(data: &[u8], dec: u8) -> i64 {
+    let intpart = read_be(&data[..5]) as i64 - DATETIMEF_INT_OFS;
+    let frac = match dec {
+        0 => 0,
+        1 | 2 => i64::from(data[5] as i8) * 10000,
+        3 | 4 => sign_extend(read_be(&data[5..7]), 2) * 100,
+        _ => sign_extend(read_be(&data[5..8]), 3),
+    };
+    (intpart << 24) + frac
+}
+
+/// Port of `my_datetime_packed_to_binary`
+#[allow(clippy::cast_possible_truncation)]
+fn datetime_packed_to_binary(nr: i64, data: &mut [u8], dec: u8) {
+    let intpart = nr >> 24;
+    let frac = nr % (1 << 24);
+    write_be(&mut data[..5], (intpart + DATETIMEF_INT_OFS) as u64);
+    match dec {
+        0 => (),
+        1 | 2 => data[5] = (frac / 10000) as i8 as u8,
+        3 | 4 => write_be(&mut data[5..7], (frac / 100) as u64),
+        _ => write_be(&mut data[5..8], frac as u64),
+    }
+}
+
+/// Port of `TIME_from_longlong_datetime_packed`
+#[allow(clippy::cast_possible_truncation)]
+fn datetime_from_packed(packed: i64) -> DateTime {
+    let tmp = packed.abs();
+    let ymdhms = tmp >> 24;
+    let ymd = ymdhms >> 17;
+    let ym = ymd >> 5;
+    let hms = ymdhms % (1 << 17);
+
+    DateTime {
+        date: Date {
+            year: (ym / 13) as u16,
+            month: (ym % 13) as u8,
+            day: (ymd % (1 << 5)) as u8,
+        },
+        hour: (hms >> 12) as u8,
+        minute: ((hms >> 6) % (1 << 6)) as u8,
+        second: (hms % (1 << 6)) as u8,
+        microsecond: (tmp % (1 << 24)) as u32,
+    }
+}
+
+/// Port of `TIME_to_longlong_datetime_packed`
+fn datetime_to_packed(dt: &DateTime, dec: u8) -> i64 {
+    let ymd =
+        ((i64::from(dt.date.year) * 13 + i64::from(dt.date.month)) << 5) | i64::from(dt.date.day);
+    let hms = (i64::from(dt.hour) << 12) | (i64::from(dt.minute) << 6) | i64::from(dt.second);
+    (((ymd << 17) | hms) << 24) + i64::from(truncate_usec(dt.microsecond, dec))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_time2_roundtrip() {
+        let times = [
+            Time::default(),
+            Time {
+                negative: false,
+                hour: 838,
+                minute: 59,
+                second: 59,
+                microsecond: 0,
+            },
+            Time {
+                negative: true,
+                hour: 0,
+                minute: 0,
+                second: 0,
+                microsecond: 10000,
+            },
+            Time {
+                negative: true,
+                hour: 12,
+                minute: 34,
+                second: 56,
+                microsecond: 123_456,
+            },
+        ];
+
+        for dec in 0..=6 {
+            for t in times {
+                let mut buf = [0u8; 6];
+                let expected = Time {
+                    microsecond: truncate_usec(t.microsecond, dec),
+                    ..t
+                };
+                time_packed_to_binary(time_to_packed(&t, dec), &mut buf, dec);
+                let len = 3 + (usize::from(dec) + 1) / 2;
+                let out = time_from_packed(time_packed_from_binary(&buf[..len], dec));
+                assert_eq!(out, expected, "dec {dec}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_time2_negative_fraction() {
+        // -00:00:00.01 with two digits is stored as `7FFFFF.FF`, see `compat56.cc`
+        let t = Time {
+            negative: true,
+            hour: 0,
+            minute: 0,
+            second: 0,
+            microsecond: 10000,
+        };
+        let mut buf = [0u8; 4];
+        time_packed_to_binary(time_to_packed(&t, 2), &mut buf, 2);
+        assert_eq!(buf, [0x7f, 0xff, 0xff, 0xff]);
+    }
+
+    #[test]
+    fn test_datetime2_roundtrip() {
+        let dt = DateTime {
+            date: Date {
+                year: 2024,
+                month: 2,
+                day: 29,
+            },
+            hour: 23,
+            minute: 59,
+            second: 58,
+            microsecond: 999_999,
+        };
+
+        for dec in 0..=6 {
+            let mut buf = [0u8; 8];
+            let expected = DateTime {
+                microsecond: truncate_usec(dt.microsecond, dec),
+                ..dt
+            };
+            datetime_packed_to_binary(datetime_to_packed(&dt, dec), &mut buf, dec);
+            let len = 5 + (usize::from(dec) + 1) / 2;
+            let out = datetime_from_packed(datetime_packed_from_binary(&buf[..len], dec));
+            assert_eq!(out, expected, "dec {dec}");
+        }
+    }
+
+    #[test]
+    fn test_int_helpers() {
+        let mut buf = [0u8; 3];
+        write_le(&mut buf, (-2i64) as u64);
+        assert_eq!(buf, [0xfe, 0xff, 0xff]);
+        assert_eq!(sign_extend(read_le(&buf), 3), -2);
+        write_be(&mut buf, 0x01_0203);
+        assert_eq!(buf, [1, 2, 3]);
+        assert_eq!(read_be(&buf), 0x01_0203);
+    }
+}
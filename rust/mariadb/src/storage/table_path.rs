@@ -0,0 +1,217 @@
+//! Paths of table files, as passed to
+//! [`Handler::delete_table`](super::Handler::delete_table) and
+//! [`Handler::rename_table`](super::Handler::rename_table)
+//!
+//! The server names tables by a path without extension, like `./db/t1`. Partitions
+//! of a partitioned table get their own path, like `./db/t1#P#p0`. Database and
+//! table names are encoded for the file system: letters, digits and `_` are
+//! unchanged, other characters are written as `@` followed by a code. Use
+//! [`filename_to_tablename`] and [`tablename_to_filename`] to convert.
+
+use std::ffi::{c_char, OsString};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+use super::{StorageError, StorageResult};
+use crate::bindings;
+
+/// Separator between the table name and the partition name in file names
+const PARTITION_SEP: &str = "#P#";
+/// Same as `PARTITION_SEP`, with `lower_case_table_names`
+const PARTITION_SEP_LOWER: &str = "#p#";
+
+/// The path of a table or partition, split into its parts
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TablePath<'a> {
+    path: &'a Path,
+    db: &'a str,
+    table: &'a str,
+    partition: Option<&'a str>,
+}
+
+impl<'a> TablePath<'a> {
+    /// Split a table path, such as the name given to
+    /// [`Handler::open`](super::Handler::open) or
+    /// [`Handler::create`](super::Handler::create)
+    pub fn new(path: &'a Path) -> Self {
+        let db = path
+            .parent()
+            .and_then(Path::file_name)
+            .and_then(|s| s.to_str())
+            .unwrap_or_default();
+        let name = path
+            .file_name()
+            .and_then(|s| s.to_str())
+            .unwrap_or_default();
+        let (table, partition) = match name
+            .find(PARTITION_SEP)
+            .or_else(|| name.find(PARTITION_SEP_LOWER))
+        {
+            Some(pos) => (&name[..pos], Some(&name[pos + PARTITION_SEP.len()..])),
+            None => (name, None),
+        };
+
+        Self {
+            path,
+            db,
+            table,
+            partition,
+        }
+    }
+
+    /// The full path, without extension
+    pub fn path(&self) -> &'a Path {
+        self.path
+    }
+
+    /// Name of the database, as encoded in the file system
+    pub fn db(&self) -> &'a str {
+        self.db
+    }
+
+    /// Name of the table, as encoded in the file system
+    pub fn table(&self) -> &'a str {
+        self.table
+    }
+
+    /// Name of the partition if this is a partition of a table. Subpartitions are
+    /// included as `p0#SP#sp0`.
+    pub fn partition(&self) -> Option<&'a str> {
+        self.partition
+    }
+
+    /// The path of the table's file with extension `ext`, which includes the dot
+    /// (as in [`Handlerton::TABLEFILE_EXTENSIONS`](super::Handlerton::TABLEFILE_EXTENSIONS))
+    pub fn with_extension(&self, ext: &str) -> PathBuf {
+        let mut path = OsString::from(self.path);
+        path.push(ext);
+        path.into()
+    }
+}
+
+/// The real name of a database or table from its name in the file system, like
+/// `t@002e1` to `t.1`
+pub fn filename_to_tablename(file_name: &str) -> String {
+    convert_name(file_name, bindings::ha_bridge_filename_to_tablename)
+}
+
+/// The name in the file system of a database or table, like `t.1` to `t@002e1`
+pub fn tablename_to_filename(table_name: &str) -> String {
+    convert_name(table_name, bindings::ha_bridge_tablename_to_filename)
+}
+
+fn convert_name(
+    name: &str,
+    convert: unsafe extern "C" fn(*const c_char, usize, *mut c_char, usize) -> usize,
+) -> String {
+    let mut buf = [0u8; bindings::FN_REFLEN as usize];
+    let len = unsafe {
+        convert(
+            name.as_ptr().cast(),
+            name.len(),
+            buf.as_mut_ptr().cast(),
+            buf.len(),
+        )
+    };
+    String::from_utf8_lossy(&buf[..len.min(buf.len())]).into_owned()
+}
+
+/// Delete the table's file for each extension in `exts`, like
+/// `handler::delete_table`
+///
+/// Missing files are skipped, but if none of them exist this returns
+/// [`StorageError::NoSuchTable`].
+pub fn delete_table_files(path: &TablePath, exts: &[&str]) -> StorageResult {
+    let mut deleted = false;
+    for ext in exts {
+        match fs::remove_file(path.with_extension(ext)) {
+            Ok(()) => deleted = true,
+            Err(e) if e.kind() == ErrorKind::NotFound => (),
+            Err(e) => return Err(io_error(&e)),
+        }
+    }
+    if deleted || exts.is_empty() {
+        Ok(())
+    } else {
+        Err(StorageError::NoSuchTable)
+    }
+}
+
+/// Rename the table's file for each extension in `exts`, like
+/// `handler::rename_table`
+///
+/// Missing files are skipped. If a rename fails, files that were already renamed
+/// are moved back.
+pub fn rename_table_files(from: &TablePath, to: &TablePath, exts: &[&str]) -> StorageResult {
+    for (idx, ext) in exts.iter().enumerate() {
+        match fs::rename(from.with_extension(ext), to.with_extension(ext)) {
+            Ok(()) => (),
+            Err(e) if e.kind() == ErrorKind::NotFound => (),
+            Err(e) => {
+                for ext in &exts[..idx] {
+                    // Best effort
+                    let _ = fs::rename(to.with_extension(ext), from.with_extension(ext));
+                }
+                return Err(io_error(&e));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn io_error(e: &io::Error) -> StorageError {
+    match e.kind() {
+        ErrorKind::NotFound => StorageError::NoSuchTable,
+        ErrorKind::AlreadyExists => StorageError::TableExist,
+        _ => StorageError::InternalError,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let path = TablePath::new(Path::new("./db1/t1"));
+        assert_eq!(path.db(), "db1");
+        assert_eq!(path.table(), "t1");
+        assert_eq!(path.partition(), None);
+        assert_eq!(path.with_extension(".CSV"), Path::new("./db1/t1.CSV"));
+
+        let path = TablePath::new(Path::new("/data/db1/t@002e1#P#p0#SP#sp1"));
+        assert_eq!(path.db(), "db1");
+        assert_eq!(path.table(), "t@002e1");
+        assert_eq!(path.partition(), Some("p0#SP#sp1"));
+
+        let path = TablePath::new(Path::new("./db1/t1#p#p1"));
+        assert_eq!(path.table(), "t1");
+        assert_eq!(path.partition(), Some("p1"));
+    }
+
+    #[test]
+    fn test_table_files() {
+        let dir = std::env::temp_dir().join(format!("table_path_test_{}", std::process::id()));
+        fs::create_dir_all(dir.join("db")).unwrap();
+        let from_buf = dir.join("db/t1");
+        let to_buf = dir.join("db/t2");
+        let from = TablePath::new(&from_buf);
+        let to = TablePath::new(&to_buf);
+        let exts = [".a", ".b"];
+
+        fs::write(from.with_extension(".a"), b"a").unwrap();
+        rename_table_files(&from, &to, &exts).unwrap();
+        assert!(!from.with_extension(".a").exists());
+        assert!(to.with_extension(".a").exists());
+
+        delete_table_files(&to, &exts).unwrap();
+        assert!(!to.with_extension(".a").exists());
+        assert!(matches!(
+            delete_table_files(&to, &exts),
+            Err(StorageError::NoSuchTable)
+        ));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
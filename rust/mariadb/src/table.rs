@@ -1,13 +1,17 @@
 #![allow(dead_code)]
 
 use std::cell::UnsafeCell;
+use std::ffi::{c_char, c_ulong, CStr};
 use std::marker::PhantomData;
+use std::path::Path;
+use std::{fmt, slice};
 
-use crate::bindings;
+use crate::{bindings, Thd};
 
+/// An open instance of a table
 #[repr(transparent)]
 pub struct Table<'a> {
-    share: UnsafeCell<bindings::TABLE>,
+    inner: UnsafeCell<bindings::TABLE>,
     phantom: PhantomData<&'a ()>,
 }
 
@@ -15,12 +19,61 @@ impl<'a> Table<'a> {
     pub(crate) unsafe fn from_raw(tab: *const bindings::TABLE) -> &'a Self {
         unsafe { &*tab.cast() }
     }
+
+    pub(crate) fn as_raw(&self) -> *mut bindings::TABLE {
+        self.inner.get()
+    }
+
+    fn inner(&self) -> &bindings::TABLE {
+        unsafe { &*self.inner.get() }
+    }
+
+    /// The shared definition of this table
+    pub fn share(&self) -> &TableShare<'a> {
+        unsafe { TableShare::from_raw(self.inner().s) }
+    }
+
+    /// The connection currently using this table, if any. Tables that are cached
+    /// but not in use by a statement have no connection.
+    pub fn thd(&self) -> Option<&Thd<'a>> {
+        let thd = self.inner().in_use;
+        (!thd.is_null()).then(|| unsafe { Thd::from_raw(thd) })
+    }
+
+    /// Columns of this table, in definition order
+    pub fn fields(&self) -> &[&Field<'a>] {
+        let count = self.share().inner().fields.try_into().unwrap();
+        unsafe { slice::from_raw_parts(self.inner().field.cast(), count) }
+    }
+
+    /// Indexes defined on this table
+    pub fn keys(&self) -> &[Key<'a>] {
+        key_slice(self.inner().key_info, self.share().inner().keys)
+    }
+
+    /// The `AUTO_INCREMENT` column, while inserting a row that needs a value for it
+    pub fn next_number_field(&self) -> Option<&Field<'a>> {
+        unsafe { self.inner().next_number_field.cast::<Field>().as_ref() }
+    }
+
+    /// Whether the current statement reads the column at `idx` (it is in the
+    /// table's `read_set`). Columns that are not read do not need to be filled in
+    /// by scans.
+    pub fn is_field_read(&self, idx: usize) -> bool {
+        assert!(idx < self.fields().len());
+        unsafe { bindings::table_bridge_field_is_read(self.as_raw(), idx.try_into().unwrap()) }
+    }
+
+    /// Pointer to the start of `record[0]`, which all field offsets are relative to
+    pub(crate) fn record0(&self) -> *const u8 {
+        self.inner().record[0]
+    }
 }
 
 /// A structure shared among all table objects. Has lots of internal locking.
 #[repr(transparent)]
 pub struct TableShare<'a> {
-    share: UnsafeCell<bindings::TABLE_SHARE>,
+    inner: UnsafeCell<bindings::TABLE_SHARE>,
     phantom: PhantomData<&'a ()>,
 }
 
@@ -28,6 +81,424 @@ impl<'a> TableShare<'a> {
     pub(crate) unsafe fn from_raw(tab: *const bindings::TABLE_SHARE) -> &'a Self {
         unsafe { &*tab.cast() }
     }
+
+    pub(crate) fn inner(&self) -> &bindings::TABLE_SHARE {
+        unsafe { &*self.inner.get() }
+    }
+
+    /// Name of the database (schema) this table belongs to
+    pub fn db(&self) -> &str {
+        let db = &self.inner().db;
+        unsafe { lex_str(db.str_, db.length) }
+    }
+
+    /// Name of the table
+    pub fn table_name(&self) -> &str {
+        let name = &self.inner().table_name;
+        unsafe { lex_str(name.str_, name.length) }
+    }
+
+    /// Path to the table definition without an extension, e.g. `./db/t1`. This is
+    /// the same name that is passed to `open` and `create`.
+    pub fn path(&self) -> &Path {
+        let path = &self.inner().normalized_path;
+        Path::new(unsafe { lex_str(path.str_, path.length) })
+    }
+
+    /// The table's `COMMENT`, empty if none was given
+    pub fn comment(&self) -> &str {
+        let comment = &self.inner().comment;
+        unsafe { lex_str(comment.str_, comment.length) }
+    }
+
+    /// Columns of this table, in definition order
+    ///
+    /// These describe the table definition; to access values in a row use the fields
+    /// from [`Table::fields`].
+    pub fn fields(&self) -> &[&Field<'a>] {
+        let count = self.inner().fields.try_into().unwrap();
+        unsafe { slice::from_raw_parts(self.inner().field.cast(), count) }
+    }
+
+    /// Indexes defined on this table
+    pub fn keys(&self) -> &[Key<'a>] {
+        key_slice(self.inner().key_info, self.inner().keys)
+    }
+
+    /// Index of the primary key in [`TableShare::keys`], if the table has one
+    pub fn primary_key(&self) -> Option<usize> {
+        let pk = self.inner().primary_key;
+        (pk != bindings::MAX_KEY).then(|| pk.try_into().unwrap())
+    }
+
+    /// Length of a row in the server's record format
+    pub fn reclength(&self) -> usize {
+        self.inner().reclength.try_into().unwrap()
+    }
+
+    /// Number of bytes at the start of a record that hold null flags
+    pub fn null_bytes(&self) -> usize {
+        self.inner().null_bytes.try_into().unwrap()
+    }
+
+    /// A record holding the default value of each column
+    pub(crate) fn default_values(&self) -> *const u8 {
+        self.inner().default_values
+    }
+}
+
+/// Type of a column, as stored in a record
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FieldType {
+    Decimal,
+    Tiny,
+    Short,
+    Long,
+    Float,
+    Double,
+    Null,
+    Timestamp,
+    LongLong,
+    Int24,
+    Date,
+    Time,
+    DateTime,
+    Year,
+    NewDate,
+    VarChar,
+    Bit,
+    Timestamp2,
+    DateTime2,
+    Time2,
+    BlobCompressed,
+    VarCharCompressed,
+    NewDecimal,
+    Enum,
+    Set,
+    TinyBlob,
+    MediumBlob,
+    LongBlob,
+    Blob,
+    VarString,
+    String,
+    Geometry,
+    /// A type this crate does not know about
+    Unknown,
+}
+
+impl FieldType {
+    pub(crate) fn from_raw(ty: bindings::enum_field_types::Type) -> Self {
+        use bindings::enum_field_types as ft;
+        match ty {
+            ft::MYSQL_TYPE_DECIMAL => Self::Decimal,
+            ft::MYSQL_TYPE_TINY => Self::Tiny,
+            ft::MYSQL_TYPE_SHORT => Self::Short,
+            ft::MYSQL_TYPE_LONG => Self::Long,
+            ft::MYSQL_TYPE_FLOAT => Self::Float,
+            ft::MYSQL_TYPE_DOUBLE => Self::Double,
+            ft::MYSQL_TYPE_NULL => Self::Null,
+            ft::MYSQL_TYPE_TIMESTAMP => Self::Timestamp,
+            ft::MYSQL_TYPE_LONGLONG => Self::LongLong,
+            ft::MYSQL_TYPE_INT24 => Self::Int24,
+            ft::MYSQL_TYPE_DATE => Self::Date,
+            ft::MYSQL_TYPE_TIME => Self::Time,
+            ft::MYSQL_TYPE_DATETIME => Self::DateTime,
+            ft::MYSQL_TYPE_YEAR => Self::Year,
+            ft::MYSQL_TYPE_NEWDATE => Self::NewDate,
+            ft::MYSQL_TYPE_VARCHAR => Self::VarChar,
+            ft::MYSQL_TYPE_BIT => Self::Bit,
+            ft::MYSQL_TYPE_TIMESTAMP2 => Self::Timestamp2,
+            ft::MYSQL_TYPE_DATETIME2 => Self::DateTime2,
+            ft::MYSQL_TYPE_TIME2 => Self::Time2,
+            ft::MYSQL_TYPE_BLOB_COMPRESSED => Self::BlobCompressed,
+            ft::MYSQL_TYPE_VARCHAR_COMPRESSED => Self::VarCharCompressed,
+            ft::MYSQL_TYPE_NEWDECIMAL => Self::NewDecimal,
+            ft::MYSQL_TYPE_ENUM => Self::Enum,
+            ft::MYSQL_TYPE_SET => Self::Set,
+            ft::MYSQL_TYPE_TINY_BLOB => Self::TinyBlob,
+            ft::MYSQL_TYPE_MEDIUM_BLOB => Self::MediumBlob,
+            ft::MYSQL_TYPE_LONG_BLOB => Self::LongBlob,
+            ft::MYSQL_TYPE_BLOB => Self::Blob,
+            ft::MYSQL_TYPE_VAR_STRING => Self::VarString,
+            ft::MYSQL_TYPE_STRING => Self::String,
+            ft::MYSQL_TYPE_GEOMETRY => Self::Geometry,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+/// A single column of a table
+#[repr(transparent)]
+pub struct Field<'a> {
+    inner: UnsafeCell<bindings::Field>,
+    phantom: PhantomData<&'a ()>,
+}
+
+impl<'a> Field<'a> {
+    pub(crate) fn as_raw(&self) -> *mut bindings::Field {
+        self.inner.get()
+    }
+
+    pub(crate) fn inner(&self) -> &bindings::Field {
+        unsafe { &*self.inner.get() }
+    }
+
+    /// The column name
+    pub fn name(&self) -> &str {
+        let name = &self.inner().field_name;
+        let bytes = unsafe { slice::from_raw_parts(name.str_.cast(), name.length) };
+        std::str::from_utf8(bytes).expect("field names are always UTF-8")
+    }
+
+    /// Position of this field in [`Table::fields`]
+    pub fn index(&self) -> usize {
+        self.inner().field_index.into()
+    }
+
+    /// Type of the field as stored by the engine
+    pub fn field_type(&self) -> FieldType {
+        FieldType::from_raw(self.real_type())
+    }
+
+    /// Type of the field as seen by the SQL layer. This differs from
+    /// [`Field::field_type`] for e.g. `ENUM` (reported as `String`).
+    pub fn sql_type(&self) -> FieldType {
+        FieldType::from_raw(unsafe { bindings::table_bridge_field_type(self.as_raw()) })
+    }
+
+    /// Type of the field as stored by the engine
+    pub(crate) fn real_type(&self) -> bindings::enum_field_types::Type {
+        unsafe { bindings::table_bridge_field_real_type(self.as_raw()) }
+    }
+
+    /// Number of bytes this field uses in a record
+    pub fn pack_length(&self) -> usize {
+        unsafe { bindings::table_bridge_field_pack_length(self.as_raw()) }
+            .try_into()
+            .unwrap()
+    }
+
+    /// Declared length, e.g. number of bytes in a `VARCHAR`
+    pub fn field_length(&self) -> usize {
+        self.inner().field_length.try_into().unwrap()
+    }
+
+    /// Scale of a decimal or fractional second digits of a temporal type
+    pub fn decimals(&self) -> u8 {
+        unsafe { bindings::table_bridge_field_decimals(self.as_raw()) }
+            .try_into()
+            .unwrap()
+    }
+
+    /// Precision of a `DECIMAL` field
+    pub fn precision(&self) -> u8 {
+        unsafe { bindings::table_bridge_field_precision(self.as_raw()) }
+            .try_into()
+            .unwrap()
+    }
+
+    pub(crate) fn flags(&self) -> u32 {
+        self.inner().flags
+    }
+
+    /// Whether this is an `UNSIGNED` numeric column
+    pub fn is_unsigned(&self) -> bool {
+        self.flags() & bindings::UNSIGNED_FLAG != 0
+    }
+
+    pub(crate) fn is_binary(&self) -> bool {
+        self.flags() & bindings::BINARY_FLAG != 0
+    }
+
+    /// Whether this column may contain `NULL`
+    pub fn is_nullable(&self) -> bool {
+        !self.inner().null_ptr.is_null()
+    }
+
+    /// Whether this column is `AUTO_INCREMENT`
+    pub fn is_auto_increment(&self) -> bool {
+        self.flags() & bindings::AUTO_INCREMENT_FLAG != 0
+    }
+
+    /// Whether this column has a default value. The value itself is available via
+    /// [`Table::default_record`](crate::Table::default_record).
+    pub fn has_default(&self) -> bool {
+        self.flags() & bindings::NO_DEFAULT_VALUE_FLAG == 0
+    }
+
+    /// Character set of a text column (e.g. `utf8mb4`), `None` for other types
+    pub fn charset(&self) -> Option<&str> {
+        let ptr = unsafe { bindings::table_bridge_field_charset_name(self.as_raw()) };
+        unsafe { cstr_opt(ptr) }
+    }
+
+    /// Collation of a text column (e.g. `utf8mb4_general_ci`), `None` for other types
+    pub fn collation(&self) -> Option<&str> {
+        let ptr = unsafe { bindings::table_bridge_field_collation_name(self.as_raw()) };
+        unsafe { cstr_opt(ptr) }
+    }
+
+    /// Offset of this field's data from the start of a record
+    pub(crate) fn offset(&self, table: &Table) -> usize {
+        let ptr = self.inner().ptr.cast_const();
+        unsafe { ptr.offset_from(table.record0()) }
+            .try_into()
+            .unwrap()
+    }
+
+    /// The byte offset and bitmask of this field's null flag within a record, if the
+    /// field is nullable
+    pub(crate) fn null_offset(&self, table: &Table) -> Option<(usize, u8)> {
+        let inner = self.inner();
+        if inner.null_ptr.is_null() {
+            return None;
+        }
+        let offset = unsafe { inner.null_ptr.cast_const().offset_from(table.record0()) };
+        Some((offset.try_into().unwrap(), inner.null_bit))
+    }
+}
+
+/// An index definition
+#[repr(transparent)]
+pub struct Key<'a> {
+    inner: UnsafeCell<bindings::KEY>,
+    phantom: PhantomData<&'a ()>,
+}
+
+impl<'a> Key<'a> {
+    pub(crate) unsafe fn from_raw(key: *const bindings::KEY) -> &'a Self {
+        unsafe { &*key.cast() }
+    }
+
+    pub(crate) fn inner(&self) -> &bindings::KEY {
+        unsafe { &*self.inner.get() }
+    }
+
+    pub(crate) fn as_raw(&self) -> *mut bindings::KEY {
+        self.inner.get()
+    }
+
+    /// Name of the index, `PRIMARY` for the primary key
+    pub fn name(&self) -> &str {
+        let name = &self.inner().name;
+        unsafe { lex_str(name.str_, name.length) }
+    }
+
+    /// Columns that make up this index, in index order
+    pub fn parts(&self) -> &[KeyPart<'a>] {
+        let count = self.inner().user_defined_key_parts.try_into().unwrap();
+        let ptr = self.inner().key_part;
+        if count == 0 || ptr.is_null() {
+            return &[];
+        }
+        unsafe { slice::from_raw_parts(ptr.cast(), count) }
+    }
+
+    /// Total length of a search key for this index, including null flags and
+    /// length prefixes
+    pub fn key_length(&self) -> usize {
+        self.inner().key_length.try_into().unwrap()
+    }
+
+    /// Whether this index disallows duplicate values (`UNIQUE` or `PRIMARY KEY`)
+    pub fn is_unique(&self) -> bool {
+        self.flags() & c_ulong::from(bindings::HA_NOSAME) != 0
+    }
+
+    /// Whether this is a `FULLTEXT` index
+    pub fn is_fulltext(&self) -> bool {
+        self.flags() & c_ulong::from(bindings::HA_FULLTEXT) != 0
+    }
+
+    /// Whether this is a `SPATIAL` index
+    pub fn is_spatial(&self) -> bool {
+        self.flags() & c_ulong::from(bindings::HA_SPATIAL) != 0
+    }
+
+    fn flags(&self) -> c_ulong {
+        self.inner().flags
+    }
+}
+
+impl fmt::Debug for Key<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Key")
+            .field("name", &self.name())
+            .field("parts", &self.parts().len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// A single column reference within a [`Key`]
+#[repr(transparent)]
+pub struct KeyPart<'a> {
+    inner: UnsafeCell<bindings::KEY_PART_INFO>,
+    phantom: PhantomData<&'a ()>,
+}
+
+impl<'a> KeyPart<'a> {
+    fn inner(&self) -> &bindings::KEY_PART_INFO {
+        unsafe { &*self.inner.get() }
+    }
+
+    /// The indexed column
+    pub fn field(&self) -> &Field<'a> {
+        unsafe { &*self.inner().field.cast() }
+    }
+
+    /// Position of the indexed column in [`Table::fields`]. This is `None` for key
+    /// parts that are not yet bound to a column, such as the new keys of an
+    /// [`AlterInplaceInfo`](crate::storage::AlterInplaceInfo).
+    pub fn field_index(&self) -> Option<usize> {
+        usize::from(self.inner().fieldnr).checked_sub(1)
+    }
+
+    /// Number of bytes of the column that are indexed. This is smaller than the
+    /// column length for prefix indexes.
+    pub fn length(&self) -> usize {
+        self.inner().length.into()
+    }
+
+    /// Number of bytes this part takes in a search key, including the null flag byte
+    /// and any length prefix
+    pub fn store_length(&self) -> usize {
+        self.inner().store_length.into()
+    }
+
+    /// Whether this part is sorted in descending order
+    pub fn is_reverse(&self) -> bool {
+        u32::from(self.inner().key_part_flag) & bindings::HA_REVERSE_SORT != 0
+    }
+
+    /// Whether the indexed column may be `NULL`. Such parts are prefixed with a null
+    /// flag byte in search keys.
+    pub fn is_nullable(&self) -> bool {
+        self.inner().null_bit != 0
+    }
+}
+
+fn key_slice<'a, 'b>(ptr: *const bindings::KEY, count: u32) -> &'b [Key<'a>] {
+    if count == 0 || ptr.is_null() {
+        return &[];
+    }
+    unsafe { slice::from_raw_parts(ptr.cast(), count.try_into().unwrap()) }
+}
+
+/// Interpret a server `LEX_CSTRING`. Identifiers are always stored as UTF-8.
+unsafe fn lex_str<'b>(ptr: *const c_char, len: usize) -> &'b str {
+    if ptr.is_null() {
+        return "";
+    }
+    let bytes = unsafe { slice::from_raw_parts(ptr.cast(), len) };
+    std::str::from_utf8(bytes).expect("identifiers are always UTF-8")
+}
+
+unsafe fn cstr_opt<'b>(ptr: *const c_char) -> Option<&'b str> {
+    if ptr.is_null() {
+        return None;
+    }
+    Some(unsafe { CStr::from_ptr(ptr) }.to_str().unwrap())
 }
 
 #[cfg(test)]
@@ -38,5 +509,8 @@ mod tests {
     fn test_layout() {
         crate::assert_layouts_eq!(bindings::TABLE, Table);
         crate::assert_layouts_eq!(bindings::TABLE_SHARE, TableShare);
+        crate::assert_layouts_eq!(bindings::Field, Field);
+        crate::assert_layouts_eq!(bindings::KEY, Key);
+        crate::assert_layouts_eq!(bindings::KEY_PART_INFO, KeyPart);
     }
 }
@@ -1,6 +1,7 @@
 #![allow(dead_code)]
 
 use std::marker::PhantomData;
+use std::ptr;
 
 use crate::bindings;
 
@@ -11,6 +12,25 @@ impl<'a> Thd<'a> {
     pub(crate) unsafe fn new_mut(ptr: *mut bindings::THD) -> &'a mut Self {
         unsafe { &mut *ptr.cast::<Self>() }
     }
+
+    pub(crate) unsafe fn from_raw(ptr: *const bindings::THD) -> &'a Self {
+        unsafe { &*ptr.cast::<Self>() }
+    }
+
+    /// Whether the current statement or connection has been killed (e.g. with `KILL
+    /// QUERY`). Long running operations should poll this and return
+    /// [`StorageError::AbortedByUser`](crate::storage::StorageError::AbortedByUser)
+    /// if set.
+    pub fn killed(&self) -> bool {
+        !matches!(self.kill_level(), ThdKillLevel::NotKilled)
+    }
+
+    /// How urgently the current statement should stop
+    pub fn kill_level(&self) -> ThdKillLevel {
+        let level = unsafe { bindings::ha_bridge_thd_kill_level(ptr::addr_of!(self.0)) };
+        // Treat levels added by newer servers as the most urgent one
+        ThdKillLevel::from_raw(level).unwrap_or(ThdKillLevel::AbortAsap)
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -22,3 +42,14 @@ pub enum ThdKillLevel {
     /// Abort as soon as possible
     AbortAsap = bindings::thd_kill_levels::THD_ABORT_ASAP as isize,
 }
+
+impl ThdKillLevel {
+    pub(crate) fn from_raw(level: bindings::thd_kill_levels::Type) -> Option<Self> {
+        match level {
+            bindings::thd_kill_levels::THD_IS_NOT_KILLED => Some(Self::NotKilled),
+            bindings::thd_kill_levels::THD_ABORT_SOFTLY => Some(Self::AbortSoftly),
+            bindings::thd_kill_levels::THD_ABORT_ASAP => Some(Self::AbortAsap),
+            _ => None,
+        }
+    }
+}
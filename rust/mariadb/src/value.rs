@@ -1,6 +1,9 @@
+#[cfg(feature = "service-sql")]
 use std::ffi::c_char;
+#[cfg(feature = "service-sql")]
 use std::slice;
 
+#[cfg(feature = "service-sql")]
 use crate::bindings;
 
 /// A SQL type and value
@@ -12,22 +15,74 @@ pub enum Value<'a> {
     I16(i16),
     Long(i64),
     LongLong(i64),
+    U8(u8),
+    U16(u16),
+    ULong(u64),
+    ULongLong(u64),
     F32(f32),
     F64(f64),
     Null,
-    Time,      // todo
-    TimeStamp, // todo
-    Date,      // todo
-    DateTime,  // todo
-    Year,      // todo
+    Time(Time),
+    TimeStamp(TimeStamp),
+    Date(Date),
+    DateTime(DateTime),
+    Year(u16),
     String(&'a [u8]),
     Blob(&'a [u8]),
     Json(&'a [u8]),
+    /// A `DECIMAL` in the server's binary format (see `decimal2bin`)
+    PackedDecimal {
+        data: &'a [u8],
+        precision: u8,
+        scale: u8,
+    },
+    /// 1-based index of an `ENUM` member, 0 for the empty string
+    Enum(u16),
+    /// Bitmap of `SET` members
+    Set(u64),
+    Bit(u64),
+}
+
+/// A `DATE` value
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+}
+
+/// A `TIME` value. This is a duration rather than a time of day, so it may be
+/// negative and may exceed 24 hours.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Time {
+    pub negative: bool,
+    pub hour: u32,
+    pub minute: u8,
+    pub second: u8,
+    pub microsecond: u32,
+}
+
+/// A `DATETIME` value
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DateTime {
+    pub date: Date,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+    pub microsecond: u32,
+}
+
+/// A `TIMESTAMP` value, stored as time since the Unix epoch in UTC
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TimeStamp {
+    pub seconds: u32,
+    pub microsecond: u32,
 }
 
 impl<'a> Value<'a> {
     /// Don't ask me why but our responses from the server API seem to all be strings
     /// So: take a pointer to the string then parse it as whatever value we expect
+    #[cfg(feature = "service-sql")]
     pub(crate) unsafe fn from_str_ptr(
         ty: bindings::enum_field_types::Type,
         ptr: *const c_char,
@@ -97,6 +152,9 @@ impl<'a> Value<'a> {
             Value::I8(v) => Some((*v).into()),
             Value::I16(v) => Some((*v).into()),
             Value::Long(v) | Value::LongLong(v) => Some((*v).into()),
+            Value::U8(v) => Some((*v).into()),
+            Value::U16(v) => Some((*v).into()),
+            Value::ULong(v) | Value::ULongLong(v) => (*v).try_into().ok(),
             _ => None,
         }
     }
@@ -0,0 +1,665 @@
+//! An append-only storage engine for audit and event tables
+//!
+//! Each table is a single log file `<name>.LOG` that is only ever appended to (see
+//! [`segment`] for the format). Rows are stored in the server's record format,
+//! followed by the data of their blob columns. There are no indexes; all reads
+//! are table scans in insertion order.
+//!
+//! Every statement that changes rows appends one segment and syncs it to disk,
+//! except that bulk inserts (`LOAD DATA`, multi-row `INSERT`) append a segment for
+//! every few megabytes of rows. `DELETE` appends delete entries for the rows, and
+//! `UPDATE` appends a delete entry and the new row.
+//!
+//! When a table is opened, the log is read to find the live rows. A segment left
+//! incomplete by a crash is removed; any other damage marks the table as crashed
+//! until `REPAIR TABLE` removes everything from the damaged segment on.
+//! `CHECK TABLE` verifies every checksum, and `OPTIMIZE TABLE` rewrites the log
+//! without deleted rows.
+
+mod segment;
+
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use mariadb::log::{debug, warn};
+use mariadb::plugin::{License, Maturity};
+use mariadb::storage::{
+    AdminMessages, AdminResult, CheckOptions, CreateInfo, Handler, Handlerton, InfoFlags, Mode,
+    OpenOp, Record, RecordMut, RepairingHandler, StatisticsMut, StorageError, StorageResult,
+    TableFlags, TablePath, ThrLock, ThrLockData, ThrLockType,
+};
+use mariadb::{register_plugin_storage, FieldType, MemRoot, Table, TableShare, Thd, Value};
+
+use crate::segment::{Index, Reader, Segment, Tail, HEADER_LEN};
+
+register_plugin_storage! {
+    name: "EVENTLOG",
+    author: "Trevor Gross",
+    description: "Append-only log storage engine for audit and event tables",
+    license: License::Gpl,
+    maturity: Maturity::Experimental,
+    version: "0.1",
+    handlerton: EventLogHton,
+}
+
+/// Extension of the log file
+const DATA_EXT: &str = ".LOG";
+/// Extension of the new log written by `OPTIMIZE TABLE`
+const NEW_DATA_EXT: &str = ".TMD";
+/// Size at which a segment of buffered rows is appended, so that bulk inserts and
+/// large deletes do not buffer without bound
+const MAX_SEGMENT_LEN: usize = 8 << 20;
+
+/// Tables that are open in at least one handler, by path
+static SHARES: Mutex<BTreeMap<PathBuf, Arc<EventLogShare>>> = Mutex::new(BTreeMap::new());
+
+struct EventLogHton;
+
+impl Handlerton for EventLogHton {
+    type Handler = EventLogHandler;
+
+    type ConnectionData = ();
+    type SavePoint = ();
+    type TableOptions = ();
+    type FieldOptions = ();
+    type IndexOptions = ();
+
+    const TABLEFILE_EXTENSIONS: &'static [&'static str] = &[DATA_EXT, NEW_DATA_EXT];
+}
+
+/// State shared by all handlers that have the same table open
+struct EventLogShare {
+    /// Length of the table's records, as stored in the log header
+    reclength: u32,
+    data_path: PathBuf,
+    new_data_path: PathBuf,
+    lock: Arc<ThrLock>,
+    state: Mutex<ShareState>,
+}
+
+struct ShareState {
+    /// Number of handlers that have the table open
+    use_count: usize,
+    index: Index,
+    /// End of the last segment, where the next one is appended
+    end: u64,
+    /// Why the table must be repaired before it can be used
+    damage: Option<String>,
+    /// Appends segments to the log, opened by the first write
+    writer: Option<File>,
+}
+
+impl EventLogShare {
+    /// Find the share for the table at `path`, or read its log if no handler has it
+    /// open yet
+    fn get(path: &Path, reclength: u32) -> StorageResult<Arc<Self>> {
+        let mut shares = SHARES.lock().unwrap();
+        if let Some(share) = shares.get(path) {
+            share.state().use_count += 1;
+            return Ok(Arc::clone(share));
+        }
+
+        let table_path = TablePath::new(path);
+        let share = Self {
+            reclength,
+            data_path: table_path.with_extension(DATA_EXT),
+            new_data_path: table_path.with_extension(NEW_DATA_EXT),
+            lock: Arc::new(ThrLock::new()),
+            state: Mutex::new(ShareState {
+                use_count: 1,
+                index: Index::default(),
+                end: HEADER_LEN,
+                damage: None,
+                writer: None,
+            }),
+        };
+        share.recover(&mut share.state())?;
+        let share = Arc::new(share);
+        shares.insert(path.to_owned(), Arc::clone(&share));
+        Ok(share)
+    }
+
+    /// Release a handler's use of the share
+    fn release(self: Arc<Self>) {
+        let mut shares = SHARES.lock().unwrap();
+        let mut state = self.state();
+        state.use_count -= 1;
+        if state.use_count == 0 {
+            shares.retain(|_, share| !Arc::ptr_eq(share, &self));
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, ShareState> {
+        self.state.lock().unwrap()
+    }
+
+    /// Read the log to rebuild the index, and remove a segment that was cut short.
+    /// Other damage is recorded in `state.damage`.
+    fn recover(&self, state: &mut ShareState) -> StorageResult {
+        let file = File::open(&self.data_path).map_err(io_error)?;
+        let scan = segment::scan(&file).map_err(io_error)?;
+        state.writer = None;
+        state.index = scan.index;
+        state.end = scan.end;
+        state.damage = match scan.tail {
+            Tail::Clean => None,
+            Tail::Torn => {
+                warn!(
+                    "eventlog: {}: removing an incomplete segment at offset {}",
+                    self.data_path.display(),
+                    scan.end
+                );
+                self.truncate(scan.end)?;
+                None
+            }
+            Tail::Damaged { offset, reason } => {
+                warn!(
+                    "eventlog: {}: damaged at offset {offset}: {reason}",
+                    self.data_path.display()
+                );
+                Some(format!("damaged at offset {offset}: {reason}"))
+            }
+        };
+        if state.damage.is_none() && scan.reclength != self.reclength {
+            return Err(StorageError::custom(format!(
+                "{} has rows of {} bytes, but the table's rows have {}",
+                self.data_path.display(),
+                scan.reclength,
+                self.reclength
+            )));
+        }
+        Ok(())
+    }
+
+    /// Cut the log off at `len`, and sync it to disk
+    fn truncate(&self, len: u64) -> StorageResult {
+        OpenOptions::new()
+            .write(true)
+            .open(&self.data_path)
+            .and_then(|f| {
+                f.set_len(len)?;
+                f.sync_data()
+            })
+            .map_err(io_error)
+    }
+
+    /// Append `segment` to the log and sync it to disk, then add its entries to the
+    /// index. `segment` is left empty, even if appending fails.
+    fn append(&self, segment: &mut Segment) -> StorageResult {
+        let mut segment = std::mem::take(segment);
+        if segment.is_empty() {
+            return Ok(());
+        }
+
+        let mut state = self.state();
+        if state.writer.is_none() {
+            let file = OpenOptions::new()
+                .write(true)
+                .open(&self.data_path)
+                .map_err(io_error)?;
+            state.writer = Some(file);
+        }
+        let start = state.end;
+        let bytes = segment.finish(start);
+        let writer = state.writer.as_mut().unwrap();
+        let res = writer
+            .seek(SeekFrom::Start(start))
+            .and_then(|_| writer.write_all(&bytes))
+            .and_then(|()| writer.sync_data());
+        if let Err(e) = res {
+            // Best effort, an incomplete segment is also removed when the table is
+            // next opened
+            let _ = writer.set_len(start);
+            return Err(io_error(e));
+        }
+
+        state.end = start + bytes.len() as u64;
+        let applied = segment.apply(start, &mut state.index);
+        debug_assert!(applied, "deleted rows are live");
+        Ok(())
+    }
+
+    /// Replace the log with one holding only the live rows, in one segment for every
+    /// [`MAX_SEGMENT_LEN`] bytes
+    fn compact(&self, state: &mut ShareState) -> io::Result<()> {
+        let new_path = &self.new_data_path;
+        let res = (|| {
+            let mut reader = Reader::new(File::open(&self.data_path)?);
+            let mut new = File::create(new_path)?;
+            new.write_all(&segment::header(self.reclength))?;
+
+            let (mut index, mut end) = (Index::default(), HEADER_LEN);
+            let mut segment = Segment::default();
+            let mut row = Vec::new();
+            let mut offsets = state.index.rows.iter().peekable();
+            while let Some((&offset, &len)) = offsets.next() {
+                reader.read_row(offset, len, &mut row)?;
+                segment.push_row(&row);
+                if segment.len() >= MAX_SEGMENT_LEN || offsets.peek().is_none() {
+                    let bytes = segment.finish(end);
+                    new.write_all(&bytes)?;
+                    segment.apply(end, &mut index);
+                    end += bytes.len() as u64;
+                    segment = Segment::default();
+                }
+            }
+            new.sync_data()?;
+            fs::rename(new_path, &self.data_path)?;
+            Ok((index, end))
+        })();
+
+        match res {
+            Ok((index, end)) => {
+                // The open writer points at the replaced log and its offsets; drop it
+                // so the next `append` reopens the compacted one
+                state.writer = None;
+                state.index = index;
+                state.end = end;
+                Ok(())
+            }
+            Err(e) => {
+                // The `.LOG` file and the in-memory index are untouched, so only the
+                // partial `.TMD` copy needs removing
+                let _ = fs::remove_file(new_path);
+                Err(e)
+            }
+        }
+    }
+}
+
+struct EventLogHandler {
+    /// Indices of the blob columns of the open table, whose data is stored after
+    /// the record
+    blobs: Vec<usize>,
+    share: Option<Arc<EventLogShare>>,
+    lock_data: Option<ThrLockData>,
+    reader: Option<Reader>,
+    /// Offset of the row last read
+    current_pos: u64,
+    /// Offset to continue the scan from
+    next_pos: u64,
+    /// End of the log when the scan started, so that rows appended during the scan
+    /// (e.g. by `UPDATE`) are not read again
+    scan_end: u64,
+    /// Rows and deletes not yet appended to the log
+    pending: Segment,
+    /// A bulk insert is running, so rows are buffered in `pending`
+    bulk_insert: bool,
+    /// The frame payload of the row last read or written
+    row: Vec<u8>,
+    /// Data of each blob column of the row last read. Blob columns of the record
+    /// point into these, so that they stay valid when `row` is reused.
+    blob_data: Vec<Vec<u8>>,
+}
+
+impl EventLogHandler {
+    fn share(&self) -> &EventLogShare {
+        self.share.as_deref().expect("table is open")
+    }
+
+    /// Read the row whose frame is at `offset` into `buf`
+    fn read_row(&mut self, offset: u64, len: u32, buf: &mut RecordMut) -> StorageResult {
+        let reader = self.reader.as_mut().expect("scan is initialized");
+        reader
+            .read_row(offset, len, &mut self.row)
+            .map_err(|e| match e.kind() {
+                io::ErrorKind::InvalidData | io::ErrorKind::UnexpectedEof => {
+                    StorageError::CrashedOnUsage
+                }
+                _ => io_error(e),
+            })?;
+        self.current_pos = offset;
+        decode_row(&self.row, &self.blobs, &mut self.blob_data, buf)
+            .ok_or(StorageError::CrashedOnUsage)
+    }
+
+    /// Append the pending segment if it is large enough, or if `all` is set
+    fn flush(&mut self, all: bool) -> StorageResult {
+        if self.pending.len() >= MAX_SEGMENT_LEN || all {
+            let share = self.share.as_deref().expect("table is open");
+            share.append(&mut self.pending)?;
+        }
+        Ok(())
+    }
+}
+
+impl Handler for EventLogHandler {
+    type Handlerton = EventLogHton;
+
+    const SUPPORTS_REPAIR: bool = true;
+
+    const TABLE_FLAGS: TableFlags = TableFlags::NO_TRANSACTIONS
+        .union(TableFlags::REC_NOT_IN_SEQ)
+        .union(TableFlags::STATS_RECORDS_IS_EXACT)
+        .union(TableFlags::NO_AUTO_INCREMENT)
+        .union(TableFlags::FILE_BASED)
+        .union(TableFlags::BINLOG_ROW_CAPABLE)
+        .union(TableFlags::BINLOG_STMT_CAPABLE)
+        .union(TableFlags::CAN_REPAIR)
+        .union(TableFlags::CRASH_SAFE);
+
+    fn new(_table: Option<&TableShare>, _mem_root: MemRoot) -> Self {
+        Self {
+            blobs: Vec::new(),
+            share: None,
+            lock_data: None,
+            reader: None,
+            current_pos: 0,
+            next_pos: 0,
+            scan_end: 0,
+            pending: Segment::default(),
+            bulk_insert: false,
+            row: Vec::new(),
+            blob_data: Vec::new(),
+        }
+    }
+
+    fn create(&mut self, name: &Path, form: &Table, _create_info: &CreateInfo) -> StorageResult {
+        let reclength = reclength(form)?;
+        let path = TablePath::new(name).with_extension(DATA_EXT);
+        File::create(path)
+            .and_then(|mut f| {
+                f.write_all(&segment::header(reclength))?;
+                f.sync_data()
+            })
+            .map_err(io_error)
+    }
+
+    fn open(
+        &mut self,
+        name: &Path,
+        table: &Table,
+        _mode: Mode,
+        open_options: OpenOp,
+    ) -> StorageResult {
+        let share = EventLogShare::get(name, reclength(table)?)?;
+        if share.state().damage.is_some() && !open_options.contains(OpenOp::FOR_REPAIR) {
+            share.release();
+            return Err(StorageError::CrashedOnUsage);
+        }
+
+        debug!("eventlog: opened {}", name.display());
+        self.blobs = table
+            .fields()
+            .iter()
+            .enumerate()
+            .filter(|(_, f)| {
+                matches!(
+                    f.field_type(),
+                    FieldType::TinyBlob
+                        | FieldType::Blob
+                        | FieldType::MediumBlob
+                        | FieldType::LongBlob
+                        | FieldType::Geometry
+                )
+            })
+            .map(|(idx, _)| idx)
+            .collect();
+        self.blob_data = vec![Vec::new(); self.blobs.len()];
+        self.lock_data = Some(ThrLockData::new(Arc::clone(&share.lock)));
+        self.share = Some(share);
+        Ok(())
+    }
+
+    fn close(&mut self) -> StorageResult {
+        let res = self.flush(true);
+        self.reader = None;
+        self.lock_data = None;
+        if let Some(share) = self.share.take() {
+            share.release();
+        }
+        res
+    }
+
+    fn write_row(&mut self, buf: &Record) -> StorageResult {
+        encode_row(buf, &self.blobs, &mut self.row);
+        self.pending.push_row(&self.row);
+        self.flush(!self.bulk_insert)
+    }
+
+    fn start_bulk_insert(&mut self, _rows_estimate: u64) {
+        self.bulk_insert = true;
+    }
+
+    fn end_bulk_insert(&mut self) -> StorageResult {
+        self.bulk_insert = false;
+        self.flush(true)
+    }
+
+    fn update_row(&mut self, _old_data: &Record, new_data: &Record) -> StorageResult {
+        // The new row goes at the end, after the end of the scan
+        self.pending.push_delete(self.current_pos);
+        encode_row(new_data, &self.blobs, &mut self.row);
+        self.pending.push_row(&self.row);
+        self.flush(false)
+    }
+
+    fn delete_row(&mut self, _buf: &Record) -> StorageResult {
+        self.pending.push_delete(self.current_pos);
+        self.flush(false)
+    }
+
+    fn delete_all_rows(&mut self) -> StorageResult {
+        self.pending = Segment::default();
+        let share = self.share();
+        let mut state = share.state();
+        share.truncate(HEADER_LEN)?;
+        state.index = Index::default();
+        state.end = HEADER_LEN;
+        Ok(())
+    }
+
+    fn rnd_init(&mut self, _scan: bool) -> StorageResult {
+        let share = self.share();
+        let (file, end) = {
+            let state = share.state();
+            if state.damage.is_some() {
+                return Err(StorageError::CrashedOnUsage);
+            }
+            (File::open(&share.data_path).map_err(io_error)?, state.end)
+        };
+        self.reader = Some(Reader::new(file));
+        self.scan_end = end;
+        self.current_pos = 0;
+        self.next_pos = 0;
+        Ok(())
+    }
+
+    fn rnd_next(&mut self, buf: &mut RecordMut) -> StorageResult {
+        let next = self
+            .share()
+            .state()
+            .index
+            .rows
+            .range(self.next_pos..self.scan_end)
+            .next()
+            .map(|(&offset, &len)| (offset, len));
+        let Some((offset, len)) = next else {
+            return Err(StorageError::EndOfFile);
+        };
+        self.next_pos = offset + 1;
+        self.read_row(offset, len, buf)
+    }
+
+    fn rnd_pos(&mut self, buf: &mut RecordMut, pos: &[u8]) -> StorageResult {
+        let offset = u64::from_le_bytes(pos.try_into().unwrap());
+        let len = self.share().state().index.rows.get(&offset).copied();
+        let len = len.ok_or(StorageError::KeyNotFound)?;
+        self.read_row(offset, len, buf)
+    }
+
+    fn position(&mut self, _row: &Record, pos: &mut [u8]) {
+        pos.copy_from_slice(&self.current_pos.to_le_bytes());
+    }
+
+    fn rnd_end(&mut self) -> StorageResult {
+        self.reader = None;
+        self.flush(true)
+    }
+
+    fn info(&mut self, _flags: InfoFlags, stats: &mut StatisticsMut) -> StorageResult {
+        let state = self.share().state();
+        let index = &state.index;
+        let rows = index.rows.len() as u64;
+        stats.set_records(rows);
+        stats.set_deleted(index.deleted);
+        stats.set_data_file_length(state.end);
+        stats.set_delete_length(index.dead_bytes);
+        stats.set_mean_rec_length(index.live_bytes.checked_div(rows).unwrap_or(0));
+        Ok(())
+    }
+
+    fn store_lock(&mut self, _thd: &Thd, lock_type: ThrLockType) -> Option<&mut ThrLockData> {
+        let lock_data = self.lock_data.as_mut()?;
+        lock_data.request(lock_type);
+        Some(lock_data)
+    }
+}
+
+impl RepairingHandler for EventLogHandler {
+    fn is_crashed(&self) -> bool {
+        self.share
+            .as_deref()
+            .is_some_and(|s| s.state().damage.is_some())
+    }
+
+    fn check(&mut self, msgs: &mut AdminMessages, _opts: &CheckOptions) -> AdminResult {
+        let share = self.share();
+        let mut state = share.state();
+        let Ok(scan) = File::open(&share.data_path).and_then(|f| segment::scan(&f)) else {
+            return AdminResult::InternalError;
+        };
+
+        let damage = match scan.tail {
+            Tail::Damaged { offset, reason } => format!("damaged at offset {offset}: {reason}"),
+            Tail::Torn => format!("incomplete segment at offset {}", scan.end),
+            Tail::Clean if scan.end != state.end || scan.index != state.index => {
+                "the log does not match the rows in memory".to_owned()
+            }
+            Tail::Clean => return AdminResult::Ok,
+        };
+        msgs.error(&damage);
+        state.damage = Some(damage);
+        AdminResult::Corrupt
+    }
+
+    fn repair(&mut self, msgs: &mut AdminMessages, _opts: &CheckOptions) -> AdminResult {
+        let share = self.share();
+        let mut state = share.state();
+        let Ok(scan) = File::open(&share.data_path).and_then(|f| segment::scan(&f)) else {
+            return AdminResult::InternalError;
+        };
+        let Ok(len) = fs::metadata(&share.data_path).map(|m| m.len()) else {
+            return AdminResult::InternalError;
+        };
+
+        if scan.end == 0 {
+            msgs.error("the log header is damaged");
+            return AdminResult::Failed;
+        }
+        if len > scan.end {
+            // Keep the segments before the damaged one
+            if share.truncate(scan.end).is_err() {
+                return AdminResult::Failed;
+            }
+            msgs.note(&format!(
+                "kept {} rows, removed {} bytes after offset {}",
+                scan.index.rows.len(),
+                len - scan.end,
+                scan.end
+            ));
+        }
+        state.writer = None;
+        state.index = scan.index;
+        state.end = scan.end;
+        state.damage = None;
+        AdminResult::Ok
+    }
+
+    fn optimize(&mut self, msgs: &mut AdminMessages, _opts: &CheckOptions) -> AdminResult {
+        let share = self.share();
+        let mut state = share.state();
+        if state.index.deleted == 0 && state.index.segments <= 1 {
+            return AdminResult::AlreadyDone;
+        }
+
+        let (deleted, old_len) = (state.index.deleted, state.end);
+        match share.compact(&mut state) {
+            Ok(()) => {
+                msgs.note(&format!(
+                    "removed {deleted} deleted rows, the log shrank from {old_len} to {} bytes",
+                    state.end
+                ));
+                AdminResult::Ok
+            }
+            Err(e) => {
+                msgs.error(&format!("compaction failed: {e}"));
+                AdminResult::Failed
+            }
+        }
+    }
+}
+
+/// The length of the table's records, as stored in the log header
+fn reclength(table: &Table) -> StorageResult<u32> {
+    let reclength = table.share().reclength();
+    u32::try_from(reclength).map_err(|_| {
+        StorageError::custom(format!("rows of {reclength} bytes are too long for a log"))
+    })
+}
+
+/// Encode `row` into `out`: the record, then the length (4 bytes) and data of each
+/// blob column in `blobs`. Blob columns in the record only hold pointers, which are
+/// replaced when the row is read.
+fn encode_row(row: &Record, blobs: &[usize], out: &mut Vec<u8>) {
+    out.clear();
+    out.extend_from_slice(row.as_bytes());
+    for &idx in blobs {
+        let data = match row.get(idx) {
+            Value::Blob(data) => data,
+            _ => &[],
+        };
+        // The length of a blob is stored in at most 4 bytes
+        let len = u32::try_from(data.len()).unwrap();
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(data);
+    }
+}
+
+/// Decode a row written by [`encode_row`] into `buf`. The data of each blob column
+/// is copied into its buffer in `blob_data`, which the record then points to.
+/// Returns `None` if the row does not fit the table.
+fn decode_row(
+    data: &[u8],
+    blobs: &[usize],
+    blob_data: &mut [Vec<u8>],
+    buf: &mut RecordMut,
+) -> Option<()> {
+    let record = buf.as_bytes_mut();
+    let (image, mut rest) = data.split_at_checked(record.len())?;
+    record.copy_from_slice(image);
+    for (&idx, stored) in blobs.iter().zip(blob_data) {
+        let (len, tail) = rest.split_first_chunk::<4>()?;
+        let (blob, tail) = tail.split_at_checked(u32::from_le_bytes(*len) as usize)?;
+        rest = tail;
+        stored.clear();
+        stored.extend_from_slice(blob);
+        // Setting the pointer also clears the null flag
+        let is_null = buf.as_record().is_null(idx);
+        buf.set_blob(idx, stored).ok()?;
+        if is_null {
+            buf.set_null(idx).ok()?;
+        }
+    }
+    rest.is_empty().then_some(())
+}
+
+fn io_error(e: io::Error) -> StorageError {
+    match e.kind() {
+        io::ErrorKind::NotFound => StorageError::NoSuchTable,
+        _ => StorageError::custom(format!("event log error: {e}")),
+    }
+}
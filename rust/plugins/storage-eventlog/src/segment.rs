@@ -0,0 +1,585 @@
+//! The log file format
+//!
+//! A log starts with a header of [`HEADER_LEN`] bytes, followed by segments. Each
+//! segment is a run of entry frames closed by a footer frame. All integers are
+//! little endian.
+//!
+//! ```text
+//! header: magic (6) | version (2) | record length (4) | CRC-32 of the rest (4)
+//! frame:  kind (1) | payload length (4) | CRC-32 of kind, length and payload (4)
+//!         | payload
+//! row:    the row's data
+//! delete: offset of the deleted row's frame (8)
+//! footer: offset of the segment's first frame (8) | number of entries (4)
+//!         | CRC-32 of the segment's entry frames (4)
+//! ```
+//!
+//! Segments are only ever appended whole. Their entries take effect once the
+//! footer is on disk, so a segment cut short by a crash is ignored (see
+//! [`Tail::Torn`]) and the log is always in the state of a complete segment.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+
+const MAGIC: [u8; 6] = *b"RSLOG\0";
+const VERSION: u16 = 1;
+/// Length of the file header, which is also the offset of the first segment
+pub const HEADER_LEN: u64 = 16;
+/// Length of a frame's kind, payload length and checksum
+const FRAME_HEADER_LEN: u64 = 9;
+/// Length of a footer's payload
+const FOOTER_LEN: u32 = 16;
+
+/// The kind of a frame, its first byte
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Kind {
+    Row = 1,
+    Delete = 2,
+    Footer = 3,
+}
+
+impl Kind {
+    fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            1 => Some(Self::Row),
+            2 => Some(Self::Delete),
+            3 => Some(Self::Footer),
+            _ => None,
+        }
+    }
+}
+
+/// The header of a new log for rows of `reclength` bytes
+pub fn header(reclength: u32) -> [u8; HEADER_LEN as usize] {
+    let mut buf = [0; HEADER_LEN as usize];
+    buf[..6].copy_from_slice(&MAGIC);
+    buf[6..8].copy_from_slice(&VERSION.to_le_bytes());
+    buf[8..12].copy_from_slice(&reclength.to_le_bytes());
+    let crc = crc32fast::hash(&buf[..12]);
+    buf[12..].copy_from_slice(&crc.to_le_bytes());
+    buf
+}
+
+/// The record length of a log header, or `None` if it is not a valid header
+fn parse_header(buf: &[u8; HEADER_LEN as usize]) -> Option<u32> {
+    let crc = u32::from_le_bytes(buf[12..].try_into().unwrap());
+    if buf[..6] != MAGIC || buf[6..8] != VERSION.to_le_bytes() || crc != crc32fast::hash(&buf[..12])
+    {
+        return None;
+    }
+    Some(u32::from_le_bytes(buf[8..12].try_into().unwrap()))
+}
+
+fn frame_header(kind: Kind, payload: &[u8]) -> [u8; FRAME_HEADER_LEN as usize] {
+    let len = u32::try_from(payload.len()).expect("payloads are smaller than 4 GiB");
+    let mut buf = [0; FRAME_HEADER_LEN as usize];
+    buf[0] = kind as u8;
+    buf[1..5].copy_from_slice(&len.to_le_bytes());
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(&buf[..5]);
+    hasher.update(payload);
+    buf[5..].copy_from_slice(&hasher.finalize().to_le_bytes());
+    buf
+}
+
+fn push_frame(buf: &mut Vec<u8>, kind: Kind, payload: &[u8]) {
+    buf.extend_from_slice(&frame_header(kind, payload));
+    buf.extend_from_slice(payload);
+}
+
+/// Live rows of a log
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Index {
+    /// Payload length of each live row, by offset of its frame
+    pub rows: BTreeMap<u64, u32>,
+    /// Total payload length of the live rows
+    pub live_bytes: u64,
+    /// Number of rows that were deleted
+    pub deleted: u64,
+    /// Bytes taken by deleted rows and delete entries, which compaction removes
+    pub dead_bytes: u64,
+    pub segments: u64,
+}
+
+impl Index {
+    fn insert(&mut self, offset: u64, len: u32) {
+        self.rows.insert(offset, len);
+        self.live_bytes += u64::from(len);
+    }
+
+    /// Remove the row at `offset`. Returns false if there is no such row.
+    fn delete(&mut self, offset: u64) -> bool {
+        let Some(len) = self.rows.remove(&offset) else {
+            return false;
+        };
+        self.live_bytes -= u64::from(len);
+        self.deleted += 1;
+        self.dead_bytes += FRAME_HEADER_LEN * 2 + u64::from(len) + 8;
+        true
+    }
+}
+
+/// A segment being built. Offsets of its frames are only known once it is placed
+/// at the end of the log by [`Segment::finish`].
+#[derive(Debug, Default)]
+pub struct Segment {
+    buf: Vec<u8>,
+    /// Offset relative to the segment and payload length of each row
+    rows: Vec<(u64, u32)>,
+    deletes: Vec<u64>,
+}
+
+impl Segment {
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    /// Length of the entry frames added so far
+    pub fn len(&self) -> usize {
+        self.buf.len()
+    }
+
+    pub fn push_row(&mut self, row: &[u8]) {
+        let len = u32::try_from(row.len()).expect("rows are smaller than 4 GiB");
+        self.rows.push((self.buf.len() as u64, len));
+        push_frame(&mut self.buf, Kind::Row, row);
+    }
+
+    /// Delete the row whose frame is at `offset`
+    pub fn push_delete(&mut self, offset: u64) {
+        self.deletes.push(offset);
+        push_frame(&mut self.buf, Kind::Delete, &offset.to_le_bytes());
+    }
+
+    /// Close the segment with its footer, for appending at `start`. Returns the
+    /// bytes to write.
+    pub fn finish(&mut self, start: u64) -> Vec<u8> {
+        let entries = u32::try_from(self.rows.len() + self.deletes.len()).unwrap();
+        let mut footer = [0; FOOTER_LEN as usize];
+        footer[..8].copy_from_slice(&start.to_le_bytes());
+        footer[8..12].copy_from_slice(&entries.to_le_bytes());
+        footer[12..].copy_from_slice(&crc32fast::hash(&self.buf).to_le_bytes());
+
+        let mut buf = std::mem::take(&mut self.buf);
+        push_frame(&mut buf, Kind::Footer, &footer);
+        buf
+    }
+
+    /// Add the entries of the segment, written at `start`, to `index`. Returns
+    /// false if a delete entry refers to a row that does not exist.
+    pub fn apply(&self, start: u64, index: &mut Index) -> bool {
+        for &(offset, len) in &self.rows {
+            index.insert(start + offset, len);
+        }
+        index.segments += 1;
+        self.deletes.iter().all(|&offset| index.delete(offset))
+    }
+}
+
+/// How a log ends after its last complete segment
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Tail {
+    /// There is nothing after the last complete segment
+    Clean,
+    /// The last segment is incomplete, as when the server stops while appending it
+    Torn,
+    /// The log is damaged at `offset`. Nothing from there on can be read.
+    Damaged { offset: u64, reason: String },
+}
+
+/// A log as read by [`scan`]
+#[derive(Clone, Debug)]
+pub struct Scan {
+    /// Record length from the header, 0 if the header is damaged
+    pub reclength: u32,
+    /// Rows of the complete segments before any damage
+    pub index: Index,
+    /// End of the last complete segment before any damage
+    pub end: u64,
+    pub tail: Tail,
+}
+
+/// An entry of a segment that is being read, by offset of its frame
+enum Entry {
+    Row(u64, u32),
+    Delete(u64, u64),
+}
+
+/// Read a whole log, verifying each frame and segment
+pub fn scan(file: &File) -> io::Result<Scan> {
+    let file_len = file.metadata()?.len();
+    let mut reader = BufReader::new(file);
+    reader.seek(SeekFrom::Start(0))?;
+
+    let mut scan = Scan {
+        reclength: 0,
+        index: Index::default(),
+        end: HEADER_LEN,
+        tail: Tail::Clean,
+    };
+    let mut header = [0; HEADER_LEN as usize];
+    let header_ok = file_len >= HEADER_LEN && {
+        reader.read_exact(&mut header)?;
+        parse_header(&header).is_some()
+    };
+    if !header_ok {
+        scan.end = 0;
+        scan.tail = damaged(0, "bad file header");
+        return Ok(scan);
+    }
+    scan.reclength = parse_header(&header).unwrap();
+
+    let mut pos = HEADER_LEN;
+    let mut entries = Vec::new();
+    let mut hasher = crc32fast::Hasher::new();
+    let mut payload = Vec::new();
+    while pos < file_len {
+        // A frame that runs past the end of the file was cut short
+        let mut frame = [0; FRAME_HEADER_LEN as usize];
+        if file_len - pos < FRAME_HEADER_LEN {
+            scan.tail = Tail::Torn;
+            return Ok(scan);
+        }
+        reader.read_exact(&mut frame)?;
+        let len = u32::from_le_bytes(frame[1..5].try_into().unwrap());
+        let crc = u32::from_le_bytes(frame[5..].try_into().unwrap());
+        if file_len - pos - FRAME_HEADER_LEN < u64::from(len) {
+            // Unless a damaged length hides complete segments after it
+            scan.tail = if footer_after(file, pos, scan.end, file_len)? {
+                damaged(pos, "frame runs past a later segment footer")
+            } else {
+                Tail::Torn
+            };
+            return Ok(scan);
+        }
+        payload.resize(len as usize, 0);
+        reader.read_exact(&mut payload)?;
+
+        let kind = Kind::from_byte(frame[0]);
+        if kind.is_none() || frame_header(kind.unwrap(), &payload)[5..] != crc.to_le_bytes() {
+            let zeros = frame.iter().chain(&payload).all(|b| *b == 0);
+            scan.tail = if zeros && rest_is_zero(&mut reader)? {
+                Tail::Torn
+            } else {
+                damaged(pos, "bad frame checksum")
+            };
+            return Ok(scan);
+        }
+
+        let next = pos + FRAME_HEADER_LEN + u64::from(len);
+        match kind.unwrap() {
+            Kind::Row => entries.push(Entry::Row(pos, len)),
+            Kind::Delete => {
+                let Ok(bytes) = payload[..].try_into() else {
+                    scan.tail = damaged(pos, "bad delete entry");
+                    return Ok(scan);
+                };
+                entries.push(Entry::Delete(pos, u64::from_le_bytes(bytes)));
+            }
+            Kind::Footer => {
+                if len != FOOTER_LEN {
+                    scan.tail = damaged(pos, "bad segment footer");
+                    return Ok(scan);
+                }
+                let start = u64::from_le_bytes(payload[..8].try_into().unwrap());
+                let count = u32::from_le_bytes(payload[8..12].try_into().unwrap());
+                let crc = u32::from_le_bytes(payload[12..].try_into().unwrap());
+                let hash = std::mem::take(&mut hasher).finalize();
+                if start != scan.end || count as usize != entries.len() || crc != hash {
+                    scan.tail = damaged(pos, "segment footer does not match its entries");
+                    return Ok(scan);
+                }
+
+                let mut index = scan.index.clone();
+                for entry in entries.drain(..) {
+                    match entry {
+                        Entry::Row(offset, len) => index.insert(offset, len),
+                        Entry::Delete(offset, row) => {
+                            if !index.delete(row) {
+                                scan.tail = damaged(offset, "deletes a row that does not exist");
+                                return Ok(scan);
+                            }
+                        }
+                    }
+                }
+                index.segments += 1;
+                scan.index = index;
+                scan.end = next;
+                pos = next;
+                continue;
+            }
+        }
+        hasher.update(&frame);
+        hasher.update(&payload);
+        pos = next;
+    }
+
+    if !entries.is_empty() {
+        scan.tail = Tail::Torn;
+    }
+    Ok(scan)
+}
+
+fn damaged(offset: u64, reason: &str) -> Tail {
+    Tail::Damaged {
+        offset,
+        reason: reason.to_owned(),
+    }
+}
+
+/// Whether a valid footer frame of a segment starting at `start` or later begins
+/// after `pos`. The file is searched backwards from its end.
+fn footer_after(mut file: &File, pos: u64, start: u64, file_len: u64) -> io::Result<bool> {
+    const FRAME_LEN: u64 = FRAME_HEADER_LEN + FOOTER_LEN as u64;
+    const CHUNK: u64 = 64 * 1024;
+    let is_footer = |frame: &[u8]| {
+        let (header, payload) = frame.split_at(FRAME_HEADER_LEN as usize);
+        let footer_start = u64::from_le_bytes(payload[..8].try_into().unwrap());
+        header == frame_header(Kind::Footer, payload) && footer_start >= start
+    };
+
+    let mut buf = Vec::new();
+    let mut end = file_len;
+    while end >= pos + 1 + FRAME_LEN {
+        // Chunks overlap so that frames across their boundary are seen
+        let chunk_start = end.saturating_sub(CHUNK).max(pos + 1);
+        buf.resize((end - chunk_start) as usize, 0);
+        file.seek(SeekFrom::Start(chunk_start))?;
+        file.read_exact(&mut buf)?;
+        if buf.windows(FRAME_LEN as usize).rev().any(is_footer) {
+            return Ok(true);
+        }
+        end = chunk_start + FRAME_LEN - 1;
+    }
+    Ok(false)
+}
+
+/// Whether the rest of `reader` is zeros. A log ending in zeros was extended but
+/// the data was never written, which also happens when a crash interrupts an
+/// append.
+fn rest_is_zero(reader: &mut impl Read) -> io::Result<bool> {
+    let mut buf = [0; 4096];
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            return Ok(true);
+        }
+        if buf[..n].iter().any(|b| *b != 0) {
+            return Ok(false);
+        }
+    }
+}
+
+/// Reads rows of a log by offset
+pub struct Reader {
+    file: BufReader<File>,
+    /// Offset of the next byte `file` will return
+    pos: u64,
+}
+
+impl Reader {
+    pub fn new(file: File) -> Self {
+        Self {
+            file: BufReader::new(file),
+            pos: u64::MAX,
+        }
+    }
+
+    /// Read the row whose frame is at `offset` and whose payload is `len` bytes into
+    /// `buf`. Returns an `InvalidData` error if the frame is damaged.
+    pub fn read_row(&mut self, offset: u64, len: u32, buf: &mut Vec<u8>) -> io::Result<()> {
+        let res = self.read_frame(offset, len, buf);
+        if res.is_err() {
+            // The position is unknown after a partial read
+            self.pos = u64::MAX;
+        }
+        res
+    }
+
+    fn read_frame(&mut self, offset: u64, len: u32, buf: &mut Vec<u8>) -> io::Result<()> {
+        if offset != self.pos {
+            self.file.seek(SeekFrom::Start(offset))?;
+            self.pos = offset;
+        }
+
+        let mut frame = [0; FRAME_HEADER_LEN as usize];
+        self.file.read_exact(&mut frame)?;
+        self.pos += FRAME_HEADER_LEN;
+        if frame[0] != Kind::Row as u8 || frame[1..5] != len.to_le_bytes() {
+            return Err(io::ErrorKind::InvalidData.into());
+        }
+        buf.resize(len as usize, 0);
+        self.file.read_exact(buf)?;
+        self.pos += u64::from(len);
+        if frame_header(Kind::Row, buf) != frame {
+            return Err(io::ErrorKind::InvalidData.into());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, OpenOptions};
+    use std::io::Write;
+    use std::path::PathBuf;
+
+    use super::*;
+
+    /// A log file in the temporary directory, removed when dropped
+    struct TempLog(PathBuf);
+
+    impl TempLog {
+        fn new(name: &str) -> Self {
+            let path =
+                std::env::temp_dir().join(format!("eventlog-{name}-{}.LOG", std::process::id()));
+            fs::write(&path, header(24)).unwrap();
+            Self(path)
+        }
+
+        /// Append `segment` and apply it to `index`
+        fn append(&self, segment: &mut Segment, index: &mut Index) -> u64 {
+            let start = fs::metadata(&self.0).unwrap().len();
+            let bytes = segment.finish(start);
+            let mut file = OpenOptions::new().append(true).open(&self.0).unwrap();
+            file.write_all(&bytes).unwrap();
+            assert!(segment.apply(start, index));
+            start
+        }
+
+        fn scan(&self) -> Scan {
+            scan(&File::open(&self.0).unwrap()).unwrap()
+        }
+
+        fn len(&self) -> u64 {
+            fs::metadata(&self.0).unwrap().len()
+        }
+    }
+
+    impl Drop for TempLog {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn test_scan_rebuilds_index() {
+        let log = TempLog::new("index");
+        let mut index = Index::default();
+
+        let mut seg = Segment::default();
+        seg.push_row(b"first");
+        seg.push_row(b"second");
+        let start = log.append(&mut seg, &mut index);
+        let first = start;
+        let second = start + FRAME_HEADER_LEN + 5;
+
+        let mut seg = Segment::default();
+        seg.push_delete(first);
+        seg.push_row(b"third");
+        log.append(&mut seg, &mut index);
+
+        let scan = log.scan();
+        assert_eq!(scan.tail, Tail::Clean);
+        assert_eq!(scan.reclength, 24);
+        assert_eq!(scan.end, log.len());
+        assert_eq!(scan.index, index);
+        assert_eq!(index.rows.len(), 2);
+        assert_eq!(index.rows[&second], 6);
+        assert_eq!(
+            (index.deleted, index.live_bytes, index.segments),
+            (1, 11, 2)
+        );
+
+        let mut reader = Reader::new(File::open(&log.0).unwrap());
+        let mut buf = Vec::new();
+        reader.read_row(second, 6, &mut buf).unwrap();
+        assert_eq!(buf, b"second");
+        let err = reader.read_row(first + 1, 5, &mut buf).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_torn_tail() {
+        let log = TempLog::new("torn");
+        let mut index = Index::default();
+        let mut seg = Segment::default();
+        seg.push_row(b"kept");
+        log.append(&mut seg, &mut index);
+        let end = log.len();
+
+        // A segment without its footer
+        let mut seg = Segment::default();
+        seg.push_row(b"lost");
+        let mut bytes = seg.finish(end);
+        bytes.truncate(bytes.len() - 3);
+        let mut file = OpenOptions::new().append(true).open(&log.0).unwrap();
+        file.write_all(&bytes).unwrap();
+        let scan = log.scan();
+        assert_eq!((scan.tail, scan.end), (Tail::Torn, end));
+        assert_eq!(scan.index, index);
+
+        // Space that was allocated but never written
+        file.set_len(end).unwrap();
+        file.write_all(&[0; 100]).unwrap();
+        let scan = log.scan();
+        assert_eq!((scan.tail, scan.end), (Tail::Torn, end));
+    }
+
+    #[test]
+    fn test_damage() {
+        let log = TempLog::new("damage");
+        let mut index = Index::default();
+        let mut seg = Segment::default();
+        seg.push_row(b"one");
+        log.append(&mut seg, &mut index);
+        let end = log.len();
+        let mut seg = Segment::default();
+        seg.push_row(b"two");
+        log.append(&mut seg, &mut index);
+
+        // Flip a bit in the second row
+        let mut bytes = fs::read(&log.0).unwrap();
+        let offset = end as usize + FRAME_HEADER_LEN as usize;
+        bytes[offset] ^= 1;
+        fs::write(&log.0, &bytes).unwrap();
+        let scan = log.scan();
+        assert_eq!(
+            scan.tail,
+            Tail::Damaged {
+                offset: end,
+                reason: "bad frame checksum".into()
+            }
+        );
+        assert_eq!((scan.end, scan.index.rows.len()), (end, 1));
+
+        // A delete of a row that is not there
+        bytes[offset] ^= 1;
+        fs::write(&log.0, &bytes).unwrap();
+        let mut seg = Segment::default();
+        seg.push_delete(end + 1);
+        let mut file = OpenOptions::new().append(true).open(&log.0).unwrap();
+        file.write_all(&seg.finish(bytes.len() as u64)).unwrap();
+        assert!(matches!(log.scan().tail, Tail::Damaged { .. }));
+
+        // A length that runs past the end, with complete segments after it
+        let mut seg = Segment::default();
+        seg.push_row(b"three");
+        log.append(&mut seg, &mut index);
+        let mut bytes = fs::read(&log.0).unwrap();
+        bytes[end as usize + 1..end as usize + 5].copy_from_slice(&u32::MAX.to_le_bytes());
+        fs::write(&log.0, &bytes).unwrap();
+        let scan = log.scan();
+        assert_eq!(
+            scan.tail,
+            damaged(end, "frame runs past a later segment footer")
+        );
+        assert_eq!(scan.end, end);
+
+        fs::write(&log.0, b"not a log").unwrap();
+        let scan = log.scan();
+        assert_eq!(scan.end, 0);
+        assert_eq!(scan.tail, damaged(0, "bad file header"));
+    }
+}
@@ -0,0 +1,311 @@
+//! Converting Parquet values into forms that can be stored in a record
+
+use std::fmt;
+
+use parquet::data_type::Decimal;
+use parquet::record::Field;
+
+use crate::schema::{Kind, Unit};
+
+pub const MICROS_PER_DAY: i64 = 86_400_000_000;
+
+/// A value read from a file, ready to be stored in a column
+#[derive(Clone, Debug, PartialEq)]
+pub enum Cell<'a> {
+    Null,
+    Int(i64),
+    UInt(u64),
+    Float(f32),
+    Double(f64),
+    /// A decimal in text form, e.g. `-12.50`
+    Decimal(String),
+    Date(Date),
+    DateTime(Date, Time),
+    /// Text or binary data
+    Bytes(&'a [u8]),
+}
+
+/// A date in the proleptic Gregorian calendar
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Date {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+}
+
+/// A time of day
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Time {
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+    pub microsecond: u32,
+}
+
+impl<'a> Cell<'a> {
+    /// Convert a value of a column of kind `kind`. Returns `None` if the value does
+    /// not match the kind.
+    pub fn new(kind: Kind, field: &'a Field) -> Option<Self> {
+        let cell = match (kind, field) {
+            (_, Field::Null) => Self::Null,
+            (Kind::Bool, Field::Bool(v)) => Self::Int((*v).into()),
+            (Kind::Int { .. }, Field::Byte(v)) => Self::Int((*v).into()),
+            (Kind::Int { .. }, Field::Short(v)) => Self::Int((*v).into()),
+            (Kind::Int { .. }, Field::Int(v)) => Self::Int((*v).into()),
+            (Kind::Int { .. }, Field::Long(v)) => Self::Int(*v),
+            (Kind::Int { .. }, Field::UByte(v)) => Self::UInt((*v).into()),
+            (Kind::Int { .. }, Field::UShort(v)) => Self::UInt((*v).into()),
+            (Kind::Int { .. }, Field::UInt(v)) => Self::UInt((*v).into()),
+            (Kind::Int { .. }, Field::ULong(v)) => Self::UInt(*v),
+            (Kind::Float16, Field::Float16(v)) => Self::Float(v.to_f32()),
+            (Kind::Float, Field::Float(v)) => Self::Float(*v),
+            (Kind::Double, Field::Double(v)) => Self::Double(*v),
+            (Kind::Decimal { scale, .. }, Field::Decimal(v)) => {
+                Self::Decimal(decimal_text(decimal_unscaled(v)?, scale))
+            }
+            (Kind::Date, Field::Date(days)) => Self::Date(Date::from_days((*days).into())),
+            (Kind::Timestamp(_) | Kind::Int96, Field::TimestampMillis(v)) => {
+                Self::datetime(v.checked_mul(1000)?)
+            }
+            (Kind::Timestamp(_), Field::TimestampMicros(v)) => Self::datetime(*v),
+            (Kind::Timestamp(Unit::Nanos), Field::Long(v)) => Self::datetime(v.div_euclid(1000)),
+            (Kind::String | Kind::Bytes | Kind::Fixed(_), Field::Str(v)) => {
+                Self::Bytes(v.as_bytes())
+            }
+            (Kind::String | Kind::Bytes | Kind::Fixed(_), Field::Bytes(v)) => Self::Bytes(v.data()),
+            _ => return None,
+        };
+        Some(cell)
+    }
+
+    fn datetime(micros: i64) -> Self {
+        let date = Date::from_days(micros.div_euclid(MICROS_PER_DAY));
+        let micros = micros.rem_euclid(MICROS_PER_DAY);
+        let seconds = micros / 1_000_000;
+        let time = Time {
+            hour: (seconds / 3600) as u32,
+            minute: (seconds / 60 % 60) as u32,
+            second: (seconds % 60) as u32,
+            microsecond: (micros % 1_000_000) as u32,
+        };
+        Self::DateTime(date, time)
+    }
+}
+
+/// Text as the server would print the value, for columns whose type does not
+/// match the file's
+impl fmt::Display for Cell<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Null => f.write_str("NULL"),
+            Self::Int(v) => write!(f, "{v}"),
+            Self::UInt(v) => write!(f, "{v}"),
+            Self::Float(v) => write!(f, "{v}"),
+            Self::Double(v) => write!(f, "{v}"),
+            Self::Decimal(v) => f.write_str(v),
+            Self::Date(d) => write!(f, "{:04}-{:02}-{:02}", d.year, d.month, d.day),
+            Self::DateTime(d, t) => write!(
+                f,
+                "{:04}-{:02}-{:02} {:02}:{:02}:{:02}.{:06}",
+                d.year, d.month, d.day, t.hour, t.minute, t.second, t.microsecond
+            ),
+            Self::Bytes(v) => f.write_str(&String::from_utf8_lossy(v)),
+        }
+    }
+}
+
+impl Date {
+    /// The date `days` days after 1970-01-01
+    pub fn from_days(days: i64) -> Self {
+        // Howard Hinnant's `civil_from_days`, with eras of 400 years
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = yoe + era * 400 + i64::from(month <= 2);
+        Self { year, month, day }
+    }
+
+    /// The number of days since 1970-01-01
+    pub fn to_days(self) -> i64 {
+        let year = self.year - i64::from(self.month <= 2);
+        let era = year.div_euclid(400);
+        let yoe = year.rem_euclid(400);
+        let month = i64::from(self.month);
+        let mp = if month > 2 { month - 3 } else { month + 9 };
+        let doy = (153 * mp + 2) / 5 + i64::from(self.day) - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        era * 146_097 + doe - 719_468
+    }
+}
+
+/// Parse a date or datetime as the server prints it, `YYYY-MM-DD` optionally
+/// followed by ` HH:MM:SS[.ffffff]`, into microseconds since the Unix epoch
+pub fn parse_datetime(text: &str) -> Option<i64> {
+    let (date, time) = match text.split_once([' ', 'T']) {
+        Some((date, time)) => (date, Some(time)),
+        None => (text, None),
+    };
+
+    let mut parts = date.splitn(3, '-');
+    let year = parts.next()?.parse().ok()?;
+    let month = parts
+        .next()?
+        .parse()
+        .ok()
+        .filter(|m| (1..=12).contains(m))?;
+    let day = parts
+        .next()?
+        .parse()
+        .ok()
+        .filter(|d| (1..=31).contains(d))?;
+    let mut micros = Date { year, month, day }.to_days() * MICROS_PER_DAY;
+
+    if let Some(time) = time {
+        let (hms, frac) = match time.split_once('.') {
+            Some((hms, frac)) => (hms, frac),
+            None => (time, ""),
+        };
+        let mut parts = hms.splitn(3, ':');
+        let hour: i64 = parts.next()?.parse().ok().filter(|h| *h < 24)?;
+        let minute: i64 = parts.next()?.parse().ok().filter(|m| *m < 60)?;
+        let second: i64 = parts.next()?.parse().ok().filter(|s| *s < 60)?;
+        if frac.len() > 6 || !frac.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        let frac: i64 = format!("{frac:0<6}").parse().ok()?;
+        micros += ((hour * 60 + minute) * 60 + second) * 1_000_000 + frac;
+    }
+    Some(micros)
+}
+
+/// The unscaled value of a decimal, which is stored as a big endian two's
+/// complement integer
+pub fn decimal_unscaled(decimal: &Decimal) -> Option<i128> {
+    let bytes = decimal.data();
+    if bytes.is_empty() || bytes.len() > 16 {
+        return None;
+    }
+    let fill = if bytes[0] & 0x80 != 0 { 0xff } else { 0 };
+    let mut buf = [fill; 16];
+    buf[16 - bytes.len()..].copy_from_slice(bytes);
+    Some(i128::from_be_bytes(buf))
+}
+
+/// Format an unscaled decimal value with `scale` digits after the point
+pub fn decimal_text(unscaled: i128, scale: u8) -> String {
+    let digits = unscaled.unsigned_abs().to_string();
+    let scale = usize::from(scale);
+    let sign = if unscaled < 0 { "-" } else { "" };
+    if scale == 0 {
+        return format!("{sign}{digits}");
+    }
+    let digits = format!("{digits:0>width$}", width = scale + 1);
+    let (int, frac) = digits.split_at(digits.len() - scale);
+    format!("{sign}{int}.{frac}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_days() {
+        let cases = [
+            (0, (1970, 1, 1)),
+            (-1, (1969, 12, 31)),
+            (59, (1970, 3, 1)),
+            (11_016, (2000, 2, 29)),
+            (19_723, (2024, 1, 1)),
+            (-719_528, (0, 1, 1)),
+            (2_932_896, (9999, 12, 31)),
+        ];
+        for (days, (year, month, day)) in cases {
+            let date = Date { year, month, day };
+            assert_eq!(Date::from_days(days), date, "{days}");
+            assert_eq!(date.to_days(), days, "{date:?}");
+        }
+    }
+
+    #[test]
+    fn test_datetime_cell() {
+        let date = Date {
+            year: 1969,
+            month: 12,
+            day: 31,
+        };
+        let time = Time {
+            hour: 23,
+            minute: 59,
+            second: 59,
+            microsecond: 999_000,
+        };
+        let field = Field::TimestampMillis(-1);
+        assert_eq!(
+            Cell::new(Kind::Timestamp(Unit::Millis), &field),
+            Some(Cell::DateTime(date, time))
+        );
+        // Nanoseconds round down
+        let field = Field::Long(-1);
+        let time = Time {
+            microsecond: 999_999,
+            ..time
+        };
+        assert_eq!(
+            Cell::new(Kind::Timestamp(Unit::Nanos), &field),
+            Some(Cell::DateTime(date, time))
+        );
+        assert_eq!(Cell::new(Kind::Date, &Field::Long(1)), None);
+        let date = Date {
+            year: 2024,
+            month: 3,
+            day: 9,
+        };
+        assert_eq!(Cell::Date(date).to_string(), "2024-03-09");
+        assert_eq!(
+            Cell::DateTime(date, time).to_string(),
+            "2024-03-09 23:59:59.999999"
+        );
+    }
+
+    #[test]
+    fn test_parse_datetime() {
+        assert_eq!(parse_datetime("1970-01-01"), Some(0));
+        assert_eq!(
+            parse_datetime("1970-01-02 00:00:01"),
+            Some(MICROS_PER_DAY + 1_000_000)
+        );
+        assert_eq!(parse_datetime("1969-12-31 23:59:59.5"), Some(-500_000));
+        assert_eq!(
+            parse_datetime("2024-02-30T10:00:00.000001").map(|m| m % 10),
+            Some(1)
+        );
+        assert_eq!(parse_datetime("2024-13-01"), None);
+        assert_eq!(parse_datetime("2024-01-01 25:00:00"), None);
+        assert_eq!(parse_datetime("10:00:00"), None);
+        assert_eq!(parse_datetime("2024-01-01 10:00:00.1234567"), None);
+    }
+
+    #[test]
+    fn test_decimal() {
+        assert_eq!(decimal_text(12345, 2), "123.45");
+        assert_eq!(decimal_text(-5, 3), "-0.005");
+        assert_eq!(decimal_text(-120, 0), "-120");
+        assert_eq!(decimal_text(99, 2), "0.99");
+        let d = Decimal::from_i32(-1250, 5, 2);
+        assert_eq!(decimal_unscaled(&d), Some(-1250));
+        let field = Field::Decimal(d);
+        let kind = Kind::Decimal {
+            precision: 5,
+            scale: 2,
+        };
+        assert_eq!(
+            Cell::new(kind, &field),
+            Some(Cell::Decimal("-12.50".into()))
+        );
+    }
+}
@@ -0,0 +1,842 @@
+//! A read-only engine for Parquet files
+//!
+//! Any `<name>.parquet` file in a database directory shows up as the table `name`,
+//! with columns discovered from the file's schema (see [`schema`]). Files elsewhere
+//! can be read by creating a table with the `FILE_NAME` option. It is a path
+//! relative to the data directory, and may not leave it:
+//!
+//! ```sql
+//! CREATE TABLE events (id BIGINT, ts DATETIME(6)) ENGINE=PARQUET
+//!     FILE_NAME='events.parquet';
+//! ```
+//!
+//! Only the columns a statement reads are decoded. Conditions pushed down from the
+//! `WHERE` clause are used to skip row groups whose statistics show that no row can
+//! match (see [`prune`]). The row count comes from the file footer.
+//!
+//! The engine never deletes a Parquet file, so a discovered table is back after
+//! `DROP TABLE` for as long as its file is in the database directory. If a file is
+//! replaced while the table is open, run `FLUSH TABLES` so that its new footer is
+//! read.
+
+mod convert;
+mod prune;
+mod schema;
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::fs::{self, File};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::{io, vec};
+
+use mariadb::log::{debug, warn};
+use mariadb::plugin::{License, Maturity};
+use mariadb::storage::{
+    filename_to_tablename, tablename_to_filename, CmpOp, CondRemainder, Condition,
+    ConditionPushdown, CreateInfo, DiscoveredTables, DiscoveringHandlerton, Expr, Handler,
+    Handlerton, HandlertonThd, InfoFlags, Mode, OpenOp, Record, RecordMut, StatisticsMut,
+    StorageError, StorageResult, TableFlags, TableOptions, TablePath, ThrLock, ThrLockData,
+    ThrLockType,
+};
+use mariadb::{register_plugin_storage, FieldType, MemRoot, Table, TableShare, Thd, Value};
+use parquet::errors::ParquetError;
+use parquet::file::metadata::{ParquetMetaData, ParquetMetaDataReader};
+use parquet::file::reader::FileReader;
+use parquet::file::serialized_reader::{ReadOptionsBuilder, SerializedFileReader};
+use parquet::record::reader::RowIter;
+use parquet::record::Row;
+use parquet::schema::types::Type;
+
+use crate::convert::Cell;
+use crate::prune::{Literal, Op, Predicate, Scalar, Test};
+use crate::schema::{Column, Kind, Schema, Unit};
+
+register_plugin_storage! {
+    name: "PARQUET",
+    author: "Trevor Gross",
+    description: "Read-only access to Parquet files",
+    license: License::Gpl,
+    maturity: Maturity::Experimental,
+    version: "0.1",
+    handlerton: ParquetHton,
+}
+
+/// Extension of the files that are discovered as tables
+const DATA_EXT: &str = ".parquet";
+
+/// Table locks, by path of the table. Entries are removed when the last handler
+/// using them closes the table.
+static LOCKS: Mutex<BTreeMap<PathBuf, Arc<ThrLock>>> = Mutex::new(BTreeMap::new());
+
+struct ParquetHton;
+
+impl Handlerton for ParquetHton {
+    type Handler = ParquetHandler;
+
+    type ConnectionData = ();
+    type SavePoint = ();
+    type TableOptions = ParquetOptions;
+    type FieldOptions = ();
+    type IndexOptions = ();
+
+    const SUPPORTS_DISCOVERY: bool = true;
+}
+
+impl DiscoveringHandlerton for ParquetHton {
+    fn discover_table(_thd: &mut HandlertonThd<Self>, share: &TableShare) -> StorageResult<String> {
+        let path = TablePath::new(share.path()).with_extension(DATA_EXT);
+        let (_, schema) = read_schema(&path)?;
+        for name in &schema.unsupported {
+            warn!(
+                "parquet: {}: column '{name}' has an unsupported type, skipping it",
+                path.display()
+            );
+        }
+        schema.create_table_sql(share.table_name()).ok_or_else(|| {
+            StorageError::custom(format!(
+                "{} has no columns that can be read",
+                path.display()
+            ))
+        })
+    }
+
+    fn discover_table_names(db: &str, tables: &mut DiscoveredTables) -> StorageResult {
+        let Ok(entries) = fs::read_dir(Path::new(".").join(tablename_to_filename(db))) else {
+            return Ok(());
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name();
+            if let Some(file) = name.to_str().and_then(|n| n.strip_suffix(DATA_EXT)) {
+                tables.add(&filename_to_tablename(file))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn discover_table_existence(db: &str, table_name: &str) -> bool {
+        Path::new(".")
+            .join(tablename_to_filename(db))
+            .join(format!("{}{DATA_EXT}", tablename_to_filename(table_name)))
+            .is_file()
+    }
+}
+
+#[derive(TableOptions)]
+struct ParquetOptions {
+    /// The file to read instead of `<table>.parquet` in the database directory,
+    /// relative to the data directory
+    file_name: Option<String>,
+}
+
+/// What the handler needs to know about a column of its table
+struct FieldInfo {
+    name: String,
+    field_type: FieldType,
+    unsigned: bool,
+    decimals: u8,
+    /// Text compares byte by byte, like string statistics
+    binary_collation: bool,
+}
+
+impl FieldInfo {
+    /// Whether comparisons on this column give the same result as comparing the
+    /// file's values, so that the file's statistics can be used for pushed
+    /// conditions. Values that are converted on the way in (e.g. rounded) may
+    /// compare differently.
+    fn compares_like(&self, kind: Kind) -> bool {
+        use FieldType as Ft;
+
+        let fractional_digits = |unit| match unit {
+            Unit::Millis => 3,
+            Unit::Micros | Unit::Nanos => 6,
+        };
+        match kind {
+            Kind::Int { unsigned, .. } => {
+                matches!(
+                    self.field_type,
+                    Ft::Tiny | Ft::Short | Ft::Int24 | Ft::Long | Ft::LongLong
+                ) && self.unsigned == unsigned
+            }
+            Kind::Float => matches!(self.field_type, Ft::Float | Ft::Double),
+            Kind::Double => self.field_type == Ft::Double,
+            Kind::Date => matches!(
+                self.field_type,
+                Ft::Date | Ft::NewDate | Ft::DateTime | Ft::DateTime2
+            ),
+            Kind::Timestamp(unit) => {
+                matches!(self.field_type, Ft::DateTime | Ft::DateTime2)
+                    && self.decimals >= fractional_digits(unit)
+            }
+            // `CHAR` strips trailing spaces, so only variable length types
+            Kind::String | Kind::Bytes => {
+                matches!(
+                    self.field_type,
+                    Ft::VarChar | Ft::TinyBlob | Ft::Blob | Ft::MediumBlob | Ft::LongBlob
+                ) && self.binary_collation
+            }
+            _ => false,
+        }
+    }
+}
+
+/// An open file
+struct ParquetFile {
+    path: PathBuf,
+    metadata: ParquetMetaData,
+    /// The file column read into each column of the table
+    columns: Vec<Column>,
+    /// Whether the statistics of each table column's file column can be used for
+    /// pushed conditions
+    comparable: Vec<bool>,
+    /// Index of the first row of each row group
+    first_rows: Vec<u64>,
+    len: u64,
+}
+
+impl ParquetFile {
+    fn num_rows(&self, group: usize) -> u64 {
+        self.metadata
+            .row_group(group)
+            .num_rows()
+            .try_into()
+            .unwrap_or(0)
+    }
+
+    /// A reader of the row groups selected by `keep`
+    fn reader(&self, keep: Vec<bool>) -> StorageResult<SerializedFileReader<File>> {
+        let file = File::open(&self.path).map_err(|e| io_error(&self.path, e))?;
+        let options = ReadOptionsBuilder::new()
+            .with_predicate(Box::new(move |_, idx| {
+                keep.get(idx).copied().unwrap_or(false)
+            }))
+            .build();
+        let reader = SerializedFileReader::new_with_options(file, options)
+            .map_err(|e| parquet_error(&self.path, e))?;
+        if reader.metadata().file_metadata().num_rows() != self.metadata.file_metadata().num_rows()
+        {
+            return Err(StorageError::custom(format!(
+                "{} changed since the table was opened, run FLUSH TABLES",
+                self.path.display()
+            )));
+        }
+        Ok(reader)
+    }
+
+    /// Iterate the rows of the row groups selected by `keep`, with only the root
+    /// columns in `roots`
+    fn rows(&self, keep: Vec<bool>, roots: &[usize]) -> StorageResult<RowIter<'static>> {
+        let fields = self
+            .metadata
+            .file_metadata()
+            .schema_descr()
+            .root_schema()
+            .get_fields();
+        let projection = Type::group_type_builder("schema")
+            .with_fields(
+                roots
+                    .iter()
+                    .map(|&root| Arc::clone(&fields[root]))
+                    .collect(),
+            )
+            .build()
+            .map_err(|e| parquet_error(&self.path, e))?;
+        let reader = self.reader(keep)?;
+        RowIter::from_file_into(Box::new(reader))
+            .project(Some(projection))
+            .map_err(|e| parquet_error(&self.path, e))
+    }
+}
+
+/// A table scan over the row groups that may match the pushed conditions
+struct Scan {
+    /// Rows of the selected row groups, or `None` if no columns are read
+    rows: Option<RowIter<'static>>,
+    /// Selected row groups that have not been started yet
+    groups: vec::IntoIter<usize>,
+    /// Index of the next row in the file
+    next_row: u64,
+    /// Rows left in the current row group
+    remaining: u64,
+}
+
+/// The rows of one row group, kept for `rnd_pos`
+struct CachedGroup {
+    group: usize,
+    projection: Vec<usize>,
+    rows: Vec<Row>,
+}
+
+struct ParquetHandler {
+    /// Columns of the open table
+    fields: Vec<FieldInfo>,
+    file: Option<ParquetFile>,
+    lock_path: PathBuf,
+    lock_data: Option<ThrLockData>,
+    /// Predicates from each pushed condition that is still active
+    pushed: Vec<Vec<Predicate>>,
+    scan: Option<Scan>,
+    cached: Option<CachedGroup>,
+    /// Table columns that are read, in the order of the file's columns
+    projection: Vec<usize>,
+    /// The row last read by `rnd_next`. Blob columns of the record point into it.
+    row: Option<Row>,
+    /// Values that had to be converted to text, by position in `projection`. Blob
+    /// columns of the record may point into these.
+    texts: Vec<String>,
+    /// Index of the row last read
+    current_pos: u64,
+}
+
+impl ParquetHandler {
+    fn file(&self) -> &ParquetFile {
+        self.file.as_ref().expect("table is open")
+    }
+
+    /// Table columns that the current statement reads, in file order
+    fn projection(&self, table: &Table) -> Vec<usize> {
+        let mut projection: Vec<_> = (0..self.fields.len())
+            .filter(|&idx| table.is_field_read(idx))
+            .collect();
+        projection.sort_by_key(|&idx| self.file().columns[idx].root);
+        projection
+    }
+
+    /// Start a scan of the row groups that may match the pushed conditions
+    fn start_scan(&mut self, table: &Table) -> StorageResult {
+        let projection = self.projection(table);
+        let file = self.file();
+        let preds: Vec<_> = self.pushed.iter().flatten().cloned().collect();
+        let keep: Vec<_> = (0..file.metadata.num_row_groups())
+            .map(|idx| prune::row_group_may_match(file.metadata.row_group(idx), &preds))
+            .collect();
+        debug!(
+            "parquet: reading {} of {} row groups of {}",
+            keep.iter().filter(|k| **k).count(),
+            keep.len(),
+            file.path.display()
+        );
+
+        let groups: Vec<_> = (0..keep.len()).filter(|&idx| keep[idx]).collect();
+        let rows = if projection.is_empty() {
+            None
+        } else {
+            let roots: Vec<_> = projection
+                .iter()
+                .map(|&idx| file.columns[idx].root)
+                .collect();
+            Some(file.rows(keep, &roots)?)
+        };
+        self.projection = projection;
+        self.scan = Some(Scan {
+            rows,
+            groups: groups.into_iter(),
+            next_row: 0,
+            remaining: 0,
+        });
+        Ok(())
+    }
+
+    /// Make sure the rows of `group` with the current projection are cached
+    fn cache_group(&mut self, group: usize, table: &Table) -> StorageResult {
+        let projection = self.projection(table);
+        if self
+            .cached
+            .as_ref()
+            .is_some_and(|c| c.group == group && c.projection == projection)
+        {
+            return Ok(());
+        }
+
+        self.cached = None;
+        let file = self.file();
+        let rows = if projection.is_empty() {
+            Vec::new()
+        } else {
+            let mut keep = vec![false; file.metadata.num_row_groups()];
+            keep[group] = true;
+            let roots: Vec<_> = projection
+                .iter()
+                .map(|&idx| file.columns[idx].root)
+                .collect();
+            file.rows(keep, &roots)?
+                .collect::<Result<_, _>>()
+                .map_err(|e| parquet_error(&file.path, e))?
+        };
+        self.cached = Some(CachedGroup {
+            group,
+            projection,
+            rows,
+        });
+        Ok(())
+    }
+
+    /// Convert a condition term to a predicate on a file column, if its statistics
+    /// can tell whether the term may be true
+    fn predicate(&self, expr: &Expr) -> Option<Predicate> {
+        let file = self.file.as_ref()?;
+        let column = |expr: &Expr| match expr {
+            Expr::Column(idx) if file.comparable[*idx] => Some(&file.columns[*idx]),
+            _ => None,
+        };
+        let scalar = |col: &Column, value: Value| Scalar::new(col.kind, literal(value)?);
+
+        let (col, test) = match expr {
+            Expr::Cmp { op, left, right } => {
+                let (col, value, op) = match (column(left), constant(right)) {
+                    (Some(col), Some(value)) => (col, value, cmp_op(*op)),
+                    _ => (column(right)?, constant(left)?, cmp_op(*op).map(Op::swap)),
+                };
+                let test = match (op, value) {
+                    (None, Value::Null) => Test::IsNull,
+                    (None, value) => Test::Cmp(Op::Eq, scalar(col, value)?),
+                    (Some(_), Value::Null) => return None,
+                    (Some(op), value) => Test::Cmp(op, scalar(col, value)?),
+                };
+                (col, test)
+            }
+            Expr::In {
+                expr,
+                list,
+                negated: false,
+            } => {
+                let col = column(expr)?;
+                let mut values = Vec::new();
+                for item in list {
+                    match constant(item)? {
+                        // Never equal to anything
+                        Value::Null => (),
+                        value => values.push(scalar(col, value)?),
+                    }
+                }
+                (col, Test::In(values))
+            }
+            Expr::IsNull { expr, negated } => {
+                let test = if *negated {
+                    Test::IsNotNull
+                } else {
+                    Test::IsNull
+                };
+                (column(expr)?, test)
+            }
+            _ => return None,
+        };
+        Some(Predicate {
+            leaf: col.leaf,
+            kind: col.kind,
+            test,
+        })
+    }
+}
+
+impl Handler for ParquetHandler {
+    type Handlerton = ParquetHton;
+
+    const SUPPORTS_COND_PUSHDOWN: bool = true;
+
+    const TABLE_FLAGS: TableFlags = TableFlags::NO_TRANSACTIONS
+        .union(TableFlags::PARTIAL_COLUMN_READ)
+        .union(TableFlags::STATS_RECORDS_IS_EXACT)
+        .union(TableFlags::NO_AUTO_INCREMENT)
+        .union(TableFlags::FILE_BASED)
+        .union(TableFlags::BINLOG_ROW_CAPABLE)
+        .union(TableFlags::BINLOG_STMT_CAPABLE)
+        .union(TableFlags::CAN_TABLE_CONDITION_PUSHDOWN)
+        .union(TableFlags::SLOW_RND_POS);
+
+    fn new(_table: Option<&TableShare>, _mem_root: MemRoot) -> Self {
+        Self {
+            fields: Vec::new(),
+            file: None,
+            lock_path: PathBuf::new(),
+            lock_data: None,
+            pushed: Vec::new(),
+            scan: None,
+            cached: None,
+            projection: Vec::new(),
+            row: None,
+            texts: Vec::new(),
+            current_pos: 0,
+        }
+    }
+
+    fn create(&mut self, _name: &Path, form: &Table, _create_info: &CreateInfo) -> StorageResult {
+        // Tables without `FILE_NAME` only exist by their file being discovered
+        // `create` is only called for tables of this engine
+        let options = unsafe { form.share().options::<ParquetHton>() };
+        let Some(file_name) = options.file_name else {
+            return Err(StorageError::custom(format!(
+                "PARQUET tables are read-only: give a FILE_NAME, or put `{}{DATA_EXT}` \
+                 in the database directory",
+                form.share().table_name()
+            )));
+        };
+
+        let path = option_path(&file_name)?;
+        let (_, schema) = read_schema(&path)?;
+        for field in form.fields() {
+            if schema.find(field.name()).is_none() {
+                return Err(StorageError::custom(format!(
+                    "{} has no readable column '{}'",
+                    path.display(),
+                    field.name()
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn open(
+        &mut self,
+        name: &Path,
+        table: &Table,
+        _mode: Mode,
+        _open_options: OpenOp,
+    ) -> StorageResult {
+        // `open` is only called for tables of this engine
+        let options = unsafe { table.share().options::<ParquetHton>() };
+        let path = match options.file_name {
+            Some(file_name) => option_path(&file_name)?,
+            None => TablePath::new(name).with_extension(DATA_EXT),
+        };
+        let (metadata, schema) = read_schema(&path)?;
+
+        self.fields = table
+            .fields()
+            .iter()
+            .map(|f| FieldInfo {
+                name: f.name().to_owned(),
+                field_type: f.field_type(),
+                unsigned: f.is_unsigned(),
+                decimals: f.decimals(),
+                binary_collation: f
+                    .collation()
+                    .is_some_and(|c| c == "binary" || c.ends_with("_nopad_bin")),
+            })
+            .collect();
+
+        let mut columns = Vec::with_capacity(self.fields.len());
+        for field in &self.fields {
+            let Some(column) = schema.find(&field.name) else {
+                return Err(StorageError::custom(format!(
+                    "{} has no readable column '{}'",
+                    path.display(),
+                    field.name
+                )));
+            };
+            columns.push(column.clone());
+        }
+        let comparable = self
+            .fields
+            .iter()
+            .zip(&columns)
+            .map(|(field, column)| field.compares_like(column.kind))
+            .collect();
+        let first_rows = metadata
+            .row_groups()
+            .iter()
+            .scan(0, |first, rg| {
+                let this = *first;
+                *first += u64::try_from(rg.num_rows()).unwrap_or(0);
+                Some(this)
+            })
+            .collect();
+        let len = fs::metadata(&path).map_err(|e| io_error(&path, e))?.len();
+
+        let lock = {
+            let mut locks = LOCKS.lock().unwrap();
+            Arc::clone(locks.entry(name.to_owned()).or_default())
+        };
+        self.lock_path = name.to_owned();
+        self.lock_data = Some(ThrLockData::new(lock));
+        self.file = Some(ParquetFile {
+            path,
+            metadata,
+            columns,
+            comparable,
+            first_rows,
+            len,
+        });
+        debug!("parquet: opened {}", name.display());
+        Ok(())
+    }
+
+    fn close(&mut self) -> StorageResult {
+        self.scan = None;
+        self.cached = None;
+        self.row = None;
+        self.file = None;
+        self.lock_data = None;
+        let mut locks = LOCKS.lock().unwrap();
+        if locks
+            .get(&self.lock_path)
+            .is_some_and(|lock| Arc::strong_count(lock) == 1)
+        {
+            locks.remove(&self.lock_path);
+        }
+        Ok(())
+    }
+
+    fn write_row(&mut self, _buf: &Record) -> StorageResult {
+        Err(StorageError::TableReadonly)
+    }
+
+    fn update_row(&mut self, _old_data: &Record, _new_data: &Record) -> StorageResult {
+        Err(StorageError::TableReadonly)
+    }
+
+    fn delete_row(&mut self, _buf: &Record) -> StorageResult {
+        Err(StorageError::TableReadonly)
+    }
+
+    fn delete_all_rows(&mut self) -> StorageResult {
+        Err(StorageError::TableReadonly)
+    }
+
+    fn rnd_init(&mut self, _scan: bool) -> StorageResult {
+        // The scan starts with the first `rnd_next`, which knows the columns to read
+        self.scan = None;
+        Ok(())
+    }
+
+    fn rnd_next(&mut self, buf: &mut RecordMut) -> StorageResult {
+        if self.scan.is_none() {
+            self.start_scan(buf.table())?;
+        }
+        let file = self.file.as_ref().expect("table is open");
+        let scan = self.scan.as_mut().unwrap();
+        while scan.remaining == 0 {
+            let Some(group) = scan.groups.next() else {
+                return Err(StorageError::EndOfFile);
+            };
+            scan.next_row = file.first_rows[group];
+            scan.remaining = file.num_rows(group);
+        }
+
+        self.current_pos = scan.next_row;
+        scan.next_row += 1;
+        scan.remaining -= 1;
+        self.row = match &mut scan.rows {
+            Some(rows) => match rows.next() {
+                Some(row) => Some(row.map_err(|e| parquet_error(&file.path, e))?),
+                None => return Err(StorageError::CrashedOnUsage),
+            },
+            None => None,
+        };
+        store_row(
+            buf,
+            self.row.as_ref(),
+            &self.projection,
+            &file.columns,
+            &mut self.texts,
+        )
+    }
+
+    fn rnd_pos(&mut self, buf: &mut RecordMut, pos: &[u8]) -> StorageResult {
+        let pos = u64::from_le_bytes(pos.try_into().unwrap());
+        let first_rows = &self.file().first_rows;
+        let group = first_rows.partition_point(|&first| first <= pos) - 1;
+        let offset = usize::try_from(pos - first_rows[group]).unwrap();
+        self.cache_group(group, buf.table())?;
+
+        let file = self.file.as_ref().unwrap();
+        let cached = self.cached.as_ref().unwrap();
+        let row = if cached.projection.is_empty() {
+            None
+        } else {
+            Some(cached.rows.get(offset).ok_or(StorageError::KeyNotFound)?)
+        };
+        self.current_pos = pos;
+        store_row(buf, row, &cached.projection, &file.columns, &mut self.texts)
+    }
+
+    fn position(&mut self, _row: &Record, pos: &mut [u8]) {
+        pos.copy_from_slice(&self.current_pos.to_le_bytes());
+    }
+
+    fn rnd_end(&mut self) -> StorageResult {
+        self.scan = None;
+        Ok(())
+    }
+
+    fn info(&mut self, _flags: InfoFlags, stats: &mut StatisticsMut) -> StorageResult {
+        let file = self.file();
+        let rows = file.metadata.file_metadata().num_rows();
+        stats.set_records(rows.try_into().unwrap_or(0));
+        stats.set_data_file_length(file.len);
+        Ok(())
+    }
+
+    fn delete_table(&mut self, _path: &TablePath) -> StorageResult {
+        // The engine creates no files: the `.parquet` files belong to the user
+        Ok(())
+    }
+
+    fn store_lock(&mut self, _thd: &Thd, lock_type: ThrLockType) -> Option<&mut ThrLockData> {
+        let lock_data = self.lock_data.as_mut()?;
+        lock_data.request(lock_type);
+        Some(lock_data)
+    }
+}
+
+impl ConditionPushdown for ParquetHandler {
+    fn cond_push(&mut self, cond: Condition) -> CondRemainder {
+        let preds = cond
+            .supported()
+            .filter_map(|(_, expr)| self.predicate(expr))
+            .collect();
+        self.pushed.push(preds);
+        // Row groups are skipped, but the rows that are read are not filtered
+        CondRemainder::All
+    }
+
+    fn cond_pop(&mut self) {
+        self.pushed.pop();
+    }
+}
+
+/// Store the values of `row` into the columns `projection` of `buf`. `columns` are
+/// the file columns of the table's columns.
+fn store_row(
+    buf: &mut RecordMut,
+    row: Option<&Row>,
+    projection: &[usize],
+    columns: &[Column],
+    texts: &mut Vec<String>,
+) -> StorageResult {
+    let table = buf.table();
+    let null_bytes = table.share().null_bytes();
+    buf.as_bytes_mut()[..null_bytes].fill(0);
+    let Some(row) = row else {
+        return Ok(());
+    };
+
+    texts.resize_with(projection.len(), String::new);
+    for ((&idx, (_, field)), text) in projection.iter().zip(row.get_column_iter()).zip(texts) {
+        let cell = Cell::new(columns[idx].kind, field).ok_or(StorageError::CrashedOnUsage)?;
+        store_cell(buf, idx, &cell, text).map_err(|_| {
+            StorageError::custom(format!(
+                "cannot store '{cell}' in column '{}'",
+                table.fields()[idx].name()
+            ))
+        })?;
+    }
+    Ok(())
+}
+
+/// Store `cell` into the column at `idx`, converting it from text if it does not
+/// match the column type. `text` must stay valid while the record is used.
+fn store_cell(buf: &mut RecordMut, idx: usize, cell: &Cell, text: &mut String) -> StorageResult {
+    let value = match *cell {
+        Cell::Null => return buf.set_null(idx),
+        Cell::Bytes(bytes) => return buf.set_text(idx, bytes),
+        Cell::Int(v) => Some(Value::LongLong(v)),
+        Cell::UInt(v) => Some(Value::ULongLong(v)),
+        // MariaDB has no infinities or NaN
+        Cell::Float(v) if !v.is_finite() => return buf.set_null(idx),
+        Cell::Double(v) if !v.is_finite() => return buf.set_null(idx),
+        Cell::Float(v) => Some(Value::F32(v)),
+        Cell::Double(v) => Some(Value::F64(v)),
+        Cell::Decimal(_) => None,
+        Cell::Date(d) => sql_date(d).map(Value::Date),
+        Cell::DateTime(d, t) => sql_date(d).map(|date| {
+            Value::DateTime(mariadb::DateTime {
+                date,
+                hour: t.hour as u8,
+                minute: t.minute as u8,
+                second: t.second as u8,
+                microsecond: t.microsecond,
+            })
+        }),
+    };
+    if let Some(value) = value {
+        if buf.set(idx, value).is_ok() {
+            return Ok(());
+        }
+    }
+
+    text.clear();
+    write!(text, "{cell}").unwrap();
+    buf.set_text(idx, text.as_bytes())
+}
+
+/// A date in the range MariaDB supports
+fn sql_date(date: convert::Date) -> Option<mariadb::Date> {
+    Some(mariadb::Date {
+        year: u16::try_from(date.year).ok().filter(|y| *y <= 9999)?,
+        month: date.month as u8,
+        day: date.day as u8,
+    })
+}
+
+/// A constant from a condition, for comparing with statistics
+fn literal(value: Value) -> Option<Literal> {
+    let lit = match value {
+        Value::LongLong(v) => Literal::Int(v.into()),
+        Value::ULongLong(v) => Literal::Int(v.into()),
+        Value::F64(v) => Literal::Float(v),
+        Value::Decimal(v) => Literal::Decimal(std::str::from_utf8(v).ok()?),
+        Value::String(v) => Literal::Str(v),
+        _ => return None,
+    };
+    Some(lit)
+}
+
+/// The value of a constant term
+fn constant(expr: &Expr) -> Option<Value<'_>> {
+    match expr {
+        Expr::Const(c) => Some(c.value()),
+        _ => None,
+    }
+}
+
+/// The operator of a comparison, or `None` for `<=>`
+fn cmp_op(op: CmpOp) -> Option<Op> {
+    match op {
+        CmpOp::NullSafeEq => None,
+        CmpOp::Eq => Some(Op::Eq),
+        CmpOp::Ne => Some(Op::Ne),
+        CmpOp::Lt => Some(Op::Lt),
+        CmpOp::Le => Some(Op::Le),
+        CmpOp::Gt => Some(Op::Gt),
+        CmpOp::Ge => Some(Op::Ge),
+    }
+}
+
+/// The path of the file given by the `FILE_NAME` option. It must be relative and
+/// stay in the data directory, which is the server's working directory, so that
+/// creating a table cannot read arbitrary files of the server.
+fn option_path(file_name: &str) -> StorageResult<PathBuf> {
+    let path = Path::new(file_name);
+    let inside = path
+        .components()
+        .all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+    if !inside || path.file_name().is_none() {
+        return Err(StorageError::custom(format!(
+            "FILE_NAME '{file_name}' must be a path relative to the data directory, \
+             without '..'"
+        )));
+    }
+    Ok(Path::new(".").join(path))
+}
+
+/// Read the footer and schema of the file at `path`
+fn read_schema(path: &Path) -> StorageResult<(ParquetMetaData, Schema)> {
+    let file = File::open(path).map_err(|e| io_error(path, e))?;
+    let metadata = ParquetMetaDataReader::new()
+        .parse_and_finish(&file)
+        .map_err(|e| parquet_error(path, e))?;
+    let schema = Schema::new(metadata.file_metadata().schema_descr());
+    Ok((metadata, schema))
+}
+
+fn io_error(path: &Path, e: io::Error) -> StorageError {
+    match e.kind() {
+        io::ErrorKind::NotFound => StorageError::NoSuchTable,
+        _ => StorageError::custom(format!("{}: {e}", path.display())),
+    }
+}
+
+fn parquet_error(path: &Path, e: ParquetError) -> StorageError {
+    StorageError::custom(format!("{}: {e}", path.display()))
+}
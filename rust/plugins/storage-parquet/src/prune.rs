@@ -0,0 +1,382 @@
+//! Skipping row groups using the column statistics in the file footer
+//!
+//! Pushed conditions are turned into [`Predicate`]s on single columns. A row group
+//! is only read if every predicate may be true for some row in it, judging by the
+//! minimum, maximum and null count of the column. The server still checks the
+//! condition on every row that is returned, so predicates only need to be right
+//! when they rule a row group out.
+
+use std::cmp::Ordering;
+
+use parquet::file::metadata::RowGroupMetaData;
+use parquet::file::statistics::Statistics;
+
+use crate::convert::{parse_datetime, MICROS_PER_DAY};
+use crate::schema::{Kind, Unit};
+
+/// Integers up to this magnitude are exact as a double
+const MAX_EXACT_FLOAT_INT: i128 = 1 << 53;
+
+/// A value in the domain that a column's statistics are compared in: integers and
+/// temporal values (as microseconds since the epoch) as `Int`, floats as `Float`,
+/// and text and binary data as `Bytes`
+#[derive(Clone, Debug, PartialEq)]
+pub enum Scalar {
+    Int(i128),
+    Float(f64),
+    Bytes(Vec<u8>),
+}
+
+/// A constant from a condition
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Literal<'a> {
+    Int(i128),
+    Float(f64),
+    /// A decimal in text form
+    Decimal(&'a str),
+    /// A string, or a temporal value as the server prints it
+    Str(&'a [u8]),
+}
+
+impl Scalar {
+    /// Convert `lit` for comparing with a column of kind `kind`. Returns `None` if
+    /// the comparison cannot be judged from statistics.
+    pub fn new(kind: Kind, lit: Literal) -> Option<Self> {
+        match (kind, lit) {
+            (Kind::Int { .. }, Literal::Int(v)) => Some(Self::Int(v)),
+            (Kind::Float | Kind::Double, Literal::Int(v)) if v.abs() <= MAX_EXACT_FLOAT_INT => {
+                Some(Self::Float(v as f64))
+            }
+            (Kind::Float | Kind::Double, Literal::Float(v)) if !v.is_nan() => Some(Self::Float(v)),
+            (Kind::Float | Kind::Double, Literal::Decimal(v)) => v.parse().ok().map(Self::Float),
+            (Kind::Date | Kind::Timestamp(_), Literal::Str(v)) => {
+                let micros = parse_datetime(std::str::from_utf8(v).ok()?)?;
+                Some(Self::Int(micros.into()))
+            }
+            (Kind::String | Kind::Bytes, Literal::Str(v)) => Some(Self::Bytes(v.to_vec())),
+            _ => None,
+        }
+    }
+
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (self, other) {
+            (Self::Int(a), Self::Int(b)) => Some(a.cmp(b)),
+            (Self::Float(a), Self::Float(b)) => a.partial_cmp(b),
+            (Self::Bytes(a), Self::Bytes(b)) => Some(a.cmp(b)),
+            _ => None,
+        }
+    }
+}
+
+/// A comparison operator, with the column on the left
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Op {
+    /// The operator with its operands swapped, e.g. `Lt` for `5 > col`
+    pub fn swap(self) -> Self {
+        match self {
+            Self::Eq | Self::Ne => self,
+            Self::Lt => Self::Gt,
+            Self::Le => Self::Ge,
+            Self::Gt => Self::Lt,
+            Self::Ge => Self::Le,
+        }
+    }
+}
+
+/// What a [`Predicate`] checks about its column
+#[derive(Clone, Debug, PartialEq)]
+pub enum Test {
+    /// `col op value`, which is never true for `NULL`
+    Cmp(Op, Scalar),
+    /// `col IN (values...)`
+    In(Vec<Scalar>),
+    IsNull,
+    IsNotNull,
+}
+
+/// A condition on a single column
+#[derive(Clone, Debug, PartialEq)]
+pub struct Predicate {
+    /// Index of the column among the file's leaf columns
+    pub leaf: usize,
+    pub kind: Kind,
+    pub test: Test,
+}
+
+/// The statistics of a column in a row group
+struct Stats {
+    /// Minimum and maximum, if known and comparable
+    bounds: Option<(Scalar, Scalar)>,
+    /// Whether the bounds are actual values rather than truncated ones
+    exact: bool,
+    nulls: Option<u64>,
+    rows: u64,
+}
+
+impl Predicate {
+    /// Whether any row of `rg` may match
+    pub fn may_match(&self, rg: &RowGroupMetaData) -> bool {
+        let Some(stats) = rg.column(self.leaf).statistics() else {
+            return true;
+        };
+        let stats = Stats {
+            bounds: bounds(self.kind, stats),
+            exact: stats.min_is_exact() && stats.max_is_exact(),
+            nulls: stats.null_count_opt(),
+            rows: rg.num_rows().try_into().unwrap_or(0),
+        };
+        self.test.may_match(&stats)
+    }
+}
+
+impl Test {
+    fn may_match(&self, stats: &Stats) -> bool {
+        let all_null = stats.nulls == Some(stats.rows);
+        match self {
+            Self::IsNull => stats.nulls != Some(0),
+            Self::IsNotNull => !all_null,
+            _ if all_null => false,
+            Self::Cmp(op, value) => cmp_may_match(*op, value, stats),
+            Self::In(values) => values.iter().any(|v| cmp_may_match(Op::Eq, v, stats)),
+        }
+    }
+}
+
+fn cmp_may_match(op: Op, value: &Scalar, stats: &Stats) -> bool {
+    let Some((min, max)) = &stats.bounds else {
+        return true;
+    };
+    let (Some(vs_min), Some(vs_max)) = (value.partial_cmp(min), value.partial_cmp(max)) else {
+        return true;
+    };
+    match op {
+        Op::Eq => vs_min.is_ge() && vs_max.is_le(),
+        Op::Ne => !(stats.exact && vs_min.is_eq() && vs_max.is_eq()),
+        // Some value is below `value`
+        Op::Lt => vs_min.is_gt(),
+        Op::Le => vs_min.is_ge(),
+        Op::Gt => vs_max.is_lt(),
+        Op::Ge => vs_max.is_le(),
+    }
+}
+
+/// Whether any row of `rg` may match all of `preds`
+pub fn row_group_may_match(rg: &RowGroupMetaData, preds: &[Predicate]) -> bool {
+    rg.num_rows() > 0 && preds.iter().all(|p| p.may_match(rg))
+}
+
+/// The minimum and maximum of a column of kind `kind`, in the domain used by
+/// [`Scalar`]
+fn bounds(kind: Kind, stats: &Statistics) -> Option<(Scalar, Scalar)> {
+    // Old files ordered statistics as signed values, which is wrong for unsigned
+    // integers and binary data
+    let deprecated = stats.is_min_max_deprecated();
+    let (min, max) = match (kind, stats) {
+        (
+            Kind::Int {
+                unsigned: false, ..
+            },
+            Statistics::Int32(s),
+        ) => (i128::from(*s.min_opt()?), i128::from(*s.max_opt()?)),
+        (Kind::Int { unsigned: true, .. }, Statistics::Int32(s)) if !deprecated => (
+            i128::from(*s.min_opt()? as u32),
+            i128::from(*s.max_opt()? as u32),
+        ),
+        (
+            Kind::Int {
+                unsigned: false, ..
+            },
+            Statistics::Int64(s),
+        ) => (i128::from(*s.min_opt()?), i128::from(*s.max_opt()?)),
+        (Kind::Int { unsigned: true, .. }, Statistics::Int64(s)) if !deprecated => (
+            i128::from(*s.min_opt()? as u64),
+            i128::from(*s.max_opt()? as u64),
+        ),
+        (Kind::Float, Statistics::Float(s)) => {
+            let (min, max) = (f64::from(*s.min_opt()?), f64::from(*s.max_opt()?));
+            return float_bounds(min, max);
+        }
+        (Kind::Double, Statistics::Double(s)) => return float_bounds(*s.min_opt()?, *s.max_opt()?),
+        (Kind::Date, Statistics::Int32(s)) => {
+            let micros = |days: i32| i128::from(days) * i128::from(MICROS_PER_DAY);
+            (micros(*s.min_opt()?), micros(*s.max_opt()?))
+        }
+        (Kind::Timestamp(unit), Statistics::Int64(s)) => {
+            let micros = |v: i64| match unit {
+                Unit::Millis => i128::from(v) * 1000,
+                Unit::Micros => i128::from(v),
+                Unit::Nanos => i128::from(v.div_euclid(1000)),
+            };
+            (micros(*s.min_opt()?), micros(*s.max_opt()?))
+        }
+        (Kind::String | Kind::Bytes, Statistics::ByteArray(s)) if !deprecated => {
+            let (min, max) = (s.min_opt()?.data().to_vec(), s.max_opt()?.data().to_vec());
+            return Some((Scalar::Bytes(min), Scalar::Bytes(max)));
+        }
+        _ => return None,
+    };
+    Some((Scalar::Int(min), Scalar::Int(max)))
+}
+
+fn float_bounds(min: f64, max: f64) -> Option<(Scalar, Scalar)> {
+    if min.is_nan() || max.is_nan() {
+        return None;
+    }
+    Some((Scalar::Float(min), Scalar::Float(max)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, File};
+    use std::sync::Arc;
+
+    use parquet::data_type::{ByteArray, ByteArrayType, Int64Type};
+    use parquet::file::metadata::{ParquetMetaData, ParquetMetaDataReader};
+    use parquet::file::properties::WriterProperties;
+    use parquet::file::writer::SerializedFileWriter;
+    use parquet::schema::parser::parse_message_type;
+
+    use super::*;
+
+    /// Write a file with two row groups and read back its metadata. `id` has the
+    /// values 1..=3 and 10..=12; `name` is `NULL` in the second group.
+    fn metadata() -> ParquetMetaData {
+        let path = std::env::temp_dir().join(format!("prune-{}.parquet", std::process::id()));
+        let schema =
+            parse_message_type("message m { required int64 id; optional binary name (UTF8); }")
+                .unwrap();
+        let props = Arc::new(WriterProperties::builder().build());
+        let mut writer =
+            SerializedFileWriter::new(File::create(&path).unwrap(), Arc::new(schema), props)
+                .unwrap();
+
+        for (ids, names) in [([1, 2, 3], vec!["b", "c", "d"]), ([10, 11, 12], vec![])] {
+            let mut rg = writer.next_row_group().unwrap();
+            let mut col = rg.next_column().unwrap().unwrap();
+            col.typed::<Int64Type>()
+                .write_batch(&ids, None, None)
+                .unwrap();
+            col.close().unwrap();
+
+            let mut col = rg.next_column().unwrap().unwrap();
+            let values: Vec<ByteArray> = names.iter().map(|&s| s.into()).collect();
+            let def = if names.is_empty() { [0; 3] } else { [1; 3] };
+            col.typed::<ByteArrayType>()
+                .write_batch(&values, Some(&def), None)
+                .unwrap();
+            col.close().unwrap();
+            rg.close().unwrap();
+        }
+        writer.close().unwrap();
+
+        let metadata = ParquetMetaDataReader::new()
+            .parse_and_finish(&File::open(&path).unwrap())
+            .unwrap();
+        fs::remove_file(&path).unwrap();
+        metadata
+    }
+
+    /// Indices of the row groups that may match `preds`
+    fn groups(metadata: &ParquetMetaData, preds: &[Predicate]) -> Vec<usize> {
+        (0..metadata.num_row_groups())
+            .filter(|&i| row_group_may_match(metadata.row_group(i), preds))
+            .collect()
+    }
+
+    fn id(test: Test) -> Predicate {
+        Predicate {
+            leaf: 0,
+            kind: Kind::Int {
+                bits: 64,
+                unsigned: false,
+            },
+            test,
+        }
+    }
+
+    fn name(test: Test) -> Predicate {
+        Predicate {
+            leaf: 1,
+            kind: Kind::String,
+            test,
+        }
+    }
+
+    #[test]
+    fn test_prune_comparisons() {
+        let metadata = metadata();
+        let int = Scalar::Int;
+        let cases = [
+            (Op::Eq, 2, vec![0]),
+            (Op::Eq, 5, vec![]),
+            (Op::Ne, 2, vec![0, 1]),
+            (Op::Lt, 10, vec![0]),
+            (Op::Le, 10, vec![0, 1]),
+            (Op::Gt, 3, vec![1]),
+            (Op::Ge, 3, vec![0, 1]),
+            (Op::Gt, 12, vec![]),
+        ];
+        for (op, value, expected) in cases {
+            let pred = id(Test::Cmp(op, int(value)));
+            assert_eq!(groups(&metadata, &[pred]), expected, "{op:?} {value}");
+        }
+
+        let pred = id(Test::In(vec![int(0), int(11)]));
+        assert_eq!(groups(&metadata, &[pred]), [1]);
+        let preds = [
+            id(Test::Cmp(Op::Ge, int(2))),
+            id(Test::Cmp(Op::Le, int(11))),
+        ];
+        assert_eq!(groups(&metadata, &preds), [0, 1]);
+        let preds = [id(Test::Cmp(Op::Gt, int(3))), name(Test::IsNotNull)];
+        assert_eq!(groups(&metadata, &preds), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn test_prune_nulls_and_strings() {
+        let metadata = metadata();
+        let bytes = |v: &str| Scalar::Bytes(v.into());
+        assert_eq!(groups(&metadata, &[name(Test::IsNull)]), [1]);
+        assert_eq!(groups(&metadata, &[name(Test::IsNotNull)]), [0]);
+        let pred = name(Test::Cmp(Op::Eq, bytes("c")));
+        assert_eq!(groups(&metadata, &[pred]), [0]);
+        let pred = name(Test::Cmp(Op::Lt, bytes("b")));
+        assert_eq!(groups(&metadata, &[pred]), Vec::<usize>::new());
+        // Comparisons with values of another domain never prune
+        let pred = name(Test::Cmp(Op::Eq, Scalar::Int(1)));
+        assert_eq!(groups(&metadata, &[pred]), [0]);
+    }
+
+    #[test]
+    fn test_scalar_new() {
+        let int = Kind::Int {
+            bits: 32,
+            unsigned: false,
+        };
+        assert_eq!(Scalar::new(int, Literal::Int(-3)), Some(Scalar::Int(-3)));
+        assert_eq!(Scalar::new(int, Literal::Float(1.5)), None);
+        assert_eq!(
+            Scalar::new(Kind::Double, Literal::Decimal("1.25")),
+            Some(Scalar::Float(1.25))
+        );
+        assert_eq!(Scalar::new(Kind::Double, Literal::Int(1 << 60)), None);
+        assert_eq!(
+            Scalar::new(Kind::Date, Literal::Str(b"1970-01-02")),
+            Some(Scalar::Int(MICROS_PER_DAY.into()))
+        );
+        assert_eq!(
+            Scalar::new(Kind::Timestamp(Unit::Millis), Literal::Str(b"now")),
+            None
+        );
+        assert_eq!(Scalar::new(Kind::Bool, Literal::Int(1)), None);
+    }
+}
@@ -0,0 +1,339 @@
+//! Mapping Parquet column types to MariaDB column types
+//!
+//! Only top-level primitive columns that are not repeated can be read. Nested
+//! groups, lists and maps, and types without a good MariaDB equivalent (e.g.
+//! `TIME`) are left out of discovered tables.
+
+use std::fmt::Write;
+
+use parquet::basic::{ConvertedType, LogicalType, Repetition, TimeUnit, Type as PhysicalType};
+use parquet::schema::types::{ColumnDescriptor, SchemaDescriptor};
+
+/// Largest precision of a `DECIMAL` that is read; its unscaled value fits an `i128`
+const MAX_DECIMAL_PRECISION: i32 = 38;
+/// Longest fixed length binary column read as `BINARY(n)` rather than a blob
+const MAX_BINARY_LENGTH: i32 = 255;
+
+/// How values of a column are stored in the file and read back
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    Bool,
+    /// Integers of `bits` bits
+    Int {
+        bits: u8,
+        unsigned: bool,
+    },
+    /// Half precision float, read as `FLOAT`
+    Float16,
+    Float,
+    Double,
+    Decimal {
+        precision: u8,
+        scale: u8,
+    },
+    /// Days since the Unix epoch
+    Date,
+    /// Time since the Unix epoch in `unit`
+    Timestamp(Unit),
+    /// Legacy `INT96` timestamps, read with millisecond precision
+    Int96,
+    /// UTF-8 text
+    String,
+    /// Binary data of any length
+    Bytes,
+    /// Binary data of a fixed length
+    Fixed(u8),
+}
+
+/// Precision of a timestamp
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Unit {
+    Millis,
+    Micros,
+    Nanos,
+}
+
+impl Kind {
+    /// The kind of a leaf column, if it can be read
+    pub fn of(descr: &ColumnDescriptor) -> Option<Self> {
+        let logical = descr.logical_type();
+        let kind = match (descr.physical_type(), descr.converted_type()) {
+            (PhysicalType::BOOLEAN, _) => Self::Bool,
+            (PhysicalType::INT32, ConvertedType::INT_8) => Self::int(8, false),
+            (PhysicalType::INT32, ConvertedType::INT_16) => Self::int(16, false),
+            (PhysicalType::INT32, ConvertedType::INT_32 | ConvertedType::NONE) => {
+                if matches!(logical, Some(LogicalType::Time { .. })) {
+                    return None;
+                }
+                Self::int(32, false)
+            }
+            (PhysicalType::INT32, ConvertedType::UINT_8) => Self::int(8, true),
+            (PhysicalType::INT32, ConvertedType::UINT_16) => Self::int(16, true),
+            (PhysicalType::INT32, ConvertedType::UINT_32) => Self::int(32, true),
+            (PhysicalType::INT32, ConvertedType::DATE) => Self::Date,
+            (PhysicalType::INT64, ConvertedType::INT_64) => Self::int(64, false),
+            (PhysicalType::INT64, ConvertedType::UINT_64) => Self::int(64, true),
+            (PhysicalType::INT64, ConvertedType::TIMESTAMP_MILLIS) => Self::Timestamp(Unit::Millis),
+            (PhysicalType::INT64, ConvertedType::TIMESTAMP_MICROS) => Self::Timestamp(Unit::Micros),
+            // Nanosecond timestamps and times have no converted type
+            (PhysicalType::INT64, ConvertedType::NONE) => match logical {
+                Some(LogicalType::Timestamp { unit, .. }) => Self::Timestamp(match unit {
+                    TimeUnit::MILLIS(_) => Unit::Millis,
+                    TimeUnit::MICROS(_) => Unit::Micros,
+                    TimeUnit::NANOS(_) => Unit::Nanos,
+                }),
+                Some(LogicalType::Time { .. }) => return None,
+                _ => Self::int(64, false),
+            },
+            (PhysicalType::INT96, _) => Self::Int96,
+            (PhysicalType::FLOAT, _) => Self::Float,
+            (PhysicalType::DOUBLE, _) => Self::Double,
+            (
+                PhysicalType::BYTE_ARRAY,
+                ConvertedType::UTF8 | ConvertedType::ENUM | ConvertedType::JSON,
+            ) => Self::String,
+            (PhysicalType::BYTE_ARRAY, ConvertedType::BSON | ConvertedType::NONE) => Self::Bytes,
+            (PhysicalType::FIXED_LEN_BYTE_ARRAY, ConvertedType::NONE) => {
+                if logical == Some(LogicalType::Float16) {
+                    Self::Float16
+                } else if descr.type_length() <= MAX_BINARY_LENGTH {
+                    Self::Fixed(descr.type_length().try_into().ok()?)
+                } else {
+                    Self::Bytes
+                }
+            }
+            (
+                PhysicalType::INT32
+                | PhysicalType::INT64
+                | PhysicalType::BYTE_ARRAY
+                | PhysicalType::FIXED_LEN_BYTE_ARRAY,
+                ConvertedType::DECIMAL,
+            ) => {
+                let (precision, scale) = (descr.type_precision(), descr.type_scale());
+                if !(1..=MAX_DECIMAL_PRECISION).contains(&precision)
+                    || !(0..=precision).contains(&scale)
+                {
+                    return None;
+                }
+                Self::Decimal {
+                    precision: precision.try_into().ok()?,
+                    scale: scale.try_into().ok()?,
+                }
+            }
+            _ => return None,
+        };
+        Some(kind)
+    }
+
+    fn int(bits: u8, unsigned: bool) -> Self {
+        Self::Int { bits, unsigned }
+    }
+
+    /// The MariaDB column type that holds values of this kind
+    pub fn sql_type(self) -> String {
+        match self {
+            Self::Bool => "BOOL".to_owned(),
+            Self::Int { bits, unsigned } => {
+                let name = match bits {
+                    8 => "TINYINT",
+                    16 => "SMALLINT",
+                    32 => "INT",
+                    _ => "BIGINT",
+                };
+                if unsigned {
+                    format!("{name} UNSIGNED")
+                } else {
+                    name.to_owned()
+                }
+            }
+            Self::Float16 | Self::Float => "FLOAT".to_owned(),
+            Self::Double => "DOUBLE".to_owned(),
+            Self::Decimal { precision, scale } => format!("DECIMAL({precision},{scale})"),
+            Self::Date => "DATE".to_owned(),
+            Self::Timestamp(Unit::Millis) | Self::Int96 => "DATETIME(3)".to_owned(),
+            Self::Timestamp(Unit::Micros | Unit::Nanos) => "DATETIME(6)".to_owned(),
+            // Binary collation, so that strings compare like their statistics
+            Self::String => "LONGTEXT CHARACTER SET utf8mb4 COLLATE utf8mb4_nopad_bin".to_owned(),
+            Self::Bytes => "LONGBLOB".to_owned(),
+            Self::Fixed(len) => format!("BINARY({len})"),
+        }
+    }
+}
+
+/// A top-level column of a file that can be read
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Column {
+    pub name: String,
+    /// Index of the column in the file's root schema, used for projection
+    pub root: usize,
+    /// Index of the column among the file's leaf columns, used for statistics
+    pub leaf: usize,
+    pub kind: Kind,
+    pub nullable: bool,
+}
+
+/// The columns of a file
+#[derive(Clone, Debug, Default)]
+pub struct Schema {
+    pub columns: Vec<Column>,
+    /// Names of the top-level columns that cannot be read
+    pub unsupported: Vec<String>,
+}
+
+impl Schema {
+    pub fn new(descr: &SchemaDescriptor) -> Self {
+        let mut schema = Self::default();
+        for (root, field) in descr.root_schema().get_fields().iter().enumerate() {
+            let info = field.get_basic_info();
+            let leaf = (0..descr.num_columns()).find(|&i| descr.get_column_root_idx(i) == root);
+            let column = leaf.filter(|_| field.is_primitive()).and_then(|leaf| {
+                let nullable = match info.repetition() {
+                    Repetition::REQUIRED => false,
+                    Repetition::OPTIONAL => true,
+                    Repetition::REPEATED => return None,
+                };
+                Some(Column {
+                    name: info.name().to_owned(),
+                    root,
+                    leaf,
+                    kind: Kind::of(&descr.column(leaf))?,
+                    nullable,
+                })
+            });
+            match column {
+                Some(column) => schema.columns.push(column),
+                None => schema.unsupported.push(info.name().to_owned()),
+            }
+        }
+        schema
+    }
+
+    /// The readable column named `name`. Column names are case insensitive, as in
+    /// MariaDB.
+    pub fn find(&self, name: &str) -> Option<&Column> {
+        self.columns
+            .iter()
+            .find(|c| c.name.eq_ignore_ascii_case(name))
+    }
+
+    /// A `CREATE TABLE` statement for a table with all readable columns, or `None`
+    /// if there are none
+    pub fn create_table_sql(&self, table_name: &str) -> Option<String> {
+        if self.columns.is_empty() {
+            return None;
+        }
+
+        let mut sql = format!("CREATE TABLE {} (", quote_ident(table_name));
+        for (idx, column) in self.columns.iter().enumerate() {
+            if idx > 0 {
+                sql.push_str(", ");
+            }
+            let null = if column.nullable { "" } else { " NOT NULL" };
+            write!(
+                sql,
+                "{} {}{null}",
+                quote_ident(&column.name),
+                column.kind.sql_type()
+            )
+            .unwrap();
+        }
+        sql.push(')');
+        Some(sql)
+    }
+}
+
+/// Quote an identifier with backticks
+fn quote_ident(name: &str) -> String {
+    format!("`{}`", name.replace('`', "``"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use parquet::schema::parser::parse_message_type;
+
+    use super::*;
+
+    fn schema(message: &str) -> Schema {
+        let ty = parse_message_type(message).unwrap();
+        Schema::new(&SchemaDescriptor::new(Arc::new(ty)))
+    }
+
+    #[test]
+    fn test_sql_types() {
+        let s = schema(
+            "message m {
+                required int32 a (INTEGER(8, true));
+                optional int32 b (INTEGER(32, false));
+                required int64 c;
+                optional int64 d (TIMESTAMP(NANOS, true));
+                optional int32 e (DATE);
+                optional binary f (UTF8);
+                optional fixed_len_byte_array(16) g (DECIMAL(20, 4));
+                optional int32 h (DECIMAL(2, 2));
+                optional fixed_len_byte_array(4) i;
+                optional boolean j;
+            }",
+        );
+        assert!(s.unsupported.is_empty());
+        let types: Vec<_> = s.columns.iter().map(|c| c.kind.sql_type()).collect();
+        assert_eq!(
+            types,
+            [
+                "TINYINT",
+                "INT UNSIGNED",
+                "BIGINT",
+                "DATETIME(6)",
+                "DATE",
+                "LONGTEXT CHARACTER SET utf8mb4 COLLATE utf8mb4_nopad_bin",
+                "DECIMAL(20,4)",
+                "DECIMAL(2,2)",
+                "BINARY(4)",
+                "BOOL",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unsupported_columns() {
+        let s = schema(
+            "message m {
+                required int32 id;
+                optional int32 t (TIME(MILLIS, true));
+                repeated int32 list;
+                optional group g { optional int32 x; optional int32 y; }
+                optional fixed_len_byte_array(32) d (DECIMAL(70, 2));
+                optional binary name (UTF8);
+            }",
+        );
+        assert_eq!(s.unsupported, ["t", "list", "g", "d"]);
+        let names: Vec<_> = s
+            .columns
+            .iter()
+            .map(|c| (&c.name[..], c.root, c.leaf))
+            .collect();
+        // The group has two leaves, so leaf and root indices differ after it
+        assert_eq!(names, [("id", 0, 0), ("name", 5, 6)]);
+        assert_eq!(s.find("NAME").unwrap().root, 5);
+    }
+
+    #[test]
+    fn test_create_table_sql() {
+        let s = schema(
+            "message m {
+                required int64 id (INTEGER(64, false));
+                optional double we`ird;
+            }",
+        );
+        assert_eq!(s.columns[1].name, "we`ird");
+        assert_eq!(
+            s.create_table_sql("t1").unwrap(),
+            "CREATE TABLE `t1` (`id` BIGINT UNSIGNED NOT NULL, `we``ird` DOUBLE)"
+        );
+        assert_eq!(
+            schema("message m { repeated int32 a; }").create_table_sql("t"),
+            None
+        );
+    }
+}